@@ -2,7 +2,11 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{commands::*, sg_client_proxy::SGClientProxy};
+use anyhow::Result;
 use libra_crypto::hash::CryptoHash;
+use node_proto::proto::node::{ChannelTransactionRecord, Payment, PaymentStatus};
+use sgtypes::channel_transaction::ChannelOp;
+use std::convert::TryFrom;
 
 /// Major command for account related operations.
 pub struct NodeCommand {}
@@ -23,6 +27,10 @@ impl Command for NodeCommand {
             Box::new(NodeCommandDepositChannel {}),
             Box::new(NodeCommandAddInvoice {}),
             Box::new(NodeCommandSendPayment {}),
+            Box::new(NodeCommandListPayments {}),
+            Box::new(NodeCommandTrackPayment {}),
+            Box::new(NodeCommandSetFeePolicy {}),
+            Box::new(NodeCommandListChannelTransactions {}),
         ];
 
         subcommand_execute(&params[0], commands, client, &params[1..]);
@@ -244,7 +252,7 @@ impl Command for NodeCommandAddInvoice {
     }
 
     fn get_params_help(&self) -> &'static str {
-        "<amount>"
+        "<amount> [expiry_secs] [memo]"
     }
 
     fn get_description(&self) -> &'static str {
@@ -295,3 +303,183 @@ impl Command for NodeCommandSendPayment {
         }
     }
 }
+
+pub struct NodeCommandListPayments {}
+
+impl Command for NodeCommandListPayments {
+    fn get_aliases(&self) -> Vec<&'static str> {
+        vec!["list_payments", "lp"]
+    }
+
+    fn get_params_help(&self) -> &'static str {
+        "[watch]"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "list payments, keep printing updates if watch is true"
+    }
+
+    fn execute(&self, client: &mut SGClientProxy, params: &[&str]) {
+        match client.list_payments(params) {
+            Ok(payments) => print_payments(payments),
+            Err(e) => report_error("Error list payments", e),
+        }
+    }
+}
+
+pub struct NodeCommandTrackPayment {}
+
+impl Command for NodeCommandTrackPayment {
+    fn get_aliases(&self) -> Vec<&'static str> {
+        vec!["track_payment", "tp"]
+    }
+
+    fn get_params_help(&self) -> &'static str {
+        "<r_hash>"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "print updates of payment until it succeeded or failed"
+    }
+
+    fn execute(&self, client: &mut SGClientProxy, params: &[&str]) {
+        if params.len() < 2 {
+            println!("Invalid number of arguments for track payment");
+            return;
+        }
+
+        match client.track_payment(params) {
+            Ok(payments) => print_payments(payments),
+            Err(e) => report_error("Error track payment", e),
+        }
+    }
+}
+
+pub struct NodeCommandSetFeePolicy {}
+
+impl Command for NodeCommandSetFeePolicy {
+    fn get_aliases(&self) -> Vec<&'static str> {
+        vec!["set_fee_policy", "sfp"]
+    }
+
+    fn get_params_help(&self) -> &'static str {
+        "<base_fee> <fee_rate_ppm> <timelock_delta> [participant_address]"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "set fee policy to forward htlc to participant, or default policy of all channels"
+    }
+
+    fn execute(&self, client: &mut SGClientProxy, params: &[&str]) {
+        if params.len() < 4 {
+            println!("Invalid number of arguments for set fee policy");
+            return;
+        }
+
+        match client.set_fee_policy(params) {
+            Ok(_) => {
+                println!("successfully set fee policy");
+            }
+            Err(e) => report_error("Error set fee policy", e),
+        }
+    }
+}
+
+pub struct NodeCommandListChannelTransactions {}
+
+impl Command for NodeCommandListChannelTransactions {
+    fn get_aliases(&self) -> Vec<&'static str> {
+        vec!["list_txns", "lt"]
+    }
+
+    fn get_params_help(&self) -> &'static str {
+        "<participant_address> [start] [limit] [op=open|close|<package>/<script>|<module>.<function>] [proposer=<address>] [from=<unix_secs>] [to=<unix_secs>]"
+    }
+
+    fn get_description(&self) -> &'static str {
+        "list applied transactions of channel with participant, with balance changes"
+    }
+
+    fn execute(&self, client: &mut SGClientProxy, params: &[&str]) {
+        if params.len() < 2 {
+            println!("Invalid number of arguments for list channel transactions");
+            return;
+        }
+
+        match client.list_channel_transactions(params) {
+            Ok(resp) => {
+                for txn in resp.transactions.iter() {
+                    print_channel_transaction(txn);
+                }
+                if resp.has_more {
+                    println!("more transactions from {}", resp.next_start);
+                }
+            }
+            Err(e) => report_error("Error list channel transactions", e),
+        }
+    }
+}
+
+fn print_channel_transaction(txn: &ChannelTransactionRecord) {
+    let op = match txn.operator.clone().map(ChannelOp::try_from) {
+        Some(Ok(op)) => op.to_string(),
+        Some(Err(_)) => "unknown".to_string(),
+        None => "travel".to_string(),
+    };
+    println!(
+        "txn {}: {}, proposer {}, gas {}, applied {}, expiration {}",
+        txn.channel_sequence_number,
+        op,
+        hex::encode(&txn.proposer),
+        txn.gas_used,
+        txn.applied_time,
+        txn.expiration_time,
+    );
+    for delta in txn.balance_deltas.iter() {
+        println!("  {}: {:+}", hex::encode(&delta.participant), delta.delta);
+    }
+}
+
+fn print_payments(payments: impl Iterator<Item = Result<Payment>>) {
+    for payment in payments {
+        match payment {
+            Ok(payment) => print_payment(&payment),
+            Err(e) => {
+                report_error("Error receive payment", e);
+                return;
+            }
+        }
+    }
+}
+
+fn print_payment(payment: &Payment) {
+    println!(
+        "payment {}: {:?}, amount {}, fee {}, receiver {}",
+        hex::encode(&payment.r_hash),
+        PaymentStatus::from_i32(payment.status),
+        payment.amount,
+        payment.fee,
+        hex::encode(&payment.receiver),
+    );
+    if let Some(failure) = &payment.failure {
+        println!("  failed: {} {}", failure.code, failure.message);
+    }
+    for attempt in payment.attempts.iter() {
+        let route: Vec<String> = attempt.route.iter().map(hex::encode).collect();
+        println!(
+            "  attempt {}: {:?}, amount {}, route {}",
+            hex::encode(&attempt.request_id),
+            PaymentStatus::from_i32(attempt.status),
+            attempt.amount,
+            route.join(" -> "),
+        );
+        if let Some(failure) = &attempt.failure {
+            println!(
+                "    failed at {}: {} {}",
+                hex::encode(&failure.hop),
+                failure.code,
+                failure.message
+            );
+        }
+    }
+}
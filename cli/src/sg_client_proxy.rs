@@ -1,7 +1,7 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 use crate::commands::*;
-use anyhow::{ensure, format_err, Error, Result};
+use anyhow::{bail, ensure, format_err, Error, Result};
 use grpcio::EnvBuilder;
 use libra_crypto::HashValue;
 use libra_types::{
@@ -16,20 +16,27 @@ use libra_types::{
 };
 use libra_wallet::{key_factory::ChildNumber, wallet_library::WalletLibrary};
 use node_client::NodeClient;
+use node_proto::proto::node::{ListChannelTransactionsResponse, Payment};
 use node_proto::{
     AddInvoiceRequest, AddInvoiceResponse, ChannelBalanceRequest, ChannelBalanceResponse,
     ChannelTransactionProposalRequest, DeployModuleRequest, DeployModuleResponse, DepositRequest,
     DepositResponse, EmptyResponse, ExecuteScriptRequest, GetChannelTransactionProposalResponse,
-    InstallChannelScriptPackageRequest, OpenChannelRequest, OpenChannelResponse, PayRequest,
-    PayResponse, PaymentRequest, WithdrawRequest, WithdrawResponse,
+    InstallChannelScriptPackageRequest, ListChannelTransactionsRequest, ListPaymentsRequest,
+    OpenChannelRequest, OpenChannelResponse, PayRequest, PayResponse, PaymentRequest,
+    SetFeePolicyRequest, TrackPaymentRequest, WithdrawRequest, WithdrawResponse,
 };
 use sgchain::{
     client_state_view::ClientStateView,
     star_chain_client::{faucet_sync, ChainClient, ChainExplorer, StarChainClient},
 };
 use sgcompiler::{Compiler, StateViewModuleLoader};
+use sgtypes::{
+    channel_transaction::ChannelOp, channel_txn_history::ChannelTxnQuery, fee_policy::FeePolicy,
+};
 use std::{convert::TryFrom, fs, path::Path, str::FromStr, sync::Arc};
 
+const DEFAULT_TXN_PAGE_SIZE: u64 = 20;
+
 /// Enum used for error formatting.
 #[derive(Debug)]
 enum InputType {
@@ -253,12 +260,22 @@ impl SGClientProxy {
 
     pub fn add_invoice(&mut self, space_delim_strings: &[&str]) -> Result<AddInvoiceResponse> {
         ensure!(
-            space_delim_strings.len() == 2,
+            space_delim_strings.len() >= 2,
             "Invalid number of arguments for add invoice"
         );
-        let response = self.node_client.add_invoice(AddInvoiceRequest {
-            amount: space_delim_strings[1].parse::<u64>()?,
-        })?;
+        let expiry = match space_delim_strings.get(2) {
+            Some(expiry) => expiry.parse::<u64>()?,
+            None => 0,
+        };
+        let memo = space_delim_strings[3.min(space_delim_strings.len())..].join(" ");
+        let response = self.node_client.add_invoice(AddInvoiceRequest::new(
+            space_delim_strings[1].parse::<u64>()?,
+            expiry,
+            memo,
+            None,
+            0,
+            vec![],
+        ))?;
         Ok(response)
     }
 
@@ -273,6 +290,85 @@ impl SGClientProxy {
         Ok(response)
     }
 
+    pub fn list_payments(
+        &mut self,
+        space_delim_strings: &[&str],
+    ) -> Result<impl Iterator<Item = Result<Payment>>> {
+        let watch = match space_delim_strings.get(1) {
+            Some(watch) => watch.parse::<bool>()?,
+            None => false,
+        };
+        self.node_client
+            .list_payments(ListPaymentsRequest::new(watch))
+    }
+
+    pub fn track_payment(
+        &mut self,
+        space_delim_strings: &[&str],
+    ) -> Result<impl Iterator<Item = Result<Payment>>> {
+        ensure!(
+            space_delim_strings.len() == 2,
+            "Invalid number of arguments for track payment"
+        );
+        let r_hash = HashValue::from_slice(&hex::decode(space_delim_strings[1])?)?;
+        self.node_client
+            .track_payment(TrackPaymentRequest::new(r_hash))
+    }
+
+    pub fn set_fee_policy(&mut self, space_delim_strings: &[&str]) -> Result<EmptyResponse> {
+        ensure!(
+            space_delim_strings.len() == 4 || space_delim_strings.len() == 5,
+            "Invalid number of arguments for set fee policy"
+        );
+        let policy = FeePolicy::new(
+            space_delim_strings[1].parse::<u64>()?,
+            space_delim_strings[2].parse::<u64>()?,
+            space_delim_strings[3].parse::<u64>()?,
+        );
+        let participant = match space_delim_strings.get(4) {
+            Some(addr) => Some(AccountAddress::from_hex_literal(addr)?),
+            None => None,
+        };
+        self.node_client
+            .set_fee_policy(SetFeePolicyRequest::new(participant, policy))
+    }
+
+    /// `list_txns <participant_address> [start] [limit] [filters]`, filters are `key=value`,
+    /// key is one of `op`, `proposer`, `from` and `to`.
+    pub fn list_channel_transactions(
+        &mut self,
+        space_delim_strings: &[&str],
+    ) -> Result<ListChannelTransactionsResponse> {
+        ensure!(
+            space_delim_strings.len() >= 2,
+            "Invalid number of arguments for list channel transactions"
+        );
+        let participant = AccountAddress::from_hex_literal(space_delim_strings[1])?;
+        let mut range = vec![];
+        let mut query = ChannelTxnQuery::new(0, DEFAULT_TXN_PAGE_SIZE);
+        for param in &space_delim_strings[2..] {
+            let mut kv = param.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some("op"), Some(op)) => query.operator = Some(parse_channel_op(op)?),
+                (Some("proposer"), Some(addr)) => {
+                    query.proposer = Some(AccountAddress::from_hex_literal(addr)?)
+                }
+                (Some("from"), Some(t)) => query.start_time = Some(t.parse::<u64>()?),
+                (Some("to"), Some(t)) => query.end_time = Some(t.parse::<u64>()?),
+                (Some(key), Some(_)) => bail!("Unknown filter {}", key),
+                _ => range.push(param.parse::<u64>()?),
+            }
+        }
+        if let Some(start) = range.get(0) {
+            query.start = *start;
+        }
+        if let Some(limit) = range.get(1) {
+            query.limit = *limit;
+        }
+        self.node_client
+            .list_channel_transactions(ListChannelTransactionsRequest::new(participant, query))
+    }
+
     pub fn get_account_address_from_parameter(&self, para: &str) -> Result<AccountAddress> {
         match is_address(para) {
             true => SGClientProxy::address_from_strings(para),
@@ -465,6 +561,31 @@ impl SGClientProxy {
     }
 }
 
+/// `open`, `close`, `<package>/<script>` or `<module>.<function>` of modules at 0x0.
+fn parse_channel_op(op: &str) -> Result<ChannelOp> {
+    let channel_op = match op {
+        "open" => ChannelOp::Open,
+        "close" => ChannelOp::Close,
+        _ => {
+            if let Some(i) = op.find('/') {
+                ChannelOp::Execute {
+                    package_name: op[..i].to_string(),
+                    script_name: op[i + 1..].to_string(),
+                }
+            } else if let Some(i) = op.find('.') {
+                ChannelOp::Action {
+                    module_address: AccountAddress::default(),
+                    module_name: op[..i].to_string(),
+                    function_name: op[i + 1..].to_string(),
+                }
+            } else {
+                bail!("Invalid channel op {}", op)
+            }
+        }
+    };
+    Ok(channel_op)
+}
+
 fn _parse_bool(para: &str) -> Result<bool> {
     Ok(para.to_lowercase().parse::<bool>()?)
 }
@@ -1,17 +1,23 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Error, Result};
+use futures01::Stream;
 use grpcio::{ChannelBuilder, Environment};
-use node_proto::proto::node::NodeClient as GrpcNodeClient;
+use node_proto::proto::node::{
+    ListChannelTransactionsResponse, NodeClient as GrpcNodeClient, Payment,
+};
 use node_proto::{
     AddInvoiceRequest, AddInvoiceResponse, ChannelBalanceRequest, ChannelBalanceResponse,
     ChannelTransactionProposalRequest, DeployModuleRequest, DeployModuleResponse, DepositRequest,
     DepositResponse, EmptyResponse, ExecuteScriptRequest, ExecuteScriptResponse,
     GetChannelTransactionProposalResponse, InstallChannelScriptPackageRequest,
-    InstallChannelScriptPackageResponse, OpenChannelRequest, OpenChannelResponse, PayRequest,
-    PayResponse, PaymentRequest, WithdrawRequest, WithdrawResponse,
+    InstallChannelScriptPackageResponse, ListChannelTransactionsRequest, ListPaymentsRequest,
+    OpenChannelRequest, OpenChannelResponse, PayRequest, PayResponse, PaymentRequest,
+    SendDappMessageRequest, SendDappMessageResponse, SetFeePolicyRequest,
+    SubscribeDappMessagesRequest, TrackPaymentRequest, WithdrawRequest, WithdrawResponse,
 };
+use sgtypes::message::InboundDappMessage;
 use std::convert::TryFrom;
 use std::sync::Arc;
 
@@ -137,4 +143,73 @@ impl NodeClient {
             Err(err) => bail!("GRPC error: {}", err),
         }
     }
+
+    pub fn set_fee_policy(&self, request: SetFeePolicyRequest) -> Result<EmptyResponse> {
+        let proto_request = request.into();
+        match self.client.set_fee_policy(&proto_request) {
+            Ok(proto_response) => Ok(EmptyResponse::try_from(proto_response)?),
+            Err(err) => bail!("GRPC error: {}", err),
+        }
+    }
+
+    pub fn list_channel_transactions(
+        &self,
+        request: ListChannelTransactionsRequest,
+    ) -> Result<ListChannelTransactionsResponse> {
+        let proto_request = request.into();
+        match self.client.list_channel_transactions(&proto_request) {
+            Ok(proto_response) => Ok(proto_response),
+            Err(err) => bail!("GRPC error: {}", err),
+        }
+    }
+
+    /// Return payments streamed by node, the iterator blocks on the next payment.
+    pub fn list_payments(
+        &self,
+        request: ListPaymentsRequest,
+    ) -> Result<impl Iterator<Item = Result<Payment>>> {
+        let proto_request = request.into();
+        match self.client.list_payments(&proto_request) {
+            Ok(stream) => Ok(stream.wait().map(|payment| payment.map_err(Error::from))),
+            Err(err) => bail!("GRPC error: {}", err),
+        }
+    }
+
+    /// Return updates of a payment until it succeeded or failed,
+    /// the iterator blocks on the next update.
+    pub fn track_payment(
+        &self,
+        request: TrackPaymentRequest,
+    ) -> Result<impl Iterator<Item = Result<Payment>>> {
+        let proto_request = request.into();
+        match self.client.track_payment(&proto_request) {
+            Ok(stream) => Ok(stream.wait().map(|payment| payment.map_err(Error::from))),
+            Err(err) => bail!("GRPC error: {}", err),
+        }
+    }
+
+    pub fn send_dapp_message(
+        &self,
+        request: SendDappMessageRequest,
+    ) -> Result<SendDappMessageResponse> {
+        let proto_request = request.into();
+        match self.client.send_dapp_message(&proto_request) {
+            Ok(proto_response) => Ok(SendDappMessageResponse::try_from(proto_response)?),
+            Err(err) => bail!("GRPC error: {}", err),
+        }
+    }
+
+    /// Return dapp messages received by node, the iterator blocks on the next message.
+    pub fn subscribe_dapp_messages(
+        &self,
+        request: SubscribeDappMessagesRequest,
+    ) -> Result<impl Iterator<Item = Result<InboundDappMessage>>> {
+        let proto_request = request.into();
+        match self.client.subscribe_dapp_messages(&proto_request) {
+            Ok(stream) => Ok(stream
+                .wait()
+                .map(|message| InboundDappMessage::try_from(message.map_err(Error::from)?))),
+            Err(err) => bail!("GRPC error: {}", err),
+        }
+    }
 }
@@ -0,0 +1,178 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::get_unix_ts;
+use anyhow::{Error, Result};
+use futures::lock::Mutex;
+use libra_crypto::HashValue;
+use libra_logger::prelude::*;
+use libra_types::account_address::AccountAddress;
+use sg_config::config::{ApprovalPolicyConfig, ApprovalRuleConfig};
+use sgstorage::{approval_store::ApprovalStore, channel_db::ChannelDB};
+use sgtypes::approval::{
+    ApprovalAuditRecord, ApprovalDecision, ApprovalPolicy, ApprovalRule, ProposalFacts,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+const DAY_IN_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+impl TryFrom<&ApprovalRuleConfig> for ApprovalRule {
+    type Error = Error;
+
+    fn try_from(config: &ApprovalRuleConfig) -> Result<Self> {
+        Ok(Self {
+            decision: config.decision.parse()?,
+            package: config.package.clone(),
+            script: config.script.clone(),
+            peer: config
+                .peer
+                .as_ref()
+                .map(|peer| AccountAddress::from_hex_literal(peer))
+                .transpose()?,
+            asset: config.asset.clone(),
+            min_amount: config.min_amount,
+            max_amount: config.max_amount,
+            daily_volume: config.daily_volume,
+        })
+    }
+}
+
+impl TryFrom<&ApprovalPolicyConfig> for ApprovalPolicy {
+    type Error = Error;
+
+    fn try_from(config: &ApprovalPolicyConfig) -> Result<Self> {
+        let rules = config
+            .rules
+            .iter()
+            .map(ApprovalRule::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(rules, config.default_decision.parse()?))
+    }
+}
+
+/// Amount approved by every rule in a day, by rule id.
+struct DailyVolumes {
+    day: u64,
+    /// approved proposals, persisted next to the audit log.
+    volumes: BTreeMap<HashValue, u64>,
+    /// approved by the policy, but not signed by the wallet yet, request id -> (rule id, amount).
+    reserved: HashMap<HashValue, (HashValue, u64)>,
+}
+
+impl DailyVolumes {
+    /// Volume of every rule which counts in decisions, including reserved ones.
+    fn in_use(&self) -> BTreeMap<HashValue, u64> {
+        let mut volumes = self.volumes.clone();
+        for (rule, amount) in self.reserved.values() {
+            *volumes.entry(*rule).or_insert(0) += amount;
+        }
+        volumes
+    }
+}
+
+/// Decide incoming proposals which need approval by the policy,
+/// and keep every decision with its reason for audit.
+#[derive(Clone)]
+pub struct ApprovalManager {
+    policy: Arc<ApprovalPolicy>,
+    store: ApprovalStore<ChannelDB>,
+    // the lock also serializes appends of audit records.
+    volumes: Arc<Mutex<DailyVolumes>>,
+}
+
+impl ApprovalManager {
+    pub fn new(policy: ApprovalPolicy, store: ApprovalStore<ChannelDB>) -> Self {
+        let volumes = DailyVolumes {
+            day: 0,
+            volumes: BTreeMap::new(),
+            reserved: HashMap::new(),
+        };
+        Self {
+            policy: Arc::new(policy),
+            store,
+            volumes: Arc::new(Mutex::new(volumes)),
+        }
+    }
+
+    /// Policy of the old `auto_approve` switch, approve or ask for all proposals.
+    pub fn auto_approve_policy(auto_approve: bool) -> ApprovalPolicy {
+        let decision = if auto_approve {
+            ApprovalDecision::Approve
+        } else {
+            ApprovalDecision::Ask
+        };
+        ApprovalPolicy::new(vec![], decision)
+    }
+
+    /// Decide proposal `request_id` in channel `channel_address` by `facts`.
+    /// Approved amount is reserved in the daily volume of the matched rule, until the proposal
+    /// is signed and `confirm`ed, or `release`d if it fails.
+    pub async fn decide(
+        &self,
+        request_id: HashValue,
+        channel_address: AccountAddress,
+        facts: &ProposalFacts,
+    ) -> Result<(ApprovalDecision, String)> {
+        let now = get_unix_ts();
+        let mut volumes = self.volumes.lock().await;
+        self.roll_to(&mut volumes, now / DAY_IN_MILLIS)?;
+        let (decision, rule, reason) = self.policy.decide(facts, &volumes.in_use());
+        if let (ApprovalDecision::Approve, Some(rule)) = (decision, rule) {
+            let rule = &self.policy.rules[rule];
+            let amount = facts.amount_of(rule.asset.as_ref().map(String::as_str));
+            volumes.reserved.insert(request_id, (rule.id(), amount));
+        }
+        info!(
+            "{} proposal {} from {}, {}",
+            decision, request_id, facts.peer, reason
+        );
+        self.store.append_audit(&ApprovalAuditRecord {
+            request_id,
+            peer: facts.peer,
+            channel_address,
+            operator: facts.operator.to_string(),
+            amount: facts.amount,
+            decision,
+            timestamp: now,
+            reason: reason.clone(),
+        })?;
+        Ok((decision, reason))
+    }
+
+    /// Count the reserved amount of approved proposal `request_id`, after the wallet signs it.
+    pub async fn confirm(&self, request_id: &HashValue) -> Result<()> {
+        let mut volumes = self.volumes.lock().await;
+        if let Some((rule, amount)) = volumes.reserved.remove(request_id) {
+            self.roll_to(&mut volumes, get_unix_ts() / DAY_IN_MILLIS)?;
+            *volumes.volumes.entry(rule).or_insert(0) += amount;
+            self.store.put_volumes(volumes.day, &volumes.volumes)?;
+        }
+        Ok(())
+    }
+
+    /// Give back the reserved amount of approved proposal `request_id`, which fails to be signed.
+    pub async fn release(&self, request_id: &HashValue) {
+        self.volumes.lock().await.reserved.remove(request_id);
+    }
+
+    /// Load volumes of `day` if it's not the current day.
+    fn roll_to(&self, volumes: &mut DailyVolumes, day: u64) -> Result<()> {
+        if volumes.day != day {
+            let loaded = self.store.get_volumes(day)?;
+            volumes.day = day;
+            volumes.volumes = loaded;
+        }
+        Ok(())
+    }
+
+    /// Return audit records starting from `start_index`, in the order decisions are made.
+    pub fn list_audits(
+        &self,
+        start_index: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, ApprovalAuditRecord)>> {
+        self.store.list_audits(start_index, limit)
+    }
+}
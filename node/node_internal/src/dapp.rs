@@ -0,0 +1,152 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use futures::{
+    channel::{mpsc, oneshot},
+    lock::Mutex,
+};
+use libra_crypto::HashValue;
+use libra_logger::prelude::*;
+use libra_types::account_address::AccountAddress;
+use sgtypes::message::{DappMessage, DappMessageReceipt, InboundDappMessage};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// max size of a dapp message payload, in bytes.
+pub const MAX_DAPP_PAYLOAD_SIZE: usize = 64 * 1024;
+
+/// subscriber of dapp messages, `topic` is None if it watches all topics.
+struct Subscriber {
+    topic: Option<String>,
+    sender: mpsc::UnboundedSender<InboundDappMessage>,
+}
+
+impl Subscriber {
+    fn matches(&self, message: &DappMessage) -> bool {
+        match &self.topic {
+            Some(topic) => topic == &message.topic,
+            None => true,
+        }
+    }
+}
+
+struct Inner {
+    subscribers: Vec<Subscriber>,
+    /// id of sent message -> waiter of its receipt.
+    receipts: HashMap<HashValue, oneshot::Sender<DappMessageReceipt>>,
+}
+
+/// Route dapp messages received from peers to subscribers by topic,
+/// and match receipts of sent messages to their senders.
+#[derive(Clone)]
+pub struct DappMessageManager {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl DappMessageManager {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                subscribers: vec![],
+                receipts: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Subscribe messages of `topic`, or of all topics if it's None.
+    pub async fn subscribe(
+        &self,
+        topic: Option<String>,
+    ) -> mpsc::UnboundedReceiver<InboundDappMessage> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.inner
+            .lock()
+            .await
+            .subscribers
+            .push(Subscriber { topic, sender });
+        receiver
+    }
+
+    /// Deliver `message` from `sender` to subscribers of its topic.
+    /// Return false if no one is listening on the topic.
+    pub async fn deliver(&self, sender: AccountAddress, message: DappMessage) -> bool {
+        let mut inner = self.inner.lock().await;
+        // drop subscribers gone away.
+        inner
+            .subscribers
+            .retain(|subscriber| !subscriber.sender.is_closed());
+        let mut delivered = false;
+        for subscriber in inner.subscribers.iter() {
+            if subscriber.matches(&message) {
+                let inbound = InboundDappMessage::new(sender, message.clone());
+                delivered |= subscriber.sender.unbounded_send(inbound).is_ok();
+            }
+        }
+        delivered
+    }
+
+    /// Wait for receipt of message `id`, it should be called before the message is sent.
+    pub async fn wait_receipt(&self, id: HashValue) -> oneshot::Receiver<DappMessageReceipt> {
+        let (sender, receiver) = oneshot::channel();
+        self.inner.lock().await.receipts.insert(id, sender);
+        receiver
+    }
+
+    /// Stop waiting for receipt of message `id`.
+    pub async fn cancel_receipt(&self, id: &HashValue) {
+        self.inner.lock().await.receipts.remove(id);
+    }
+
+    /// A receipt arrives, it's a no-op if no one is waiting for it.
+    pub async fn receipt(&self, receipt: DappMessageReceipt) {
+        match self.inner.lock().await.receipts.remove(&receipt.id) {
+            Some(waiter) => {
+                let _ = waiter.send(receipt);
+            }
+            None => debug!("no one waits receipt of dapp message {}", receipt.id),
+        }
+    }
+}
+
+#[test]
+fn test_route_dapp_messages() {
+    use futures::StreamExt;
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let mgr = DappMessageManager::new();
+        let peer = AccountAddress::random();
+        let mut game = mgr.subscribe(Some("game".to_string())).await;
+        let mut all = mgr.subscribe(None).await;
+
+        let move_msg = DappMessage::new(HashValue::random(), "game".to_string(), vec![1], true);
+        assert!(mgr.deliver(peer, move_msg.clone()).await);
+        let chat_msg = DappMessage::new(HashValue::random(), "chat".to_string(), vec![2], false);
+        assert!(mgr.deliver(peer, chat_msg.clone()).await);
+
+        assert_eq!(
+            Some(InboundDappMessage::new(peer, move_msg.clone())),
+            game.next().await
+        );
+        let received: Vec<InboundDappMessage> = all.by_ref().take(2).collect().await;
+        assert_eq!(
+            vec![
+                InboundDappMessage::new(peer, move_msg),
+                InboundDappMessage::new(peer, chat_msg.clone())
+            ],
+            received
+        );
+
+        // no one listens on chat once both subscribers are gone.
+        drop(game);
+        drop(all);
+        assert!(!mgr.deliver(peer, chat_msg).await);
+
+        let id = HashValue::random();
+        let waiter = mgr.wait_receipt(id).await;
+        mgr.receipt(DappMessageReceipt::new(HashValue::random(), true))
+            .await;
+        mgr.receipt(DappMessageReceipt::new(id, true)).await;
+        assert_eq!(DappMessageReceipt::new(id, true), waiter.await.unwrap());
+    });
+}
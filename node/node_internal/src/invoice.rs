@@ -2,165 +2,785 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::get_unix_ts;
-use anyhow::{Error, Result};
+use anyhow::{bail, ensure, Result};
 use futures::lock::Mutex;
-use hex;
 use libra_crypto::HashValue;
 use libra_logger::prelude::*;
-use libra_types::account_address::{AccountAddress, ADDRESS_LENGTH};
-use rand::prelude::*;
-use std::collections::HashMap;
-use std::convert::{From, TryFrom};
+use libra_types::account_address::AccountAddress;
+use rand::rngs::OsRng;
+use sgstorage::{channel_db::ChannelDB, invoice_store::InvoiceStore};
+use sgtypes::invoice::{Invoice, InvoiceRecord, InvoiceState, RouteHint, SignedInvoice};
+use sgwallet::signer::Signer;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
-#[derive(Clone)]
-pub struct InvoiceManager {
-    r_hash_map: Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>,
-    r_hash_previous_hop_map: Arc<Mutex<HashMap<Vec<u8>, AccountAddress>>>,
+/// default expiry of invoice, in milliseconds.
+pub const DEFAULT_INVOICE_EXPIRY: u64 = 60 * 60 * 1000;
+/// shards of a multi-path payment should all arrive in this duration, in milliseconds.
+pub const MULTI_PATH_TIMEOUT: u64 = 60 * 1000;
+/// default min timeout of the htlc received by the last hop, in blocks.
+pub const DEFAULT_MIN_FINAL_TIMEOUT: u64 = 100;
+
+/// Options of a new invoice, zero values mean defaults.
+#[derive(Clone, Debug, Default)]
+pub struct InvoiceOptions {
+    pub description: String,
+    /// seconds before the invoice expires.
+    pub expiry: u64,
+    /// min timeout in blocks of the htlc received by the last hop.
+    pub min_final_timeout: u64,
+    pub route_hints: Vec<RouteHint>,
+    /// preimage chosen by caller, e.g. hodl invoice. It's drawn from OS random source if None.
+    pub preimage: Option<Vec<u8>>,
 }
 
+/// Htlc shard of a multi-path payment, identified by the id of the channel request carrying it,
+/// so that several shards can come from the same payer.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Invoice {
-    pub r_hash: Vec<u8>,
+pub struct PaymentShard {
+    pub request_id: HashValue,
+    pub payer: AccountAddress,
     pub amount: u64,
-    pub receiver: AccountAddress,
 }
 
-impl Invoice {
-    fn as_vec(&self) -> Vec<u8> {
-        let mut result = Vec::new();
-        result.extend_from_slice(&self.receiver.to_vec());
-        result.extend_from_slice(&self.amount.to_be_bytes());
-        result.extend_from_slice(&self.r_hash);
-        result
+impl PaymentShard {
+    pub fn new(request_id: HashValue, payer: AccountAddress, amount: u64) -> Self {
+        Self {
+            request_id,
+            payer,
+            amount,
+        }
     }
 }
 
-impl TryFrom<Vec<u8>> for Invoice {
-    type Error = Error;
+/// Invoice paid by htlc payments, the preimage should be sent back to the payer of every shard.
+#[derive(Clone, Debug)]
+pub struct AcceptedPayment {
+    pub preimage: Vec<u8>,
+    pub shards: Vec<PaymentShard>,
+}
 
-    fn try_from(value: Vec<u8>) -> Result<Self> {
-        let receiver = AccountAddress::try_from(&value[0..ADDRESS_LENGTH])?;
-        let mut amount_bytes: [u8; 8] = [0; 8];
-        amount_bytes.copy_from_slice(&value[ADDRESS_LENGTH..ADDRESS_LENGTH + 8]);
-        let amount = u64::from_be_bytes(amount_bytes);
-        let r_hash = Vec::from(&value[ADDRESS_LENGTH + 8..]);
-        Ok(Self {
-            receiver,
-            amount,
-            r_hash,
-        })
-    }
+/// Result of `InvoiceManager::sweep_expired`.
+#[derive(Clone, Debug, Default)]
+pub struct SweptInvoices {
+    /// r_hash of invoices expired.
+    pub expired: Vec<HashValue>,
+    /// shards which can't make up a paid invoice, they should be failed back to their payers.
+    pub failed_shards: Vec<PaymentShard>,
 }
 
-impl From<Invoice> for Vec<u8> {
-    fn from(value: Invoice) -> Self {
-        value.as_vec()
-    }
+/// htlc shards received for an invoice, which are not enough to pay it yet.
+struct PartialPayment {
+    first_seen: u64,
+    shards: Vec<PaymentShard>,
 }
 
-impl TryFrom<String> for Invoice {
-    type Error = Error;
+impl PartialPayment {
+    fn new(first_seen: u64) -> Self {
+        Self {
+            first_seen,
+            shards: vec![],
+        }
+    }
 
-    fn try_from(value: String) -> Result<Self> {
-        let bytes_value = hex::decode(value)?;
-        Invoice::try_from(bytes_value)
+    fn is_timeout(&self, now: u64) -> bool {
+        now >= self.first_seen.saturating_add(MULTI_PATH_TIMEOUT)
     }
 }
 
-impl From<Invoice> for String {
-    fn from(value: Invoice) -> Self {
-        hex::encode_upper(&value.as_vec())
+#[derive(Default)]
+struct Partials {
+    payments: HashMap<HashValue, PartialPayment>,
+    /// shards refused or dropped since last sweep.
+    failed: Vec<PaymentShard>,
+}
+
+impl Partials {
+    fn fail(&mut self, r_hash: &HashValue) {
+        if let Some(partial) = self.payments.remove(r_hash) {
+            warn!(
+                "partial payment of invoice {} fails, fail back {} shards",
+                r_hash,
+                partial.shards.len()
+            );
+            self.failed.extend(partial.shards);
+        }
     }
 }
 
+/// Htlcs to claim from a payer.
+#[derive(Default)]
+struct Claims {
+    /// request id and preimage of the claim waiting for the payer to sign.
+    in_flight: Option<(HashValue, Vec<u8>)>,
+    /// preimages of htlcs to claim after it.
+    queued: VecDeque<Vec<u8>>,
+}
+
+#[derive(Clone)]
+pub struct InvoiceManager {
+    store: InvoiceStore<ChannelDB>,
+    signer: Arc<dyn Signer>,
+    // partial payments are kept in memory only, the lock also serializes the state
+    // transitions of invoices.
+    partials: Arc<Mutex<Partials>>,
+    claims: Arc<Mutex<HashMap<AccountAddress, Claims>>>,
+}
+
 impl InvoiceManager {
-    pub fn new() -> Self {
+    pub fn new(store: InvoiceStore<ChannelDB>, signer: Arc<dyn Signer>) -> Self {
         Self {
-            r_hash_map: Arc::new(Mutex::new(HashMap::new())),
-            r_hash_previous_hop_map: Arc::new(Mutex::new(HashMap::new())),
+            store,
+            signer,
+            partials: Arc::new(Mutex::new(Partials::default())),
+            claims: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    pub async fn new_invoice(&self, amount: u64, receiver: AccountAddress) -> Invoice {
-        let mut rng: StdRng = SeedableRng::seed_from_u64(get_unix_ts());
-        let preimage = HashValue::random_with_rng(&mut rng).to_vec();
-        let r_hash = HashValue::from_sha3_256(preimage.as_slice()).to_vec();
-
-        info!(
-            "preimage is {},r_hash is {}",
-            hex::encode(preimage.clone()),
-            hex::encode(r_hash.clone())
+    /// Create a new invoice signed by receiver's key.
+    pub async fn new_invoice(&self, amount: u64, options: InvoiceOptions) -> Result<SignedInvoice> {
+        let preimage = match options.preimage {
+            Some(preimage) => preimage,
+            None => {
+                let mut rng = OsRng::new()?;
+                HashValue::random_with_rng(&mut rng).to_vec()
+            }
+        };
+        ensure!(
+            preimage.len() == HashValue::LENGTH,
+            "preimage should be {} bytes, got {}",
+            HashValue::LENGTH,
+            preimage.len()
         );
-        self.r_hash_map
-            .lock()
-            .await
-            .insert(r_hash.clone(), preimage.clone());
-        Invoice {
-            r_hash,
+        let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+        info!("new invoice, r_hash is {}", r_hash);
+
+        let expiry = match options.expiry {
+            0 => DEFAULT_INVOICE_EXPIRY,
+            expiry => expiry.saturating_mul(1000),
+        };
+        let min_final_timeout = match options.min_final_timeout {
+            0 => DEFAULT_MIN_FINAL_TIMEOUT,
+            timeout => timeout,
+        };
+        let invoice = Invoice {
+            r_hash: r_hash.to_vec(),
             amount,
-            receiver,
-        }
+            receiver: AccountAddress::from_public_key(&self.signer.public_key()),
+            timestamp: get_unix_ts(),
+            expiry,
+            description: options.description,
+            min_final_timeout,
+            route_hints: options.route_hints,
+        };
+        let signature = self.signer.sign_invoice(&invoice)?;
+        let invoice = invoice.with_signature(self.signer.public_key(), signature);
+
+        let _guard = self.partials.lock().await;
+        ensure!(
+            self.store.get_invoice(&r_hash)?.is_none(),
+            "invoice with r_hash {} already exists",
+            r_hash
+        );
+        self.store
+            .save_invoice(&InvoiceRecord::new(r_hash, preimage, invoice.clone()))?;
+        Ok(invoice)
     }
 
-    pub async fn get_preimage(&self, r_hash: &HashValue) -> Option<Vec<u8>> {
-        match self.r_hash_map.lock().await.get(&r_hash.to_vec()) {
-            Some(v) => {
-                let mut result = Vec::new();
-                result.extend_from_slice(v);
-                return Some(result);
-            }
+    pub async fn get_invoice(&self, r_hash: &HashValue) -> Result<Option<InvoiceRecord>> {
+        self.store.get_invoice(r_hash)
+    }
+
+    pub async fn list_invoices(&self) -> Result<Vec<InvoiceRecord>> {
+        self.store.list_invoices()
+    }
+
+    /// Called when a htlc payment locked by `r_hash` arrives in request `request_id` from `payer`.
+    /// A payment less than the invoice amount is kept as a shard of multi-path payment,
+    /// until shards add up to the invoice amount or `MULTI_PATH_TIMEOUT` is reached.
+    /// Return the preimage and all shards once the invoice is paid, and mark it as accepted.
+    /// Shards refused or timeout are failed back by the next `sweep_expired`.
+    pub async fn accept_payment(
+        &self,
+        r_hash: &HashValue,
+        request_id: HashValue,
+        payer: AccountAddress,
+        amount: u64,
+        timeout: u64,
+    ) -> Result<Option<AcceptedPayment>> {
+        let mut partials = self.partials.lock().await;
+        let shard = PaymentShard::new(request_id, payer, amount);
+        let mut invoice = match self.store.get_invoice(r_hash)? {
+            Some(invoice) => invoice,
             None => {
-                return None;
+                warn!("invoice {} not exists, refuse to accept", r_hash);
+                partials.failed.push(shard);
+                return Ok(None);
             }
         };
+        let now = get_unix_ts();
+        if invoice.is_expired(now) {
+            invoice.set_state(InvoiceState::Expired);
+            self.store.save_invoice(&invoice)?;
+        }
+        if invoice.state() != InvoiceState::Open {
+            warn!(
+                "invoice {} is {:?}, refuse to accept",
+                r_hash,
+                invoice.state()
+            );
+            partials.fail(r_hash);
+            partials.failed.push(shard);
+            return Ok(None);
+        }
+        if timeout < invoice.min_final_timeout() {
+            warn!(
+                "payment timeout {} is less than min final timeout {}",
+                timeout,
+                invoice.min_final_timeout()
+            );
+            partials.failed.push(shard);
+            return Ok(None);
+        }
+
+        if partials
+            .payments
+            .get(r_hash)
+            .map(|partial| partial.is_timeout(now))
+            .unwrap_or(false)
+        {
+            partials.fail(r_hash);
+        }
+        let partial = partials
+            .payments
+            .entry(*r_hash)
+            .or_insert_with(|| PartialPayment::new(now));
+        if partial.shards.iter().any(|s| s.request_id == request_id) {
+            warn!("duplicate shard {} of invoice {}", request_id, r_hash);
+            return Ok(None);
+        }
+        partial.shards.push(shard);
+        let received = partial
+            .shards
+            .iter()
+            .fold(0u64, |sum, s| sum.saturating_add(s.amount));
+        if received < invoice.amount() {
+            info!(
+                "invoice {} received {} of {}, wait for more shards",
+                r_hash,
+                received,
+                invoice.amount()
+            );
+            return Ok(None);
+        }
+
+        let partial = partials
+            .payments
+            .remove(r_hash)
+            .expect("partial payment should exist");
+        invoice.set_state(InvoiceState::Accepted);
+        self.store.save_invoice(&invoice)?;
+        Ok(Some(AcceptedPayment {
+            preimage: invoice.preimage().to_vec(),
+            shards: partial.shards,
+        }))
     }
 
-    pub async fn add_previous_hop(&self, r_hash: HashValue, previous_addr: AccountAddress) {
-        self.r_hash_previous_hop_map
-            .lock()
+    /// Mark invoice as settled after preimage is released.
+    pub async fn settle(&self, r_hash: &HashValue) -> Result<()> {
+        self.transit(r_hash, InvoiceState::Accepted, InvoiceState::Settled)
             .await
-            .insert(r_hash.to_vec(), previous_addr);
     }
 
-    pub async fn get_previous_hop(&self, preimage: Vec<u8>) -> Option<AccountAddress> {
-        let r_hash = HashValue::from_sha3_256(preimage.as_slice()).to_vec();
-
-        match self
-            .r_hash_previous_hop_map
-            .lock()
+    /// Cancel an open invoice, so that it can not be paid anymore.
+    pub async fn cancel_invoice(&self, r_hash: &HashValue) -> Result<()> {
+        self.transit(r_hash, InvoiceState::Open, InvoiceState::Canceled)
             .await
-            .get(&r_hash.to_vec())
-        {
-            Some(v) => {
-                return Some(v.clone());
+    }
+
+    /// Expire all open invoices which reach their expiry at `now`, and fail the partial
+    /// payments which reach `MULTI_PATH_TIMEOUT`. Return the r_hash of expired invoices,
+    /// and the shards to fail back.
+    pub async fn sweep_expired(&self, now: u64) -> Result<SweptInvoices> {
+        let mut partials = self.partials.lock().await;
+        let timeout = partials
+            .payments
+            .iter()
+            .filter(|(_, partial)| partial.is_timeout(now))
+            .map(|(r_hash, _)| *r_hash)
+            .collect::<Vec<_>>();
+        for r_hash in timeout {
+            partials.fail(&r_hash);
+        }
+        let mut expired = vec![];
+        for mut invoice in self.store.list_invoices()? {
+            if invoice.is_expired(now) {
+                invoice.set_state(InvoiceState::Expired);
+                self.store.save_invoice(&invoice)?;
+                partials.fail(invoice.r_hash());
+                expired.push(*invoice.r_hash());
             }
-            None => {
-                return None;
+        }
+        Ok(SweptInvoices {
+            expired,
+            failed_shards: std::mem::replace(&mut partials.failed, vec![]),
+        })
+    }
+
+    /// Record `previous_addr` as the previous hop of an htlc locked by `r_hash` we forwarded.
+    /// Shards of a multi-path payment may pass through us from several previous hops, or in
+    /// several htlcs of the same one, each of them is recorded.
+    pub async fn add_previous_hop(
+        &self,
+        r_hash: HashValue,
+        previous_addr: AccountAddress,
+    ) -> Result<()> {
+        let _guard = self.partials.lock().await;
+        let mut previous_hops = self.store.get_previous_hops(&r_hash)?;
+        previous_hops.push(previous_addr);
+        self.store.save_previous_hops(&r_hash, &previous_hops)
+    }
+
+    /// Take previous hops of all htlcs locked by hash of `preimage`, to claim them by the
+    /// preimage. They are returned once.
+    pub async fn take_previous_hops(&self, preimage: Vec<u8>) -> Result<Vec<AccountAddress>> {
+        let _guard = self.partials.lock().await;
+        let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+        let previous_hops = self.store.get_previous_hops(&r_hash)?;
+        if !previous_hops.is_empty() {
+            self.store.delete_previous_hops(&r_hash)?;
+        }
+        Ok(previous_hops)
+    }
+
+    /// Queue claiming an htlc from `payer` by `preimage`.
+    pub async fn add_claim(&self, payer: AccountAddress, preimage: Vec<u8>) {
+        let mut claims = self.claims.lock().await;
+        claims.entry(payer).or_default().queued.push_back(preimage);
+    }
+
+    /// Preimage of the next htlc to claim from `payer`, if there is no claim of it in flight.
+    /// The channel holds one pending proposal at a time, so htlcs in it are claimed one by one.
+    pub async fn next_claim(&self, payer: AccountAddress) -> Option<Vec<u8>> {
+        let mut claims = self.claims.lock().await;
+        let claim = claims.get_mut(&payer)?;
+        if claim.in_flight.is_some() {
+            return None;
+        }
+        let preimage = claim.queued.pop_front();
+        if preimage.is_none() {
+            claims.remove(&payer);
+        }
+        preimage
+    }
+
+    /// The claim by `preimage` is proposed to `payer` in request `request_id`.
+    pub async fn claim_sent(
+        &self,
+        payer: AccountAddress,
+        request_id: HashValue,
+        preimage: Vec<u8>,
+    ) {
+        let mut claims = self.claims.lock().await;
+        claims.entry(payer).or_default().in_flight = Some((request_id, preimage));
+    }
+
+    /// The claim by `preimage` can't be proposed now, retry it after next txn with `payer`.
+    pub async fn claim_failed(&self, payer: AccountAddress, preimage: Vec<u8>) {
+        let mut claims = self.claims.lock().await;
+        claims.entry(payer).or_default().queued.push_front(preimage);
+    }
+
+    /// Txn `request_id` with `payer` is applied, so no claim of it is pending any more.
+    /// The claim in flight is done if it's the txn, or else it was dropped and is queued again.
+    pub async fn txn_applied(&self, payer: AccountAddress, request_id: HashValue) {
+        let mut claims = self.claims.lock().await;
+        if let Some(claim) = claims.get_mut(&payer) {
+            if let Some((claim_request_id, preimage)) = claim.in_flight.take() {
+                if claim_request_id != request_id {
+                    claim.queued.push_front(preimage);
+                }
             }
+        }
+    }
+
+    async fn transit(
+        &self,
+        r_hash: &HashValue,
+        from: InvoiceState,
+        to: InvoiceState,
+    ) -> Result<()> {
+        let _guard = self.partials.lock().await;
+        let mut invoice = match self.store.get_invoice(r_hash)? {
+            Some(invoice) => invoice,
+            None => bail!("invoice {} not exists", r_hash),
         };
+        ensure!(
+            invoice.state() == from,
+            "invoice {} is {:?}, expect {:?}",
+            r_hash,
+            invoice.state(),
+            from
+        );
+        invoice.set_state(to);
+        self.store.save_invoice(&invoice)
     }
 }
 
+#[cfg(test)]
+fn new_test_invoice_manager(path: &libra_tools::tempdir::TempPath) -> InvoiceManager {
+    use libra_crypto::{
+        ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+        test_utils::KeyPair,
+        Uniform,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use sgwallet::signer::LocalSigner;
+
+    let mut rng: StdRng = SeedableRng::from_seed([0; 32]);
+    let keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> =
+        Arc::new(KeyPair::generate_for_testing(&mut rng));
+    let owner = AccountAddress::from_public_key(&keypair.public_key);
+    let storage = sgstorage::storage::SgStorage::new(owner, path);
+    let channel_db = ChannelDB::new(owner, Arc::new(storage));
+    InvoiceManager::new(
+        InvoiceStore::new(channel_db),
+        Arc::new(LocalSigner::new(keypair)),
+    )
+}
+
 #[test]
 fn test_invoice() {
     use std::convert::TryInto;
 
-    let preimage = HashValue::random().to_vec();
-    let r_hash = HashValue::from_sha3_256(preimage.as_slice()).to_vec();
-    let account_address = AccountAddress::random();
-    let amount = 1000;
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
 
-    let invoice = Invoice {
-        r_hash,
-        amount,
-        receiver: account_address,
-    };
+    rt.block_on(async {
+        let mgr = new_test_invoice_manager(&path);
+        let options = InvoiceOptions {
+            description: "coffee".to_string(),
+            route_hints: vec![RouteHint::new(vec![AccountAddress::random()])],
+            ..Default::default()
+        };
+        let invoice = mgr.new_invoice(1000, options).await.unwrap();
+
+        let invoice_string: String = invoice.clone().into();
+        let invoice_decode: SignedInvoice = invoice_string.try_into().unwrap();
+        assert_eq!(invoice_decode, invoice);
+        assert_eq!(
+            DEFAULT_MIN_FINAL_TIMEOUT,
+            invoice_decode.invoice.min_final_timeout
+        );
+        assert_eq!(DEFAULT_INVOICE_EXPIRY, invoice_decode.invoice.expiry);
+    });
+}
+
+#[test]
+fn test_invoice_preimage_unique() {
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mgr = new_test_invoice_manager(&path);
+        let mut r_hashes = std::collections::HashSet::new();
+        for _ in 0..16 {
+            let invoice = mgr
+                .new_invoice(1000, InvoiceOptions::default())
+                .await
+                .unwrap();
+            assert!(r_hashes.insert(invoice.invoice.r_hash));
+        }
+    });
+}
+
+#[test]
+fn test_invoice_with_preimage() {
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mgr = new_test_invoice_manager(&path);
+        let preimage = HashValue::random().to_vec();
+        let options = InvoiceOptions {
+            preimage: Some(preimage.clone()),
+            ..Default::default()
+        };
+        let invoice = mgr.new_invoice(1000, options.clone()).await.unwrap();
+        let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+        assert_eq!(r_hash.to_vec(), invoice.invoice.r_hash);
+        let accepted = mgr
+            .accept_payment(
+                &r_hash,
+                HashValue::random(),
+                AccountAddress::random(),
+                1000,
+                DEFAULT_MIN_FINAL_TIMEOUT,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(preimage, accepted.preimage);
+
+        // same preimage can not be reused.
+        assert!(mgr.new_invoice(1000, options).await.is_err());
+        let options = InvoiceOptions {
+            preimage: Some(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        assert!(mgr.new_invoice(1000, options).await.is_err());
+    });
+}
+
+#[test]
+fn test_invoice_lifecycle() {
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let r_hash = {
+            let mgr = new_test_invoice_manager(&path);
+            let options = InvoiceOptions {
+                description: "coffee".to_string(),
+                ..Default::default()
+            };
+            let invoice = mgr.new_invoice(1000, options).await.unwrap();
+            HashValue::from_slice(&invoice.invoice.r_hash).unwrap()
+        };
+
+        // preimage survives restart.
+        let mgr = new_test_invoice_manager(&path);
+        let record = mgr.get_invoice(&r_hash).await.unwrap().unwrap();
+        assert_eq!(InvoiceState::Open, record.state());
+        assert_eq!("coffee", record.description());
+
+        let timeout = DEFAULT_MIN_FINAL_TIMEOUT;
+        let payer = AccountAddress::random();
+        let refused = PaymentShard::new(HashValue::random(), payer, 1000);
+        assert!(mgr
+            .accept_payment(&r_hash, refused.request_id, payer, 1000, timeout - 1)
+            .await
+            .unwrap()
+            .is_none());
+        let shard = PaymentShard::new(HashValue::random(), payer, 1000);
+        let accepted = mgr
+            .accept_payment(&r_hash, shard.request_id, payer, 1000, timeout)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            r_hash,
+            HashValue::from_sha3_256(accepted.preimage.as_slice())
+        );
+        assert_eq!(vec![shard], accepted.shards);
+        let swept = mgr.sweep_expired(get_unix_ts()).await.unwrap();
+        assert_eq!(vec![refused], swept.failed_shards);
+        mgr.settle(&r_hash).await.unwrap();
+        let record = mgr.get_invoice(&r_hash).await.unwrap().unwrap();
+        assert_eq!(InvoiceState::Settled, record.state());
+        assert!(mgr
+            .accept_payment(
+                &r_hash,
+                HashValue::random(),
+                AccountAddress::random(),
+                1000,
+                timeout
+            )
+            .await
+            .unwrap()
+            .is_none());
+    });
+}
 
-    let invoice_string: String = invoice.clone().into();
-    let invoice_decode: Invoice = invoice_string.try_into().unwrap();
+#[test]
+fn test_invoice_sweep_expired() {
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mgr = new_test_invoice_manager(&path);
+        let options = InvoiceOptions {
+            expiry: 1,
+            ..Default::default()
+        };
+        let invoice = mgr.new_invoice(1000, options).await.unwrap();
+        let r_hash = HashValue::from_slice(&invoice.invoice.r_hash).unwrap();
+
+        assert!(mgr
+            .sweep_expired(get_unix_ts())
+            .await
+            .unwrap()
+            .expired
+            .is_empty());
+        let swept = mgr.sweep_expired(get_unix_ts() + 1000).await.unwrap();
+        assert_eq!(vec![r_hash], swept.expired);
+
+        let record = mgr.get_invoice(&r_hash).await.unwrap().unwrap();
+        assert_eq!(InvoiceState::Expired, record.state());
+        assert!(mgr
+            .accept_payment(
+                &r_hash,
+                HashValue::random(),
+                AccountAddress::random(),
+                1000,
+                DEFAULT_MIN_FINAL_TIMEOUT
+            )
+            .await
+            .unwrap()
+            .is_none());
+        assert!(mgr.cancel_invoice(&r_hash).await.is_err());
+    });
+}
+
+#[test]
+fn test_invoice_multi_path() {
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mgr = new_test_invoice_manager(&path);
+        let invoice = mgr
+            .new_invoice(1000, InvoiceOptions::default())
+            .await
+            .unwrap();
+        let r_hash = HashValue::from_slice(&invoice.invoice.r_hash).unwrap();
+        let timeout = DEFAULT_MIN_FINAL_TIMEOUT;
+        let (payer1, payer2) = (AccountAddress::random(), AccountAddress::random());
+        let shard1 = PaymentShard::new(HashValue::random(), payer1, 400);
+        let shard2 = PaymentShard::new(HashValue::random(), payer1, 300);
+
+        assert!(mgr
+            .accept_payment(&r_hash, shard1.request_id, payer1, 400, timeout)
+            .await
+            .unwrap()
+            .is_none());
+        // the same htlc is counted once.
+        assert!(mgr
+            .accept_payment(&r_hash, shard1.request_id, payer1, 400, timeout)
+            .await
+            .unwrap()
+            .is_none());
+        // same payer can send several shards.
+        assert!(mgr
+            .accept_payment(&r_hash, shard2.request_id, payer1, 300, timeout)
+            .await
+            .unwrap()
+            .is_none());
+
+        // incomplete set is failed back after timeout.
+        let swept = mgr
+            .sweep_expired(get_unix_ts() + MULTI_PATH_TIMEOUT)
+            .await
+            .unwrap();
+        assert!(swept.expired.is_empty());
+        assert_eq!(vec![shard1, shard2], swept.failed_shards);
+        assert!(mgr
+            .sweep_expired(get_unix_ts())
+            .await
+            .unwrap()
+            .failed_shards
+            .is_empty());
 
-    assert_eq!(invoice_decode.receiver, invoice.receiver);
-    assert_eq!(invoice_decode.r_hash, invoice.r_hash);
-    assert_eq!(invoice_decode.amount, invoice.amount);
+        let shard3 = PaymentShard::new(HashValue::random(), payer1, 600);
+        let shard4 = PaymentShard::new(HashValue::random(), payer2, 400);
+        assert!(mgr
+            .accept_payment(&r_hash, shard3.request_id, payer1, 600, timeout)
+            .await
+            .unwrap()
+            .is_none());
+        let record = mgr.get_invoice(&r_hash).await.unwrap().unwrap();
+        assert_eq!(InvoiceState::Open, record.state());
+
+        let accepted = mgr
+            .accept_payment(&r_hash, shard4.request_id, payer2, 400, timeout)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![shard3, shard4], accepted.shards);
+        let record = mgr.get_invoice(&r_hash).await.unwrap().unwrap();
+        assert_eq!(InvoiceState::Accepted, record.state());
+    });
+}
+
+#[test]
+fn test_invoice_claim_shards_of_one_payer() {
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mgr = new_test_invoice_manager(&path);
+        let invoice = mgr
+            .new_invoice(1000, InvoiceOptions::default())
+            .await
+            .unwrap();
+        let r_hash = HashValue::from_slice(&invoice.invoice.r_hash).unwrap();
+        let timeout = DEFAULT_MIN_FINAL_TIMEOUT;
+        let payer = AccountAddress::random();
+        let shard1 = PaymentShard::new(HashValue::random(), payer, 600);
+        let shard2 = PaymentShard::new(HashValue::random(), payer, 400);
+        assert!(mgr
+            .accept_payment(&r_hash, shard1.request_id, payer, 600, timeout)
+            .await
+            .unwrap()
+            .is_none());
+        let accepted = mgr
+            .accept_payment(&r_hash, shard2.request_id, payer, 400, timeout)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![shard1, shard2], accepted.shards);
+        for shard in accepted.shards.iter() {
+            mgr.add_claim(shard.payer, accepted.preimage.clone()).await;
+        }
+
+        // both htlcs are in the same channel, the second is claimed after the first is applied.
+        let preimage = mgr.next_claim(payer).await.unwrap();
+        let claim1 = HashValue::random();
+        mgr.claim_sent(payer, claim1, preimage).await;
+        assert!(mgr.next_claim(payer).await.is_none());
+        mgr.txn_applied(payer, claim1).await;
+        let preimage = mgr.next_claim(payer).await.unwrap();
+        assert_eq!(accepted.preimage, preimage);
+
+        // the claim is queued again if another txn is applied instead of it.
+        mgr.claim_sent(payer, HashValue::random(), preimage).await;
+        mgr.txn_applied(payer, HashValue::random()).await;
+        let preimage = mgr.next_claim(payer).await.unwrap();
+        mgr.claim_failed(payer, preimage).await;
+        let preimage = mgr.next_claim(payer).await.unwrap();
+        let claim2 = HashValue::random();
+        mgr.claim_sent(payer, claim2, preimage).await;
+        mgr.txn_applied(payer, claim2).await;
+        assert!(mgr.next_claim(payer).await.is_none());
+    });
+}
+
+#[test]
+fn test_invoice_previous_hops() {
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let mgr = new_test_invoice_manager(&path);
+        let preimage = HashValue::random().to_vec();
+        let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+        let (hop1, hop2) = (AccountAddress::random(), AccountAddress::random());
+        // shards forwarded from two previous hops, two of them from hop1.
+        for hop in vec![hop1, hop2, hop1] {
+            mgr.add_previous_hop(r_hash, hop).await.unwrap();
+        }
+        assert_eq!(
+            vec![hop1, hop2, hop1],
+            mgr.take_previous_hops(preimage.clone()).await.unwrap()
+        );
+        assert!(mgr.take_previous_hops(preimage).await.unwrap().is_empty());
+    });
 }
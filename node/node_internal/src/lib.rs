@@ -3,10 +3,16 @@
 
 #![recursion_limit = "1024"]
 
-mod invoice;
+pub mod approval;
+pub mod dapp;
+pub mod invoice;
+pub mod negotiation;
 mod message_processor;
+mod multi_path;
 pub mod node;
 mod node_command;
+mod onion;
+pub mod payment;
 
 use std::time::{SystemTime, UNIX_EPOCH};
 
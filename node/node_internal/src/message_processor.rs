@@ -39,7 +39,7 @@ impl<T> Future for MessageFuture<T> {
                         return Ok(Async::Ready(v));
                     }
                     Err(e) => {
-                        return Err(error_translate(e));
+                        return Err(error_translate(&e));
                     }
                 },
                 None => {
@@ -122,7 +122,7 @@ where
     }
 }
 
-fn error_translate(e: Error) -> SgError {
+pub(crate) fn error_translate(e: &Error) -> SgError {
     if let Some(err) = e.downcast_ref::<SgError>() {
         info!("this is a sg error");
         err.clone()
@@ -0,0 +1,120 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use libra_types::account_address::AccountAddress;
+use sgtypes::message::BalanceQueryResponse;
+use std::collections::HashSet;
+
+/// max number of shards a payment can be split into.
+pub const MAX_PAYMENT_SHARDS: usize = 8;
+
+/// Split `amount` into shards over `paths`, better paths should come first.
+/// A shard is what the receiver gets, the sender pays fees of intermediate hops on top of it.
+/// Intermediate hops of the chosen paths are disjoint, so that no channel carries two shards
+/// of one payment at the same time, and every hop forwards the preimage to a unique previous hop.
+/// Return None if the paths can't carry `amount` in `max_shards` shards.
+pub fn split_payment(
+    paths: Vec<Vec<BalanceQueryResponse>>,
+    amount: u64,
+    max_shards: usize,
+) -> Option<Vec<(Vec<BalanceQueryResponse>, u64)>> {
+    let mut used_hops: HashSet<AccountAddress> = HashSet::new();
+    let mut shards = Vec::new();
+    let mut remaining = amount;
+    for path in paths {
+        if remaining == 0 || shards.len() >= max_shards {
+            break;
+        }
+        if path.is_empty() {
+            continue;
+        }
+        let hops: Vec<AccountAddress> = path[1..].iter().map(|hop| hop.local_addr).collect();
+        if hops.iter().any(|hop| used_hops.contains(hop)) {
+            continue;
+        }
+        let shard = std::cmp::min(path_capacity(&path), remaining);
+        if shard == 0 {
+            continue;
+        }
+        used_hops.extend(hops);
+        remaining -= shard;
+        shards.push((path, shard));
+    }
+    if remaining > 0 {
+        return None;
+    }
+    Some(shards)
+}
+
+/// Max amount can be delivered over the path, after intermediate hops take their fees.
+fn path_capacity(path: &[BalanceQueryResponse]) -> u64 {
+    let mut capacity = path[0].local_balance;
+    for hop in &path[1..] {
+        let policy = hop.fee_policy.unwrap_or_default();
+        capacity = std::cmp::min(hop.local_balance, policy.max_forward(capacity));
+    }
+    capacity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sgtypes::fee_policy::FeePolicy;
+
+    fn path(vertexes: &[AccountAddress], balance: u64) -> Vec<BalanceQueryResponse> {
+        vertexes
+            .windows(2)
+            .map(|pair| BalanceQueryResponse::new(pair[0], pair[1], balance, balance, 0, None))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_payment() {
+        let (sender, receiver) = (AccountAddress::random(), AccountAddress::random());
+        let (hop1, hop2) = (AccountAddress::random(), AccountAddress::random());
+
+        let paths = vec![
+            path(&[sender, hop1, receiver], 600),
+            path(&[sender, hop2, receiver], 300),
+            path(&[sender, receiver], 200),
+        ];
+        let shards = split_payment(paths.clone(), 1000, MAX_PAYMENT_SHARDS).unwrap();
+        let amounts: Vec<u64> = shards.iter().map(|(_, amount)| *amount).collect();
+        assert_eq!(vec![600, 300, 100], amounts);
+
+        assert!(split_payment(paths.clone(), 1200, MAX_PAYMENT_SHARDS).is_none());
+        assert!(split_payment(paths, 1000, 2).is_none());
+    }
+
+    #[test]
+    fn test_split_payment_disjoint_hops() {
+        let (sender, receiver) = (AccountAddress::random(), AccountAddress::random());
+        let (hop1, hop2) = (AccountAddress::random(), AccountAddress::random());
+
+        // the second path shares hop1 with the first one.
+        let paths = vec![
+            path(&[sender, hop1, receiver], 600),
+            path(&[sender, hop2, hop1, receiver], 600),
+        ];
+        assert!(split_payment(paths.clone(), 1000, MAX_PAYMENT_SHARDS).is_none());
+        let shards = split_payment(paths, 600, MAX_PAYMENT_SHARDS).unwrap();
+        assert_eq!(1, shards.len());
+    }
+
+    #[test]
+    fn test_split_payment_with_fee() {
+        let (sender, receiver, hop) = (
+            AccountAddress::random(),
+            AccountAddress::random(),
+            AccountAddress::random(),
+        );
+        let mut paid = path(&[sender, hop, receiver], 600);
+        paid[1].fee_policy = Some(FeePolicy::new(100, 0, 10));
+        let paths = vec![paid, path(&[sender, receiver], 600)];
+
+        // hop takes 100 out of the 600 sender can send to it.
+        let shards = split_payment(paths, 1000, MAX_PAYMENT_SHARDS).unwrap();
+        let amounts: Vec<u64> = shards.iter().map(|(_, amount)| *amount).collect();
+        assert_eq!(vec![500, 500], amounts);
+    }
+}
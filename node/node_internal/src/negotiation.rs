@@ -0,0 +1,200 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, Result};
+use futures::{channel::oneshot, lock::Mutex};
+use libra_types::{
+    account_address::AccountAddress, account_config::coin_struct_tag,
+    transaction::TransactionArgument,
+};
+use sgtypes::{
+    channel_transaction::{ChannelOp, ChannelTransaction},
+    message::{RawNegotiateMessage, StructTag},
+    open_channel_policy::OpenChannelPolicy,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// waiter of terms agreed in a negotiation we started.
+type AgreedWaiter = oneshot::Sender<Result<RawNegotiateMessage>>;
+
+struct Inner {
+    policy: OpenChannelPolicy,
+    /// peer -> terms we proposed to it, and waiter of the agreed terms.
+    proposing: HashMap<AccountAddress, (RawNegotiateMessage, AgreedWaiter)>,
+    /// peer -> terms agreed with it, the open txn it proposes later should match them.
+    agreed: HashMap<AccountAddress, RawNegotiateMessage>,
+}
+
+/// Keep state of open channel negotiations with peers,
+/// as the opener who waits for terms to be agreed, or as the receiver who waits for the open txn.
+#[derive(Clone)]
+pub struct OpenNegotiationManager {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl OpenNegotiationManager {
+    pub fn new(policy: OpenChannelPolicy) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                policy,
+                proposing: HashMap::new(),
+                agreed: HashMap::new(),
+            })),
+        }
+    }
+
+    pub async fn policy(&self) -> OpenChannelPolicy {
+        self.inner.lock().await.policy.clone()
+    }
+
+    pub async fn set_policy(&self, policy: OpenChannelPolicy) {
+        self.inner.lock().await.policy = policy;
+    }
+
+    /// Start to negotiate `terms` with `peer`, return the waiter of agreed terms.
+    /// There is at most one negotiation with a peer at a time.
+    pub async fn propose(
+        &self,
+        peer: AccountAddress,
+        terms: RawNegotiateMessage,
+    ) -> Result<oneshot::Receiver<Result<RawNegotiateMessage>>> {
+        let mut inner = self.inner.lock().await;
+        if inner.proposing.contains_key(&peer) {
+            bail!("open channel with {} is being negotiated", peer);
+        }
+        let (sender, receiver) = oneshot::channel();
+        inner.proposing.insert(peer, (terms, sender));
+        Ok(receiver)
+    }
+
+    /// Terms we proposed to `peer`, None if we are not negotiating with it.
+    pub async fn proposed(&self, peer: &AccountAddress) -> Option<RawNegotiateMessage> {
+        self.inner
+            .lock()
+            .await
+            .proposing
+            .get(peer)
+            .map(|(terms, _)| terms.clone())
+    }
+
+    /// End the negotiation with `peer` we started, with agreed terms or why it fails.
+    pub async fn finish(&self, peer: &AccountAddress, result: Result<RawNegotiateMessage>) {
+        if let Some((_, waiter)) = self.inner.lock().await.proposing.remove(peer) {
+            let _ = waiter.send(result);
+        }
+    }
+
+    /// `terms` proposed by `peer` are agreed, wait for its open txn.
+    pub async fn agree(&self, peer: AccountAddress, terms: RawNegotiateMessage) {
+        self.inner.lock().await.agreed.insert(peer, terms);
+    }
+
+    /// Check whether `txn` proposed by `peer` opens the channel with terms agreed with it,
+    /// the agreement is used up and returned if so.
+    pub async fn take_agreed(
+        &self,
+        peer: &AccountAddress,
+        txn: &ChannelTransaction,
+    ) -> Option<RawNegotiateMessage> {
+        let mut inner = self.inner.lock().await;
+        let matched = match inner.agreed.get(peer) {
+            Some(terms) => txn.proposer() == *peer && open_txn_matches(terms, txn),
+            None => false,
+        };
+        if matched {
+            inner.agreed.remove(peer)
+        } else {
+            None
+        }
+    }
+}
+
+/// Tag of the default asset, which is the one an open txn deposits.
+pub fn default_asset_tag() -> StructTag {
+    let tag = coin_struct_tag();
+    StructTag::new(
+        tag.address,
+        tag.module.as_str().to_string(),
+        tag.name.as_str().to_string(),
+        vec![],
+    )
+}
+
+/// open txn args of two participants are the receiver, amount of the sender, then amount of the receiver.
+fn open_txn_matches(terms: &RawNegotiateMessage, txn: &ChannelTransaction) -> bool {
+    let args = txn.args();
+    if txn.operator() != &ChannelOp::Open || args.len() != 3 {
+        return false;
+    }
+    if terms.resource_type != default_asset_tag() {
+        return false;
+    }
+    match (&args[0], &args[1], &args[2]) {
+        (
+            TransactionArgument::Address(receiver),
+            TransactionArgument::U64(sender_amount),
+            TransactionArgument::U64(receiver_amount),
+        ) => {
+            receiver == &terms.receiver_addr
+                && *sender_amount == terms.sender_amount
+                && *receiver_amount == terms.receiver_amount
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn test_open_negotiation() {
+    use std::time::Duration;
+
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let mgr = OpenNegotiationManager::new(OpenChannelPolicy::default());
+        let sender = AccountAddress::random();
+        let receiver = AccountAddress::random();
+        let terms =
+            RawNegotiateMessage::new(sender, default_asset_tag(), 100, receiver, 50, 0, vec![]);
+
+        let waiter = mgr.propose(receiver, terms.clone()).await.unwrap();
+        assert!(mgr.propose(receiver, terms.clone()).await.is_err());
+        assert_eq!(Some(terms.clone()), mgr.proposed(&receiver).await);
+        mgr.finish(&receiver, Ok(terms.clone())).await;
+        assert_eq!(terms, waiter.await.unwrap().unwrap());
+        assert_eq!(None, mgr.proposed(&receiver).await);
+
+        let open_txn = |args| {
+            ChannelTransaction::new(
+                0,
+                AccountAddress::random(),
+                0,
+                ChannelOp::Open,
+                args,
+                sender,
+                0,
+                Duration::from_secs(0),
+            )
+        };
+        mgr.agree(sender, terms.clone()).await;
+        let other = open_txn(vec![
+            TransactionArgument::Address(receiver),
+            TransactionArgument::U64(100),
+            TransactionArgument::U64(100),
+        ]);
+        assert!(mgr.take_agreed(&sender, &other).await.is_none());
+        let agreed = open_txn(vec![
+            TransactionArgument::Address(receiver),
+            TransactionArgument::U64(100),
+            TransactionArgument::U64(50),
+        ]);
+        assert!(mgr.take_agreed(&sender, &agreed).await.is_some());
+        assert!(mgr.take_agreed(&sender, &agreed).await.is_none());
+
+        // the open txn deposits the default asset, not the agreed one.
+        let mut point_terms = terms.clone();
+        point_terms.resource_type =
+            StructTag::new(sender, "Point".to_string(), "T".to_string(), vec![]);
+        mgr.agree(sender, point_terms).await;
+        assert!(mgr.take_agreed(&sender, &agreed).await.is_none());
+    });
+}
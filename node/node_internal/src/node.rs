@@ -2,47 +2,87 @@
 // SPDX-License-Identifier: Apache-2.0
 use futures::{
     compat::{Future01CompatExt, Stream01CompatExt},
+    future::{abortable, AbortHandle},
     prelude::*,
 };
 use futures_timer::Delay;
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 use tokio::runtime::{Handle, Runtime};
 
-use anyhow::{bail, ensure, Error, Result};
+use anyhow::{bail, ensure, format_err, Error, Result};
 use libra_crypto::HashValue;
 
 use libra_logger::prelude::*;
+use libra_types::language_storage::StructTag;
 use libra_types::transaction::TransactionArgument;
 use libra_types::{account_address::AccountAddress, account_config::AccountResource};
-use network::{NetworkMessage, NetworkService};
+use network::{NetworkMessage, NetworkService, PeerEvent};
 use node_proto::{
     DeployModuleResponse, DepositResponse, EmptyResponse, ExecuteScriptResponse,
     GetChannelTransactionProposalResponse, OpenChannelResponse, PayResponse, WithdrawResponse,
 };
+use sgtypes::channel_reestablish::ReestablishAction;
+use sgtypes::channel_txn_history::{ChannelTxnPage, ChannelTxnQuery};
 use sgtypes::script_package::ChannelScriptPackage;
 use sgtypes::{
     channel_transaction::{ChannelTransactionRequest, ChannelTransactionResponse},
     message::*,
     system_event::Event,
 };
-use sgwallet::{utils::*, wallet::WalletHandle};
+use sgwallet::{
+    htlc_watcher::HtlcRecallRequest,
+    utils::*,
+    wallet::{ExpiredProposal, WalletHandle, DEFAULT_ASSET},
+};
 
-use crate::message_processor::{MessageFuture, MessageProcessor};
+use crate::approval::ApprovalManager;
+use crate::dapp::{DappMessageManager, MAX_DAPP_PAYLOAD_SIZE};
+use crate::message_processor::{error_translate, MessageFuture, MessageProcessor};
+use crate::multi_path::{split_payment, MAX_PAYMENT_SHARDS};
+use crate::negotiation::{default_asset_tag, OpenNegotiationManager};
 
-use crate::invoice::{Invoice, InvoiceManager};
+use crate::get_unix_ts;
+use crate::invoice::{InvoiceManager, InvoiceOptions, PaymentShard};
 use crate::node_command::NodeMessage;
+use crate::onion::{ForwardedHtlc, OnionManager, ReceivedHtlc, SentOnion};
+use crate::payment::PaymentManager;
 use futures_01::sink::Sink;
 use futures_01::sync::{
     mpsc::{UnboundedReceiver, UnboundedSender},
     oneshot,
 };
-use router::Router;
+use router::{path_fee_policies, Router};
+use sgstorage::{
+    approval_store::ApprovalStore, channel_db::ChannelDB, invoice_store::InvoiceStore,
+    payment_store::PaymentStore,
+};
+use sgtypes::approval::{ApprovalAuditRecord, ApprovalDecision, ApprovalPolicy, ProposalFacts};
+use sgtypes::fee_policy::{route_amounts, FeePolicy};
+use sgtypes::htlc::HtlcPayment;
+use sgtypes::invoice::{InvoiceRecord, RouteHint, SignedInvoice};
+use sgtypes::onion::{create_failure, decode_failure, wrap_failure, OnionPacket, SharedSecret};
+use sgtypes::open_channel_policy::{ChannelTerms, NegotiateResponse, OpenChannelPolicy};
+use sgtypes::payment::{AttemptFailure, PaymentAttempt, PaymentRecord, PaymentStatus};
 use sgtypes::sg_error::{SgError, SgErrorCode};
 use sgtypes::signed_channel_transaction::SignedChannelTransaction;
-use stats::PayEnum;
+use stats::{PayEnum, PaymentOutcome};
 use std::convert::TryInto;
 use std::sync::atomic::{AtomicU64, Ordering};
 
+/// interval of sweeping expired invoices, in milliseconds.
+const INVOICE_SWEEP_INTERVAL: u64 = 60 * 1000;
+/// interval of checking channels due for checkpoint, in milliseconds.
+const CHECKPOINT_SWEEP_INTERVAL: u64 = 60 * 1000;
+/// default timeout in blocks of htlc payment sent by invoice.
+const DEFAULT_HTLC_TIMEOUT: u64 = 20000;
+/// failed htlc payment is retried over other paths for this long, in milliseconds.
+const PAYMENT_RETRY_TIMEOUT: u64 = 60 * 1000;
+
 pub struct Node {
     executor: Handle,
     node_inner: Option<NodeInner>,
@@ -60,6 +100,10 @@ pub struct Node {
         Option<futures::channel::mpsc::UnboundedReceiver<(AccountAddress, RouterNetworkMessage)>>,
     wallet: Arc<WalletHandle>,
     invoice_mgr: InvoiceManager,
+    payment_mgr: PaymentManager,
+    dapp_mgr: DappMessageManager,
+    approval_mgr: ApprovalManager,
+    negotiation_mgr: OpenNegotiationManager,
 }
 
 struct NodeInner {
@@ -69,8 +113,12 @@ struct NodeInner {
     message_processor: MessageProcessor<u64>,
     default_future_timeout: AtomicU64,
     network_service: NetworkService,
-    auto_approve: bool,
+    approval_mgr: ApprovalManager,
+    negotiation_mgr: OpenNegotiationManager,
     invoice_mgr: InvoiceManager,
+    payment_mgr: PaymentManager,
+    dapp_mgr: DappMessageManager,
+    onion_mgr: OnionManager,
     router_message_sender:
         futures::channel::mpsc::UnboundedSender<(AccountAddress, RouterNetworkMessage)>,
     router: Box<dyn Router>,
@@ -102,7 +150,20 @@ impl Node {
 
         let (command_sender, command_receiver) = futures_01::sync::mpsc::unbounded();
 
-        let invoice_mgr = InvoiceManager::new();
+        let invoice_mgr = InvoiceManager::new(
+            InvoiceStore::new(ChannelDB::new(wallet.account(), wallet.storage())),
+            wallet.signer(),
+        );
+        let payment_mgr = PaymentManager::new(PaymentStore::new(ChannelDB::new(
+            wallet.account(),
+            wallet.storage(),
+        )));
+        let dapp_mgr = DappMessageManager::new();
+        let approval_mgr = ApprovalManager::new(
+            ApprovalManager::auto_approve_policy(auto_approve),
+            ApprovalStore::new(ChannelDB::new(wallet.account(), wallet.storage())),
+        );
+        let negotiation_mgr = OpenNegotiationManager::new(OpenChannelPolicy::default());
 
         let node_inner = NodeInner {
             executor: executor_clone,
@@ -111,8 +172,12 @@ impl Node {
             message_processor: MessageProcessor::new(),
             default_future_timeout: AtomicU64::new(default_future_timeout),
             network_service: network_service.clone(),
-            auto_approve,
+            approval_mgr: approval_mgr.clone(),
+            negotiation_mgr: negotiation_mgr.clone(),
             invoice_mgr: invoice_mgr.clone(),
+            payment_mgr: payment_mgr.clone(),
+            dapp_mgr: dapp_mgr.clone(),
+            onion_mgr: OnionManager::new(),
             router_message_sender,
             router,
         };
@@ -132,7 +197,24 @@ impl Node {
             router_message_receiver: Some(router_message_receiver),
             wallet,
             invoice_mgr,
+            payment_mgr,
+            dapp_mgr,
+            approval_mgr,
+            negotiation_mgr,
+        }
+    }
+
+    /// Decide proposals which need approval by `policy`, instead of the `auto_approve` switch.
+    /// It should be set before the node starts.
+    pub fn with_approval_policy(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval_mgr = ApprovalManager::new(
+            policy,
+            ApprovalStore::new(ChannelDB::new(self.wallet.account(), self.wallet.storage())),
+        );
+        if let Some(node_inner) = self.node_inner.as_mut() {
+            node_inner.approval_mgr = self.approval_mgr.clone();
         }
+        self
     }
 
     pub async fn open_channel_oneshot(
@@ -194,6 +276,70 @@ impl Node {
         resp_receiver.await?
     }
 
+    /// Open a channel with all participants in `deposits`, which should contain this node.
+    /// Negotiate with `receiver` about deposits of both sides, reserve and packages of the channel,
+    /// then open it with the agreed terms. `receiver` may counter-propose, which is accepted if
+    /// it doesn't ask us to deposit more or to allow packages we didn't propose.
+    pub async fn negotiate_open_channel_async(
+        &self,
+        receiver: AccountAddress,
+        sender_amount: u64,
+        receiver_amount: u64,
+        min_reserve: u64,
+        packages: Vec<String>,
+    ) -> Result<(RawNegotiateMessage, MessageFuture<u64>)> {
+        if !self.network_service.is_connected(receiver) {
+            bail!("could not connect to receiver")
+        }
+        let terms = RawNegotiateMessage::new(
+            self.wallet.account(),
+            default_asset_tag(),
+            sender_amount,
+            receiver,
+            receiver_amount,
+            min_reserve,
+            packages,
+        );
+        terms.check_reserve()?;
+
+        let (responder, resp_receiver) = futures::channel::oneshot::channel();
+        self.command_sender
+            .unbounded_send(NodeMessage::NegotiateOpenChannel { terms, responder })?;
+        resp_receiver.await?
+    }
+
+    /// Set terms we accept for channels opened by others.
+    pub async fn set_open_channel_policy(&self, policy: OpenChannelPolicy) {
+        info!("set open channel policy to {:?}", policy);
+        self.negotiation_mgr.set_policy(policy).await
+    }
+
+    pub async fn open_multi_party_channel_async(
+        &self,
+        deposits: BTreeMap<AccountAddress, u64>,
+    ) -> Result<MessageFuture<u64>> {
+        for (participant, amount) in deposits.iter() {
+            if participant == &self.wallet.account() {
+                continue;
+            }
+            if amount > &self.default_max_deposit {
+                bail!("deposit coin amount too big")
+            }
+            if !self.network_service.is_connected(participant.clone()) {
+                bail!("could not connect to participant {}", participant)
+            }
+        }
+
+        let (responder, resp_receiver) = futures::channel::oneshot::channel();
+        self.command_sender
+            .unbounded_send(NodeMessage::OpenMultiPartyChannel {
+                deposits,
+                responder,
+            })?;
+
+        resp_receiver.await?
+    }
+
     pub async fn deposit_oneshot(
         &self,
         receiver: AccountAddress,
@@ -336,23 +482,49 @@ impl Node {
         resp_receiver.await?
     }
 
+    /// Pay `amount` of `asset` to `receiver_address` in the channel with it.
+    pub async fn off_chain_pay_asset_async(
+        &self,
+        receiver_address: AccountAddress,
+        asset: StructTag,
+        amount: u64,
+    ) -> Result<MessageFuture<u64>> {
+        if !self.network_service.is_connected(receiver_address) {
+            bail!("could not connect to receiver")
+        }
+
+        let (responder, resp_receiver) = futures::channel::oneshot::channel();
+        self.command_sender
+            .unbounded_send(NodeMessage::ChannelPayAsset {
+                receiver_address,
+                asset,
+                amount,
+                responder,
+            })?;
+
+        resp_receiver.await?
+    }
+
     pub async fn off_chain_pay_htlc_async_string(
         &self,
         encoded_invoice: String,
     ) -> Result<MessageFuture<u64>> {
-        let invoice: Result<Invoice> = encoded_invoice.try_into();
-        match invoice {
-            Ok(invoice) => {
-                self.off_chain_pay_htlc_async(
-                    invoice.receiver,
-                    invoice.amount,
-                    invoice.r_hash,
-                    20000,
-                )
-                .await
-            }
-            Err(e) => Err(e),
-        }
+        let signed_invoice: SignedInvoice = encoded_invoice.try_into()?;
+        let invoice = signed_invoice.invoice;
+        ensure!(
+            !invoice.is_expired(get_unix_ts()),
+            "invoice {} is expired",
+            hex::encode(&invoice.r_hash)
+        );
+        let timeout = std::cmp::max(DEFAULT_HTLC_TIMEOUT, invoice.min_final_timeout);
+        self.off_chain_pay_htlc_with_hints_async(
+            invoice.receiver,
+            invoice.amount,
+            invoice.r_hash,
+            timeout,
+            invoice.route_hints,
+        )
+        .await
     }
 
     pub async fn off_chain_pay_htlc_async(
@@ -361,6 +533,25 @@ impl Node {
         amount: u64,
         hash_lock: Vec<u8>,
         timeout: u64,
+    ) -> Result<MessageFuture<u64>> {
+        self.off_chain_pay_htlc_with_hints_async(
+            receiver_address,
+            amount,
+            hash_lock,
+            timeout,
+            vec![],
+        )
+        .await
+    }
+
+    /// Pay by htlc, `route_hints` are tried when receiver is not reachable in channel graph.
+    pub async fn off_chain_pay_htlc_with_hints_async(
+        &self,
+        receiver_address: AccountAddress,
+        amount: u64,
+        hash_lock: Vec<u8>,
+        timeout: u64,
+        route_hints: Vec<RouteHint>,
     ) -> Result<MessageFuture<u64>> {
         let is_receiver_connected = self.network_service.is_connected(receiver_address);
         if !is_receiver_connected {
@@ -374,6 +565,7 @@ impl Node {
                 amount,
                 hash_lock,
                 timeout,
+                route_hints,
                 responder,
             })?;
 
@@ -456,6 +648,32 @@ impl Node {
         receiver.await?
     }
 
+    /// My balances of all assets in the channel with `participant`.
+    pub async fn channel_balances_async(
+        &self,
+        participant: AccountAddress,
+    ) -> Result<Vec<(StructTag, u64)>> {
+        self.wallet.channel_balances(participant).await
+    }
+
+    /// Close channels lost from local store at the state in the backup at `backup_path`,
+    /// together with their participants, or by force if a participant is unreachable or refuses.
+    /// Return the channels being closed.
+    pub async fn restore_channels_async(
+        &self,
+        backup_path: PathBuf,
+    ) -> Result<Vec<AccountAddress>> {
+        let (responder, receiver) = futures::channel::oneshot::channel();
+
+        self.command_sender
+            .unbounded_send(NodeMessage::RestoreChannels {
+                backup_path,
+                responder,
+            })?;
+
+        receiver.await?
+    }
+
     pub fn set_default_timeout(&self, timeout: u64) -> Result<()> {
         self.command_sender
             .unbounded_send(NodeMessage::SetTimeout {
@@ -537,6 +755,27 @@ impl Node {
         Ok(resp_receiver)
     }
 
+    /// Execute script in a channel which may have more than two participants.
+    pub async fn execute_script_in_channel_async(
+        &self,
+        channel_address: AccountAddress,
+        package_name: String,
+        script_name: String,
+        transaction_args: Vec<TransactionArgument>,
+    ) -> Result<MessageFuture<u64>> {
+        let (responder, resp_receiver) = futures::channel::oneshot::channel();
+        self.command_sender
+            .unbounded_send(NodeMessage::ExecuteInChannel {
+                channel_address,
+                package_name,
+                script_name,
+                transaction_args,
+                responder,
+            })?;
+
+        resp_receiver.await?
+    }
+
     pub async fn get_txn_by_channel_sequence_number(
         &self,
         participant_address: AccountAddress,
@@ -624,11 +863,106 @@ impl Node {
         }
     }
 
-    pub async fn add_invoice(&self, amount: u64) -> Result<Invoice> {
-        Ok(self
-            .invoice_mgr
-            .new_invoice(amount, self.wallet.account())
-            .await)
+    pub async fn add_invoice(&self, amount: u64, options: InvoiceOptions) -> Result<SignedInvoice> {
+        self.invoice_mgr.new_invoice(amount, options).await
+    }
+
+    pub async fn cancel_invoice(&self, r_hash: HashValue) -> Result<()> {
+        self.invoice_mgr.cancel_invoice(&r_hash).await
+    }
+
+    pub async fn list_invoices(&self) -> Result<Vec<InvoiceRecord>> {
+        self.invoice_mgr.list_invoices().await
+    }
+
+    /// Set fee policy to forward htlc to `participant`, or the default one if it's None.
+    pub fn set_fee_policy(
+        &self,
+        participant: Option<AccountAddress>,
+        policy: FeePolicy,
+    ) -> Result<()> {
+        info!("set fee policy of {:?} to {:?}", participant, policy);
+        self.wallet.set_fee_policy(participant, policy)
+    }
+
+    pub async fn list_channel_txns(
+        &self,
+        participant_address: AccountAddress,
+        query: ChannelTxnQuery,
+    ) -> Result<ChannelTxnPage> {
+        self.wallet
+            .list_channel_txns(participant_address, query)
+            .await
+    }
+
+    /// Return decisions made on incoming proposals starting from `start_index`, with reasons.
+    pub fn list_approval_audits(
+        &self,
+        start_index: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, ApprovalAuditRecord)>> {
+        self.approval_mgr.list_audits(start_index, limit)
+    }
+
+    pub async fn get_payment(&self, r_hash: HashValue) -> Result<Option<PaymentRecord>> {
+        self.payment_mgr.get_payment(&r_hash).await
+    }
+
+    pub async fn list_payments(&self) -> Result<Vec<PaymentRecord>> {
+        self.payment_mgr.list_payments().await
+    }
+
+    /// Stream updates of all outgoing payments.
+    pub async fn subscribe_payments(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<PaymentRecord> {
+        self.payment_mgr.subscribe().await
+    }
+
+    /// Stream updates of payment `r_hash` from its current state, until it succeeded or failed.
+    pub async fn track_payment(
+        &self,
+        r_hash: HashValue,
+    ) -> Result<futures::channel::mpsc::UnboundedReceiver<PaymentRecord>> {
+        self.payment_mgr.track(&r_hash).await
+    }
+
+    /// Send `payload` on `topic` to `peer`, which should have a channel with us.
+    /// If `need_receipt` is set, wait for the receipt until the default timeout,
+    /// None is returned if it doesn't arrive in time.
+    pub async fn send_dapp_message_async(
+        &self,
+        peer: AccountAddress,
+        topic: String,
+        payload: Vec<u8>,
+        need_receipt: bool,
+    ) -> Result<(HashValue, Option<DappMessageReceipt>)> {
+        ensure!(
+            !topic.is_empty(),
+            "topic of dapp message should not be empty"
+        );
+        ensure!(
+            payload.len() <= MAX_DAPP_PAYLOAD_SIZE,
+            "dapp message payload is too large, {} > {}",
+            payload.len(),
+            MAX_DAPP_PAYLOAD_SIZE
+        );
+        let (responder, receiver) = futures::channel::oneshot::channel();
+        self.command_sender
+            .unbounded_send(NodeMessage::SendDappMessage {
+                peer,
+                message: DappMessage::new(HashValue::random(), topic, payload, need_receipt),
+                responder,
+            })?;
+        receiver.await?
+    }
+
+    /// Stream dapp messages received on `topic`, or on all topics if it's None.
+    pub async fn subscribe_dapp_messages(
+        &self,
+        topic: Option<String>,
+    ) -> futures::channel::mpsc::UnboundedReceiver<InboundDappMessage> {
+        self.dapp_mgr.subscribe(topic).await
     }
 
     async fn start_network(
@@ -644,6 +978,11 @@ impl Node {
         info!("start receive message");
         let mut receiver = receiver.compat().fuse();
         let mut event_receiver = event_receiver.compat().fuse();
+        let mut peer_event_receiver = node_inner
+            .network_service
+            .subscribe_peer_events()
+            .compat()
+            .fuse();
         //let mut router_message_receiver = router_message_receiver.compat().fuse();
 
         loop {
@@ -661,6 +1000,12 @@ impl Node {
                 (peer_id,message) = router_message_receiver.select_next_some() => {
                        node_inner.send_router_message(peer_id,message).await.unwrap();
                 },
+                peer_event = peer_event_receiver.select_next_some() => {
+                    if let Ok(PeerEvent::Open(peer_id)) = peer_event {
+                        let node_inner = node_inner.clone();
+                        executor.spawn(async move { node_inner.reestablish_channels(peer_id).await });
+                    }
+                },
                 _ = event_receiver.select_next_some() => {
                     debug!("To shutdown network");
                     break;
@@ -680,6 +1025,11 @@ impl Node {
         info!("start receive command");
         let mut event_receiver = event_receiver.compat().fuse();
         let mut command_receiver = command_receiver.compat().fuse();
+        let mut sweep_timer = Delay::new(Duration::from_millis(INVOICE_SWEEP_INTERVAL)).fuse();
+        let mut checkpoint_timer =
+            Delay::new(Duration::from_millis(CHECKPOINT_SWEEP_INTERVAL)).fuse();
+        let mut htlc_recall_receiver = node_inner.wallet.subscribe_htlc_recall().fuse();
+        let mut expired_proposal_receiver = node_inner.wallet.subscribe_expired_proposals().fuse();
 
         loop {
             futures::select! {
@@ -692,6 +1042,23 @@ impl Node {
                         Err(_) => {}
                     }
                 },
+                _ = sweep_timer => {
+                    let node_inner = node_inner.clone();
+                    executor.spawn(async move { node_inner.sweep_expired_invoices().await });
+                    sweep_timer = Delay::new(Duration::from_millis(INVOICE_SWEEP_INTERVAL)).fuse();
+                },
+                _ = checkpoint_timer => {
+                    let node_inner = node_inner.clone();
+                    executor.spawn(async move { node_inner.checkpoint_channels().await });
+                    checkpoint_timer = Delay::new(Duration::from_millis(CHECKPOINT_SWEEP_INTERVAL)).fuse();
+                },
+                recall = htlc_recall_receiver.select_next_some() => {
+                    let node_inner = node_inner.clone();
+                    executor.spawn(async move { node_inner.send_htlc_recall(recall) });
+                },
+                expired = expired_proposal_receiver.select_next_some() => {
+                    node_inner.notify_proposal_expired(expired);
+                },
                 _ = event_receiver.select_next_some() => {
                     debug!("To shutdown command ");
                     let _ = network_service_close_tx.send(());
@@ -712,6 +1079,11 @@ impl Node {
     pub fn wallet(&self) -> Arc<WalletHandle> {
         self.wallet.clone()
     }
+
+    /// Address of the network peer of the node, seeds of other nodes refer to it.
+    pub fn network_peer(&self) -> AccountAddress {
+        self.network_service.identify()
+    }
 }
 
 impl NodeInner {
@@ -729,6 +1101,47 @@ impl NodeInner {
         //        }
     }
 
+    /// Tell other participants the pending proposal is cancelled by its deadline,
+    /// so that they stop waiting for it.
+    fn notify_proposal_expired(&self, expired: ExpiredProposal) {
+        let ExpiredProposal {
+            channel_address,
+            channel_txn_id,
+            participants,
+        } = expired;
+        info!(
+            "proposal {} of channel {} is expired",
+            channel_txn_id, channel_address
+        );
+        for participant in participants {
+            if participant == self.wallet.account() {
+                continue;
+            }
+            self.send_error_message(
+                participant,
+                ErrorMessage::new(
+                    channel_txn_id,
+                    SgError::new_proposal_expired_error(&channel_txn_id),
+                )
+                .with_channel_address(channel_address),
+            );
+        }
+    }
+
+    /// Deliver the recall of a timeout htlc proposed by wallet to the participant,
+    /// wallet forces the recall onchain if the participant doesn't answer.
+    fn send_htlc_recall(&self, recall: HtlcRecallRequest) {
+        let HtlcRecallRequest {
+            participant,
+            request,
+        } = recall;
+        if let Err(e) =
+            self.send_channel_request(participant, request, MessageType::ChannelTransactionRequest)
+        {
+            warn!("fail to send htlc recall to {}, {}", participant, e);
+        }
+    }
+
     async fn send_router_message(
         &self,
         peer_id: AccountAddress,
@@ -750,7 +1163,14 @@ impl NodeInner {
         let msg_type = parse_message_type(&data);
         info!("message type is {:?}", msg_type);
         match msg_type {
-            MessageType::OpenChannelNodeNegotiateMessage => {}
+            MessageType::OpenChannelNodeNegotiateMessage => {
+                if let Err(e) = node_inner
+                    .handle_negotiate_message(peer_id, data[2..].to_vec())
+                    .await
+                {
+                    warn!("fail to handle negotiation from {}, {}", peer_id, e);
+                }
+            }
             MessageType::ChannelTransactionRequest => node_inner
                 .handle_receiver_channel(data[2..].to_vec(), peer_id)
                 .await
@@ -759,7 +1179,11 @@ impl NodeInner {
                 .handle_sender_channel(data[2..].to_vec(), peer_id)
                 .await
                 .unwrap(),
-            MessageType::ErrorMessage => node_inner.handle_error_message(data[2..].to_vec()),
+            MessageType::ErrorMessage => {
+                node_inner
+                    .handle_error_message(data[2..].to_vec(), peer_id)
+                    .await
+            }
             MessageType::MultiHopChannelTransactionRequest => node_inner
                 .handle_multi_hop_receiver_channel(data[2..].to_vec(), peer_id)
                 .await
@@ -768,61 +1192,419 @@ impl NodeInner {
                 .handle_router_msg(peer_id, data[2..].to_vec())
                 .await
                 .unwrap(),
+            MessageType::NodeMessage => {
+                if let Err(e) = node_inner
+                    .handle_node_network_msg(peer_id, data[2..].to_vec())
+                    .await
+                {
+                    warn!("fail to handle node message from {}, {}", peer_id, e);
+                }
+            }
         };
     }
 
-    async fn handle_router_msg(&self, peer_id: AccountAddress, data: Vec<u8>) -> Result<()> {
-        let msg = RouterNetworkMessage::from_proto_bytes(data)?;
-        self.router_message_sender.unbounded_send((peer_id, msg))?;
+    async fn handle_node_network_msg(&self, peer_id: AccountAddress, data: Vec<u8>) -> Result<()> {
+        match NodeNetworkMessage::from_proto_bytes(data)? {
+            NodeNetworkMessage::DappMessage(message) => {
+                // only peers we have channels with can reach our dapps.
+                if self.wallet.channel_handle(peer_id).await.is_err() {
+                    bail!("no channel with {}, drop dapp message", peer_id);
+                }
+                let id = message.id;
+                let need_receipt = message.need_receipt;
+                let delivered = self.dapp_mgr.deliver(peer_id, message).await;
+                debug!(
+                    "dapp message {} from {} delivered: {}",
+                    id, peer_id, delivered
+                );
+                if need_receipt {
+                    self.send_node_message(
+                        peer_id,
+                        NodeNetworkMessage::DappMessageReceipt(DappMessageReceipt::new(
+                            id, delivered,
+                        )),
+                    )?;
+                }
+            }
+            NodeNetworkMessage::DappMessageReceipt(receipt) => {
+                self.dapp_mgr.receipt(receipt).await;
+            }
+            NodeNetworkMessage::ChannelCheckpointRequest(checkpoint) => {
+                if self.wallet.channel_handle(peer_id).await.is_err() {
+                    bail!("no channel with {}, drop checkpoint request", peer_id);
+                }
+                let version = checkpoint.version();
+                let checkpoint = self.wallet.cosign_checkpoint(peer_id, checkpoint)?;
+                info!("checkpoint channel with {} at {}", peer_id, version);
+                self.send_node_message(
+                    peer_id,
+                    NodeNetworkMessage::ChannelCheckpointResponse(checkpoint),
+                )?;
+            }
+            NodeNetworkMessage::ChannelCheckpointResponse(checkpoint) => {
+                if self.wallet.channel_handle(peer_id).await.is_err() {
+                    bail!("no channel with {}, drop checkpoint response", peer_id);
+                }
+                let version = checkpoint.version();
+                self.wallet.save_checkpoint(peer_id, checkpoint)?;
+                info!("checkpoint channel with {} at {}", peer_id, version);
+            }
+            NodeNetworkMessage::ChannelReestablish(remote) => {
+                let channel_address = remote.channel_address;
+                match self.wallet.reestablish_channel(peer_id, remote).await? {
+                    ReestablishAction::None => {}
+                    ReestablishAction::Rollback(channel_txn_id) => {
+                        info!(
+                            "roll back proposal {} of channel {}, {} never received it",
+                            channel_txn_id, channel_address, peer_id
+                        );
+                        let error = SgError::new(
+                            SgErrorCode::REJECT,
+                            "proposal is rolled back after reconnect".to_string(),
+                        );
+                        self.message_processor
+                            .future_error(ErrorMessage::new(channel_txn_id, error))?;
+                    }
+                    ReestablishAction::Unrecoverable(reason) => warn!(
+                        "channel {} can not be reestablished with {}, {}",
+                        channel_address, peer_id, reason
+                    ),
+                    action => info!(
+                        "reestablish channel {} with {}, {:?}",
+                        channel_address, peer_id, action
+                    ),
+                }
+            }
+        }
         Ok(())
     }
 
-    async fn handle_node_msg(node_inner: Arc<NodeInner>, msg: NodeMessage) {
-        match msg {
-            NodeMessage::Install {
-                channel_script_package,
-                responder,
-            } => {
-                node_inner
-                    .install_package(channel_script_package, responder)
-                    .await;
-            }
-            NodeMessage::Execute {
-                receiver_address,
-                package_name,
-                script_name,
-                force_execute,
-                transaction_args,
-                responder,
-            } => {
-                node_inner
-                    .execute_script(
-                        receiver_address,
-                        package_name,
-                        script_name,
-                        force_execute,
-                        transaction_args,
-                        responder,
-                    )
-                    .await;
+    fn send_node_message(&self, peer_id: AccountAddress, msg: NodeNetworkMessage) -> Result<()> {
+        let msg = add_message_type(msg.into_proto_bytes()?, MessageType::NodeMessage);
+        self.sender.unbounded_send(NetworkMessage {
+            peer_id,
+            data: msg.to_vec(),
+        })?;
+        Ok(())
+    }
+
+    async fn send_dapp_message(
+        &self,
+        peer: AccountAddress,
+        message: DappMessage,
+        responder: futures::channel::oneshot::Sender<
+            Result<(HashValue, Option<DappMessageReceipt>)>,
+        >,
+    ) {
+        let id = message.id;
+        let result = async {
+            ensure!(
+                self.wallet.channel_handle(peer).await.is_ok(),
+                "no channel with {}",
+                peer
+            );
+            ensure!(
+                self.network_service.is_connected(peer),
+                "could not connect to {}",
+                peer
+            );
+            if !message.need_receipt {
+                self.send_node_message(peer, NodeNetworkMessage::DappMessage(message))?;
+                return Ok((id, None));
             }
-            NodeMessage::Deposit {
-                receiver,
-                sender_amount,
-                responder,
-            } => {
-                node_inner.deposit(receiver, sender_amount, responder).await;
+            let receipt = self.dapp_mgr.wait_receipt(id).await.fuse();
+            if let Err(e) = self.send_node_message(peer, NodeNetworkMessage::DappMessage(message)) {
+                self.dapp_mgr.cancel_receipt(&id).await;
+                return Err(e);
             }
-            NodeMessage::OpenChannel {
-                receiver,
-                sender_amount,
-                receiver_amount,
-                responder,
-            } => {
-                info!("get open channel message");
-                node_inner
-                    .open_channel(receiver, sender_amount, receiver_amount, responder)
-                    .await;
+            let timeout = self.default_future_timeout.load(Ordering::Relaxed);
+            let mut delay = Delay::new(Duration::from_millis(timeout)).fuse();
+            futures::pin_mut!(receipt);
+            let receipt = futures::select! {
+                receipt = receipt => receipt.ok(),
+                _ = delay => None,
+            };
+            if receipt.is_none() {
+                warn!("no receipt of dapp message {} from {}", id, peer);
+                self.dapp_mgr.cancel_receipt(&id).await;
+            }
+            Ok((id, receipt))
+        }
+            .await;
+        respond_with(responder, result);
+    }
+
+    /// Sign the negotiated terms by our account key.
+    fn sign_negotiate_message(&self, message: &mut OpenChannelNodeNegotiateMessage) -> Result<()> {
+        let signer = self.wallet.signer();
+        let signature = signer.sign_negotiate_message(&message.raw_negotiate_message)?;
+        message.add_signature(signer.public_key(), signature)
+    }
+
+    fn send_negotiate_message(
+        &self,
+        peer_id: AccountAddress,
+        message: OpenChannelNodeNegotiateMessage,
+    ) -> Result<()> {
+        let msg = add_message_type(
+            message.into_proto_bytes()?,
+            MessageType::OpenChannelNodeNegotiateMessage,
+        );
+        self.sender.unbounded_send(NetworkMessage {
+            peer_id,
+            data: msg.to_vec(),
+        })?;
+        Ok(())
+    }
+
+    /// Propose `terms` to their receiver, and open the channel once they are agreed.
+    async fn negotiate_open_channel(
+        &self,
+        terms: RawNegotiateMessage,
+        responder: futures::channel::oneshot::Sender<
+            Result<(RawNegotiateMessage, MessageFuture<u64>)>,
+        >,
+    ) {
+        let receiver = terms.receiver_addr;
+        let result = async {
+            let agreed = self
+                .negotiation_mgr
+                .propose(receiver, terms.clone())
+                .await?
+                .fuse();
+            let mut proposal = OpenChannelNodeNegotiateMessage::new(
+                terms,
+                NegotiateAction::Propose,
+                None,
+                None,
+                String::new(),
+            );
+            self.sign_negotiate_message(&mut proposal)?;
+            if let Err(e) = self.send_negotiate_message(receiver, proposal) {
+                self.negotiation_mgr
+                    .finish(&receiver, Err(format_err!("fail to send proposal")))
+                    .await;
+                return Err(e);
+            }
+
+            let timeout = self.default_future_timeout.load(Ordering::Relaxed);
+            let mut delay = Delay::new(Duration::from_millis(timeout)).fuse();
+            futures::pin_mut!(agreed);
+            let agreed = futures::select! {
+                agreed = agreed => agreed?,
+                _ = delay => {
+                    self.negotiation_mgr
+                        .finish(&receiver, Err(format_err!("negotiation timeout")))
+                        .await;
+                    bail!("negotiation with {} timeout", receiver)
+                },
+            }?;
+
+            info!(
+                "open channel with {} by agreed terms {:?}",
+                receiver, agreed
+            );
+            self.wallet
+                .set_channel_terms(receiver, ChannelTerms::from(&agreed))?;
+            let request = self
+                .wallet
+                .open(receiver, agreed.sender_amount, agreed.receiver_amount)
+                .await?;
+            let future = self.send_channel_request(
+                receiver,
+                request,
+                MessageType::ChannelTransactionRequest,
+            )?;
+            Ok((agreed, future))
+        }
+            .await;
+        respond_with(responder, result);
+    }
+
+    async fn handle_negotiate_message(&self, peer_id: AccountAddress, data: Vec<u8>) -> Result<()> {
+        let message = OpenChannelNodeNegotiateMessage::from_proto_bytes(data)?;
+        message.verify()?;
+        let terms = message.raw_negotiate_message.clone();
+        let account = self.wallet.account();
+        let is_opener = terms.sender_addr == account && terms.receiver_addr == peer_id;
+        ensure!(
+            is_opener || (terms.sender_addr == peer_id && terms.receiver_addr == account),
+            "negotiation from {} is not between us",
+            peer_id
+        );
+        match (message.action, is_opener) {
+            (NegotiateAction::Propose, false) => {
+                ensure!(message.sender_sign.is_some(), "proposal is not signed");
+                let policy = self.negotiation_mgr.policy().await;
+                let installed = |package: &str| self.wallet.get_package(package).is_some();
+                let reply = match policy.respond(&terms, installed) {
+                    NegotiateResponse::Accept => {
+                        let mut reply = message;
+                        reply.action = NegotiateAction::Accept;
+                        self.sign_negotiate_message(&mut reply)?;
+                        self.negotiation_mgr.agree(peer_id, terms).await;
+                        reply
+                    }
+                    NegotiateResponse::Counter(counter, reason) => {
+                        info!("counter open channel proposal of {}, {}", peer_id, reason);
+                        let mut reply = OpenChannelNodeNegotiateMessage::new(
+                            counter,
+                            NegotiateAction::Counter,
+                            None,
+                            None,
+                            reason,
+                        );
+                        self.sign_negotiate_message(&mut reply)?;
+                        reply
+                    }
+                    NegotiateResponse::Reject(reason) => {
+                        info!("reject open channel proposal of {}, {}", peer_id, reason);
+                        OpenChannelNodeNegotiateMessage::new_reject(terms, reason)
+                    }
+                };
+                self.send_negotiate_message(peer_id, reply)?;
+            }
+            (NegotiateAction::Counter, true) => {
+                ensure!(message.receiver_sign.is_some(), "counter is not signed");
+                let proposed = self
+                    .negotiation_mgr
+                    .proposed(&peer_id)
+                    .await
+                    .ok_or_else(|| format_err!("not negotiating with {}", peer_id))?;
+                match proposed.check_counter(&terms) {
+                    Ok(()) => {
+                        let mut reply = message;
+                        reply.action = NegotiateAction::Accept;
+                        self.sign_negotiate_message(&mut reply)?;
+                        self.send_negotiate_message(peer_id, reply)?;
+                        self.negotiation_mgr.finish(&peer_id, Ok(terms)).await;
+                    }
+                    Err(e) => {
+                        let reason = format!("counter is not acceptable, {}", e);
+                        self.send_negotiate_message(
+                            peer_id,
+                            OpenChannelNodeNegotiateMessage::new_reject(terms, reason.clone()),
+                        )?;
+                        self.negotiation_mgr
+                            .finish(
+                                &peer_id,
+                                Err(SgError::new(SgErrorCode::REJECT, reason).into()),
+                            )
+                            .await;
+                    }
+                }
+            }
+            (NegotiateAction::Accept, true) => {
+                ensure!(message.is_agreed(), "accepted terms are not signed by both");
+                ensure!(
+                    self.negotiation_mgr.proposed(&peer_id).await == Some(terms.clone()),
+                    "accepted terms are not the proposed ones"
+                );
+                self.negotiation_mgr.finish(&peer_id, Ok(terms)).await;
+            }
+            (NegotiateAction::Accept, false) => {
+                // the opener accepts our counter.
+                ensure!(message.is_agreed(), "accepted terms are not signed by both");
+                self.negotiation_mgr.agree(peer_id, terms).await;
+            }
+            (NegotiateAction::Reject, true) => {
+                info!(
+                    "open channel proposal is rejected by {}, {}",
+                    peer_id, message.reason
+                );
+                self.negotiation_mgr
+                    .finish(
+                        &peer_id,
+                        Err(SgError::new(SgErrorCode::REJECT, message.reason).into()),
+                    )
+                    .await;
+            }
+            (NegotiateAction::Reject, false) => {
+                info!("counter is rejected by {}, {}", peer_id, message.reason);
+            }
+            (action, _) => bail!("unexpected negotiate action {:?} from {}", action, peer_id),
+        }
+        Ok(())
+    }
+
+    async fn handle_router_msg(&self, peer_id: AccountAddress, data: Vec<u8>) -> Result<()> {
+        let msg = RouterNetworkMessage::from_proto_bytes(data)?;
+        self.router_message_sender.unbounded_send((peer_id, msg))?;
+        Ok(())
+    }
+
+    async fn handle_node_msg(node_inner: Arc<NodeInner>, msg: NodeMessage) {
+        match msg {
+            NodeMessage::Install {
+                channel_script_package,
+                responder,
+            } => {
+                node_inner
+                    .install_package(channel_script_package, responder)
+                    .await;
+            }
+            NodeMessage::Execute {
+                receiver_address,
+                package_name,
+                script_name,
+                force_execute,
+                transaction_args,
+                responder,
+            } => {
+                node_inner
+                    .execute_script(
+                        receiver_address,
+                        package_name,
+                        script_name,
+                        force_execute,
+                        transaction_args,
+                        responder,
+                    )
+                    .await;
+            }
+            NodeMessage::ExecuteInChannel {
+                channel_address,
+                package_name,
+                script_name,
+                transaction_args,
+                responder,
+            } => {
+                node_inner
+                    .execute_script_in_channel(
+                        channel_address,
+                        package_name,
+                        script_name,
+                        transaction_args,
+                        responder,
+                    )
+                    .await;
+            }
+            NodeMessage::Deposit {
+                receiver,
+                sender_amount,
+                responder,
+            } => {
+                node_inner.deposit(receiver, sender_amount, responder).await;
+            }
+            NodeMessage::OpenChannel {
+                receiver,
+                sender_amount,
+                receiver_amount,
+                responder,
+            } => {
+                info!("get open channel message");
+                node_inner
+                    .open_channel(receiver, sender_amount, receiver_amount, responder)
+                    .await;
+            }
+            NodeMessage::OpenMultiPartyChannel {
+                deposits,
+                responder,
+            } => {
+                info!("get open multi party channel message");
+                node_inner
+                    .open_multi_party_channel(deposits, responder)
+                    .await;
             }
             NodeMessage::Withdraw {
                 receiver,
@@ -843,17 +1625,35 @@ impl NodeInner {
                     .await
                     .unwrap();
             }
+            NodeMessage::ChannelPayAsset {
+                receiver_address,
+                asset,
+                amount,
+                responder,
+            } => {
+                node_inner
+                    .off_chain_pay_asset(receiver_address, asset, amount, responder)
+                    .await;
+            }
             NodeMessage::ChannelPayHTLC {
                 receiver_address,
                 amount,
                 hash_lock,
                 timeout,
+                route_hints,
                 responder,
             } => {
-                node_inner
-                    .off_chain_pay_htlc(receiver_address, amount, hash_lock, timeout, responder)
-                    .await
-                    .unwrap();
+                Self::off_chain_pay_htlc(
+                    node_inner.clone(),
+                    receiver_address,
+                    amount,
+                    hash_lock,
+                    timeout,
+                    route_hints,
+                    responder,
+                )
+                .await
+                .unwrap();
             }
             NodeMessage::ChannelBalance {
                 participant,
@@ -879,6 +1679,16 @@ impl NodeInner {
                     .tnx_by_sn(participant_address, channel_seq_number, responder)
                     .await;
             }
+            NodeMessage::SendDappMessage {
+                peer,
+                message,
+                responder,
+            } => {
+                node_inner.send_dapp_message(peer, message, responder).await;
+            }
+            NodeMessage::NegotiateOpenChannel { terms, responder } => {
+                node_inner.negotiate_open_channel(terms, responder).await;
+            }
             NodeMessage::SetTimeout {
                 default_future_timeout,
             } => {
@@ -899,6 +1709,13 @@ impl NodeInner {
                     )
                     .await;
             }
+            NodeMessage::RestoreChannels {
+                backup_path,
+                responder,
+            } => {
+                let result = node_inner.restore_channels(backup_path).await;
+                respond_with(responder, result);
+            }
         }
     }
 
@@ -908,43 +1725,192 @@ impl NodeInner {
         peer_id: AccountAddress,
     ) -> Result<()> {
         info!("handle_multi_hop_receiver_channel");
-        let mut open_channel_message = MultiHopChannelRequest::from_proto_bytes(data)?;
+        let open_channel_message = MultiHopChannelRequest::from_proto_bytes(data)?;
+        let request = open_channel_message.request;
+        let request_id = request.request_id();
+        let payment = match parse_htlc_payment(request.channel_txn().args()) {
+            Ok(payment) if is_htlc_transfer(request.channel_txn().operator()) => payment,
+            _ => {
+                warn!("should be a htlc transfer");
+                self.send_error(peer_id, request_id, format_err!("not a htlc transfer"));
+                return Ok(());
+            }
+        };
+        let onion = &open_channel_message.onion;
+        let peeled = self
+            .wallet
+            .signer()
+            .onion_shared_secret(&onion.ephemeral_key)
+            .and_then(|secret| onion.peel_with_secret(secret, &payment.hash_lock().to_vec()));
+        let (hop, next_onion, secret) = match peeled {
+            Ok(peeled) => peeled,
+            Err(e) => {
+                // we can't tell the sender, the previous hop will do.
+                warn!("fail to peel onion from {}, {}", peer_id, e);
+                self.send_error(peer_id, request_id, e);
+                return Ok(());
+            }
+        };
 
-        self.handle_channel_transaction_request(peer_id, &open_channel_message.request)
+        let checked = match next_onion {
+            Some(_) => self.check_forward(&payment, &hop),
+            None => self.check_final(&payment, &hop),
+        };
+        if let Err(e) = checked {
+            warn!("refuse htlc from {}, {}", peer_id, e);
+            let error = error_translate(&e);
+            let onion_error = create_failure(&secret, &error);
+            self.send_error_message(
+                peer_id,
+                ErrorMessage::new(request_id, error).with_onion_error(onion_error),
+            );
+            return Ok(());
+        }
+        if next_onion.is_none() {
+            // keep the secret to fail back the shard if the payment is incomplete.
+            self.onion_mgr
+                .add_received(
+                    request_id,
+                    ReceivedHtlc::new(*payment.hash_lock(), secret.clone()),
+                )
+                .await;
+        }
+        self.handle_channel_transaction_request(peer_id, &request)
             .await?;
 
-        info!("hops is {:?}", open_channel_message.hops);
-        if open_channel_message.hops.len() > 0 {
-            let operator = open_channel_message.request.channel_txn().operator();
-            let hop = open_channel_message.hops.remove(0);
-            if is_htlc_transfer(operator) {
-                let payment =
-                    parse_htlc_payment(open_channel_message.request.channel_txn().args())?;
-                let request = self
-                    .wallet
-                    .send_payment(
-                        hop.remote_addr.clone(),
-                        hop.amount,
-                        payment.hash_lock().to_vec(),
-                        payment.timeout(),
-                    )
-                    .await?;
-                let multi_request = MultiHopChannelRequest::new(request, open_channel_message.hops);
-                self.send_multi_hop_channel_request(
+        if let Some(onion) = next_onion {
+            info!(
+                "forward htlc to {}, amount {}, fee {}",
+                hop.remote_addr,
+                hop.amount,
+                payment.amount() - hop.amount
+            );
+            let forward_request = self
+                .wallet
+                .send_payment(
                     hop.remote_addr,
-                    multi_request,
-                    MessageType::MultiHopChannelTransactionRequest,
-                )?;
-                self.invoice_mgr
-                    .add_previous_hop(payment.hash_lock().clone(), peer_id)
-                    .await;
-            } else {
-                warn!("should be a htlc transfer");
-            }
+                    hop.amount,
+                    payment.hash_lock().to_vec(),
+                    hop.timeout,
+                )
+                .await?;
+            self.onion_mgr
+                .add_forwarded(
+                    forward_request.request_id(),
+                    ForwardedHtlc::new(peer_id, request_id, *payment.hash_lock(), secret),
+                )
+                .await;
+            self.send_multi_hop_channel_request(
+                hop.remote_addr,
+                MultiHopChannelRequest::new(forward_request, onion),
+                MessageType::MultiHopChannelTransactionRequest,
+            )?;
+            self.invoice_mgr
+                .add_previous_hop(payment.hash_lock().clone(), peer_id)
+                .await?;
         }
         Ok(())
     }
 
+    /// Check the incoming htlc pays our fee to forward it to `hop`,
+    /// and leaves us enough blocks to recall the forwarded one.
+    fn check_forward(&self, payment: &HtlcPayment, hop: &NextHop) -> Result<()> {
+        let policy = self.wallet.fee_policy(hop.remote_addr)?;
+        let fee = policy.fee(hop.amount);
+        if payment.amount() < hop.amount.saturating_add(fee) {
+            let err = SgError::new(
+                SgErrorCode::FEE_INSUFFICIENT,
+                format!(
+                    "forward {} needs fee {}, but only {} is received",
+                    hop.amount,
+                    fee,
+                    payment.amount()
+                ),
+            );
+            return Err(err.into());
+        }
+        if payment.timeout() < hop.timeout.saturating_add(policy.timelock_delta) {
+            let err = SgError::new(
+                SgErrorCode::TIMELOCK_TOO_SHORT,
+                format!(
+                    "timeout {} is less than {} to forward plus timelock delta {}",
+                    payment.timeout(),
+                    hop.timeout,
+                    policy.timelock_delta
+                ),
+            );
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Check the htlc for us is what the sender put in the onion,
+    /// so previous hops can't take more than their fee.
+    fn check_final(&self, payment: &HtlcPayment, hop: &NextHop) -> Result<()> {
+        if hop.remote_addr != self.wallet.account() {
+            let err = SgError::new(
+                SgErrorCode::REJECT,
+                format!("onion ends at {}, not us", hop.remote_addr),
+            );
+            return Err(err.into());
+        }
+        if payment.amount() < hop.amount {
+            let err = SgError::new(
+                SgErrorCode::FEE_INSUFFICIENT,
+                format!(
+                    "sender pays {}, but only {} is received",
+                    hop.amount,
+                    payment.amount()
+                ),
+            );
+            return Err(err.into());
+        }
+        if payment.timeout() < hop.timeout {
+            let err = SgError::new(
+                SgErrorCode::TIMELOCK_TOO_SHORT,
+                format!(
+                    "sender sets timeout {}, but only {} is received",
+                    hop.timeout,
+                    payment.timeout()
+                ),
+            );
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Decide proposal from `peer_id` which needs approval by the approval policy,
+    /// leave it to the user if it fails to decide.
+    async fn decide_approval(
+        &self,
+        peer_id: AccountAddress,
+        request: &ChannelTransactionRequest,
+    ) -> (ApprovalDecision, String) {
+        let request_id = request.request_id();
+        let channel_address = request.channel_address();
+        let decision = match self.wallet.pending_txn_outflows(channel_address).await {
+            Ok(outflows) => {
+                let operator = request.channel_txn().operator().clone();
+                let mut facts = ProposalFacts::new(peer_id, operator, 0);
+                for (asset, amount) in outflows {
+                    if asset.asset == *DEFAULT_ASSET {
+                        facts.amount = amount;
+                    } else {
+                        facts = facts.with_asset_amount(asset.package_name, amount);
+                    }
+                }
+                self.approval_mgr
+                    .decide(request_id, channel_address, &facts)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        decision.unwrap_or_else(|e| {
+            warn!("fail to decide proposal {}, {}", request_id, e);
+            (ApprovalDecision::Ask, format!("fail to decide, {}", e))
+        })
+    }
+
     async fn handle_receiver_channel(&self, data: Vec<u8>, peer_id: AccountAddress) -> Result<()> {
         info!("receive channel");
         let open_channel_message = ChannelTransactionRequest::from_proto_bytes(data)?;
@@ -969,12 +1935,62 @@ impl NodeInner {
                 match tx {
                     Some(t) => receiver_open_txn = t,
                     None => {
-                        if self.auto_approve {
-                            receiver_open_txn =
-                                self.wallet.approve_txn(peer_id, request_id).await.unwrap()
-                        } else {
-                            info!("need approved by user");
-                            return Ok(()); // it means user approval is needed.
+                        let channel_address = open_channel_message.channel_address();
+                        let agreed = self
+                            .negotiation_mgr
+                            .take_agreed(&peer_id, open_channel_message.channel_txn())
+                            .await;
+                        let decision = match agreed {
+                            Some(terms) => {
+                                info!("open channel with {} as negotiated", peer_id);
+                                match self
+                                    .wallet
+                                    .set_channel_terms(peer_id, ChannelTerms::from(&terms))
+                                {
+                                    Ok(()) => (ApprovalDecision::Approve, "negotiated".to_string()),
+                                    Err(e) => (
+                                        ApprovalDecision::Reject,
+                                        format!("fail to save agreed terms: {}", e),
+                                    ),
+                                }
+                            }
+                            None => self.decide_approval(peer_id, &open_channel_message).await,
+                        };
+                        match decision {
+                            (ApprovalDecision::Approve, _) => {
+                                match self
+                                    .wallet
+                                    .approve_channel_txn(channel_address, request_id)
+                                    .await
+                                {
+                                    Ok(t) => {
+                                        if let Err(e) = self.approval_mgr.confirm(&request_id).await
+                                        {
+                                            warn!(
+                                                "fail to count approved proposal {}, {}",
+                                                request_id, e
+                                            );
+                                        }
+                                        receiver_open_txn = t
+                                    }
+                                    Err(e) => {
+                                        self.approval_mgr.release(&request_id).await;
+                                        return Err(e);
+                                    }
+                                }
+                            }
+                            (ApprovalDecision::Reject, reason) => {
+                                self.wallet
+                                    .reject_channel_txn(channel_address, request_id)
+                                    .await?;
+                                let err = SgError::new(SgErrorCode::REJECT, reason);
+                                self.send_error(peer_id, request_id, err.into());
+                                return Ok(());
+                            }
+                            (ApprovalDecision::Ask, _) => {
+                                info!("need approved by user");
+                                return Ok(()); // it means user approval is needed.
+                            }
                         }
                     }
                 }
@@ -993,19 +2009,38 @@ impl NodeInner {
         self.apply_txn(peer_id, request_id, receiver_open_txn).await;
         if is_htlc_transfer(operator) {
             let payment = parse_htlc_payment(open_channel_message.channel_txn().args())?;
-            match self.invoice_mgr.get_preimage(payment.hash_lock()).await {
-                Some(preimage) => {
-                    let request = self.wallet.receive_payment(peer_id, preimage).await?;
-                    info!("last hop generate request");
-                    self.send_channel_request(
-                        peer_id,
-                        request,
-                        MessageType::ChannelTransactionRequest,
-                    )?;
+            match self
+                .invoice_mgr
+                .accept_payment(
+                    payment.hash_lock(),
+                    request_id,
+                    peer_id,
+                    payment.amount(),
+                    payment.timeout(),
+                )
+                .await?
+            {
+                Some(accepted) => {
+                    let mut payers = BTreeSet::new();
+                    for shard in accepted.shards {
+                        info!(
+                            "last hop claims shard {} from {}",
+                            shard.request_id, shard.payer
+                        );
+                        self.invoice_mgr
+                            .add_claim(shard.payer, accepted.preimage.clone())
+                            .await;
+                        payers.insert(shard.payer);
+                    }
+                    self.invoice_mgr.settle(payment.hash_lock()).await?;
+                    for payer in payers {
+                        self.claim_htlcs(payer).await;
+                    }
+                    self.onion_mgr.settle(payment.hash_lock(), &[]).await;
                 }
                 None => {
                     info!(
-                        "could not find preimage by rhash {},wait for timeout",
+                        "could not release preimage by rhash {} yet,wait for more shards or timeout",
                         payment.hash_lock()
                     );
                 }
@@ -1013,56 +2048,132 @@ impl NodeInner {
         }
         if is_htlc_receive(operator) {
             let preimage = parse_htlc_preimage(open_channel_message.channel_txn().args())?;
-            match self
-                .invoice_mgr
-                .get_previous_hop(preimage.clone().to_vec())
-                .await
-            {
-                Some(previous_addr) => {
-                    info!(
-                        "get router receive payment message from {} to {}",
-                        peer_id, previous_addr
-                    );
-                    let request = self
-                        .wallet
-                        .receive_payment(previous_addr.clone(), preimage.to_vec())
-                        .await?;
-                    self.send_channel_request(
-                        previous_addr,
-                        request,
-                        MessageType::ChannelTransactionRequest,
-                    )?;
-                }
-                None => {
-                    info!(
-                        "could not find privous addr by preimage {},wait for timeout",
-                        preimage
-                    );
+            let mut settled = vec![];
+            if let Some(payment) = self.payment_mgr.succeed(preimage.to_vec()).await? {
+                for attempt in payment.attempts() {
+                    settled.push(attempt.request_id);
+                    if attempt.status == PaymentStatus::Succeeded {
+                        self.report_payment(attempt.route.clone(), attempt.amount, None)
+                            .await;
+                    }
                 }
             }
+            self.onion_mgr
+                .settle(
+                    &HashValue::from_sha3_256(preimage.to_vec().as_slice()),
+                    &settled,
+                )
+                .await;
+            let previous_hops = self
+                .invoice_mgr
+                .take_previous_hops(preimage.to_vec())
+                .await?;
+            if previous_hops.is_empty() {
+                info!(
+                    "could not find privous addr by preimage {},wait for timeout",
+                    preimage
+                );
+            }
+            for previous_addr in previous_hops.iter() {
+                info!(
+                    "get router receive payment message from {} to {}",
+                    peer_id, previous_addr
+                );
+                self.invoice_mgr
+                    .add_claim(*previous_addr, preimage.to_vec())
+                    .await;
+            }
+            for previous_addr in previous_hops.into_iter().collect::<BTreeSet<_>>() {
+                self.claim_htlcs(previous_addr).await;
+            }
         }
         Ok(())
     }
 
+    /// Propose to claim the next queued htlc from `payer`, unless a claim of it is in flight.
+    /// Htlcs which can't be claimed now are retried after next txn with `payer` is applied.
+    async fn claim_htlcs(&self, payer: AccountAddress) {
+        let preimage = match self.invoice_mgr.next_claim(payer).await {
+            Some(preimage) => preimage,
+            None => return,
+        };
+        match self.wallet.receive_payment(payer, preimage.clone()).await {
+            Ok(request) => {
+                self.invoice_mgr
+                    .claim_sent(payer, request.request_id(), preimage)
+                    .await;
+                if let Err(e) = self.send_channel_request(
+                    payer,
+                    request,
+                    MessageType::ChannelTransactionRequest,
+                ) {
+                    warn!("fail to send claim to {}, {}", payer, e);
+                }
+            }
+            Err(e) => {
+                warn!("fail to claim htlc from {}, retry later, {}", payer, e);
+                self.invoice_mgr.claim_failed(payer, preimage).await;
+            }
+        }
+    }
+
     async fn apply_txn(
         &self,
         peer_id: AccountAddress,
         request_id: HashValue,
         receiver_open_txn: ChannelTransactionResponse,
     ) {
-        let msg = add_message_type(
-            receiver_open_txn.clone().into_proto_bytes().unwrap(),
-            MessageType::ChannelTransactionResponse,
-        );
-        debug!("send msg to {:?}", peer_id);
-        self.sender
-            .unbounded_send(NetworkMessage {
-                peer_id,
-                data: msg.to_vec(),
-            })
-            .unwrap();
+        let channel_address = receiver_open_txn.channel_txn().channel_address();
+        let participants = match self.wallet.channel_participants(channel_address).await {
+            Ok(participants) => participants,
+            Err(e) => {
+                warn!(
+                    "get participants of channel {} fail, err: {:?}",
+                    channel_address, &e
+                );
+                return;
+            }
+        };
+        // every participant should collect signatures of all others.
+        for participant in participants.iter() {
+            if participant == &self.wallet.account() {
+                continue;
+            }
+            let msg = add_message_type(
+                receiver_open_txn.clone().into_proto_bytes().unwrap(),
+                MessageType::ChannelTransactionResponse,
+            );
+            debug!("send msg to {:?}", participant);
+            self.sender
+                .unbounded_send(NetworkMessage {
+                    peer_id: participant.clone(),
+                    data: msg.to_vec(),
+                })
+                .unwrap();
+        }
+        match self
+            .wallet
+            .is_pending_txn_agreed(channel_address, request_id)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                info!(
+                    "txn {} wait for signatures of other participants",
+                    request_id
+                );
+                return;
+            }
+            Err(e) => {
+                warn!("check pending txn fail, err: {:?}", &e);
+                return;
+            }
+        }
         match self.wallet.apply_txn(peer_id, &receiver_open_txn).await {
-            Ok(_) => {}
+            Ok(_) => {
+                self.invoice_mgr.txn_applied(peer_id, request_id).await;
+                self.claim_htlcs(peer_id).await;
+            }
             Err(e) => {
                 warn!("apply tx fail, err: {:?}", &e);
                 self.sender
@@ -1079,30 +2190,143 @@ impl NodeInner {
     async fn handle_sender_channel(&self, data: Vec<u8>, peer_id: AccountAddress) -> Result<()> {
         debug!("sender channel");
         let open_channel_message = ChannelTransactionResponse::from_proto_bytes(&data)?;
+        let channel_address = open_channel_message.channel_txn().channel_address();
+        if self.wallet.is_restoring_channel(channel_address) {
+            self.wallet
+                .apply_restore_close(peer_id, &open_channel_message)
+                .await?;
+            self.message_processor.send_response(
+                open_channel_message.request_id(),
+                open_channel_message.channel_txn().channel_sequence_number() + 1,
+            )?;
+            return Ok(());
+        }
 
-        self.wallet
+        let agreed = self
+            .wallet
             .verify_txn_response(peer_id, &open_channel_message)
             .await?;
+        if !agreed {
+            debug!(
+                "txn {} wait for signatures of other participants",
+                open_channel_message.request_id()
+            );
+            return Ok(());
+        }
 
         self.wallet
             .apply_txn(peer_id, &open_channel_message)
             .await?;
+        self.invoice_mgr
+            .txn_applied(peer_id, open_channel_message.request_id())
+            .await;
+        self.claim_htlcs(peer_id).await;
 
-        let channel_seq_number = self.wallet.channel_sequence_number(peer_id).await?;
+        let channel_seq_number = self
+            .wallet
+            .channel_sequence_number_of(channel_address)
+            .await?;
 
         self.message_processor
             .send_response(open_channel_message.request_id(), channel_seq_number)?;
 
+        if self
+            .wallet
+            .channel_participants(channel_address)
+            .await?
+            .len()
+            > 2
+        {
+            return Ok(());
+        }
         self.router.stats(
             (self.wallet.account(), peer_id),
             (open_channel_message.request_id(), 0, PayEnum::Payed),
         )
     }
 
-    fn handle_error_message(&self, data: Vec<u8>) {
+    fn send_error(&self, peer_id: AccountAddress, request_id: HashValue, e: Error) {
+        self.send_error_message(peer_id, ErrorMessage::new(request_id, error_translate(&e)));
+    }
+
+    fn send_error_message(&self, peer_id: AccountAddress, message: ErrorMessage) {
+        let data = add_message_type(
+            message.into_proto_bytes().unwrap(),
+            MessageType::ErrorMessage,
+        );
+        if let Err(e) = self.sender.unbounded_send(NetworkMessage {
+            peer_id,
+            data: data.to_vec(),
+        }) {
+            warn!("fail to send error message to {}, {}", peer_id, e);
+        }
+    }
+
+    async fn handle_error_message(&self, data: Vec<u8>, peer_id: AccountAddress) {
         debug!("off error message");
         match ErrorMessage::from_proto_bytes(&data) {
-            Ok(msg) => {
+            Ok(mut msg) => {
+                let hash = msg.raw_transaction_hash;
+                if msg.error.error_code == SgErrorCode::PROPOSAL_EXPIRED {
+                    // the peer gave up the proposal, don't wait our own timer to cancel it.
+                    match msg.channel_address {
+                        Some(channel_address) => {
+                            if let Err(e) = self
+                                .wallet
+                                .cancel_pending_request_in_channel(channel_address, peer_id, hash)
+                                .await
+                            {
+                                warn!("fail to cancel expired proposal {}, {}", hash, e);
+                            }
+                        }
+                        None => warn!(
+                            "expired proposal {} from {} has no channel address",
+                            hash, peer_id
+                        ),
+                    }
+                }
+                if let Some(htlc) = self.onion_mgr.take_forwarded(&hash).await {
+                    // htlc we forwarded fails, pass the failure back to the sender.
+                    let onion_error = if msg.onion_error.is_empty() {
+                        create_failure(&htlc.secret, &msg.error)
+                    } else {
+                        wrap_failure(&htlc.secret, msg.onion_error.clone())
+                    };
+                    let error = SgError::new(
+                        SgErrorCode::UNKNOWN,
+                        "htlc fails after forwarded".to_string(),
+                    );
+                    self.send_error_message(
+                        htlc.peer_id,
+                        ErrorMessage::new(htlc.request_id, error).with_onion_error(onion_error),
+                    );
+                }
+                let mut failed_node = peer_id;
+                if let Some(sent) = self.onion_mgr.take_sent(&hash).await {
+                    let failed_hop = match decode_failure(&sent.secrets, &msg.onion_error) {
+                        Ok((index, error)) => {
+                            info!("hop {} of payment fails, {}", index + 1, error);
+                            failed_node = sent.route[index + 1];
+                            msg.error = error;
+                            // a hop fails to forward to the next one, or the receiver refuses.
+                            std::cmp::min(index + 1, sent.route.len() - 2)
+                        }
+                        Err(e) => {
+                            warn!("fail to decode onion error from {}, {}", peer_id, e);
+                            0
+                        }
+                    };
+                    self.report_payment(sent.route, sent.amount, Some(failed_hop))
+                        .await;
+                }
+                let failure = AttemptFailure::new(&msg.error, Some(failed_node));
+                if let Err(e) = self
+                    .payment_mgr
+                    .fail_attempt(&msg.raw_transaction_hash, failure)
+                    .await
+                {
+                    warn!("fail to update payment attempt, {}", e);
+                }
                 self.message_processor.future_error(msg).unwrap();
             }
             Err(_e) => {
@@ -1135,6 +2359,38 @@ impl NodeInner {
         Ok(message_future)
     }
 
+    /// Send `request` to all other participants of the channel, the future resolves
+    /// when all of them signed the request.
+    fn send_channel_request_to_all(
+        &self,
+        participants: &BTreeSet<AccountAddress>,
+        request: ChannelTransactionRequest,
+    ) -> Result<MessageFuture<u64>> {
+        let hash_value = request.request_id();
+        let msg = add_message_type(
+            request.into_proto_bytes()?,
+            MessageType::ChannelTransactionRequest,
+        );
+        for participant in participants.iter() {
+            if participant == &self.wallet.account() {
+                continue;
+            }
+            self.sender.unbounded_send(NetworkMessage {
+                peer_id: participant.clone(),
+                data: msg.to_vec(),
+            })?;
+        }
+        let (tx, rx) = futures_01::sync::mpsc::channel(1);
+        let message_future = MessageFuture::new(rx);
+        self.message_processor.add_future(hash_value.clone(), tx);
+        self.future_timeout(
+            hash_value,
+            self.default_future_timeout.load(Ordering::Relaxed),
+        );
+
+        Ok(message_future)
+    }
+
     fn send_multi_hop_channel_request(
         &self,
         peer_id: AccountAddress,
@@ -1184,79 +2440,425 @@ impl NodeInner {
         Ok(())
     }
 
-    async fn off_chain_pay_htlc(
+    async fn off_chain_pay_asset(
         &self,
         receiver_address: AccountAddress,
+        asset: StructTag,
+        amount: u64,
+        responder: futures::channel::oneshot::Sender<Result<MessageFuture<u64>>>,
+    ) {
+        let result = match self
+            .wallet
+            .transfer_asset(receiver_address, &asset, amount)
+            .await
+        {
+            Ok(txn) => self.send_channel_request(
+                receiver_address,
+                txn,
+                MessageType::ChannelTransactionRequest,
+            ),
+            Err(e) => Err(e),
+        };
+        respond_with(responder, result);
+    }
+
+    async fn off_chain_pay_htlc(
+        node_inner: Arc<NodeInner>,
+        receiver_address: AccountAddress,
         amount: u64,
         hash_lock: Vec<u8>,
         timeout: u64,
+        route_hints: Vec<RouteHint>,
         responder: futures::channel::oneshot::Sender<Result<MessageFuture<u64>>>,
     ) -> Result<()> {
-        self.router.stats(
-            (self.wallet.account(), receiver_address),
+        node_inner.router.stats(
+            (node_inner.wallet.account(), receiver_address),
             (
                 HashValue::from_sha3_256(&hash_lock),
                 amount,
                 PayEnum::Paying,
             ),
         )?;
+        let r_hash = match HashValue::from_slice(&hash_lock) {
+            Ok(r_hash) => r_hash,
+            Err(e) => {
+                respond_with(responder, Err(e));
+                return Ok(());
+            }
+        };
+        if let Err(e) = node_inner
+            .payment_mgr
+            .begin(r_hash, receiver_address, amount)
+            .await
+        {
+            respond_with(responder, Err(e));
+            return Ok(());
+        }
 
-        let path = self
-            .router
-            .find_path_by_addr(self.wallet.account(), receiver_address)
+        let path = node_inner
+            .find_payment_path(receiver_address, amount, &route_hints)
             .await;
-        match path {
-            Ok(v) => {
-                info!("path is {:?}", v);
-                let is_balance_enough = self.check_balance(&v, amount)?;
-                info!("is balance enough is {}", is_balance_enough);
-                if is_balance_enough == false {
-                    let err = SgError::new(
-                        SgErrorCode::BALANCE_NOT_ENOUGH,
-                        format!(
-                            "path balance is not enough ,from {} to {}",
-                            self.wallet.account(),
-                            receiver_address
-                        ),
-                    );
-                    respond_with(responder, Err(err.into()));
-                    return Ok(());
+        let result = match path {
+            Ok((path, policies)) => Self::send_htlc_with_retry(
+                node_inner.clone(),
+                path,
+                policies,
+                amount,
+                hash_lock,
+                timeout,
+                route_hints,
+            )
+            .await
+            .map(|(f, _)| f),
+            Err(e) => {
+                info!("no single path can carry {}, try multi path, {}", amount, e);
+                match Self::pay_multi_path(
+                    node_inner.clone(),
+                    receiver_address,
+                    amount,
+                    hash_lock,
+                    timeout,
+                )
+                .await
+                {
+                    Ok(f) => Ok(f),
+                    Err(mpp_err) => {
+                        warn!("multi path payment failed, {}", mpp_err);
+                        Err(e)
+                    }
                 }
-                let v = self.balance_response_to_address(&v)?;
-                match self
-                    .get_multi_hop_request(v, amount, hash_lock, timeout)
+            }
+        };
+        if let Err(e) = &result {
+            let failure = AttemptFailure::new(&error_translate(e), None);
+            node_inner.payment_mgr.fail(&r_hash, failure).await?;
+        }
+        respond_with(responder, result);
+        Ok(())
+    }
+
+    /// Send htlc over `path` as an attempt of payment locked by `hash_lock`,
+    /// `policies` are fee policies of intermediate hops of the path.
+    /// The attempt is marked as failed if the first hop rejects it or doesn't answer in time.
+    async fn send_htlc_shard(
+        &self,
+        path: Vec<AccountAddress>,
+        policies: Vec<FeePolicy>,
+        amount: u64,
+        hash_lock: Vec<u8>,
+        timeout: u64,
+    ) -> Result<MessageFuture<u64>> {
+        let r_hash = HashValue::from_slice(&hash_lock)?;
+        let (off_chain_pay_tx, next_addr, fee, secrets) = self
+            .get_multi_hop_request(path.clone(), &policies, amount, hash_lock, timeout)
+            .await?;
+        let request_id = off_chain_pay_tx.request.request_id();
+        self.onion_mgr
+            .add_sent(request_id, SentOnion::new(path.clone(), amount, secrets))
+            .await;
+        self.payment_mgr
+            .add_attempt(&r_hash, PaymentAttempt::new(request_id, path, amount, fee))
+            .await?;
+        let f = match self.send_multi_hop_channel_request(
+            next_addr,
+            off_chain_pay_tx,
+            MessageType::MultiHopChannelTransactionRequest,
+        ) {
+            Ok(f) => f,
+            Err(e) => {
+                self.onion_mgr.take_sent(&request_id).await;
+                let failure = AttemptFailure::new(&error_translate(&e), None);
+                self.payment_mgr.fail_attempt(&request_id, failure).await?;
+                return Err(e);
+            }
+        };
+
+        let payment_mgr = self.payment_mgr.clone();
+        let onion_mgr = self.onion_mgr.clone();
+        let (tx, rx) = futures_01::sync::mpsc::channel(1);
+        self.executor.spawn(async move {
+            let result = f.compat().await;
+            if let Err(e) = &result {
+                if e.error_code == SgErrorCode::TIMEOUT {
+                    // no failure will come back.
+                    onion_mgr.take_sent(&request_id).await;
+                }
+                let failure = AttemptFailure::new(e, None);
+                if let Err(e) = payment_mgr.fail_attempt(&request_id, failure).await {
+                    warn!("fail to update payment attempt, {}", e);
+                }
+            }
+            if let Err(_e) = tx.send(result.map_err(Error::from)).compat().await {
+                warn!("fail to send payment attempt result");
+            }
+        });
+        Ok(MessageFuture::new(rx))
+    }
+
+    /// Send htlc over `path` like `send_htlc_shard`. Outcome of every attempt is reported
+    /// to router, and a failed one is retried over the next best path to the same receiver
+    /// until `PAYMENT_RETRY_TIMEOUT` passes or there is no other path.
+    /// Retrying stops when the returned handle is aborted, and the future resolves as timeout.
+    async fn send_htlc_with_retry(
+        node_inner: Arc<NodeInner>,
+        path: Vec<AccountAddress>,
+        policies: Vec<FeePolicy>,
+        amount: u64,
+        hash_lock: Vec<u8>,
+        timeout: u64,
+        route_hints: Vec<RouteHint>,
+    ) -> Result<(MessageFuture<u64>, AbortHandle)> {
+        let deadline = get_unix_ts() + PAYMENT_RETRY_TIMEOUT;
+        let receiver_address = *path.last().expect("path should not be empty");
+        let mut f = node_inner
+            .send_htlc_shard(path.clone(), policies, amount, hash_lock.clone(), timeout)
+            .await?;
+
+        let (tx, rx) = futures_01::sync::mpsc::channel(1);
+        let executor = node_inner.executor.clone();
+        let (retry, abort_handle) = abortable(async move {
+            let mut tried = vec![path];
+            let result = loop {
+                let path = tried
+                    .last()
+                    .expect("tried paths should not be empty")
+                    .clone();
+                let e = match f.compat().await {
+                    Ok(gas_used) => {
+                        // only the first hop is known to succeed until preimage comes back.
+                        node_inner
+                            .report_payment(path[..2].to_vec(), amount, None)
+                            .await;
+                        break Ok(gas_used);
+                    }
+                    Err(e) => e,
+                };
+                // other failures are reported when they come back in onion.
+                if e.error_code == SgErrorCode::TIMEOUT {
+                    node_inner.report_payment(path, amount, Some(0)).await;
+                }
+                if get_unix_ts() >= deadline {
+                    break Err(e.into());
+                }
+                let (next_path, policies) = match node_inner
+                    .find_payment_path(receiver_address, amount, &route_hints)
                     .await
                 {
-                    Ok((off_chain_pay_tx, next_addr)) => respond_with(
-                        responder,
-                        self.send_multi_hop_channel_request(
-                            next_addr,
-                            off_chain_pay_tx,
-                            MessageType::MultiHopChannelTransactionRequest,
-                        ),
-                    ),
-                    Err(e) => respond_with(responder, Err(e)),
+                    Ok((ref next_path, _)) if tried.contains(next_path) => {
+                        info!("no other path to {}, stop retrying", receiver_address);
+                        break Err(e.into());
+                    }
+                    Ok(v) => v,
+                    Err(find_err) => {
+                        info!("stop retrying payment, {}", find_err);
+                        break Err(e.into());
+                    }
                 };
+                info!("payment attempt failed, {}, retry over {:?}", e, next_path);
+                f = match node_inner
+                    .send_htlc_shard(
+                        next_path.clone(),
+                        policies,
+                        amount,
+                        hash_lock.clone(),
+                        timeout,
+                    )
+                    .await
+                {
+                    Ok(f) => f,
+                    Err(send_err) => break Err(send_err),
+                };
+                tried.push(next_path);
+            };
+            if let Err(_e) = tx.send(result).compat().await {
+                warn!("fail to send payment result");
             }
-            _ => {
+        });
+        executor.spawn(retry.map(|_| ()));
+        Ok((MessageFuture::new(rx), abort_handle))
+    }
+
+    /// tell router how the payment went over `route`, so it picks better paths later.
+    async fn report_payment(
+        &self,
+        route: Vec<AccountAddress>,
+        amount: u64,
+        failed_hop: Option<usize>,
+    ) {
+        let outcome = PaymentOutcome::new(route, amount, failed_hop);
+        if let Err(e) = self.router.report_payment(outcome).await {
+            warn!("fail to report payment outcome to router, {}", e);
+        }
+    }
+
+    /// Split payment into shards locked by the same `hash_lock`, and send them over
+    /// several paths. The returned future is ready when all shards are sent out.
+    /// Once a shard fails, others stop retrying, and the receiver fails them back
+    /// when the incomplete set times out.
+    async fn pay_multi_path(
+        node_inner: Arc<NodeInner>,
+        receiver_address: AccountAddress,
+        amount: u64,
+        hash_lock: Vec<u8>,
+        timeout: u64,
+    ) -> Result<MessageFuture<u64>> {
+        let paths = node_inner
+            .router
+            .find_paths_by_addr(node_inner.wallet.account(), receiver_address, amount)
+            .await?;
+        let shards = match split_payment(paths, amount, MAX_PAYMENT_SHARDS) {
+            Some(shards) => shards,
+            None => {
                 let err = SgError::new(
-                    SgErrorCode::NOT_PATH,
+                    SgErrorCode::BALANCE_NOT_ENOUGH,
                     format!(
-                        "could not find path ,from {} to {}",
-                        self.wallet.account(),
+                        "paths balance is not enough ,from {} to {}",
+                        node_inner.wallet.account(),
                         receiver_address
                     ),
                 );
-                respond_with(responder, Err(err.into()));
-                return Ok(());
+                return Err(err.into());
             }
         };
-        Ok(())
+        info!("split payment {} into {} shards", amount, shards.len());
+
+        let mut shard_futures = Vec::new();
+        let mut abort_handles = Vec::new();
+        for (path, shard_amount) in shards {
+            let policies = path_fee_policies(&path);
+            let sent = match node_inner.balance_response_to_address(&path) {
+                Ok(path) => {
+                    Self::send_htlc_with_retry(
+                        node_inner.clone(),
+                        path,
+                        policies,
+                        shard_amount,
+                        hash_lock.clone(),
+                        timeout,
+                        vec![],
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            match sent {
+                Ok((f, abort_handle)) => {
+                    shard_futures.push(f.compat());
+                    abort_handles.push(abort_handle);
+                }
+                Err(e) => {
+                    warn!(
+                        "fail to send shard of multi path payment, cancel {} sent shards, {}",
+                        abort_handles.len(),
+                        e
+                    );
+                    abort_handles.iter().for_each(AbortHandle::abort);
+                    return Err(e);
+                }
+            }
+        }
+
+        let (tx, rx) = futures_01::sync::mpsc::channel(1);
+        node_inner.executor.spawn(async move {
+            let result = futures::future::try_join_all(shard_futures)
+                .await
+                .map(|gas_used: Vec<u64>| gas_used.into_iter().sum::<u64>())
+                .map_err(Error::from);
+            if result.is_err() {
+                // the payment can't complete, stop retrying other shards.
+                abort_handles.iter().for_each(AbortHandle::abort);
+            }
+            if let Err(_e) = tx.send(result).compat().await {
+                warn!("fail to send multi path payment result");
+            }
+        });
+        Ok(MessageFuture::new(rx))
     }
 
+    /// Find path from self to `receiver_address` in channel graph,
+    /// fall back to the private routes hinted by receiver if there is none.
+    /// Return the path and fee policies of its intermediate hops,
+    /// hops out of the channel graph are assumed to charge the default fee.
+    async fn find_payment_path(
+        &self,
+        receiver_address: AccountAddress,
+        amount: u64,
+        route_hints: &[RouteHint],
+    ) -> Result<(Vec<AccountAddress>, Vec<FeePolicy>)> {
+        if let Ok(v) = self
+            .router
+            .find_path_by_addr(self.wallet.account(), receiver_address, amount)
+            .await
+        {
+            if !v.is_empty() {
+                return self.checked_path(v, amount, receiver_address);
+            }
+        }
+        for hint in route_hints {
+            let entry = match hint.hops.first() {
+                Some(entry) => *entry,
+                None => continue,
+            };
+            let (mut path, mut policies) = if entry == self.wallet.account() {
+                (vec![entry], vec![])
+            } else {
+                match self
+                    .router
+                    .find_path_by_addr(self.wallet.account(), entry, amount)
+                    .await
+                {
+                    Ok(v) if !v.is_empty() => self.checked_path(v, amount, receiver_address)?,
+                    _ => continue,
+                }
+            };
+            path.extend_from_slice(&hint.hops[1..]);
+            path.push(receiver_address);
+            policies.resize(path.len() - 2, FeePolicy::default());
+            info!("use route hint, path is {:?}", path);
+            return Ok((path, policies));
+        }
+        let err = SgError::new(
+            SgErrorCode::NOT_PATH,
+            format!(
+                "could not find path ,from {} to {}",
+                self.wallet.account(),
+                receiver_address
+            ),
+        );
+        Err(err.into())
+    }
+
+    fn checked_path(
+        &self,
+        path: Vec<BalanceQueryResponse>,
+        amount: u64,
+        receiver_address: AccountAddress,
+    ) -> Result<(Vec<AccountAddress>, Vec<FeePolicy>)> {
+        info!("path is {:?}", path);
+        let is_balance_enough = self.check_balance(&path, amount)?;
+        info!("is balance enough is {}", is_balance_enough);
+        if !is_balance_enough {
+            let err = SgError::new(
+                SgErrorCode::BALANCE_NOT_ENOUGH,
+                format!(
+                    "path balance is not enough ,from {} to {}",
+                    self.wallet.account(),
+                    receiver_address
+                ),
+            );
+            return Err(err.into());
+        }
+        Ok((
+            self.balance_response_to_address(&path)?,
+            path_fee_policies(&path),
+        ))
+    }
+
+    /// Check every hop can carry the amount it forwards, fees of later hops included.
     fn check_balance(&self, path: &Vec<BalanceQueryResponse>, amount: u64) -> Result<bool> {
-        for response in path {
-            if response.local_balance < amount {
+        let amounts = route_amounts(amount, 0, &path_fee_policies(path))?;
+        for (response, (hop_amount, _)) in path.iter().zip(amounts) {
+            if response.local_balance < hop_amount {
                 return Ok(false);
             }
         }
@@ -1283,40 +2885,71 @@ impl NodeInner {
         Ok(result)
     }
 
-    // vertexes contains node self. need pop self out
+    // vertexes contains node self.
+    // every intermediate hop gets its fee and timelock delta on top of what it forwards,
+    // what to forward is wrapped in the onion, every hop can only read its own part.
+    // return the request to first hop, the address of first hop, the total fee,
+    // and secrets shared with the hops.
     async fn get_multi_hop_request(
         &self,
-        mut vertexes: Vec<AccountAddress>,
+        vertexes: Vec<AccountAddress>,
+        policies: &[FeePolicy],
         amount: u64,
         hash_lock: Vec<u8>,
         timeout: u64,
-    ) -> Result<(MultiHopChannelRequest, AccountAddress)> {
+    ) -> Result<(
+        MultiHopChannelRequest,
+        AccountAddress,
+        u64,
+        Vec<SharedSecret>,
+    )> {
         let len = vertexes.len();
         ensure!(len >= 2, "should have at least 2 hops");
-        let mut hops = Vec::new();
-        let first_addr = vertexes.remove(0);
-        info!(
-            "first hop is {:?},my account addr is {}",
-            first_addr,
-            self.wallet.account()
+        ensure!(
+            policies.len() == len - 2,
+            "should have fee policy of every intermediate hop"
         );
-        if self.wallet.account() == first_addr {
-            let receiver_address = vertexes.remove(0);
-            for (index, _vertex) in vertexes.iter().enumerate() {
-                let next_hop = NextHop::new(
-                    vertexes.get(index).take().expect("should have").clone(),
-                    amount,
-                );
-                hops.push(next_hop);
+        ensure!(
+            vertexes[0] == self.wallet.account(),
+            "can't gen multi hop request, first hop is {}",
+            vertexes[0]
+        );
+        // amounts[i] is what the (i + 1)th vertex receives.
+        let amounts = route_amounts(amount, timeout, policies)?;
+        let mut public_keys = Vec::with_capacity(len - 1);
+        let mut payloads = Vec::with_capacity(len - 1);
+        for (index, vertex) in vertexes.iter().enumerate().skip(1) {
+            match self.router.node_public_key(*vertex).await? {
+                Some(public_key) => public_keys.push(public_key),
+                None => {
+                    let err = SgError::new(
+                        SgErrorCode::NOT_PATH,
+                        format!("public key of {} is unknown", vertex),
+                    );
+                    return Err(err.into());
+                }
             }
-            let request = self
-                .wallet
-                .send_payment(receiver_address.clone(), amount, hash_lock, timeout)
-                .await?;
-            return Ok((MultiHopChannelRequest::new(request, hops), receiver_address));
-        } else {
-            bail!("can't gen multi hop request")
+            // the receiver checks what it receives against its payload.
+            let payload = match vertexes.get(index + 1) {
+                Some(next) => NextHop::new(*next, amounts[index].0, amounts[index].1),
+                None => NextHop::new(*vertex, amount, amounts[index - 1].1),
+            };
+            payloads.push(payload);
         }
+        let (onion, secrets) = OnionPacket::new(&public_keys, &payloads, &hash_lock)?;
+
+        let first_addr = vertexes[1];
+        let (first_amount, first_timeout) = amounts[0];
+        let request = self
+            .wallet
+            .send_payment(first_addr, first_amount, hash_lock, first_timeout)
+            .await?;
+        Ok((
+            MultiHopChannelRequest::new(request, onion),
+            first_addr,
+            first_amount - amount,
+            secrets,
+        ))
     }
 
     async fn execute_script(
@@ -1366,6 +2999,37 @@ impl NodeInner {
         }
     }
 
+    async fn execute_script_in_channel(
+        &self,
+        channel_address: AccountAddress,
+        package_name: String,
+        script_name: String,
+        transaction_args: Vec<TransactionArgument>,
+        responder: futures::channel::oneshot::Sender<Result<MessageFuture<u64>>>,
+    ) {
+        let participants = match self.wallet.channel_participants(channel_address).await {
+            Ok(participants) => participants,
+            Err(e) => {
+                respond_with(responder, Err(e));
+                return;
+            }
+        };
+        let result = match self
+            .wallet
+            .execute_script_in_channel(
+                channel_address,
+                &package_name,
+                &script_name,
+                transaction_args,
+            )
+            .await
+        {
+            Ok(request) => self.send_channel_request_to_all(&participants, request),
+            Err(e) => Err(e),
+        };
+        respond_with(responder, result);
+    }
+
     async fn install_package(
         &self,
         channel_script_package: ChannelScriptPackage,
@@ -1451,6 +3115,19 @@ impl NodeInner {
         respond_with(responder, result);
     }
 
+    async fn open_multi_party_channel(
+        &self,
+        deposits: BTreeMap<AccountAddress, u64>,
+        responder: futures::channel::oneshot::Sender<Result<MessageFuture<u64>>>,
+    ) {
+        let participants = deposits.keys().cloned().collect::<BTreeSet<_>>();
+        let result = match self.wallet.open_multi_party(deposits).await {
+            Ok(request) => self.send_channel_request_to_all(&participants, request),
+            Err(e) => Err(e),
+        };
+        respond_with(responder, result);
+    }
+
     async fn deposit(
         &self,
         receiver: AccountAddress,
@@ -1526,6 +3203,149 @@ impl NodeInner {
             .unwrap();
     }
 
+    async fn sweep_expired_invoices(&self) {
+        match self.invoice_mgr.sweep_expired(get_unix_ts()).await {
+            Ok(swept) => {
+                for r_hash in swept.expired {
+                    info!("invoice {} expired", r_hash);
+                }
+                for shard in swept.failed_shards {
+                    self.fail_back_shard(shard).await;
+                }
+            }
+            Err(e) => {
+                warn!("sweep expired invoices err ,{}", e);
+            }
+        }
+    }
+
+    /// Tell the payer of a shard that can't make up a paid invoice, so that the sender stops
+    /// waiting for it. The htlc is recalled by the payer after timeout.
+    async fn fail_back_shard(&self, shard: PaymentShard) {
+        info!(
+            "fail back shard {} from {}, amount {}",
+            shard.request_id, shard.payer, shard.amount
+        );
+        let error = SgError::new(
+            SgErrorCode::MULTI_PATH_TIMEOUT,
+            "multi path payment is incomplete".to_string(),
+        );
+        let mut msg = ErrorMessage::new(shard.request_id, error.clone());
+        if let Some(htlc) = self.onion_mgr.take_received(&shard.request_id).await {
+            msg = msg.with_onion_error(create_failure(&htlc.secret, &error));
+        }
+        self.send_error_message(shard.payer, msg);
+    }
+
+    /// Close channels restored from backup with their participants,
+    /// and force close those whose participant is unreachable or refuses.
+    async fn restore_channels(&self, backup_path: PathBuf) -> Result<Vec<AccountAddress>> {
+        let mut closing = vec![];
+        for channel_address in self.wallet.restore_channels(&backup_path).await? {
+            match self.close_restored_channel(channel_address).await {
+                Ok(_) => {
+                    info!("restored channel {} is closed with peer", channel_address);
+                    closing.push(channel_address);
+                    continue;
+                }
+                Err(e) => warn!(
+                    "fail to close restored channel {} with peer, force close it, {}",
+                    channel_address, e
+                ),
+            }
+            match self
+                .wallet
+                .force_close_restored_channel(channel_address)
+                .await
+            {
+                Ok(true) => closing.push(channel_address),
+                Ok(false) => {}
+                Err(e) => warn!("fail to force close channel {}, {}", channel_address, e),
+            }
+        }
+        Ok(closing)
+    }
+
+    async fn close_restored_channel(&self, channel_address: AccountAddress) -> Result<()> {
+        let (participant, request) = self.wallet.restore_close_request(channel_address)?;
+        ensure!(
+            self.network_service.is_connected(participant),
+            "participant {} is unreachable",
+            participant
+        );
+        let f = self.send_channel_request(
+            participant,
+            request,
+            MessageType::ChannelTransactionRequest,
+        )?;
+        f.compat().await?;
+        Ok(())
+    }
+
+    /// Report state of channels with `peer_id` after it reconnects, so that both sides can
+    /// resend signatures the other misses, or roll back proposals the other never received.
+    async fn reestablish_channels(&self, peer_id: AccountAddress) {
+        let mut channels = vec![];
+        if let Ok(channel) = self.wallet.channel_handle(peer_id).await {
+            channels.push(channel.channel_address().clone());
+        }
+        match self.wallet.get_multi_party_channels().await {
+            Ok(multi_party) => channels.extend(
+                multi_party
+                    .into_iter()
+                    .filter(|(_, participants)| participants.contains(&peer_id))
+                    .map(|(channel_address, _)| channel_address),
+            ),
+            Err(e) => warn!("get multi-party channels err, {}", e),
+        }
+        for channel_address in channels {
+            let result = self
+                .wallet
+                .channel_reestablish(channel_address)
+                .await
+                .and_then(|state| {
+                    self.send_node_message(peer_id, NodeNetworkMessage::ChannelReestablish(state))
+                });
+            if let Err(e) = result {
+                warn!(
+                    "reestablish channel {} with {} err, {}",
+                    channel_address, peer_id, e
+                );
+            }
+        }
+    }
+
+    /// Ask peers to co-sign checkpoints of channels due for it,
+    /// the one with smaller address starts it, so that only one is in flight.
+    async fn checkpoint_channels(&self) {
+        let participants = match self.wallet.channels_due_for_checkpoint().await {
+            Ok(participants) => participants,
+            Err(e) => {
+                warn!("get channels due for checkpoint err, {}", e);
+                return;
+            }
+        };
+        for participant in participants {
+            if self.wallet.account() > participant
+                || !self.network_service.is_connected(participant)
+            {
+                continue;
+            }
+            let result = self
+                .wallet
+                .sign_checkpoint(participant)
+                .and_then(|checkpoint| {
+                    self.send_node_message(
+                        participant,
+                        NodeNetworkMessage::ChannelCheckpointRequest(checkpoint),
+                    )
+                });
+            if let Err(e) = result {
+                warn!("checkpoint channel with {} err, {}", participant, e);
+            }
+        }
+    }
+
     pub fn set_timeout(&self, timeout: u64) {
         self.default_future_timeout.swap(timeout, Ordering::Relaxed);
     }
@@ -1,10 +1,14 @@
 use crate::message_processor::MessageFuture;
 use anyhow::Result;
 use futures::channel::oneshot;
+use libra_types::language_storage::StructTag;
 use libra_types::transaction::TransactionArgument;
 use libra_types::{account_address::AccountAddress, account_config::AccountResource};
+use sgtypes::invoice::RouteHint;
+use sgtypes::message::{DappMessage, DappMessageReceipt, RawNegotiateMessage};
 use sgtypes::script_package::ChannelScriptPackage;
 use sgtypes::signed_channel_transaction::SignedChannelTransaction;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use libra_crypto::HashValue;
 use node_proto::DeployModuleResponse;
@@ -18,6 +22,13 @@ pub enum NodeMessage {
         transaction_args: Vec<TransactionArgument>,
         responder: oneshot::Sender<Result<MessageFuture<u64>>>,
     },
+    ExecuteInChannel {
+        channel_address: AccountAddress,
+        package_name: String,
+        script_name: String,
+        transaction_args: Vec<TransactionArgument>,
+        responder: oneshot::Sender<Result<MessageFuture<u64>>>,
+    },
     Install {
         channel_script_package: ChannelScriptPackage,
         responder: oneshot::Sender<Result<()>>,
@@ -33,6 +44,10 @@ pub enum NodeMessage {
         receiver_amount: u64,
         responder: oneshot::Sender<Result<MessageFuture<u64>>>,
     },
+    OpenMultiPartyChannel {
+        deposits: BTreeMap<AccountAddress, u64>,
+        responder: oneshot::Sender<Result<MessageFuture<u64>>>,
+    },
     Withdraw {
         receiver: AccountAddress,
         sender_amount: u64,
@@ -43,11 +58,18 @@ pub enum NodeMessage {
         amount: u64,
         responder: oneshot::Sender<Result<MessageFuture<u64>>>,
     },
+    ChannelPayAsset {
+        receiver_address: AccountAddress,
+        asset: StructTag,
+        amount: u64,
+        responder: oneshot::Sender<Result<MessageFuture<u64>>>,
+    },
     ChannelPayHTLC {
         receiver_address: AccountAddress,
         amount: u64,
         hash_lock: Vec<u8>,
         timeout: u64,
+        route_hints: Vec<RouteHint>,
         responder: oneshot::Sender<Result<MessageFuture<u64>>>,
     },
     ChannelBalance {
@@ -66,6 +88,15 @@ pub enum NodeMessage {
         channel_seq_number: u64,
         responder: oneshot::Sender<Result<SignedChannelTransaction>>,
     },
+    SendDappMessage {
+        peer: AccountAddress,
+        message: DappMessage,
+        responder: oneshot::Sender<Result<(HashValue, Option<DappMessageReceipt>)>>,
+    },
+    NegotiateOpenChannel {
+        terms: RawNegotiateMessage,
+        responder: oneshot::Sender<Result<(RawNegotiateMessage, MessageFuture<u64>)>>,
+    },
     SetTimeout {
         default_future_timeout: u64,
     },
@@ -75,4 +106,8 @@ pub enum NodeMessage {
         approve: bool,
         responder: oneshot::Sender<Result<MessageFuture<u64>>>,
     },
+    RestoreChannels {
+        backup_path: PathBuf,
+        responder: oneshot::Sender<Result<Vec<AccountAddress>>>,
+    },
 }
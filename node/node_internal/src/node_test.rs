@@ -1,15 +1,14 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::invoice::InvoiceOptions;
 use crate::node::Node;
 use anyhow::{bail, Result};
-use libra_crypto::{
-    ed25519::Ed25519PrivateKey,
-    hash::{CryptoHash, CryptoHasher, TestOnlyHasher},
-    traits::SigningKey,
-};
+use libra_crypto::{ed25519::Ed25519PrivateKey, HashValue};
 use libra_types::account_address::AccountAddress;
+use sgtypes::fee_policy::FeePolicy;
 use sgtypes::message::*;
+use sgtypes::payment::PaymentStatus;
 use sgtypes::sg_error::SgError;
 use std::sync::Arc;
 use std::time::Duration;
@@ -46,9 +45,9 @@ fn node_test_all() -> Result<()> {
     );
     node1.start_server(&mut rt);
 
-    let addr1_hex = hex::encode(addr1);
+    let peer1_hex = hex::encode(node1.network_peer());
 
-    let seed = format!("{}/p2p/{}", &network_config1.listen, addr1_hex);
+    let seed = format!("{}/p2p/{}", &network_config1.listen, peer1_hex);
     let network_config2 = create_node_network_config(
         format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
         vec![seed.clone()],
@@ -152,9 +151,12 @@ fn node_test_all() -> Result<()> {
 
         let transfer_amount = 1_000;
 
-        let invoice = node1.add_invoice(transfer_amount).await.unwrap();
+        let invoice = node1
+            .add_invoice(transfer_amount, InvoiceOptions::default())
+            .await
+            .unwrap();
         node2
-            .off_chain_pay_htlc_async(addr1, transfer_amount, invoice.r_hash, 1000)
+            .off_chain_pay_htlc_async(addr1, transfer_amount, invoice.invoice.r_hash, 1000)
             .await
             .unwrap()
             .compat()
@@ -195,9 +197,12 @@ fn node_test_all() -> Result<()> {
             fund_amount + transfer_amount * 2
         );
 
-        let invoice = node1.add_invoice(transfer_amount).await.unwrap();
+        let invoice = node1
+            .add_invoice(transfer_amount, InvoiceOptions::default())
+            .await
+            .unwrap();
         node3
-            .off_chain_pay_htlc_async(addr1, transfer_amount, invoice.r_hash, 1000)
+            .off_chain_pay_htlc_async(addr1, transfer_amount, invoice.invoice.r_hash, 1000)
             .await
             .unwrap()
             .compat()
@@ -290,9 +295,9 @@ fn node_test_four_hop() -> Result<()> {
     );
     node1.start_server(&mut rt);
 
-    let addr1_hex = hex::encode(addr1);
+    let peer1_hex = hex::encode(node1.network_peer());
 
-    let seed = format!("{}/p2p/{}", &network_config1.listen, addr1_hex);
+    let seed = format!("{}/p2p/{}", &network_config1.listen, peer1_hex);
     let network_config2 = create_node_network_config(
         format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
         vec![seed.clone()],
@@ -407,12 +412,18 @@ fn node_test_four_hop() -> Result<()> {
         );
 
         let transfer_amount = 1_000;
+        // node3 charges to forward, node2 forwards for free.
+        let fee = 10;
+        node3.set_fee_policy(None, FeePolicy::new(fee, 0, 10))?;
 
         _delay(Duration::from_millis(5000)).await;
 
-        let invoice = node1.add_invoice(transfer_amount).await.unwrap();
+        let invoice = node1
+            .add_invoice(transfer_amount, InvoiceOptions::default())
+            .await
+            .unwrap();
         node4
-            .off_chain_pay_htlc_async(addr1, transfer_amount, invoice.r_hash.clone(), 1000)
+            .off_chain_pay_htlc_async(addr1, transfer_amount, invoice.invoice.r_hash.clone(), 1000)
             .await
             .unwrap()
             .compat()
@@ -431,7 +442,7 @@ fn node_test_four_hop() -> Result<()> {
 
         assert_eq!(
             node4.channel_balance_async(addr3).await.unwrap(),
-            fund_amount - transfer_amount
+            fund_amount - transfer_amount - fee
         );
         assert_eq!(
             node3.channel_balance_async(addr2).await.unwrap(),
@@ -451,8 +462,13 @@ fn node_test_four_hop() -> Result<()> {
         );
         assert_eq!(
             node3.channel_balance_async(addr4).await.unwrap(),
-            fund_amount + transfer_amount
+            fund_amount + transfer_amount + fee
         );
+        let payment = node4
+            .get_payment(HashValue::from_slice(&invoice.invoice.r_hash)?)
+            .await?
+            .expect("payment should be recorded");
+        assert_eq!(fee, payment.fee());
 
         let offchain_txn = node3
             .off_chain_pay_async(addr2, transfer_amount * 4)
@@ -476,12 +492,15 @@ fn node_test_four_hop() -> Result<()> {
             fund_amount - transfer_amount * 5
         );
 
-        let invoice = node1.add_invoice(fund_amount).await.unwrap();
+        let invoice = node1
+            .add_invoice(fund_amount, InvoiceOptions::default())
+            .await
+            .unwrap();
         match node4
             .off_chain_pay_htlc_async(
                 addr1,
                 fund_amount - transfer_amount * 4,
-                invoice.r_hash,
+                invoice.invoice.r_hash.clone(),
                 1000,
             )
             .await
@@ -489,6 +508,12 @@ fn node_test_four_hop() -> Result<()> {
             Ok(_) => assert_eq!(1, 2),
             Err(_) => assert_eq!(1, 1),
         }
+        let payment = node4
+            .get_payment(HashValue::from_slice(&invoice.invoice.r_hash)?)
+            .await?
+            .expect("payment should be recorded");
+        assert_eq!(PaymentStatus::Failed, payment.status());
+        assert!(payment.failure().is_some());
 
         node1.wallet().stop().await?;
         node2.wallet().stop().await?;
@@ -539,9 +564,9 @@ fn node_test_approve() -> Result<()> {
     );
     node1.start_server(&mut rt);
 
-    let addr1_hex = hex::encode(addr1);
+    let peer1_hex = hex::encode(node1.network_peer());
 
-    let seed = format!("{}/p2p/{}", &network_config1.listen, addr1_hex);
+    let seed = format!("{}/p2p/{}", &network_config1.listen, peer1_hex);
 
     let network_config2 = create_node_network_config(
         format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
@@ -633,9 +658,9 @@ fn node_test_reject() -> Result<()> {
     );
     node1.start_server(&mut rt);
 
-    let addr1_hex = hex::encode(addr1);
+    let peer1_hex = hex::encode(node1.network_peer());
 
-    let seed = format!("{}/p2p/{}", &network_config1.listen, addr1_hex);
+    let seed = format!("{}/p2p/{}", &network_config1.listen, peer1_hex);
 
     let network_config2 = create_node_network_config(
         format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
@@ -697,6 +722,117 @@ fn node_test_reject() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn node_test_proposal_expired() -> Result<()> {
+    use crate::test_helper::*;
+    use anyhow::Error;
+    use futures::compat::Future01CompatExt;
+    use libra_config::utils::get_available_port;
+    use libra_logger::prelude::*;
+    use sgchain::star_chain_client::MockChainClient;
+    use std::sync::Arc;
+    use tokio::runtime::Runtime;
+
+    libra_logger::init_for_e2e_testing();
+    let mut rt = Runtime::new().unwrap();
+    let executor = rt.handle().clone();
+
+    let (mock_chain_service, _handle) = MockChainClient::new();
+    let client = Arc::new(mock_chain_service);
+    let network_config1 = create_node_network_config(
+        format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
+        vec![],
+    );
+
+    // node1 never approves the payment, and only expires proposals after a day by itself.
+    let (mut node1, addr1) = gen_node(
+        rt.block_on(setup_wallet(client.clone(), 10_000_000))?,
+        executor.clone(),
+        &network_config1,
+        client.clone(),
+        false,
+    );
+    node1.start_server(&mut rt);
+
+    let peer1_hex = hex::encode(node1.network_peer());
+
+    let seed = format!("{}/p2p/{}", &network_config1.listen, peer1_hex);
+
+    let network_config2 = create_node_network_config(
+        format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
+        vec![seed],
+    );
+    let (mut node2, addr2) = gen_node(
+        rt.block_on(setup_wallet_with(client.clone(), 10_000_000, |wallet| {
+            wallet.with_proposal_expiration(Duration::from_secs(2))
+        }))?,
+        executor.clone(),
+        &network_config2,
+        client.clone(),
+        true,
+    );
+    node2.start_server(&mut rt);
+
+    let node1 = Arc::new(node1);
+    let node2 = Arc::new(node2);
+    let _node1_clone = node1.clone();
+    let _node2_clone = node2.clone();
+
+    let f = async move {
+        let fund_amount = 1000000;
+        let _result = node1
+            .open_channel_async(addr2, fund_amount, fund_amount)
+            .await
+            .unwrap()
+            .compat()
+            .await
+            .unwrap();
+        wait_channel_sequence_number(node1.clone(), node2.clone(), 1).await?;
+
+        let _payment = node2.off_chain_pay_async(addr1, 100).await?;
+        _delay(Duration::from_millis(500)).await;
+        assert!(node1
+            .wallet()
+            .get_pending_txn_request(addr2)
+            .await?
+            .is_some());
+
+        // node2 expires the payment and tells node1, which cancels it before its own check.
+        let mut wait_times = 0u64;
+        while node1
+            .wallet()
+            .get_pending_txn_request(addr2)
+            .await?
+            .is_some()
+        {
+            if wait_times >= 6 {
+                bail!("{} is not told the payment is expired", addr1);
+            }
+            delay_for(Duration::from_millis(1000)).await;
+            wait_times += 1;
+        }
+        assert!(node2
+            .wallet()
+            .get_pending_txn_request(addr1)
+            .await?
+            .is_none());
+        assert_eq!(
+            node1.channel_balance_async(addr2).await.unwrap(),
+            fund_amount
+        );
+
+        node1.wallet().stop().await?;
+        node2.wallet().stop().await?;
+        node1.shutdown().unwrap();
+        node2.shutdown().unwrap();
+        Ok::<_, Error>(())
+    };
+    rt.block_on(f)?;
+    drop(rt);
+    debug!("here");
+    Ok(())
+}
+
 async fn _delay(duration: Duration) {
     delay_for(duration).await;
 }
@@ -796,10 +932,18 @@ fn _create_negotiate_message(
     private_key: Ed25519PrivateKey,
 ) -> OpenChannelNodeNegotiateMessage {
     let resource_type = StructTag::new(sender_addr, "test".to_string(), "test".to_string(), vec![]);
-    let rtx = RawNegotiateMessage::new(sender_addr, resource_type, 10, receiver_addr, 20);
-    let mut hasher = TestOnlyHasher::default();
-    hasher.write(&rtx.clone().into_proto_bytes().unwrap());
-    let hash_value = hasher.finish();
-    let sender_sign = private_key.sign_message(&hash_value);
-    OpenChannelNodeNegotiateMessage::new(rtx, sender_sign, None)
+    let rtx =
+        RawNegotiateMessage::new(sender_addr, resource_type, 10, receiver_addr, 20, 0, vec![]);
+    let mut message = OpenChannelNodeNegotiateMessage::new(
+        rtx,
+        NegotiateAction::Propose,
+        None,
+        None,
+        String::new(),
+    );
+    let public_key = (&private_key).into();
+    message
+        .sign(&private_key, public_key)
+        .expect("sender should be of the private key");
+    message
 }
@@ -0,0 +1,130 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use futures::lock::Mutex;
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use sgtypes::onion::SharedSecret;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Onion sent by us, kept to decode failures coming back.
+#[derive(Clone, Debug)]
+pub struct SentOnion {
+    pub route: Vec<AccountAddress>,
+    pub amount: u64,
+    /// secrets shared with every hop after us.
+    pub secrets: Vec<SharedSecret>,
+}
+
+impl SentOnion {
+    pub fn new(route: Vec<AccountAddress>, amount: u64, secrets: Vec<SharedSecret>) -> Self {
+        Self {
+            route,
+            amount,
+            secrets,
+        }
+    }
+}
+
+/// Htlc forwarded by us, kept to pass failures back to `peer_id`.
+#[derive(Clone, Debug)]
+pub struct ForwardedHtlc {
+    pub peer_id: AccountAddress,
+    /// id of the request received from `peer_id`.
+    pub request_id: HashValue,
+    pub hash_lock: HashValue,
+    /// secret shared with the sender.
+    pub secret: SharedSecret,
+}
+
+impl ForwardedHtlc {
+    pub fn new(
+        peer_id: AccountAddress,
+        request_id: HashValue,
+        hash_lock: HashValue,
+        secret: SharedSecret,
+    ) -> Self {
+        Self {
+            peer_id,
+            request_id,
+            hash_lock,
+            secret,
+        }
+    }
+}
+
+/// Htlc received by us as the final hop, kept to fail it back in onion.
+#[derive(Clone, Debug)]
+pub struct ReceivedHtlc {
+    pub hash_lock: HashValue,
+    /// secret shared with the sender.
+    pub secret: SharedSecret,
+}
+
+impl ReceivedHtlc {
+    pub fn new(hash_lock: HashValue, secret: SharedSecret) -> Self {
+        Self { hash_lock, secret }
+    }
+}
+
+#[derive(Default)]
+struct Inner {
+    /// request id sent to the first hop -> onion in it.
+    sent: HashMap<HashValue, SentOnion>,
+    /// request id sent to the next hop -> htlc it forwards.
+    forwarded: HashMap<HashValue, ForwardedHtlc>,
+    /// request id received from the previous hop -> htlc paid to us.
+    received: HashMap<HashValue, ReceivedHtlc>,
+}
+
+/// Keep the state of onions in flight, it's lost on restart, when failures
+/// can't be decoded or passed back, and htlcs are recalled after timeout.
+#[derive(Clone, Default)]
+pub struct OnionManager {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl OnionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn add_sent(&self, request_id: HashValue, onion: SentOnion) {
+        self.inner.lock().await.sent.insert(request_id, onion);
+    }
+
+    pub async fn take_sent(&self, request_id: &HashValue) -> Option<SentOnion> {
+        self.inner.lock().await.sent.remove(request_id)
+    }
+
+    pub async fn add_forwarded(&self, request_id: HashValue, htlc: ForwardedHtlc) {
+        self.inner.lock().await.forwarded.insert(request_id, htlc);
+    }
+
+    pub async fn take_forwarded(&self, request_id: &HashValue) -> Option<ForwardedHtlc> {
+        self.inner.lock().await.forwarded.remove(request_id)
+    }
+
+    pub async fn add_received(&self, request_id: HashValue, htlc: ReceivedHtlc) {
+        self.inner.lock().await.received.insert(request_id, htlc);
+    }
+
+    pub async fn take_received(&self, request_id: &HashValue) -> Option<ReceivedHtlc> {
+        self.inner.lock().await.received.remove(request_id)
+    }
+
+    /// The htlcs locked by `hash_lock` are settled, no failure will come back.
+    pub async fn settle(&self, hash_lock: &HashValue, request_ids: &[HashValue]) {
+        let mut inner = self.inner.lock().await;
+        for request_id in request_ids {
+            inner.sent.remove(request_id);
+        }
+        inner
+            .forwarded
+            .retain(|_, htlc| &htlc.hash_lock != hash_lock);
+        inner
+            .received
+            .retain(|_, htlc| &htlc.hash_lock != hash_lock);
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::get_unix_ts;
+use anyhow::{bail, Result};
+use futures::{channel::mpsc, lock::Mutex};
+use libra_crypto::HashValue;
+use libra_logger::prelude::*;
+use libra_types::account_address::AccountAddress;
+use sgstorage::{channel_db::ChannelDB, payment_store::PaymentStore};
+use sgtypes::payment::{AttemptFailure, PaymentAttempt, PaymentRecord, PaymentStatus};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// subscriber of payment updates, `r_hash` is None if it watches all payments.
+struct Subscriber {
+    r_hash: Option<HashValue>,
+    sender: mpsc::UnboundedSender<PaymentRecord>,
+}
+
+impl Subscriber {
+    /// Return false if the subscriber should be dropped.
+    fn notify(&self, payment: &PaymentRecord) -> bool {
+        match self.r_hash {
+            Some(r_hash) if &r_hash != payment.r_hash() => true,
+            Some(_) => {
+                self.sender.unbounded_send(payment.clone()).is_ok() && !payment.status().is_final()
+            }
+            None => self.sender.unbounded_send(payment.clone()).is_ok(),
+        }
+    }
+}
+
+struct Inner {
+    /// request id of attempt -> r_hash of its payment, for attempts sent since startup.
+    attempts: HashMap<HashValue, HashValue>,
+    subscribers: Vec<Subscriber>,
+}
+
+/// Keep outgoing htlc payments and their attempts, and notify subscribers on every update.
+#[derive(Clone)]
+pub struct PaymentManager {
+    store: PaymentStore<ChannelDB>,
+    // the lock also serializes the updates of payments.
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl PaymentManager {
+    pub fn new(store: PaymentStore<ChannelDB>) -> Self {
+        Self {
+            store,
+            inner: Arc::new(Mutex::new(Inner {
+                attempts: HashMap::new(),
+                subscribers: vec![],
+            })),
+        }
+    }
+
+    pub async fn get_payment(&self, r_hash: &HashValue) -> Result<Option<PaymentRecord>> {
+        self.store.get_payment(r_hash)
+    }
+
+    pub async fn list_payments(&self) -> Result<Vec<PaymentRecord>> {
+        self.store.list_payments()
+    }
+
+    /// Subscribe updates of all payments.
+    pub async fn subscribe(&self) -> mpsc::UnboundedReceiver<PaymentRecord> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.inner.lock().await.subscribers.push(Subscriber {
+            r_hash: None,
+            sender,
+        });
+        receiver
+    }
+
+    /// Subscribe updates of payment `r_hash`, starting from its current state.
+    /// The receiver is closed once the payment succeeded or failed.
+    pub async fn track(
+        &self,
+        r_hash: &HashValue,
+    ) -> Result<mpsc::UnboundedReceiver<PaymentRecord>> {
+        let mut inner = self.inner.lock().await;
+        let payment = match self.store.get_payment(r_hash)? {
+            Some(payment) => payment,
+            None => bail!("payment {} not found", r_hash),
+        };
+        let (sender, receiver) = mpsc::unbounded();
+        let subscriber = Subscriber {
+            r_hash: Some(*r_hash),
+            sender,
+        };
+        if subscriber.notify(&payment) {
+            inner.subscribers.push(subscriber);
+        }
+        Ok(receiver)
+    }
+
+    /// Start paying `amount` to `receiver` by htlc locked by `r_hash`.
+    /// A failed payment can be paid again, a payment in flight or succeeded can not.
+    pub async fn begin(
+        &self,
+        r_hash: HashValue,
+        receiver: AccountAddress,
+        amount: u64,
+    ) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        let payment = match self.store.get_payment(&r_hash)? {
+            Some(payment) => {
+                match payment.status() {
+                    PaymentStatus::Succeeded => bail!("payment {} already succeeded", r_hash),
+                    PaymentStatus::InFlight => bail!("payment {} is in flight", r_hash),
+                    PaymentStatus::Failed => {}
+                }
+                payment
+            }
+            None => PaymentRecord::new(r_hash, receiver, amount, get_unix_ts()),
+        };
+        self.save(&mut inner, &payment)
+    }
+
+    /// The payment failed before any attempt is sent out.
+    pub async fn fail(&self, r_hash: &HashValue, failure: AttemptFailure) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        let mut payment = match self.store.get_payment(r_hash)? {
+            Some(payment) if payment.attempts().is_empty() => payment,
+            _ => return Ok(()),
+        };
+        payment.fail(failure, get_unix_ts());
+        self.save(&mut inner, &payment)
+    }
+
+    pub async fn add_attempt(&self, r_hash: &HashValue, attempt: PaymentAttempt) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        let mut payment = match self.store.get_payment(r_hash)? {
+            Some(payment) => payment,
+            None => bail!("payment {} not found", r_hash),
+        };
+        inner.attempts.insert(attempt.request_id, *r_hash);
+        payment.add_attempt(attempt, get_unix_ts());
+        self.save(&mut inner, &payment)
+    }
+
+    /// Attempt `request_id` failed, it's a no-op if the attempt is unknown or not in flight.
+    pub async fn fail_attempt(
+        &self,
+        request_id: &HashValue,
+        failure: AttemptFailure,
+    ) -> Result<()> {
+        let mut inner = self.inner.lock().await;
+        let r_hash = match inner.attempts.get(request_id) {
+            Some(r_hash) => *r_hash,
+            None => return Ok(()),
+        };
+        let mut payment = match self.store.get_payment(&r_hash)? {
+            Some(payment) => payment,
+            None => return Ok(()),
+        };
+        if payment.fail_attempt(request_id, failure, get_unix_ts()) {
+            info!("attempt {} of payment {} failed", request_id, r_hash);
+            self.save(&mut inner, &payment)?;
+        }
+        Ok(())
+    }
+
+    /// The preimage is released back to the payer, it's a no-op if the payment is not ours.
+    /// Return the succeeded payment.
+    pub async fn succeed(&self, preimage: Vec<u8>) -> Result<Option<PaymentRecord>> {
+        let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+        let mut inner = self.inner.lock().await;
+        let mut payment = match self.store.get_payment(&r_hash)? {
+            Some(payment) if payment.status() != PaymentStatus::Succeeded => payment,
+            _ => return Ok(None),
+        };
+        info!("payment {} succeeded", r_hash);
+        payment.succeed(preimage, get_unix_ts());
+        self.save(&mut inner, &payment)?;
+        Ok(Some(payment))
+    }
+
+    fn save(&self, inner: &mut Inner, payment: &PaymentRecord) -> Result<()> {
+        self.store.save_payment(payment)?;
+        inner
+            .subscribers
+            .retain(|subscriber| subscriber.notify(payment));
+        Ok(())
+    }
+}
+
+#[test]
+fn test_track_payment() {
+    use futures::StreamExt;
+    use sgtypes::sg_error::{SgError, SgErrorCode};
+
+    let path = libra_tools::tempdir::TempPath::new();
+    path.create_as_dir().unwrap();
+    let mut rt = tokio::runtime::Runtime::new().unwrap();
+
+    rt.block_on(async {
+        let (payer, receiver) = (AccountAddress::random(), AccountAddress::random());
+        let storage = Arc::new(sgstorage::storage::SgStorage::new(payer, &path));
+        let mgr = PaymentManager::new(PaymentStore::new(ChannelDB::new(payer, storage)));
+        let preimage = HashValue::random().to_vec();
+        let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+
+        mgr.begin(r_hash, receiver, 1000).await.unwrap();
+        assert!(mgr.begin(r_hash, receiver, 1000).await.is_err());
+        let mut tracker = mgr.track(&r_hash).await.unwrap();
+        let mut all = mgr.subscribe().await;
+
+        let request_id = HashValue::random();
+        let attempt = PaymentAttempt::new(request_id, vec![payer, receiver], 1000, 0);
+        mgr.add_attempt(&r_hash, attempt).await.unwrap();
+        let error = SgError::new(SgErrorCode::TIMEOUT, "future time out".to_string());
+        mgr.fail_attempt(&request_id, AttemptFailure::new(&error, None))
+            .await
+            .unwrap();
+
+        // payment is tracked from its current state, until it's failed.
+        let updates: Vec<PaymentRecord> = tracker.by_ref().collect().await;
+        let status: Vec<PaymentStatus> = updates.iter().map(|p| p.status()).collect();
+        assert_eq!(
+            vec![
+                PaymentStatus::InFlight,
+                PaymentStatus::InFlight,
+                PaymentStatus::Failed
+            ],
+            status
+        );
+
+        mgr.begin(r_hash, receiver, 1000).await.unwrap();
+        mgr.succeed(preimage.clone()).await.unwrap();
+        let payment = mgr.get_payment(&r_hash).await.unwrap().unwrap();
+        assert_eq!(PaymentStatus::Succeeded, payment.status());
+        assert_eq!(Some(preimage.as_slice()), payment.preimage());
+
+        // attempt, failure, retry and success.
+        let updates: Vec<PaymentRecord> = all.by_ref().take(4).collect().await;
+        assert_eq!(&payment, updates.last().unwrap());
+    });
+}
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 #![allow(dead_code)]
 
-use network::build_network_service;
+use network::build_network_service_with_identity;
 use rand::prelude::*;
 
 use crate::node::Node;
@@ -14,12 +14,22 @@ use libra_types::account_address::AccountAddress;
 use router::TableRouter;
 use sg_config::config::NetworkConfig;
 use sgchain::star_chain_client::{ChainClient, MockChainClient};
+use sgtypes::network_identity::NetworkIdentity;
 use sgwallet::wallet::*;
 use stats::Stats;
 use std::{sync::Arc, thread, time::Duration};
 use tokio::runtime::Handle;
 
 pub async fn setup_wallet(client: Arc<dyn ChainClient>, init_balance: u64) -> Result<WalletHandle> {
+    setup_wallet_with(client, init_balance, |wallet| wallet).await
+}
+
+/// Setup a wallet configured by `configure` before it's started.
+pub async fn setup_wallet_with(
+    client: Arc<dyn ChainClient>,
+    init_balance: u64,
+    configure: impl FnOnce(Wallet) -> Wallet,
+) -> Result<WalletHandle> {
     let mut seed_rng = rand::rngs::OsRng::new().expect("can't access OsRng");
     let seed_buf: [u8; 32] = seed_rng.gen();
     let mut rng0: StdRng = SeedableRng::from_seed(seed_buf);
@@ -29,8 +39,12 @@ pub async fn setup_wallet(client: Arc<dyn ChainClient>, init_balance: u64) -> Re
 
     client.faucet(account, init_balance).await?;
     // enable channel for wallet
-    let wallet =
-        Wallet::new_with_client(account, account_keypair, client.clone(), TempPath::new())?;
+    let wallet = configure(Wallet::new_with_client(
+        account,
+        account_keypair,
+        client.clone(),
+        TempPath::new(),
+    )?);
     let handle = wallet.start().await?;
     let gas_used = handle.enable_channel().await?;
     handle.get_chain_client().faucet(account, gas_used).await?;
@@ -68,7 +82,13 @@ pub fn gen_node(
     );
     router.start().unwrap();
 
-    let (network, tx, rx, close_tx) = build_network_service(config, wallet.keypair());
+    let network_key: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+        KeyPair::generate_for_testing(&mut rand::rngs::OsRng::new().expect("can't access OsRng"));
+    let identity = NetworkIdentity::new(network_key.public_key.clone());
+    let signature = wallet.signer().sign_network_identity(&identity).unwrap();
+    let identity = identity.with_signature(wallet.signer().public_key(), signature);
+    let (network, tx, rx, close_tx) =
+        build_network_service_with_identity(config, Arc::new(network_key), identity);
     let _identify = network.identify();
 
     thread::sleep(Duration::from_millis(1000));
@@ -8,7 +8,12 @@ use anyhow::{format_err, Error, Result};
 use libra_crypto::HashValue;
 use libra_types::account_address::AccountAddress;
 use libra_types::transaction::{TransactionArgument, TransactionWithProof};
-use sgtypes::channel_transaction::ChannelTransaction;
+use sgtypes::channel_transaction::{ChannelOp, ChannelTransaction};
+use sgtypes::channel_txn_history::{ChannelTxnPage, ChannelTxnQuery, ChannelTxnRecord};
+use sgtypes::fee_policy::FeePolicy;
+use sgtypes::invoice::RouteHint;
+use sgtypes::message::{DappMessage, DappMessageReceipt, InboundDappMessage};
+use sgtypes::payment::{AttemptFailure, PaymentRecord, PaymentStatus};
 use sgtypes::script_package::ChannelScriptPackage;
 use std::convert::{TryFrom, TryInto};
 
@@ -674,11 +679,30 @@ impl From<EmptyResponse> for crate::proto::node::EmptyResponse {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct AddInvoiceRequest {
     pub amount: u64,
+    pub expiry: u64,
+    pub memo: String,
+    pub preimage: Option<Vec<u8>>,
+    pub min_final_timeout: u64,
+    pub route_hints: Vec<RouteHint>,
 }
 
 impl AddInvoiceRequest {
-    pub fn new(amount: u64) -> Self {
-        Self { amount }
+    pub fn new(
+        amount: u64,
+        expiry: u64,
+        memo: String,
+        preimage: Option<Vec<u8>>,
+        min_final_timeout: u64,
+        route_hints: Vec<RouteHint>,
+    ) -> Self {
+        Self {
+            amount,
+            expiry,
+            memo,
+            preimage,
+            min_final_timeout,
+            route_hints,
+        }
     }
 }
 
@@ -686,7 +710,27 @@ impl TryFrom<crate::proto::node::AddInvoiceRequest> for AddInvoiceRequest {
     type Error = Error;
 
     fn try_from(request: crate::proto::node::AddInvoiceRequest) -> Result<Self> {
-        Ok(Self::new(request.amount))
+        let preimage = if request.preimage.is_empty() {
+            None
+        } else {
+            Some(request.preimage)
+        };
+        let mut route_hints = Vec::new();
+        for hint in request.route_hints {
+            let mut hops = Vec::new();
+            for hop in hint.hops {
+                hops.push(AccountAddress::try_from(hop)?);
+            }
+            route_hints.push(RouteHint::new(hops));
+        }
+        Ok(Self::new(
+            request.amount,
+            request.expiry,
+            request.memo,
+            preimage,
+            request.min_final_timeout,
+            route_hints,
+        ))
     }
 }
 
@@ -694,6 +738,17 @@ impl From<AddInvoiceRequest> for crate::proto::node::AddInvoiceRequest {
     fn from(request: AddInvoiceRequest) -> Self {
         Self {
             amount: request.amount,
+            expiry: request.expiry,
+            memo: request.memo,
+            preimage: request.preimage.unwrap_or_default(),
+            min_final_timeout: request.min_final_timeout,
+            route_hints: request
+                .route_hints
+                .into_iter()
+                .map(|hint| crate::proto::node::RouteHint {
+                    hops: hint.hops.into_iter().map(|hop| hop.to_vec()).collect(),
+                })
+                .collect(),
         }
     }
 }
@@ -752,6 +807,380 @@ impl From<PaymentRequest> for crate::proto::node::PaymentRequest {
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListPaymentsRequest {
+    pub watch: bool,
+}
+
+impl ListPaymentsRequest {
+    pub fn new(watch: bool) -> Self {
+        Self { watch }
+    }
+}
+
+impl TryFrom<crate::proto::node::ListPaymentsRequest> for ListPaymentsRequest {
+    type Error = Error;
+
+    fn try_from(request: crate::proto::node::ListPaymentsRequest) -> Result<Self> {
+        Ok(Self::new(request.watch))
+    }
+}
+
+impl From<ListPaymentsRequest> for crate::proto::node::ListPaymentsRequest {
+    fn from(request: ListPaymentsRequest) -> Self {
+        Self {
+            watch: request.watch,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrackPaymentRequest {
+    pub r_hash: HashValue,
+}
+
+impl TrackPaymentRequest {
+    pub fn new(r_hash: HashValue) -> Self {
+        Self { r_hash }
+    }
+}
+
+impl TryFrom<crate::proto::node::TrackPaymentRequest> for TrackPaymentRequest {
+    type Error = Error;
+
+    fn try_from(request: crate::proto::node::TrackPaymentRequest) -> Result<Self> {
+        Ok(Self::new(HashValue::from_slice(&request.r_hash)?))
+    }
+}
+
+impl From<TrackPaymentRequest> for crate::proto::node::TrackPaymentRequest {
+    fn from(request: TrackPaymentRequest) -> Self {
+        Self {
+            r_hash: request.r_hash.to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SetFeePolicyRequest {
+    /// None to set the default policy.
+    pub participant_address: Option<AccountAddress>,
+    pub policy: FeePolicy,
+}
+
+impl SetFeePolicyRequest {
+    pub fn new(participant_address: Option<AccountAddress>, policy: FeePolicy) -> Self {
+        Self {
+            participant_address,
+            policy,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::node::SetFeePolicyRequest> for SetFeePolicyRequest {
+    type Error = Error;
+
+    fn try_from(request: crate::proto::node::SetFeePolicyRequest) -> Result<Self> {
+        let participant_address = if request.participant_address.is_empty() {
+            None
+        } else {
+            Some(AccountAddress::try_from(request.participant_address)?)
+        };
+        Ok(Self::new(
+            participant_address,
+            FeePolicy::new(request.base_fee, request.fee_rate, request.timelock_delta),
+        ))
+    }
+}
+
+impl From<SetFeePolicyRequest> for crate::proto::node::SetFeePolicyRequest {
+    fn from(request: SetFeePolicyRequest) -> Self {
+        Self {
+            participant_address: request
+                .participant_address
+                .map(|addr| addr.to_vec())
+                .unwrap_or_default(),
+            base_fee: request.policy.base_fee,
+            fee_rate: request.policy.fee_rate,
+            timelock_delta: request.policy.timelock_delta,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ListChannelTransactionsRequest {
+    pub participant_address: AccountAddress,
+    pub query: ChannelTxnQuery,
+}
+
+impl ListChannelTransactionsRequest {
+    pub fn new(participant_address: AccountAddress, query: ChannelTxnQuery) -> Self {
+        Self {
+            participant_address,
+            query,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::node::ListChannelTransactionsRequest>
+    for ListChannelTransactionsRequest
+{
+    type Error = Error;
+
+    fn try_from(request: crate::proto::node::ListChannelTransactionsRequest) -> Result<Self> {
+        let participant_address = AccountAddress::try_from(request.participant_address)?;
+        let mut query = ChannelTxnQuery::new(request.start, request.limit).time_window(
+            Some(request.start_time).filter(|t| *t > 0),
+            Some(request.end_time).filter(|t| *t > 0),
+        );
+        query.operator = request.operator.map(ChannelOp::try_from).transpose()?;
+        if !request.proposer.is_empty() {
+            query = query.proposer(AccountAddress::try_from(request.proposer)?);
+        }
+        Ok(Self::new(participant_address, query))
+    }
+}
+
+impl From<ListChannelTransactionsRequest> for crate::proto::node::ListChannelTransactionsRequest {
+    fn from(request: ListChannelTransactionsRequest) -> Self {
+        let query = request.query;
+        Self {
+            participant_address: request.participant_address.to_vec(),
+            start: query.start,
+            limit: query.limit,
+            operator: query.operator.map(Into::into),
+            proposer: query.proposer.map(|p| p.to_vec()).unwrap_or_default(),
+            start_time: query.start_time.unwrap_or_default(),
+            end_time: query.end_time.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<ChannelTxnRecord> for crate::proto::node::ChannelTransactionRecord {
+    fn from(record: ChannelTxnRecord) -> Self {
+        Self {
+            channel_sequence_number: record.channel_sequence_number,
+            proposer: record.proposer.to_vec(),
+            travel: record.travel,
+            operator: record.operator.map(Into::into),
+            args: record.args.into_iter().map(Into::into).collect(),
+            expiration_time: record.expiration_time,
+            applied_time: record.applied_time,
+            gas_used: record.gas_used,
+            balance_deltas: record
+                .balance_deltas
+                .into_iter()
+                .map(|(participant, delta)| crate::proto::node::BalanceDelta {
+                    participant: participant.to_vec(),
+                    delta,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<ChannelTxnPage> for crate::proto::node::ListChannelTransactionsResponse {
+    fn from(page: ChannelTxnPage) -> Self {
+        Self {
+            transactions: page.txns.into_iter().map(Into::into).collect(),
+            has_more: page.next_start.is_some(),
+            next_start: page.next_start.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<PaymentStatus> for crate::proto::node::PaymentStatus {
+    fn from(status: PaymentStatus) -> Self {
+        match status {
+            PaymentStatus::InFlight => crate::proto::node::PaymentStatus::InFlight,
+            PaymentStatus::Succeeded => crate::proto::node::PaymentStatus::Succeeded,
+            PaymentStatus::Failed => crate::proto::node::PaymentStatus::Failed,
+        }
+    }
+}
+
+impl From<&AttemptFailure> for crate::proto::node::PaymentFailure {
+    fn from(failure: &AttemptFailure) -> Self {
+        Self {
+            code: failure.code.into(),
+            message: failure.message.clone(),
+            hop: failure.hop.map(|hop| hop.to_vec()).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<PaymentRecord> for crate::proto::node::Payment {
+    fn from(payment: PaymentRecord) -> Self {
+        let attempts = payment
+            .attempts()
+            .iter()
+            .map(|attempt| crate::proto::node::PaymentAttempt {
+                request_id: attempt.request_id.to_vec(),
+                route: attempt.route.iter().map(|hop| hop.to_vec()).collect(),
+                amount: attempt.amount,
+                fee: attempt.fee,
+                status: crate::proto::node::PaymentStatus::from(attempt.status) as i32,
+                failure: attempt.failure.as_ref().map(Into::into),
+            })
+            .collect();
+        Self {
+            r_hash: payment.r_hash().to_vec(),
+            receiver: payment.receiver().to_vec(),
+            amount: payment.amount(),
+            fee: payment.fee(),
+            status: crate::proto::node::PaymentStatus::from(payment.status()) as i32,
+            attempts,
+            preimage: payment.preimage().map(|p| p.to_vec()).unwrap_or_default(),
+            failure: payment.failure().map(Into::into),
+            created_at: payment.created_at(),
+            updated_at: payment.updated_at(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SendDappMessageRequest {
+    pub peer: AccountAddress,
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub need_receipt: bool,
+}
+
+impl SendDappMessageRequest {
+    pub fn new(peer: AccountAddress, topic: String, payload: Vec<u8>, need_receipt: bool) -> Self {
+        Self {
+            peer,
+            topic,
+            payload,
+            need_receipt,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::node::SendDappMessageRequest> for SendDappMessageRequest {
+    type Error = Error;
+
+    fn try_from(request: crate::proto::node::SendDappMessageRequest) -> Result<Self> {
+        Ok(Self::new(
+            AccountAddress::try_from(request.peer)?,
+            request.topic,
+            request.payload,
+            request.need_receipt,
+        ))
+    }
+}
+
+impl From<SendDappMessageRequest> for crate::proto::node::SendDappMessageRequest {
+    fn from(request: SendDappMessageRequest) -> Self {
+        Self {
+            peer: request.peer.to_vec(),
+            topic: request.topic,
+            payload: request.payload,
+            need_receipt: request.need_receipt,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SendDappMessageResponse {
+    pub id: HashValue,
+    /// None if no receipt is asked.
+    pub receipt: Option<DappMessageReceipt>,
+}
+
+impl SendDappMessageResponse {
+    pub fn new(id: HashValue, receipt: Option<DappMessageReceipt>) -> Self {
+        Self { id, receipt }
+    }
+}
+
+impl TryFrom<crate::proto::node::SendDappMessageResponse> for SendDappMessageResponse {
+    type Error = Error;
+
+    fn try_from(response: crate::proto::node::SendDappMessageResponse) -> Result<Self> {
+        let id = HashValue::from_slice(&response.id)?;
+        let receipt = if response.acked {
+            Some(DappMessageReceipt::new(id, response.delivered))
+        } else {
+            None
+        };
+        Ok(Self::new(id, receipt))
+    }
+}
+
+impl From<SendDappMessageResponse> for crate::proto::node::SendDappMessageResponse {
+    fn from(response: SendDappMessageResponse) -> Self {
+        Self {
+            id: response.id.to_vec(),
+            acked: response.receipt.is_some(),
+            delivered: response
+                .receipt
+                .map(|receipt| receipt.delivered)
+                .unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscribeDappMessagesRequest {
+    /// None to receive messages of all topics.
+    pub topic: Option<String>,
+}
+
+impl SubscribeDappMessagesRequest {
+    pub fn new(topic: Option<String>) -> Self {
+        Self { topic }
+    }
+}
+
+impl TryFrom<crate::proto::node::SubscribeDappMessagesRequest> for SubscribeDappMessagesRequest {
+    type Error = Error;
+
+    fn try_from(request: crate::proto::node::SubscribeDappMessagesRequest) -> Result<Self> {
+        let topic = if request.topic.is_empty() {
+            None
+        } else {
+            Some(request.topic)
+        };
+        Ok(Self::new(topic))
+    }
+}
+
+impl From<SubscribeDappMessagesRequest> for crate::proto::node::SubscribeDappMessagesRequest {
+    fn from(request: SubscribeDappMessagesRequest) -> Self {
+        Self {
+            topic: request.topic.unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<crate::proto::node::DappMessage> for InboundDappMessage {
+    type Error = Error;
+
+    fn try_from(message: crate::proto::node::DappMessage) -> Result<Self> {
+        Ok(InboundDappMessage::new(
+            AccountAddress::try_from(message.sender)?,
+            DappMessage::new(
+                HashValue::from_slice(&message.id)?,
+                message.topic,
+                message.payload,
+                false,
+            ),
+        ))
+    }
+}
+
+impl From<InboundDappMessage> for crate::proto::node::DappMessage {
+    fn from(inbound: InboundDappMessage) -> Self {
+        Self {
+            sender: inbound.sender.to_vec(),
+            id: inbound.message.id.to_vec(),
+            topic: inbound.message.topic,
+            payload: inbound.message.payload,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
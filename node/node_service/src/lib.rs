@@ -2,18 +2,27 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::Result;
-use futures::{channel::oneshot, FutureExt, TryFutureExt};
+use futures::{
+    channel::oneshot, compat::Future01CompatExt, FutureExt, StreamExt, TryFutureExt, TryStreamExt,
+};
+use futures01::Sink;
 use grpc_helpers::provide_grpc_response;
-use grpcio::{EnvBuilder, RpcStatus, RpcStatusCode};
+use grpcio::{EnvBuilder, RpcStatus, RpcStatusCode, WriteFlags};
+use libra_logger::prelude::*;
+use node_internal::invoice::InvoiceOptions;
 use node_internal::node::Node as Node_Internal;
 use node_proto::proto::node::create_node;
 use node_proto::{
     AddInvoiceRequest, AddInvoiceResponse, ChannelBalanceRequest, ChannelBalanceResponse,
     ChannelTransactionProposalRequest, DeployModuleRequest, DepositRequest, EmptyResponse,
     ExecuteScriptRequest, InstallChannelScriptPackageRequest, InstallChannelScriptPackageResponse,
-    OpenChannelRequest, PayRequest, PaymentRequest, QueryTransactionQuest, WithdrawRequest,
+    ListChannelTransactionsRequest, ListPaymentsRequest, OpenChannelRequest, PayRequest,
+    PaymentRequest, QueryTransactionQuest, SendDappMessageRequest, SendDappMessageResponse,
+    SetFeePolicyRequest, SubscribeDappMessagesRequest, TrackPaymentRequest, WithdrawRequest,
 };
 use sg_config::config::NodeConfig;
+use sgtypes::message::InboundDappMessage;
+use sgtypes::payment::PaymentRecord;
 use std::convert::TryFrom;
 use std::sync::Arc;
 
@@ -283,7 +292,14 @@ impl node_proto::proto::node::Node for NodeService {
         let node = self.node.clone();
         let f = async move {
             let request = AddInvoiceRequest::try_from(req).unwrap();
-            match node.add_invoice(request.amount).await {
+            let options = InvoiceOptions {
+                description: request.memo,
+                expiry: request.expiry,
+                min_final_timeout: request.min_final_timeout,
+                route_hints: request.route_hints,
+                preimage: request.preimage,
+            };
+            match node.add_invoice(request.amount, options).await {
                 Ok(invoice) => {
                     let response = AddInvoiceResponse::new(invoice.into());
                     sink.success(response.into());
@@ -327,6 +343,195 @@ impl node_proto::proto::node::Node for NodeService {
         };
         ctx.spawn(f.boxed().unit_error().compat());
     }
+
+    fn list_payments(
+        &mut self,
+        ctx: ::grpcio::RpcContext,
+        req: node_proto::proto::node::ListPaymentsRequest,
+        sink: ::grpcio::ServerStreamingSink<node_proto::proto::node::Payment>,
+    ) {
+        let node = self.node.clone();
+        let f = async move {
+            let request = ListPaymentsRequest::try_from(req).unwrap();
+            // subscribe before listing, so that no update is missed.
+            let updates = if request.watch {
+                Some(node.subscribe_payments().await)
+            } else {
+                None
+            };
+            match node.list_payments().await {
+                Ok(payments) => {
+                    let payments = futures::stream::iter(payments);
+                    match updates {
+                        Some(updates) => send_payments(payments.chain(updates), sink).await,
+                        None => send_payments(payments, sink).await,
+                    }
+                }
+                Err(e) => {
+                    set_streaming_failure_message(
+                        RpcStatusCode::UNKNOWN,
+                        format!("Failed to process request: {}", e),
+                        sink,
+                    );
+                }
+            }
+        };
+        ctx.spawn(f.boxed().unit_error().compat());
+    }
+
+    fn track_payment(
+        &mut self,
+        ctx: ::grpcio::RpcContext,
+        req: node_proto::proto::node::TrackPaymentRequest,
+        sink: ::grpcio::ServerStreamingSink<node_proto::proto::node::Payment>,
+    ) {
+        let node = self.node.clone();
+        let f = async move {
+            let request = TrackPaymentRequest::try_from(req).unwrap();
+            match node.track_payment(request.r_hash).await {
+                Ok(updates) => send_payments(updates, sink).await,
+                Err(e) => {
+                    set_streaming_failure_message(
+                        RpcStatusCode::NOT_FOUND,
+                        format!("Failed to process request: {}", e),
+                        sink,
+                    );
+                }
+            }
+        };
+        ctx.spawn(f.boxed().unit_error().compat());
+    }
+
+    fn set_fee_policy(
+        &mut self,
+        ctx: ::grpcio::RpcContext,
+        req: node_proto::proto::node::SetFeePolicyRequest,
+        sink: ::grpcio::UnarySink<node_proto::proto::node::EmptyResponse>,
+    ) {
+        let node = self.node.clone();
+        let f = async move {
+            let request = SetFeePolicyRequest::try_from(req).unwrap();
+            match node.set_fee_policy(request.participant_address, request.policy) {
+                Ok(()) => {
+                    sink.success(EmptyResponse::new().into());
+                }
+                Err(e) => {
+                    set_failure_message(
+                        RpcStatusCode::UNKNOWN,
+                        format!("Failed to process request: {}", e),
+                        sink,
+                    );
+                }
+            }
+        };
+        ctx.spawn(f.boxed().unit_error().compat());
+    }
+
+    fn list_channel_transactions(
+        &mut self,
+        ctx: ::grpcio::RpcContext,
+        req: node_proto::proto::node::ListChannelTransactionsRequest,
+        sink: ::grpcio::UnarySink<node_proto::proto::node::ListChannelTransactionsResponse>,
+    ) {
+        let node = self.node.clone();
+        let f = async move {
+            let request = ListChannelTransactionsRequest::try_from(req).unwrap();
+            match node
+                .list_channel_txns(request.participant_address, request.query)
+                .await
+            {
+                Ok(page) => {
+                    sink.success(page.into());
+                }
+                Err(e) => {
+                    set_failure_message(
+                        RpcStatusCode::UNKNOWN,
+                        format!("Failed to process request: {}", e),
+                        sink,
+                    );
+                }
+            }
+        };
+        ctx.spawn(f.boxed().unit_error().compat());
+    }
+
+    fn send_dapp_message(
+        &mut self,
+        ctx: ::grpcio::RpcContext,
+        req: node_proto::proto::node::SendDappMessageRequest,
+        sink: ::grpcio::UnarySink<node_proto::proto::node::SendDappMessageResponse>,
+    ) {
+        let node = self.node.clone();
+        let f = async move {
+            let request = SendDappMessageRequest::try_from(req).unwrap();
+            match node
+                .send_dapp_message_async(
+                    request.peer,
+                    request.topic,
+                    request.payload,
+                    request.need_receipt,
+                )
+                .await
+            {
+                Ok((id, receipt)) => {
+                    sink.success(SendDappMessageResponse::new(id, receipt).into());
+                }
+                Err(e) => {
+                    set_failure_message(
+                        RpcStatusCode::UNKNOWN,
+                        format!("Failed to process request: {}", e),
+                        sink,
+                    );
+                }
+            }
+        };
+        ctx.spawn(f.boxed().unit_error().compat());
+    }
+
+    fn subscribe_dapp_messages(
+        &mut self,
+        ctx: ::grpcio::RpcContext,
+        req: node_proto::proto::node::SubscribeDappMessagesRequest,
+        sink: ::grpcio::ServerStreamingSink<node_proto::proto::node::DappMessage>,
+    ) {
+        let node = self.node.clone();
+        let f = async move {
+            let request = SubscribeDappMessagesRequest::try_from(req).unwrap();
+            let messages = node.subscribe_dapp_messages(request.topic).await;
+            send_dapp_messages(messages, sink).await;
+        };
+        ctx.spawn(f.boxed().unit_error().compat());
+    }
+}
+
+/// Send dapp `messages` to client until the client goes away.
+async fn send_dapp_messages<S>(
+    messages: S,
+    sink: grpcio::ServerStreamingSink<node_proto::proto::node::DappMessage>,
+) where
+    S: futures::Stream<Item = InboundDappMessage> + Unpin,
+{
+    let messages = messages
+        .map(|message| Ok::<_, grpcio::Error>((message.into(), WriteFlags::default())))
+        .compat();
+    if let Err(e) = sink.send_all(messages).compat().await {
+        debug!("stop streaming dapp messages, {}", e);
+    }
+}
+
+/// Send `payments` to client until the stream ends or the client goes away.
+async fn send_payments<S>(
+    payments: S,
+    sink: grpcio::ServerStreamingSink<node_proto::proto::node::Payment>,
+) where
+    S: futures::Stream<Item = PaymentRecord> + Unpin,
+{
+    let payments = payments
+        .map(|payment| Ok::<_, grpcio::Error>((payment.into(), WriteFlags::default())))
+        .compat();
+    if let Err(e) = sink.send_all(payments).compat().await {
+        debug!("stop streaming payments, {}", e);
+    }
 }
 
 async fn process_response<T, S>(resp: oneshot::Receiver<Result<T>>, sink: grpcio::UnarySink<S>)
@@ -362,3 +567,13 @@ fn set_failure_message<T>(
     let status = RpcStatus::new(status_code, Some(details));
     sink.fail(status)
 }
+
+fn set_streaming_failure_message<T>(
+    status_code: RpcStatusCode,
+    details: String,
+    sink: grpcio::ServerStreamingSink<T>,
+) {
+    let status = RpcStatus::new(status_code, Some(details));
+    // the status is sent once `fail` is called, no need to wait for it.
+    let _ = sink.fail(status);
+}
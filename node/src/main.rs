@@ -4,9 +4,11 @@ mod wallet_utils;
 
 use std::sync::Arc;
 
-use crate::wallet_utils::WalletLibrary;
+use crate::wallet_utils::{
+    read_new_passphrase, read_passphrase, read_private_key, WalletLibrary, PASSPHRASE_ENV,
+};
 use ant::{AntRouter, MixRouter};
-use anyhow::Result;
+use anyhow::{format_err, Result};
 use futures_01::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use libra_crypto::{
     ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
@@ -14,15 +16,26 @@ use libra_crypto::{
 };
 use libra_logger::prelude::*;
 use libra_types::account_address::AccountAddress;
-use network::{build_network_service, NetworkMessage, NetworkService};
+use network::{build_network_service_with_identity, NetworkMessage, NetworkService};
 use node::client;
 use node_internal::node::Node;
 use node_service::setup_node_service;
 use router::{Router, TableRouter};
 use sg_config::config::{load_from, NodeConfig, WalletConfig};
 use sgchain::star_chain_client::StarChainClient;
-use sgwallet::wallet::*;
+use sgtypes::{
+    approval::ApprovalPolicy,
+    keystore::{load_or_create_network_key, network_key_path, KeyStore},
+    network_identity::{NetworkIdentity, SignedNetworkIdentity},
+};
+#[cfg(unix)]
+use sgwallet::signer::{node_sign_policy, RemoteSigner, SignerServer};
+use sgwallet::{
+    signer::{LocalSigner, Signer},
+    wallet::*,
+};
 use stats::Stats;
+use std::convert::TryFrom;
 use std::path::Path;
 use structopt::StructOpt;
 use tokio::runtime::{Handle, Runtime};
@@ -44,6 +57,50 @@ struct Args {
     pub faucet_key_path: String,
     #[structopt(short = "n", long = "child_number", default_value = "1")]
     pub child_num: u64,
+    /// Encrypted keystore of accounts, the faucet key file is used if it's not set.
+    #[structopt(short = "k", long = "keystore")]
+    pub keystore: Option<String>,
+    /// Account in keystore to start node with, the first account by default.
+    #[structopt(short = "a", long = "account")]
+    pub account: Option<String>,
+    /// File containing the keystore passphrase. If not set, the passphrase is read from
+    /// env STARGATE_KEYSTORE_PASSPHRASE, or the console.
+    #[structopt(long = "passphrase_file")]
+    pub passphrase_file: Option<String>,
+    #[structopt(subcommand)]
+    pub cmd: Option<AccountCommand>,
+}
+
+/// Manage accounts in the keystore instead of starting the node.
+#[derive(Debug, StructOpt)]
+enum AccountCommand {
+    /// Create a new account, and the keystore if it does not exist.
+    #[structopt(name = "create")]
+    Create,
+    /// Import the account at child_number of a mnemonic file,
+    /// or a private key read from the console.
+    #[structopt(name = "import")]
+    Import {
+        #[structopt(short = "m", long = "mnemonic_file")]
+        mnemonic_file: Option<String>,
+    },
+    /// Print the private key of an account.
+    #[structopt(name = "export")]
+    Export { account: String },
+    /// List accounts in the keystore.
+    #[structopt(name = "list")]
+    List,
+    /// Encrypt the keystore by a new passphrase.
+    #[structopt(name = "change_passphrase")]
+    ChangePassphrase,
+    /// Serve sign requests of a node whose wallet `remote_signer` is `socket`,
+    /// by the account key, until the process is killed.
+    #[structopt(name = "signer")]
+    Signer { socket: String },
+    /// Replace the key of an account by a new account, move its funds on chain to the new one,
+    /// then retire the old key. Channels of the account should be closed first.
+    #[structopt(name = "rotate")]
+    Rotate { account: String },
 }
 
 pub struct Swarm {
@@ -62,16 +119,195 @@ fn launch_swarm(args: &Args) -> Result<Swarm> {
 fn load_from_keyfile(
     faucet_account_file: &str,
     child_num: u64,
-) -> KeyPair<Ed25519PrivateKey, Ed25519PublicKey> {
-    let wallet_library = WalletLibrary::recover(faucet_account_file).unwrap();
-    wallet_library.get_keypair(child_num).unwrap()
+) -> Result<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> {
+    WalletLibrary::recover(faucet_account_file)?.get_keypair(child_num)
 }
 
-fn create_wallet(
+fn load_from_keystore(args: &Args) -> Result<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> {
+    let keystore = open_keystore(args, false)?;
+    let account = match &args.account {
+        Some(account) => Some(parse_account(account)?),
+        None => None,
+    };
+    keystore.keypair(account)
+}
+
+fn parse_account(account: &str) -> Result<AccountAddress> {
+    AccountAddress::try_from(hex::decode(account.trim_start_matches("0x"))?)
+}
+
+fn open_keystore(args: &Args, create_if_missing: bool) -> Result<KeyStore> {
+    let path = args
+        .keystore
+        .as_ref()
+        .ok_or(format_err!("keystore is not set, set it by --keystore"))?;
+    if create_if_missing && !Path::new(path).exists() {
+        let passphrase = match (&args.passphrase_file, std::env::var(PASSPHRASE_ENV)) {
+            (None, Err(_)) => read_new_passphrase()?,
+            _ => read_passphrase(args.passphrase_file.as_ref())?,
+        };
+        return KeyStore::create(path, &passphrase);
+    }
+    KeyStore::unlock(path, &read_passphrase(args.passphrase_file.as_ref())?)
+}
+
+fn run_account_command(args: &Args, cmd: &AccountCommand) -> Result<()> {
+    match cmd {
+        AccountCommand::Create => {
+            let account = open_keystore(args, true)?.create_account()?;
+            println!("created account {}", hex::encode(account));
+        }
+        AccountCommand::Import { mnemonic_file } => {
+            let private_key = match mnemonic_file {
+                Some(file) => {
+                    WalletLibrary::recover(file)?
+                        .get_keypair(args.child_num)?
+                        .private_key
+                }
+                None => read_private_key()?,
+            };
+            let account = open_keystore(args, true)?.import_account(private_key)?;
+            println!("imported account {}", hex::encode(account));
+        }
+        AccountCommand::Export { account } => {
+            let private_key =
+                open_keystore(args, false)?.export_account(parse_account(account)?)?;
+            eprintln!("keep the private key secret, anyone who has it controls the account.");
+            println!("{}", hex::encode(private_key.to_bytes()));
+        }
+        AccountCommand::List => {
+            for (index, account) in open_keystore(args, false)?.accounts().iter().enumerate() {
+                println!("#{} {}", index, hex::encode(account));
+            }
+        }
+        AccountCommand::ChangePassphrase => {
+            open_keystore(args, false)?.change_passphrase(&read_new_passphrase()?)?;
+            println!("keystore passphrase changed");
+        }
+        AccountCommand::Signer { socket } => serve_signer(load_keypair(args)?, socket)?,
+        AccountCommand::Rotate { account } => rotate_account(args, parse_account(account)?)?,
+    }
+    Ok(())
+}
+
+fn rotate_account(args: &Args, account: AccountAddress) -> Result<()> {
+    let swarm = launch_swarm(args)?;
+    let mut keystore = open_keystore(args, false)?;
+    let keypair = Arc::new(keystore.keypair(Some(account))?);
+    let wallet = create_wallet(Arc::new(LocalSigner::new(keypair)), &swarm.config.wallet)?;
+    let mut rt = Runtime::new()?;
+    let wallet = rt.block_on(wallet.start())?;
+    // fail before the keystore is touched if the funds can't be moved.
+    rt.block_on(wallet.sweep_amount())?;
+    let sequence_number = wallet.sequence_number()?;
+
+    // the new key is saved before funds are moved to it.
+    let successor = keystore.rotate_account(account)?;
+    println!(
+        "account {} is rotated to {}",
+        hex::encode(account),
+        hex::encode(successor)
+    );
+    let amount = match rt.block_on(wallet.sweep(successor)) {
+        Ok(amount) => amount,
+        Err(e) => {
+            // keep the new key if the transfer may be on chain.
+            if wallet.sequence_number()? == sequence_number {
+                keystore.undo_rotation(account)?;
+                println!("rotation of account {} is undone", hex::encode(account));
+            }
+            return Err(e);
+        }
+    };
+    println!("moved {} to {}", amount, hex::encode(successor));
+    keystore.retire_account(account)?;
+    println!("account {} is retired", hex::encode(account));
+    Ok(())
+}
+
+fn load_keypair(args: &Args) -> Result<Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>> {
+    Ok(Arc::new(match &args.keystore {
+        Some(_) => load_from_keystore(args)?,
+        None => load_from_keyfile(&args.faucet_key_path, args.child_num)?,
+    }))
+}
+
+#[cfg(unix)]
+fn serve_signer(
     keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+    socket: &str,
+) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let listener = std::os::unix::net::UnixListener::bind(socket)?;
+    // only the owner of the signer may connect to it.
+    std::fs::set_permissions(socket, std::fs::Permissions::from_mode(0o600))?;
+    let account = AccountAddress::from_public_key(&keypair.public_key);
+    println!(
+        "signer of account {} is listening on {}",
+        hex::encode(account),
+        socket
+    );
+    SignerServer::new(Arc::new(LocalSigner::new(keypair)))
+        .with_policy(node_sign_policy(account))
+        .serve(listener)
+        .join()
+        .map_err(|_| format_err!("signer server panicked"))?
+}
+
+#[cfg(not(unix))]
+fn serve_signer(
+    _keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+    _socket: &str,
+) -> Result<()> {
+    Err(format_err!("signer process is only supported on unix"))
+}
+
+/// Network key of the node, kept next to the keystore, or in the config dir without keystore.
+fn load_network_key(args: &Args) -> Result<Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>> {
+    let path = match &args.keystore {
+        Some(keystore) => network_key_path(keystore),
+        None => Path::new(&args.config_dir).join("network.key"),
+    };
+    Ok(Arc::new(KeyPair::from(load_or_create_network_key(path)?)))
+}
+
+/// Bind `network_key` to the account of `signer`, so that peers know who is behind it.
+fn sign_network_identity(
+    signer: &Arc<dyn Signer>,
+    network_key: &KeyPair<Ed25519PrivateKey, Ed25519PublicKey>,
+    rt: &mut Runtime,
+) -> Result<SignedNetworkIdentity> {
+    let identity = NetworkIdentity::new(network_key.public_key.clone());
+    let signature = rt.block_on(async { signer.sign_network_identity(&identity) })?;
+    Ok(identity.with_signature(signer.public_key(), signature))
+}
+
+/// Signer of the node, the key is loaded in process unless `remote_signer` is configured.
+fn create_signer(
+    args: &Args,
     wallet_config: &WalletConfig,
-) -> Result<Wallet> {
-    let account_address = AccountAddress::from_public_key(&keypair.public_key);
+    rt: &mut Runtime,
+) -> Result<Arc<dyn Signer>> {
+    match &wallet_config.remote_signer {
+        Some(path) => connect_signer(path, rt),
+        None => Ok(Arc::new(LocalSigner::new(load_keypair(args)?))),
+    }
+}
+
+#[cfg(unix)]
+fn connect_signer(path: &str, rt: &mut Runtime) -> Result<Arc<dyn Signer>> {
+    let signer = rt.block_on(async { RemoteSigner::connect(path) })?;
+    Ok(Arc::new(signer))
+}
+
+#[cfg(not(unix))]
+fn connect_signer(_path: &str, _rt: &mut Runtime) -> Result<Arc<dyn Signer>> {
+    Err(format_err!("remote signer is only supported on unix"))
+}
+
+fn create_wallet(signer: Arc<dyn Signer>, wallet_config: &WalletConfig) -> Result<Wallet> {
+    let account_address = AccountAddress::from_public_key(&signer.public_key());
     let client = StarChainClient::new(
         &wallet_config.chain_address,
         wallet_config.chain_port as u32,
@@ -80,12 +316,20 @@ fn create_wallet(
     let client = Arc::new(client);
 
     info!("account addr is {:?}", hex::encode(account_address));
-    Wallet::new_with_client(
+    let wallet = Wallet::new_with_signer(
         account_address,
-        keypair.clone(),
+        signer,
         client.clone(),
         &wallet_config.store_dir,
-    )
+    )?;
+    let wallet = match &wallet_config.channel_backup {
+        Some(path) => wallet.with_channel_backup(path),
+        None => wallet,
+    };
+    Ok(match wallet_config.checkpoint_interval {
+        Some(interval) => wallet.with_checkpoint_interval(interval),
+        None => wallet,
+    })
 }
 
 async fn start_wallet(wallet: Wallet) -> Result<WalletHandle> {
@@ -176,23 +420,41 @@ fn gen_node(
     ))
 }
 
+/// Unwrap `result`, or report the error and exit.
+fn or_exit<T>(result: Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{:?}", e);
+        std::process::exit(1);
+    })
+}
+
 fn main() {
     let _g = libra_logger::set_default_global_logger(false /* async */, Some(25600));
     env_logger::init();
 
     let args = Args::from_args();
-    let swarm = launch_swarm(&args).unwrap();
+    if let Some(cmd) = &args.cmd {
+        or_exit(run_account_command(&args, cmd));
+        return;
+    }
+    let swarm = or_exit(launch_swarm(&args));
 
     info!("swarm is {:?}", swarm.config);
     let mut rt = Runtime::new().unwrap();
     let executor = rt.handle().clone();
 
-    let keypair = Arc::new(load_from_keyfile(&args.faucet_key_path, args.child_num));
+    let signer = or_exit(create_signer(&args, &swarm.config.wallet, &mut rt));
+    let network_key = or_exit(load_network_key(&args));
+    let identity = or_exit(sign_network_identity(&signer, &network_key, &mut rt));
     let (network_service, tx, rx, close_tx) =
-        build_network_service(&swarm.config.net_config, keypair.clone());
+        build_network_service_with_identity(&swarm.config.net_config, network_key, identity);
+    info!(
+        "network peer of the node is {}, seeds of others refer to it",
+        hex::encode(network_service.identify())
+    );
 
-    let wallet = create_wallet(keypair.clone(), &swarm.config.wallet).unwrap();
-    let wallet = rt.block_on(start_wallet(wallet)).unwrap();
+    let wallet = or_exit(create_wallet(signer, &swarm.config.wallet));
+    let wallet = or_exit(rt.block_on(start_wallet(wallet)));
 
     let path: Option<&Path>;
     match &swarm.config.rpc_config.path {
@@ -216,6 +478,9 @@ fn main() {
         path,
     )
     .unwrap();
+    if let Some(policy) = &swarm.config.rpc_config.approval_policy {
+        node = node.with_approval_policy(ApprovalPolicy::try_from(policy).unwrap());
+    }
 
     node.start_server(&mut rt);
     let api_node = Arc::new(node);
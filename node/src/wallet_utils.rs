@@ -3,7 +3,7 @@ pub use libra_crypto::{
     hash::CryptoHash,
 };
 
-use anyhow::{ensure, Result};
+use anyhow::{ensure, format_err, Result};
 use libra_crypto::ed25519::Ed25519PrivateKey;
 use libra_crypto::test_utils::KeyPair;
 use libra_crypto::PrivateKey;
@@ -12,6 +12,7 @@ use libra_wallet::{
     Mnemonic,
 };
 use std::{
+    convert::TryFrom,
     fs::File,
     io::{BufRead, BufReader},
 };
@@ -19,6 +20,39 @@ use std::{
 /// Delimiter used to ser/deserialize account data.
 pub const DELIMITER: &str = ";";
 
+/// Env var holding the keystore passphrase, used if no passphrase file is given.
+pub const PASSPHRASE_ENV: &str = "STARGATE_KEYSTORE_PASSPHRASE";
+
+/// Read the keystore passphrase from `passphrase_file`, env `PASSPHRASE_ENV`, or the console.
+pub fn read_passphrase(passphrase_file: Option<&String>) -> Result<String> {
+    if let Some(path) = passphrase_file {
+        let passphrase = std::fs::read_to_string(path)?;
+        return Ok(passphrase.trim_end_matches(&['\r', '\n'][..]).to_string());
+    }
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    Ok(rpassword::read_password_from_tty(Some(
+        "keystore passphrase: ",
+    ))?)
+}
+
+/// Read a new passphrase from the console, and ask to repeat it.
+pub fn read_new_passphrase() -> Result<String> {
+    let passphrase = rpassword::read_password_from_tty(Some("new keystore passphrase: "))?;
+    let repeated = rpassword::read_password_from_tty(Some("repeat the passphrase: "))?;
+    ensure!(passphrase == repeated, "passphrases mismatched");
+    Ok(passphrase)
+}
+
+/// Read a hex encoded private key from the console without echo.
+pub fn read_private_key() -> Result<Ed25519PrivateKey> {
+    let key = rpassword::read_password_from_tty(Some("private key in hex: "))?;
+    let bytes = hex::decode(key.trim().trim_start_matches("0x"))?;
+    Ed25519PrivateKey::try_from(bytes.as_slice())
+        .map_err(|e| format_err!("invalid private key: {}", e))
+}
+
 pub struct WalletLibrary {
     key_factory: KeyFactory,
 }
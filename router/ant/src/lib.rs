@@ -36,14 +36,14 @@ use sgtypes::system_event::Event;
 
 use futures_timer::Delay;
 use libra_crypto::HashValue;
-use router::{message_processor::*, Router, TableRouter};
+use router::{message_processor::*, path_cost_with_history, Router, TableRouter};
 use sgtypes::message::{
     AntFinalMessage, AntQueryMessage, BalanceQueryResponse, ExchangeSeedMessageRequest,
     ExchangeSeedMessageResponse, RouterNetworkMessage,
 };
 use sgtypes::s_value::SValue;
-use stats::{DirectedChannel, PaymentInfo, Stats};
-use std::collections::{HashMap, HashSet};
+use stats::{DirectedChannel, PaymentInfo, PaymentOutcome, Stats};
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use async_trait::async_trait;
@@ -189,6 +189,10 @@ impl MixRouter {
                 ant_network_sender
                     .unbounded_send((peer_id, RouterNetworkMessage::AntFinalMessage(message)))?;
             }
+            RouterNetworkMessage::Gossip(message) => {
+                table_network_sender
+                    .unbounded_send((peer_id, RouterNetworkMessage::Gossip(message)))?;
+            }
         }
         Ok(())
     }
@@ -200,8 +204,13 @@ impl Router for MixRouter {
         &self,
         start: AccountAddress,
         end: AccountAddress,
+        amount: u64,
     ) -> Result<Vec<BalanceQueryResponse>> {
-        match self.table_router.find_path_by_addr(start, end).await {
+        match self
+            .table_router
+            .find_path_by_addr(start, end, amount)
+            .await
+        {
             Ok(r) => {
                 if r.len() > 0 {
                     return Ok(r);
@@ -214,7 +223,44 @@ impl Router for MixRouter {
                 );
             }
         }
-        return self.ant_router.find_path_by_addr(start, end).await;
+        return self.ant_router.find_path_by_addr(start, end, amount).await;
+    }
+
+    async fn find_paths_by_addr(
+        &self,
+        start: AccountAddress,
+        end: AccountAddress,
+        amount: u64,
+    ) -> Result<Vec<Vec<BalanceQueryResponse>>> {
+        match self
+            .table_router
+            .find_paths_by_addr(start, end, amount)
+            .await
+        {
+            Ok(r) => {
+                if r.len() > 0 {
+                    return Ok(r);
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "could not find paths by table router from {},to {},e is {}",
+                    start, end, e
+                );
+            }
+        }
+        let path = self
+            .ant_router
+            .find_path_by_addr(start, end, amount)
+            .await?;
+        if path.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(vec![path])
+    }
+
+    async fn node_public_key(&self, node: AccountAddress) -> Result<Option<Ed25519PublicKey>> {
+        self.table_router.node_public_key(node).await
     }
 
     fn stats(&self, channel: DirectedChannel, payment_info: PaymentInfo) -> Result<()> {
@@ -222,6 +268,11 @@ impl Router for MixRouter {
         Ok(())
     }
 
+    async fn report_payment(&self, outcome: PaymentOutcome) -> Result<()> {
+        self.stats_mgr.report(outcome).await;
+        Ok(())
+    }
+
     async fn shutdown(&self) -> Result<()> {
         self.table_router.shutdown().await?;
         self.ant_router.shutdown().await?;
@@ -256,6 +307,7 @@ enum RouterCommand {
     FindPath {
         start: AccountAddress,
         end: AccountAddress,
+        amount: u64,
         responder: futures::channel::oneshot::Sender<Result<Vec<BalanceQueryResponse>>>,
     },
 }
@@ -324,6 +376,7 @@ impl Router for AntRouter {
         &self,
         start: AccountAddress,
         end: AccountAddress,
+        amount: u64,
     ) -> Result<Vec<BalanceQueryResponse>> {
         let (resp_sender, resp_receiver) = futures::channel::oneshot::channel();
 
@@ -331,6 +384,7 @@ impl Router for AntRouter {
             .unbounded_send(RouterCommand::FindPath {
                 start,
                 end,
+                amount,
                 responder: resp_sender,
             })?;
 
@@ -342,6 +396,11 @@ impl Router for AntRouter {
         Ok(())
     }
 
+    async fn report_payment(&self, outcome: PaymentOutcome) -> Result<()> {
+        self.stats_mgr.report(outcome).await;
+        Ok(())
+    }
+
     async fn shutdown(&self) -> Result<()> {
         self.control_sender.unbounded_send(Event::SHUTDOWN)?;
         Ok(())
@@ -384,9 +443,10 @@ impl AntRouterInner {
             RouterCommand::FindPath {
                 start,
                 end,
+                amount,
                 responder,
             } => {
-                return router_inner.find_path(start, end, responder).await;
+                return router_inner.find_path(start, end, amount, responder).await;
             }
         }
     }
@@ -421,6 +481,7 @@ impl AntRouterInner {
         &self,
         start: AccountAddress,
         end: AccountAddress,
+        amount: u64,
         responder: futures::channel::oneshot::Sender<Result<Vec<BalanceQueryResponse>>>,
     ) -> Result<()> {
         let sender_seed = generate_random_u128();
@@ -459,7 +520,10 @@ impl AntRouterInner {
                 let paths = self.path_store.take_path(&r).await;
                 match paths {
                     Some(resp) => {
-                        respond_with(responder, self.find_path_by_pressure(resp, start, end));
+                        respond_with(
+                            responder,
+                            self.find_path_by_cost(resp, start, end, amount).await,
+                        );
                     }
                     None => {
                         respond_with(responder, Err(anyhow!("no path found")));
@@ -478,28 +542,28 @@ impl AntRouterInner {
         Ok(())
     }
 
-    fn find_path_by_pressure(
+    /// Pick the path with the least cost, see `router::path_cost_with_history`.
+    async fn find_path_by_cost(
         &self,
         paths: Vec<AntFinalMessage>,
         start: AccountAddress,
         end: AccountAddress,
+        amount: u64,
     ) -> Result<Vec<BalanceQueryResponse>> {
-        let mut balance_map = HashMap::new();
-        let mut min_pressure = std::i128::MAX;
+        let mut best = None;
+        let mut min_cost = std::i128::MAX;
         for path in paths.into_iter() {
             let balances = self.format_response_list(path, start, end);
-            let mut pressure: i128 = 0;
-            for balance in balances.iter() {
-                pressure =
-                    pressure + balance.total_pay_amount as i128 + balance.remote_balance as i128
-                        - balance.local_balance as i128;
-            }
-            balance_map.insert(pressure, balances);
-            if pressure < min_pressure {
-                min_pressure = pressure;
+            let cost = match path_cost_with_history(&self.stats_mgr, &balances, amount).await {
+                Some(cost) => cost,
+                None => continue,
+            };
+            if best.is_none() || cost < min_cost {
+                min_cost = cost;
+                best = Some(balances);
             }
         }
-        match balance_map.remove(&min_pressure) {
+        match best {
             Some(t) => Ok(t),
             None => bail!("no path find"),
         }
@@ -626,6 +690,7 @@ impl AntRouterInner {
                     .participant_channel_balance(participant.clone())
                     .await?,
                 total_amount,
+                Some(self.wallet.fee_policy(participant.clone())?),
             );
             balance_list_clone.push(balance_query_response);
             let ant_query_message = AntQueryMessage::new(s, peer_id, balance_list_clone);
@@ -863,7 +928,7 @@ fn mix_router_test() {
         _delay(Duration::from_millis(5000)).await;
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr3.clone())
+            .find_path_by_addr(addr1.clone(), addr3.clone(), 100)
             .await?;
 
         assert_eq!(path.len(), 2);
@@ -871,7 +936,7 @@ fn mix_router_test() {
         assert_eq!(path.get(1).expect("should have").remote_addr, addr3.clone());
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr2.clone())
+            .find_path_by_addr(addr1.clone(), addr2.clone(), 100)
             .await?;
 
         assert_eq!(path.len(), 1);
@@ -1051,7 +1116,7 @@ fn ant_router_test() {
         _delay(Duration::from_millis(5000)).await;
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr4.clone())
+            .find_path_by_addr(addr1.clone(), addr4.clone(), 100)
             .await?;
 
         assert_eq!(path.len(), 3);
@@ -1059,7 +1124,7 @@ fn ant_router_test() {
         assert_eq!(path.get(2).expect("should have").remote_addr, addr4.clone());
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr2.clone())
+            .find_path_by_addr(addr1.clone(), addr2.clone(), 100)
             .await?;
 
         assert_eq!(path.len(), 1);
@@ -1067,7 +1132,7 @@ fn ant_router_test() {
         assert_eq!(path.get(0).expect("should have").remote_addr, addr2.clone());
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr5.clone())
+            .find_path_by_addr(addr1.clone(), addr5.clone(), 100)
             .await;
         match path {
             Ok(_) => assert_eq!(1, 2),
@@ -138,19 +138,33 @@ impl GraphStore {
         Ok(())
     }
 
+    pub fn contains_edge(&self, edge: &Edge) -> bool {
+        self.edge_index_map.borrow().contains_key(edge)
+    }
+
     pub fn remove_edge(&self, edge: &Edge) -> Result<()> {
-        match self.edge_index_map.borrow().get(edge) {
+        let edge_index = self.edge_index_map.borrow_mut().remove(edge);
+        match edge_index {
             Some(edge_index) => {
-                self.graph.borrow_mut().remove_edge(*edge_index);
+                let mut graph = self.graph.borrow_mut();
+                graph.remove_edge(edge_index);
+                // the last edge of the graph takes the index of removed one.
+                if let Some(moved) = graph.edge_weight(edge_index) {
+                    self.edge_index_map
+                        .borrow_mut()
+                        .insert(moved.clone(), edge_index);
+                }
             }
             None => {
                 info!("no such edge in map {:?}", edge);
             }
         }
-        self.storage
-            .as_ref()
-            .expect("should have edge storage")
-            .delete::<EdgeSchema>(edge)?;
+        if self.persist_data {
+            self.storage
+                .as_ref()
+                .expect("should have edge storage")
+                .delete::<EdgeSchema>(edge)?;
+        }
         Ok(())
     }
 
@@ -123,3 +123,38 @@ fn graph_storage_test() {
 
     assert_eq!(result.unwrap().expect("should have").len(), 3);
 }
+
+#[test]
+fn graph_remove_edge_test() {
+    use crate::edge::Edge;
+    use crate::graph_store::GraphStore;
+    use crate::vertex::{Type, Vertex};
+    use libra_types::account_address::AccountAddress;
+
+    let vertex1 = Vertex::new(AccountAddress::random(), Type("a".to_string()));
+    let vertex2 = Vertex::new(AccountAddress::random(), Type("b".to_string()));
+    let vertex3 = Vertex::new(AccountAddress::random(), Type("c".to_string()));
+
+    let edge1 = Edge::new(vertex1.clone(), Type("a".to_string()), vertex2.clone());
+    let edge2 = Edge::new(vertex2.clone(), Type("a".to_string()), vertex3.clone());
+
+    let graph_store = GraphStore::new(false, None).unwrap();
+    graph_store.put_edge(&edge1, 1, true).unwrap();
+    graph_store.put_edge(&edge2, 1, true).unwrap();
+    assert!(graph_store.contains_edge(&edge1));
+
+    graph_store.remove_edge(&edge1).unwrap();
+    assert!(!graph_store.contains_edge(&edge1));
+    assert!(graph_store
+        .find_all_path(&vertex1, &vertex3, 5)
+        .unwrap()
+        .is_none());
+
+    // edge2 is moved to the index of edge1, and still can be removed.
+    graph_store.remove_edge(&edge2).unwrap();
+    assert!(!graph_store.contains_edge(&edge2));
+    assert!(graph_store
+        .find_all_path(&vertex2, &vertex3, 5)
+        .unwrap()
+        .is_none());
+}
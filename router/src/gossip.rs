@@ -0,0 +1,380 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, ensure, Result};
+use libra_crypto::ed25519::Ed25519PublicKey;
+use libra_types::account_address::AccountAddress;
+use sgtypes::gossip::{ChannelAnnouncement, ChannelUpdate, Gossip};
+use std::collections::{BTreeSet, HashMap};
+
+/// a node re-announces its node and channels this often, in seconds.
+pub const ANNOUNCE_INTERVAL: u64 = 60;
+/// channels and nodes without gossip newer than this are pruned, in seconds.
+pub const STALE_TIMEOUT: u64 = 10 * ANNOUNCE_INTERVAL;
+/// gossip timestamp can be ahead of local clock at most this much, in seconds.
+pub const MAX_CLOCK_DRIFT: u64 = 60;
+/// at most this many gossip signed by one node are accepted in `RATE_WINDOW` seconds.
+pub const RATE_LIMIT: u32 = 1_000;
+pub const RATE_WINDOW: u64 = 60;
+
+/// Change to be made to the channel graph, with sorted channel participants.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GraphChange {
+    Add(Vec<AccountAddress>),
+    Remove(Vec<AccountAddress>),
+}
+
+struct ChannelInfo {
+    announcement: ChannelAnnouncement,
+    /// latest update of every participant.
+    updates: HashMap<AccountAddress, ChannelUpdate>,
+}
+
+impl ChannelInfo {
+    fn participants(&self) -> Vec<AccountAddress> {
+        let mut participants = self.announcement.participants.clone();
+        participants.sort();
+        participants
+    }
+
+    fn enabled(&self) -> bool {
+        self.updates.values().all(|update| !update.disabled)
+    }
+
+    fn last_seen(&self) -> u64 {
+        self.updates
+            .values()
+            .map(|update| update.timestamp)
+            .fold(self.announcement.timestamp, std::cmp::max)
+    }
+}
+
+/// What the router learns from gossip. Channels in it are already checked on chain,
+/// the enabled ones are in the channel graph.
+#[derive(Default)]
+pub struct GossipState {
+    channels: HashMap<AccountAddress, ChannelInfo>,
+    nodes: HashMap<AccountAddress, u64>,
+    rates: HashMap<AccountAddress, (u64, u32)>,
+    /// public keys of gossip signers, used to encrypt onion for them.
+    public_keys: HashMap<AccountAddress, Ed25519PublicKey>,
+}
+
+impl GossipState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// count a gossip from `signer`, return false if it sends too many.
+    pub fn check_rate(&mut self, signer: AccountAddress, now: u64) -> bool {
+        let (window_start, count) = self.rates.entry(signer).or_insert((now, 0));
+        if *window_start + RATE_WINDOW <= now {
+            *window_start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= RATE_LIMIT
+    }
+
+    pub fn check_timestamp(&self, timestamp: u64, now: u64) -> Result<()> {
+        ensure!(
+            timestamp + STALE_TIMEOUT > now,
+            "gossip at {} is stale",
+            timestamp
+        );
+        ensure!(
+            timestamp <= now + MAX_CLOCK_DRIFT,
+            "gossip at {} is from future",
+            timestamp
+        );
+        Ok(())
+    }
+
+    pub fn add_public_key(&mut self, signer: AccountAddress, public_key: Ed25519PublicKey) {
+        self.public_keys.insert(signer, public_key);
+    }
+
+    pub fn public_key(&self, node: &AccountAddress) -> Option<&Ed25519PublicKey> {
+        self.public_keys.get(node)
+    }
+
+    /// whether the announced channel is already checked on chain.
+    pub fn is_known_channel(&self, announcement: &ChannelAnnouncement) -> bool {
+        match self.channels.get(&announcement.channel_address) {
+            Some(info) => {
+                let participants = announcement
+                    .participants
+                    .iter()
+                    .cloned()
+                    .collect::<BTreeSet<_>>();
+                participants == info.participants().into_iter().collect()
+            }
+            None => false,
+        }
+    }
+
+    /// Apply a verified gossip, return None if it's not newer than what we know,
+    /// which should not be forwarded to others.
+    pub fn apply(&mut self, gossip: &Gossip) -> Result<Option<Vec<GraphChange>>> {
+        match gossip {
+            Gossip::ChannelAnnouncement(announcement) => {
+                if let Some(info) = self.channels.get_mut(&announcement.channel_address) {
+                    if announcement.timestamp <= info.announcement.timestamp {
+                        return Ok(None);
+                    }
+                    info.announcement.timestamp = announcement.timestamp;
+                    return Ok(Some(vec![]));
+                }
+                let info = ChannelInfo {
+                    announcement: announcement.clone(),
+                    updates: HashMap::new(),
+                };
+                let change = GraphChange::Add(info.participants());
+                self.channels.insert(announcement.channel_address, info);
+                Ok(Some(vec![change]))
+            }
+            Gossip::ChannelUpdate(update) => {
+                let info = match self.channels.get_mut(&update.channel_address) {
+                    Some(info) => info,
+                    None => bail!("channel {} is not announced", update.channel_address),
+                };
+                ensure!(
+                    info.announcement.participants.contains(&update.local_addr)
+                        && info.announcement.participants.contains(&update.remote_addr),
+                    "{} and {} are not participants of channel {}",
+                    update.local_addr,
+                    update.remote_addr,
+                    update.channel_address
+                );
+                if let Some(old) = info.updates.get(&update.local_addr) {
+                    if update.timestamp <= old.timestamp {
+                        return Ok(None);
+                    }
+                }
+                let enabled = info.enabled();
+                info.updates.insert(update.local_addr, update.clone());
+                let changes = match (enabled, info.enabled()) {
+                    (true, false) => vec![GraphChange::Remove(info.participants())],
+                    (false, true) => vec![GraphChange::Add(info.participants())],
+                    _ => vec![],
+                };
+                Ok(Some(changes))
+            }
+            Gossip::NodeAnnouncement(announcement) => {
+                if let Some(timestamp) = self.nodes.get(&announcement.node) {
+                    if announcement.timestamp <= *timestamp {
+                        return Ok(None);
+                    }
+                }
+                self.nodes.insert(announcement.node, announcement.timestamp);
+                Ok(Some(vec![]))
+            }
+        }
+    }
+
+    /// Drop stale nodes, and channels which are stale or have a stale participant.
+    pub fn prune(&mut self, now: u64) -> Vec<GraphChange> {
+        let is_stale = |timestamp: u64| timestamp + STALE_TIMEOUT <= now;
+        let stale_nodes = self
+            .nodes
+            .iter()
+            .filter(|(_, timestamp)| is_stale(**timestamp))
+            .map(|(node, _)| *node)
+            .collect::<Vec<_>>();
+        for node in stale_nodes.iter() {
+            self.nodes.remove(node);
+        }
+
+        let stale_channels = self
+            .channels
+            .iter()
+            .filter(|(_, info)| {
+                is_stale(info.last_seen())
+                    || info
+                        .announcement
+                        .participants
+                        .iter()
+                        .any(|p| stale_nodes.contains(p))
+            })
+            .map(|(channel_address, _)| *channel_address)
+            .collect::<Vec<_>>();
+        let mut changes = vec![];
+        for channel_address in stale_channels.iter() {
+            if let Some(info) = self.channels.remove(channel_address) {
+                if info.enabled() {
+                    changes.push(GraphChange::Remove(info.participants()));
+                }
+            }
+        }
+
+        self.rates
+            .retain(|_, (window_start, _)| *window_start + RATE_WINDOW > now);
+        let (nodes, channels) = (&self.nodes, &self.channels);
+        self.public_keys.retain(|node, _| {
+            nodes.contains_key(node)
+                || channels
+                    .values()
+                    .any(|info| info.announcement.participants.contains(node))
+        });
+        changes
+    }
+
+    /// latest update of `local_addr` about its channel with `remote_addr`.
+    pub fn channel_update(
+        &self,
+        local_addr: AccountAddress,
+        remote_addr: AccountAddress,
+    ) -> Option<&ChannelUpdate> {
+        let participants = vec![local_addr, remote_addr]
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        self.channels
+            .get(&AccountAddress::from(&participants))
+            .and_then(|info| info.updates.get(&local_addr))
+    }
+
+    /// whether `local_addr` can forward `amount` to `remote_addr` as far as gossip knows.
+    pub fn can_forward(
+        &self,
+        local_addr: AccountAddress,
+        remote_addr: AccountAddress,
+        amount: u64,
+    ) -> bool {
+        match self.channel_update(local_addr, remote_addr) {
+            Some(update) => !update.disabled && update.capacity >= amount,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::{ed25519::Ed25519PrivateKey, PrivateKey, Uniform};
+    use rand::{rngs::StdRng, SeedableRng};
+    use sgtypes::fee_policy::FeePolicy;
+    use sgtypes::gossip::NodeAnnouncement;
+
+    fn channel(a: AccountAddress, b: AccountAddress) -> (AccountAddress, Vec<AccountAddress>) {
+        let participants = vec![a, b].into_iter().collect::<BTreeSet<_>>();
+        (
+            AccountAddress::from(&participants),
+            participants.into_iter().collect(),
+        )
+    }
+
+    fn update(a: AccountAddress, b: AccountAddress, disabled: bool, timestamp: u64) -> Gossip {
+        let (channel_address, _) = channel(a, b);
+        Gossip::ChannelUpdate(ChannelUpdate::new(
+            channel_address,
+            a,
+            b,
+            1000,
+            FeePolicy::default(),
+            disabled,
+            timestamp,
+        ))
+    }
+
+    #[test]
+    fn test_apply_gossip() {
+        let (a, b) = (AccountAddress::random(), AccountAddress::random());
+        let (channel_address, participants) = channel(a, b);
+        let mut state = GossipState::new();
+
+        assert!(state.apply(&update(a, b, false, 1)).is_err());
+
+        let announcement = ChannelAnnouncement::new(channel_address, participants.clone(), 1);
+        assert!(!state.is_known_channel(&announcement));
+        let gossip = Gossip::ChannelAnnouncement(announcement.clone());
+        assert_eq!(
+            Some(vec![GraphChange::Add(participants.clone())]),
+            state.apply(&gossip).unwrap()
+        );
+        assert!(state.is_known_channel(&announcement));
+        assert_eq!(None, state.apply(&gossip).unwrap());
+
+        assert_eq!(Some(vec![]), state.apply(&update(a, b, false, 2)).unwrap());
+        assert!(state.can_forward(a, b, 1000));
+        assert!(!state.can_forward(a, b, 1001));
+        // no update from b yet.
+        assert!(state.can_forward(b, a, 1001));
+
+        assert_eq!(
+            Some(vec![GraphChange::Remove(participants.clone())]),
+            state.apply(&update(a, b, true, 3)).unwrap()
+        );
+        assert_eq!(None, state.apply(&update(a, b, false, 3)).unwrap());
+        assert_eq!(
+            Some(vec![GraphChange::Add(participants.clone())]),
+            state.apply(&update(a, b, false, 4)).unwrap()
+        );
+
+        let node = Gossip::NodeAnnouncement(NodeAnnouncement::new(a, 4));
+        assert_eq!(Some(vec![]), state.apply(&node).unwrap());
+        assert_eq!(None, state.apply(&node).unwrap());
+    }
+
+    #[test]
+    fn test_prune_and_rate() {
+        let (a, b, c) = (
+            AccountAddress::random(),
+            AccountAddress::random(),
+            AccountAddress::random(),
+        );
+        let (ab, ab_participants) = channel(a, b);
+        let (bc, bc_participants) = channel(b, c);
+        let mut state = GossipState::new();
+        state
+            .apply(&Gossip::ChannelAnnouncement(ChannelAnnouncement::new(
+                ab,
+                ab_participants.clone(),
+                100,
+            )))
+            .unwrap();
+        state
+            .apply(&Gossip::ChannelAnnouncement(ChannelAnnouncement::new(
+                bc,
+                bc_participants.clone(),
+                100,
+            )))
+            .unwrap();
+        state.apply(&update(c, b, false, 200)).unwrap();
+        state
+            .apply(&Gossip::NodeAnnouncement(NodeAnnouncement::new(c, 200)))
+            .unwrap();
+
+        let public_key =
+            Ed25519PrivateKey::generate_for_testing(&mut StdRng::seed_from_u64(1)).public_key();
+        state.add_public_key(a, public_key.clone());
+        state.add_public_key(c, public_key.clone());
+
+        assert!(state.prune(100 + STALE_TIMEOUT - 1).is_empty());
+        // ab is stale, bc is kept alive by the update of c.
+        assert_eq!(
+            vec![GraphChange::Remove(ab_participants)],
+            state.prune(100 + STALE_TIMEOUT)
+        );
+        // c stops announcing itself.
+        assert_eq!(
+            vec![GraphChange::Remove(bc_participants)],
+            state.prune(200 + STALE_TIMEOUT)
+        );
+        assert!(state.channel_update(c, b).is_none());
+        assert!(state.public_key(&a).is_none());
+        assert!(state.public_key(&c).is_none());
+
+        assert!(state.check_timestamp(100, 100 + STALE_TIMEOUT).is_err());
+        assert!(state
+            .check_timestamp(100 + MAX_CLOCK_DRIFT + 1, 100)
+            .is_err());
+        assert!(state.check_timestamp(100, 100).is_ok());
+
+        for _ in 0..RATE_LIMIT {
+            assert!(state.check_rate(a, 0));
+        }
+        assert!(!state.check_rate(a, RATE_WINDOW - 1));
+        assert!(state.check_rate(b, RATE_WINDOW - 1));
+        assert!(state.check_rate(a, RATE_WINDOW));
+    }
+}
@@ -1,17 +1,16 @@
-#[macro_use]
-extern crate lazy_static;
-
+pub mod gossip;
 pub mod message_processor;
 
 use anyhow::{bail, ensure, Result};
 
+use crate::gossip::{GossipState, GraphChange, ANNOUNCE_INTERVAL};
 use crate::message_processor::*;
 use async_trait::async_trait;
 use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
 use futures::channel::oneshot;
 use futures::compat::Stream01CompatExt;
 use futures::stream::StreamExt;
-use graphdb::storage::{OffSetSchema, Storage};
+use graphdb::storage::Storage;
 use graphdb::{edge::Edge, graph_store::GraphStore, vertex::Vertex};
 use libra_crypto::hash::CryptoHash;
 use libra_crypto::{
@@ -22,41 +21,106 @@ use libra_crypto::{
 use libra_logger::prelude::*;
 use libra_tools::tempdir::TempPath;
 use libra_types::account_address::AccountAddress;
+use libra_types::language_storage::StructTag;
 use network::{build_network_service, NetworkMessage};
 use rand::prelude::*;
 use sg_config::config::NetworkConfig;
 use sgchain::star_chain_client::ChainClient;
 use sgchain::star_chain_client::{faucet_async_2, MockChainClient};
+use sgtypes::fee_policy::{route_fee, FeePolicy};
+use sgtypes::gossip::{ChannelAnnouncement, ChannelUpdate, Gossip, NodeAnnouncement, SignedGossip};
 use sgtypes::message::{BalanceQueryRequest, BalanceQueryResponse, RouterNetworkMessage};
 use sgtypes::system_event::Event;
-use sgwallet::wallet::{Wallet, WalletHandle};
-use sgwallet::{get_channel_events, ChannelChangeEvent};
-use stats::{DirectedChannel, PaymentInfo, Stats};
-use std::collections::{HashMap, HashSet};
+use sgwallet::get_channel_participants;
+use sgwallet::wallet::{Wallet, WalletHandle, DEFAULT_ASSET};
+use stats::{DirectedChannel, PaymentInfo, PaymentOutcome, Stats};
+use std::collections::{BTreeSet, HashSet};
 use std::path::Path;
 use std::{
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::runtime::Handle;
-
-lazy_static! {
-    static ref OFFSET_KEY: String = "Offset".to_string();
-}
+use tokio::time::interval;
 
 #[async_trait]
 pub trait Router: Send + Sync {
+    /// find the path with the least fee plus pressure to pay `amount` from start to end.
     async fn find_path_by_addr(
         &self,
         start: AccountAddress,
         end: AccountAddress,
+        amount: u64,
     ) -> Result<Vec<BalanceQueryResponse>>;
 
+    /// find candidate paths which can carry shards of one payment, better ones come first.
+    /// Routers which only know the best path return just that one.
+    async fn find_paths_by_addr(
+        &self,
+        start: AccountAddress,
+        end: AccountAddress,
+        amount: u64,
+    ) -> Result<Vec<Vec<BalanceQueryResponse>>> {
+        let path = self.find_path_by_addr(start, end, amount).await?;
+        if path.is_empty() {
+            return Ok(vec![]);
+        }
+        Ok(vec![path])
+    }
+
+    /// find the path to pay `amount` of `asset`, over channels holding it.
+    /// Routers which only know the default asset fail for others.
+    async fn find_asset_path_by_addr(
+        &self,
+        start: AccountAddress,
+        end: AccountAddress,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<Vec<BalanceQueryResponse>> {
+        ensure!(
+            asset == &*DEFAULT_ASSET,
+            "asset {:?} is not routable",
+            asset
+        );
+        self.find_path_by_addr(start, end, amount).await
+    }
+
+    /// find candidate paths which can carry shards of one payment of `asset`.
+    async fn find_asset_paths_by_addr(
+        &self,
+        start: AccountAddress,
+        end: AccountAddress,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<Vec<Vec<BalanceQueryResponse>>> {
+        ensure!(
+            asset == &*DEFAULT_ASSET,
+            "asset {:?} is not routable",
+            asset
+        );
+        self.find_paths_by_addr(start, end, amount).await
+    }
+
+    /// public key of `node` to encrypt onion for it, None if unknown.
+    /// Routers which don't know keys of other nodes always return None.
+    async fn node_public_key(&self, _node: AccountAddress) -> Result<Option<Ed25519PublicKey>> {
+        Ok(None)
+    }
+
     fn stats(&self, channel: DirectedChannel, payment_info: PaymentInfo) -> Result<()>;
 
+    /// learn from the outcome of a payment attempt,
+    /// paths over channels which failed recently are less likely to be picked.
+    async fn report_payment(&self, outcome: PaymentOutcome) -> Result<()>;
+
     async fn shutdown(&self) -> Result<()>;
 }
 
+/// paths less likely to succeed than this are not picked.
+pub const MIN_PATH_PROBABILITY: f64 = 0.01;
+/// cost of a failed attempt besides the amount, in path cost.
+pub const ATTEMPT_COST: u64 = 100;
+
 pub struct TableRouter {
     inner: Option<RouterInner>,
     executor: Handle,
@@ -65,25 +129,41 @@ pub struct TableRouter {
     network_receiver: Option<UnboundedReceiver<(AccountAddress, RouterNetworkMessage)>>,
     receiver: Option<UnboundedReceiver<RouterMessage>>,
     control_receiver: Option<UnboundedReceiver<Event>>,
-    chain_client: Arc<dyn ChainClient>,
     stats_mgr: Arc<Stats>,
 }
 
+/// The channel graph is built from gossip of other nodes, see `gossip`.
 struct RouterInner {
     graph_store: GraphStore,
     network_sender: UnboundedSender<(AccountAddress, RouterNetworkMessage)>,
     wallet: Arc<WalletHandle>,
+    chain_client: Arc<dyn ChainClient>,
     message_processor: MessageProcessor<RouterNetworkMessage>,
     stats_mgr: Arc<Stats>,
-    storage: Option<Arc<Storage>>,
+    gossip: Mutex<GossipState>,
+    /// participants of local channels which are announced, and when all of them are announced last time.
+    announced: Mutex<(HashSet<AccountAddress>, u64)>,
 }
 
 enum RouterMessage {
     FindPath {
         start: Vertex,
         end: Vertex,
+        asset: StructTag,
+        amount: u64,
         responder: oneshot::Sender<Result<Vec<BalanceQueryResponse>>>,
     },
+    FindAllPaths {
+        start: Vertex,
+        end: Vertex,
+        asset: StructTag,
+        amount: u64,
+        responder: oneshot::Sender<Result<Vec<Vec<BalanceQueryResponse>>>>,
+    },
+    NodePublicKey {
+        node: AccountAddress,
+        responder: oneshot::Sender<Result<Option<Ed25519PublicKey>>>,
+    },
 }
 
 impl TableRouter {
@@ -100,16 +180,13 @@ impl TableRouter {
         let (control_sender, control_receiver) = futures::channel::mpsc::unbounded();
         let message_processor = MessageProcessor::new();
 
-        let storage: Option<Arc<Storage>>;
         let graph_store: GraphStore;
         match path {
             Some(p) => {
                 let storage_arc = Arc::new(Storage::new(p));
-                graph_store = GraphStore::new(true, Some(storage_arc.clone())).unwrap();
-                storage = Some(storage_arc);
+                graph_store = GraphStore::new(true, Some(storage_arc)).unwrap();
             }
             None => {
-                storage = None;
                 graph_store = GraphStore::new(false, None).unwrap();
             }
         }
@@ -117,13 +194,14 @@ impl TableRouter {
         let inner = RouterInner {
             wallet,
             network_sender,
+            chain_client,
             message_processor,
             stats_mgr: stats_mgr.clone(),
-            storage,
             graph_store,
+            gossip: Mutex::new(GossipState::new()),
+            announced: Mutex::new((HashSet::new(), 0)),
         };
         Self {
-            chain_client,
             inner: Some(inner),
             executor,
             sender,
@@ -135,13 +213,21 @@ impl TableRouter {
         }
     }
 
-    async fn find_path(&self, start: Vertex, end: Vertex) -> Result<Vec<BalanceQueryResponse>> {
+    async fn find_path(
+        &self,
+        start: Vertex,
+        end: Vertex,
+        asset: StructTag,
+        amount: u64,
+    ) -> Result<Vec<BalanceQueryResponse>> {
         let (responder, resp_receiver) = futures::channel::oneshot::channel();
 
         info!("find by path {:?},{:?}", start, end);
         self.sender.unbounded_send(RouterMessage::FindPath {
             start,
             end,
+            asset,
+            amount,
             responder,
         })?;
 
@@ -150,13 +236,32 @@ impl TableRouter {
         result
     }
 
+    async fn find_all_paths(
+        &self,
+        start: Vertex,
+        end: Vertex,
+        asset: StructTag,
+        amount: u64,
+    ) -> Result<Vec<Vec<BalanceQueryResponse>>> {
+        let (responder, resp_receiver) = futures::channel::oneshot::channel();
+
+        self.sender.unbounded_send(RouterMessage::FindAllPaths {
+            start,
+            end,
+            asset,
+            amount,
+            responder,
+        })?;
+
+        resp_receiver.await?
+    }
+
     pub fn start(&mut self) -> Result<()> {
         let inner = self.inner.take().expect("should have inner");
         let network_receiver = self
             .network_receiver
             .take()
             .expect("should have network receiver");
-        let chain_client = self.chain_client.clone();
         let receiver = self.receiver.take().expect("should have");
         let control_receiver = self.control_receiver.take().expect("should have");
 
@@ -165,7 +270,6 @@ impl TableRouter {
         self.executor.spawn(RouterInner::start(
             self.executor.clone(),
             inner,
-            chain_client,
             control_receiver,
             receiver,
             network_receiver,
@@ -180,11 +284,53 @@ impl Router for TableRouter {
         &self,
         start: AccountAddress,
         end: AccountAddress,
+        amount: u64,
     ) -> Result<Vec<BalanceQueryResponse>> {
+        self.find_asset_path_by_addr(start, end, &DEFAULT_ASSET, amount)
+            .await
+    }
+
+    async fn find_paths_by_addr(
+        &self,
+        start: AccountAddress,
+        end: AccountAddress,
+        amount: u64,
+    ) -> Result<Vec<Vec<BalanceQueryResponse>>> {
+        self.find_asset_paths_by_addr(start, end, &DEFAULT_ASSET, amount)
+            .await
+    }
+
+    async fn find_asset_path_by_addr(
+        &self,
+        start: AccountAddress,
+        end: AccountAddress,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<Vec<BalanceQueryResponse>> {
+        let start_node = Vertex::new_with_bi_type(start);
+        let end_node = Vertex::new_with_bi_type(end);
+        self.find_path(start_node, end_node, asset.clone(), amount)
+            .await
+    }
+
+    async fn find_asset_paths_by_addr(
+        &self,
+        start: AccountAddress,
+        end: AccountAddress,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<Vec<Vec<BalanceQueryResponse>>> {
         let start_node = Vertex::new_with_bi_type(start);
         let end_node = Vertex::new_with_bi_type(end);
-        let vertexes = self.find_path(start_node, end_node).await;
-        vertexes
+        self.find_all_paths(start_node, end_node, asset.clone(), amount)
+            .await
+    }
+
+    async fn node_public_key(&self, node: AccountAddress) -> Result<Option<Ed25519PublicKey>> {
+        let (responder, resp_receiver) = futures::channel::oneshot::channel();
+        self.sender
+            .unbounded_send(RouterMessage::NodePublicKey { node, responder })?;
+        resp_receiver.await?
     }
 
     fn stats(&self, channel: DirectedChannel, payment_info: PaymentInfo) -> Result<()> {
@@ -192,6 +338,11 @@ impl Router for TableRouter {
         Ok(())
     }
 
+    async fn report_payment(&self, outcome: PaymentOutcome) -> Result<()> {
+        self.stats_mgr.report(outcome).await;
+        Ok(())
+    }
+
     async fn shutdown(&self) -> Result<()> {
         self.control_sender.unbounded_send(Event::SHUTDOWN)?;
         Ok(())
@@ -202,32 +353,12 @@ impl RouterInner {
     async fn start(
         executor: Handle,
         inner: Arc<RouterInner>,
-        chain_client: Arc<dyn ChainClient>,
         mut control_receiver: UnboundedReceiver<Event>,
         mut command_receiver: UnboundedReceiver<RouterMessage>,
         mut network_receiver: UnboundedReceiver<(AccountAddress, RouterNetworkMessage)>,
     ) -> Result<()> {
-        let client = chain_client.clone();
-
-        let mut start_number = 0;
-        let offset: Option<u64>;
-        match inner.storage.as_ref() {
-            Some(s) => {
-                offset = s.get::<OffSetSchema>(&OFFSET_KEY)?;
-            }
-            None => {
-                offset = None;
-            }
-        }
-
-        match offset {
-            Some(t) => {
-                start_number = t;
-            }
-            None => {}
-        }
-        let stream = get_channel_events(client, start_number, 100).fuse();
-        let mut stream = Box::pin(stream);
+        // new local channels are announced at next tick, all of them every ANNOUNCE_INTERVAL.
+        let mut announce_interval = interval(Duration::from_secs(1)).fuse();
 
         loop {
             futures::select! {
@@ -241,8 +372,8 @@ impl RouterInner {
                 command = command_receiver.select_next_some() => {
                     executor.spawn(Self::handle_router_msg(inner.clone(), command));
                 },
-                message = stream.select_next_some() => {
-                    executor.spawn(Self::handle_stream(inner.clone(),message));
+                _ = announce_interval.select_next_some() => {
+                    executor.spawn(Self::handle_announce(inner.clone()));
                 },
                 _ = control_receiver.select_next_some() =>{
                     info!("shutdown stream");
@@ -250,7 +381,6 @@ impl RouterInner {
                 },
             }
         }
-        drop(stream);
         Ok(())
     }
 
@@ -272,6 +402,11 @@ impl RouterInner {
                     .await
                     .unwrap();
             }
+            RouterNetworkMessage::Gossip(message) => {
+                if let Err(e) = inner.handle_gossip(peer_id, message).await {
+                    warn!("drop gossip from {}, {}", peer_id, e);
+                }
+            }
             _ => {}
         }
     }
@@ -281,46 +416,123 @@ impl RouterInner {
             RouterMessage::FindPath {
                 start,
                 end,
+                asset,
+                amount,
                 responder,
             } => {
-                let paths = inner.graph_store.find_all_path(&start, &end, 5)?;
+                let paths = inner.find_usable_paths(&start, &end, &asset, amount)?;
 
                 info!("path is {:?}", paths);
                 let result = match paths {
-                    Some(t) => inner.find_path(t).await?,
+                    Some(t) => inner.find_path(t, &asset, amount).await?,
                     None => vec![],
                 };
                 respond_with(responder, Ok(result));
             }
+            RouterMessage::FindAllPaths {
+                start,
+                end,
+                asset,
+                amount,
+                responder,
+            } => {
+                let paths = inner.find_usable_paths(&start, &end, &asset, amount)?;
+                let result = match paths {
+                    Some(t) => inner.find_all_paths(t, &asset, amount).await,
+                    None => vec![],
+                };
+                info!("find {} paths from {:?} to {:?}", result.len(), start, end);
+                respond_with(responder, Ok(result));
+            }
+            RouterMessage::NodePublicKey { node, responder } => {
+                let public_key = inner.gossip.lock().unwrap().public_key(&node).cloned();
+                respond_with(responder, Ok(public_key));
+            }
         }
         Ok(())
     }
 
-    async fn find_path(&self, paths: HashSet<Vec<Vertex>>) -> Result<Vec<BalanceQueryResponse>> {
-        let mut balance_map = HashMap::new();
-        let mut min_pressure = std::i128::MAX;
+    /// find paths in the graph, except those gossip tells can't carry `amount`.
+    /// The first hop is checked by querying balance later.
+    /// Gossip only announces capacity of the default asset, paths of other assets are checked
+    /// by querying balances of all hops.
+    fn find_usable_paths(
+        &self,
+        start: &Vertex,
+        end: &Vertex,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<Option<HashSet<Vec<Vertex>>>> {
+        let mut paths = match self.graph_store.find_all_path(start, end, 5)? {
+            Some(paths) => paths,
+            None => return Ok(None),
+        };
+        if asset == &*DEFAULT_ASSET {
+            let gossip = self.gossip.lock().unwrap();
+            paths.retain(|path| {
+                path.windows(2)
+                    .skip(1)
+                    .all(|hop| gossip.can_forward(hop[0].id, hop[1].id, amount))
+            });
+        }
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(paths))
+    }
+
+    async fn find_path(
+        &self,
+        paths: HashSet<Vec<Vertex>>,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<Vec<BalanceQueryResponse>> {
+        let mut best = None;
+        let mut min_cost = std::i128::MAX;
         for path in paths.into_iter() {
-            let balances = self.vertexes_to_balance_list(path).await?;
-            let mut pressure: i128 = 0;
-            for balance in balances.iter() {
-                pressure =
-                    pressure + balance.total_pay_amount as i128 + balance.remote_balance as i128
-                        - balance.local_balance as i128;
-            }
-            balance_map.insert(pressure, balances);
-            if pressure < min_pressure {
-                min_pressure = pressure;
+            let balances = self.vertexes_to_balance_list(path, asset).await?;
+            let cost = match path_cost_with_history(&self.stats_mgr, &balances, amount).await {
+                Some(cost) => cost,
+                None => continue,
+            };
+            if best.is_none() || cost < min_cost {
+                min_cost = cost;
+                best = Some(balances);
             }
         }
-        match balance_map.remove(&min_pressure) {
-            Some(t) => Ok(t),
-            None => Ok(vec![]),
+        Ok(best.unwrap_or_default())
+    }
+
+    /// query balances of all paths, and sort them by cost with payment history.
+    /// Paths whose balance can't be queried, or unlikely to succeed are skipped.
+    async fn find_all_paths(
+        &self,
+        paths: HashSet<Vec<Vertex>>,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Vec<Vec<BalanceQueryResponse>> {
+        let mut result = Vec::new();
+        for path in paths.into_iter() {
+            let balances = match self.vertexes_to_balance_list(path, asset).await {
+                Ok(balances) => balances,
+                Err(e) => {
+                    warn!("query path balance error, {}", e);
+                    continue;
+                }
+            };
+            if let Some(cost) = path_cost_with_history(&self.stats_mgr, &balances, amount).await {
+                result.push((cost, balances));
+            }
         }
+        result.sort_by_key(|(cost, _)| *cost);
+        result.into_iter().map(|(_, balances)| balances).collect()
     }
 
+    /// query balances of `asset` of all hops in the path.
     async fn vertexes_to_balance_list(
         &self,
         mut vertexes: Vec<Vertex>,
+        asset: &StructTag,
     ) -> Result<Vec<BalanceQueryResponse>> {
         ensure!(vertexes.len() >= 2, "should have at lease 1 hops");
         let mut result = Vec::new();
@@ -339,11 +551,14 @@ impl RouterInner {
         let response = BalanceQueryResponse::new(
             first,
             second.clone(),
-            self.wallet.channel_balance(second.clone()).await?,
             self.wallet
-                .participant_channel_balance(second.clone())
+                .asset_channel_balance(second.clone(), asset)
+                .await?,
+            self.wallet
+                .participant_asset_channel_balance(second.clone(), asset)
                 .await?,
             total_amount,
+            Some(self.wallet.fee_policy(second)?),
         );
         info!("find first hop balance info {:?}", response);
         result.push(response);
@@ -358,7 +573,9 @@ impl RouterInner {
                         "check hop balance from {} to {}",
                         local_addr.id, remote_addr.id,
                     );
-                    let response = self.query_balance(local_addr.id, remote_addr.id).await?;
+                    let response = self
+                        .query_balance(local_addr.id, remote_addr.id, asset)
+                        .await?;
                     info!(
                         "check hop balance from {} to {},balance is {}",
                         local_addr.id, remote_addr.id, response.local_balance
@@ -371,57 +588,194 @@ impl RouterInner {
         Ok(result)
     }
 
-    async fn handle_stream(inner: Arc<RouterInner>, result: Result<(u64, ChannelChangeEvent)>) {
-        match result {
-            Ok((u, t)) => {
-                info!("get {} from stream,event is {:?}", u, t);
-                let result = inner.handle_channel_stream(u, t).await;
-                match result {
-                    Ok(_t) => {}
-                    Err(e) => {
-                        warn!("get error from server channel stream, {}", e);
+    async fn handle_gossip(&self, peer_id: AccountAddress, message: SignedGossip) -> Result<()> {
+        message.verify()?;
+        let signer = message.signer();
+        let now = now_secs();
+        {
+            let mut gossip = self.gossip.lock().unwrap();
+            gossip.check_timestamp(message.gossip.timestamp(), now)?;
+            ensure!(
+                gossip.check_rate(signer, now),
+                "too many gossip signed by {}",
+                signer
+            );
+            gossip.add_public_key(signer, message.public_key.clone());
+        }
+        if let Gossip::ChannelAnnouncement(announcement) = &message.gossip {
+            let known = self.gossip.lock().unwrap().is_known_channel(announcement);
+            if !known {
+                self.check_channel_on_chain(announcement).await?;
+            }
+        }
+        if self.apply_gossip(&message.gossip)? {
+            self.broadcast_gossip(message, Some(peer_id)).await?;
+        }
+        Ok(())
+    }
+
+    async fn check_channel_on_chain(&self, announcement: &ChannelAnnouncement) -> Result<()> {
+        announcement.check_channel_address()?;
+        let participants =
+            get_channel_participants(self.chain_client.clone(), announcement.channel_address)
+                .await?;
+        match participants {
+            Some(participants) => ensure!(
+                participants.into_iter().collect::<BTreeSet<_>>()
+                    == announcement.participants.iter().cloned().collect(),
+                "participants of channel {} mismatch with chain",
+                announcement.channel_address
+            ),
+            None => bail!("channel {} is not on chain", announcement.channel_address),
+        }
+        Ok(())
+    }
+
+    /// apply gossip and update the graph, return whether it's new.
+    fn apply_gossip(&self, gossip: &Gossip) -> Result<bool> {
+        let mut state = self.gossip.lock().unwrap();
+        match state.apply(gossip)? {
+            Some(changes) => {
+                self.update_graph(changes)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// should be called with gossip state locked, so that graph changes are in order.
+    fn update_graph(&self, changes: Vec<GraphChange>) -> Result<()> {
+        for change in changes.into_iter() {
+            match change {
+                GraphChange::Add(participants) => {
+                    let edge = generate_edge(participants)?;
+                    if !self.graph_store.contains_edge(&edge) {
+                        self.graph_store.put_edge(&edge, 0, true)?;
                     }
                 }
+                GraphChange::Remove(participants) => {
+                    self.graph_store
+                        .remove_edge(&generate_edge(participants)?)?;
+                }
             }
-            Err(e) => {
-                warn!("get error from server channel stream, {}", e);
+        }
+        Ok(())
+    }
+
+    /// send gossip to channel participants, except where it comes from and who signs it.
+    async fn broadcast_gossip(
+        &self,
+        message: SignedGossip,
+        from: Option<AccountAddress>,
+    ) -> Result<()> {
+        let signer = message.signer();
+        for participant in self.wallet.get_all_channels().await?.into_iter() {
+            if Some(participant) == from || participant == signer {
+                continue;
             }
+            self.network_sender
+                .unbounded_send((participant, RouterNetworkMessage::Gossip(message.clone())))?;
         }
+        Ok(())
     }
 
-    async fn handle_channel_stream(&self, number: u64, event: ChannelChangeEvent) -> Result<()> {
-        match event {
-            ChannelChangeEvent::Opened {
-                channel_address,
-                balances,
-            } => {
-                ensure!(balances.len() == 2, "balances len should be 2");
-                let edge = generate_edge(channel_address, balances)?;
-                let _index = self.graph_store.put_edge(&edge, 0, true)?;
+    async fn handle_announce(inner: Arc<RouterInner>) {
+        if let Err(e) = inner.announce().await {
+            warn!("announce channels error, {}", e);
+        }
+    }
+
+    /// Announce channels which are new on chain, or all channels and the node itself
+    /// every ANNOUNCE_INTERVAL. Closed channels are announced as disabled.
+    async fn announce(&self) -> Result<()> {
+        let now = now_secs();
+        let account = self.wallet.account();
+        let channels = self.wallet.get_all_channels().await?;
+        let (announced, last_announce) = {
+            let announced = self.announced.lock().unwrap();
+            (announced.0.clone(), announced.1)
+        };
+        let announce_all = last_announce + ANNOUNCE_INTERVAL <= now;
+
+        let mut messages = vec![];
+        if announce_all {
+            messages.push(Gossip::NodeAnnouncement(NodeAnnouncement::new(
+                account, now,
+            )));
+        }
+        let mut now_announced = HashSet::new();
+        for participant in channels.iter() {
+            if !announce_all && announced.contains(participant) {
+                now_announced.insert(*participant);
+                continue;
             }
-            ChannelChangeEvent::Closed {
-                channel_address,
-                balances,
-            } => {
-                ensure!(balances.len() == 2, "balances len should be 2");
-                let edge = generate_edge(channel_address, balances)?;
-                self.graph_store.remove_edge(&edge)?;
+            let participants = vec![account, *participant]
+                .into_iter()
+                .collect::<BTreeSet<_>>();
+            let channel_address = AccountAddress::from(&participants);
+            if get_channel_participants(self.chain_client.clone(), channel_address)
+                .await?
+                .is_none()
+            {
+                // not on chain yet, others can't check it.
+                continue;
             }
-            ChannelChangeEvent::Locked {
+            let capacity = self.wallet.channel_balance(*participant).await?
+                + self
+                    .wallet
+                    .participant_channel_balance(*participant)
+                    .await?;
+            messages.push(Gossip::ChannelAnnouncement(ChannelAnnouncement::new(
                 channel_address,
-                balances,
-            } => {
-                ensure!(balances.len() == 2, "balances len should be 2");
-                let edge = generate_edge(channel_address, balances)?;
-                self.graph_store.remove_edge(&edge)?;
-            }
+                participants.into_iter().collect(),
+                now,
+            )));
+            messages.push(Gossip::ChannelUpdate(ChannelUpdate::new(
+                channel_address,
+                account,
+                *participant,
+                capacity,
+                self.wallet.fee_policy(*participant)?,
+                false,
+                now,
+            )));
+            now_announced.insert(*participant);
         }
-        match self.storage.as_ref() {
-            Some(s) => {
-                s.put::<OffSetSchema>(&OFFSET_KEY, &number)?;
+        for participant in announced.difference(&channels) {
+            let participants = vec![account, *participant]
+                .into_iter()
+                .collect::<BTreeSet<_>>();
+            messages.push(Gossip::ChannelUpdate(ChannelUpdate::new(
+                AccountAddress::from(&participants),
+                account,
+                *participant,
+                0,
+                FeePolicy::default(),
+                true,
+                now,
+            )));
+        }
+
+        let signer = self.wallet.signer();
+        for gossip in messages.into_iter() {
+            match self.apply_gossip(&gossip) {
+                Ok(true) => {
+                    let signature = signer.sign_gossip(&gossip)?;
+                    let signed = gossip.with_signature(signer.public_key(), signature);
+                    self.broadcast_gossip(signed, None).await?
+                }
+                Ok(false) => {}
+                Err(e) => warn!("skip announcing {:?}, {}", gossip, e),
             }
-            None => {}
         }
+        if announce_all {
+            let changes = self.gossip.lock().unwrap().prune(now);
+            self.update_graph(changes)?;
+        }
+        *self.announced.lock().unwrap() = (
+            now_announced,
+            if announce_all { now } else { last_announce },
+        );
         Ok(())
     }
 
@@ -439,7 +793,11 @@ impl RouterInner {
             );
             return Ok(());
         }
-        let balance = self.wallet.channel_balance(msg.remote_addr).await?;
+        let asset = msg.asset.unwrap_or_else(|| DEFAULT_ASSET.clone());
+        let balance = self
+            .wallet
+            .asset_channel_balance(msg.remote_addr, &asset)
+            .await?;
         let total_amount = self
             .stats_mgr
             .back_pressure(&(msg.local_addr, msg.remote_addr))
@@ -449,9 +807,10 @@ impl RouterInner {
             msg.remote_addr,
             balance,
             self.wallet
-                .participant_channel_balance(msg.remote_addr)
+                .participant_asset_channel_balance(msg.remote_addr, &asset)
                 .await?,
             total_amount,
+            Some(self.wallet.fee_policy(msg.remote_addr)?),
         );
         info!("send message to {}", sender_addr);
         self.network_sender.unbounded_send((
@@ -481,8 +840,17 @@ impl RouterInner {
         &self,
         local_addr: AccountAddress,
         remote_addr: AccountAddress,
+        asset: &StructTag,
     ) -> Result<BalanceQueryResponse> {
-        let request = BalanceQueryRequest::new(local_addr.clone(), remote_addr.clone());
+        let request = if asset == &*DEFAULT_ASSET {
+            BalanceQueryRequest::new(local_addr.clone(), remote_addr.clone())
+        } else {
+            BalanceQueryRequest::new_with_asset(
+                local_addr.clone(),
+                remote_addr.clone(),
+                asset.clone(),
+            )
+        };
         self.network_sender.unbounded_send((
             local_addr.clone(),
             RouterNetworkMessage::BalanceQueryRequest(request),
@@ -500,19 +868,82 @@ impl RouterInner {
     }
 }
 
-fn generate_edge(
-    channel_address: AccountAddress,
-    balances: HashMap<AccountAddress, u64>,
-) -> Result<Edge> {
-    let mut vertexes = Vec::new();
-    for (addr, balance) in balances.iter() {
-        info!(
-            "get channel opened event ,channel addr is {} , addr {} balance is {}",
-            channel_address, addr, balance,
-        );
-        vertexes.push(Vertex::new_with_bi_type(addr.clone()));
+fn generate_edge(participants: Vec<AccountAddress>) -> Result<Edge> {
+    Edge::from_vertexes(
+        participants
+            .into_iter()
+            .map(Vertex::new_with_bi_type)
+            .collect(),
+    )
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+fn path_pressure(balances: &[BalanceQueryResponse]) -> i128 {
+    let mut pressure: i128 = 0;
+    for balance in balances.iter() {
+        pressure = pressure + balance.total_pay_amount as i128 + balance.remote_balance as i128
+            - balance.local_balance as i128;
+    }
+    pressure
+}
+
+/// Fee policies of intermediate hops of the path, a hop whose policy is unknown
+/// is assumed to charge the default fee.
+pub fn path_fee_policies(balances: &[BalanceQueryResponse]) -> Vec<FeePolicy> {
+    balances
+        .iter()
+        .skip(1)
+        .map(|balance| balance.fee_policy.unwrap_or_default())
+        .collect()
+}
+
+/// Cost to pay `amount` over the path, which is the total fee to intermediate hops
+/// plus the pressure of the path as the risk that the payment gets stuck.
+pub fn path_cost(balances: &[BalanceQueryResponse], amount: u64) -> i128 {
+    match route_fee(amount, &path_fee_policies(balances)) {
+        Ok(fee) => fee as i128 + path_pressure(balances),
+        Err(_) => std::i128::MAX,
+    }
+}
+
+/// Probability of the payment getting through every channel of the path.
+pub async fn path_probability(
+    stats: &Stats,
+    balances: &[BalanceQueryResponse],
+    amount: u64,
+) -> f64 {
+    let mut probability = 1.0;
+    for balance in balances.iter() {
+        probability *= stats
+            .probability(&(balance.local_addr, balance.remote_addr), amount)
+            .await;
+    }
+    probability
+}
+
+/// `path_cost` plus the expected cost of failed attempts over the path,
+/// None if the path is unlikely to succeed.
+pub fn penalized_cost(cost: i128, probability: f64, amount: u64) -> Option<i128> {
+    if probability < MIN_PATH_PROBABILITY {
+        return None;
     }
-    Edge::from_vertexes(vertexes)
+    let penalty = (amount as f64 + ATTEMPT_COST as f64) * (1.0 / probability - 1.0);
+    Some(cost.saturating_add(penalty as i128))
+}
+
+pub async fn path_cost_with_history(
+    stats: &Stats,
+    balances: &[BalanceQueryResponse],
+    amount: u64,
+) -> Option<i128> {
+    let probability = path_probability(stats, balances, amount).await;
+    penalized_cost(path_cost(balances, amount), probability, amount)
 }
 
 fn respond_with<T>(responder: futures::channel::oneshot::Sender<T>, msg: T) {
@@ -521,6 +952,35 @@ fn respond_with<T>(responder: futures::channel::oneshot::Sender<T>, msg: T) {
     };
 }
 
+#[test]
+fn path_cost_test() {
+    let (a, b, c, d) = (
+        AccountAddress::random(),
+        AccountAddress::random(),
+        AccountAddress::random(),
+        AccountAddress::random(),
+    );
+    let hop = |local, remote, policy| BalanceQueryResponse::new(local, remote, 100, 100, 0, policy);
+
+    let cheap = vec![hop(a, b, None), hop(b, d, Some(FeePolicy::new(1, 0, 10)))];
+    let expensive = vec![hop(a, c, None), hop(c, d, Some(FeePolicy::new(10, 0, 10)))];
+    assert_eq!(1, path_cost(&cheap, 50));
+    assert_eq!(10, path_cost(&expensive, 50));
+
+    // the policy of first hop is ours, and unknown policy is the default one.
+    let unknown = vec![hop(a, c, Some(FeePolicy::new(10, 0, 10))), hop(c, d, None)];
+    assert_eq!(path_pressure(&unknown), path_cost(&unknown, 50));
+}
+
+#[test]
+fn penalized_cost_test() {
+    assert_eq!(Some(10), penalized_cost(10, 1.0, 100));
+    assert_eq!(Some(210), penalized_cost(10, 0.5, 100));
+    assert_eq!(None, penalized_cost(10, MIN_PATH_PROBABILITY / 2.0, 100));
+    // a cheap path which failed recently costs more than an expensive one.
+    assert!(penalized_cost(10, 0.1, 100) > penalized_cost(500, 0.6, 100));
+}
+
 #[test]
 fn router_test() {
     use anyhow::Error;
@@ -668,7 +1128,7 @@ fn router_test() {
         _delay(Duration::from_millis(5000)).await;
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr2.clone())
+            .find_path_by_addr(addr1.clone(), addr2.clone(), 100)
             .await?;
 
         assert_eq!(path.len(), 1);
@@ -676,7 +1136,7 @@ fn router_test() {
         assert_eq!(path.get(0).expect("should have").remote_addr, addr2.clone());
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr4.clone())
+            .find_path_by_addr(addr1.clone(), addr4.clone(), 100)
             .await?;
 
         assert_eq!(path.len(), 3);
@@ -684,7 +1144,7 @@ fn router_test() {
         assert_eq!(path.get(2).expect("should have").remote_addr, addr4.clone());
 
         let path = router1
-            .find_path_by_addr(addr1.clone(), addr5.clone())
+            .find_path_by_addr(addr1.clone(), addr5.clone(), 100)
             .await;
         match path {
             Ok(_) => assert_eq!(1, 2),
@@ -719,6 +1179,161 @@ fn router_test() {
     debug!("here");
 }
 
+#[test]
+fn router_asset_test() {
+    use anyhow::Error;
+    use libra_config::utils::get_available_port;
+    use sgchain::star_chain_client::MockChainClient;
+    use std::sync::Arc;
+    use tokio::runtime::Runtime;
+
+    libra_logger::init_for_e2e_testing();
+    let mut rt = Runtime::new().unwrap();
+    let executor = rt.handle().clone();
+
+    let (mock_chain_service, _handle) = MockChainClient::new();
+    let client = Arc::new(mock_chain_service);
+
+    let (wallet1, addr1, keypair1) = rt.block_on(_gen_wallet(client.clone())).unwrap();
+    let (wallet2, addr2, keypair2) = rt.block_on(_gen_wallet(client.clone())).unwrap();
+    let (wallet3, addr3, keypair3) = rt.block_on(_gen_wallet(client.clone())).unwrap();
+
+    let network_config1 = _create_node_network_config(
+        format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
+        vec![],
+    );
+    let seed = format!("{}/p2p/{}", &network_config1.listen, hex::encode(addr1));
+    let network_config2 = _create_node_network_config(
+        format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
+        vec![seed.clone()],
+    );
+    let network_config3 = _create_node_network_config(
+        format!("/ip4/127.0.0.1/tcp/{}", get_available_port()),
+        vec![seed.clone()],
+    );
+
+    let mut routers = vec![];
+    let mut close_txs = vec![];
+    for (wallet, config, keypair) in vec![
+        (wallet1.clone(), network_config1, keypair1),
+        (wallet2.clone(), network_config2, keypair2),
+        (wallet3.clone(), network_config3, keypair3),
+    ] {
+        let (_network, tx, rx, close_tx) = build_network_service(&config, keypair);
+        let (rtx, rrx) = _prepare_network(tx, rx, executor.clone());
+        let mut router = TableRouter::new(
+            client.clone(),
+            executor.clone(),
+            wallet,
+            rtx,
+            rrx,
+            Arc::new(Stats::new(executor.clone())),
+            None,
+        );
+        router.start().unwrap();
+        routers.push(Arc::new(router));
+        close_txs.push(close_tx);
+    }
+
+    let f = async move {
+        let wallets = vec![wallet1.clone(), wallet2.clone(), wallet3.clone()];
+        let point = _setup_point_asset(wallet1.clone(), wallets.clone()).await?;
+        // 1 and 2 hold both libra and point in their channel, 2 and 3 hold libra only.
+        _open_channel_with_asset(wallet1.clone(), wallet2.clone(), &point, 100000, 500).await?;
+        _open_channel(wallet2.clone(), wallet3.clone(), 100000, 100000).await?;
+
+        _delay(Duration::from_millis(5000)).await;
+
+        let router1 = routers[0].clone();
+        let path = router1.find_path_by_addr(addr1, addr3, 1000).await?;
+        assert_eq!(path.len(), 2);
+
+        let path = router1
+            .find_asset_path_by_addr(addr1, addr2, &point, 100)
+            .await?;
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].remote_addr, addr2);
+
+        // point balance of the channel is less than the libra one.
+        let path = router1
+            .find_asset_path_by_addr(addr1, addr2, &point, 1000)
+            .await;
+        assert!(path.map(|path| path.is_empty()).unwrap_or(true));
+
+        // no point can be forwarded over the channel of 2 and 3.
+        let path = router1
+            .find_asset_path_by_addr(addr1, addr3, &point, 100)
+            .await;
+        assert!(path.map(|path| path.is_empty()).unwrap_or(true));
+
+        for router in routers.iter() {
+            router.shutdown().await?;
+        }
+        for close_tx in close_txs.into_iter() {
+            close_tx.send(()).unwrap();
+        }
+        for wallet in wallets.iter() {
+            wallet.stop().await?;
+        }
+        Ok::<_, Error>(())
+    };
+
+    rt.block_on(f).unwrap();
+}
+
+/// Deploy the point asset of sgwallet test case by `deployer`, register it and mint some by all
+/// `wallets`, return the asset tag.
+async fn _setup_point_asset(
+    deployer: Arc<WalletHandle>,
+    wallets: Vec<Arc<WalletHandle>>,
+) -> Result<StructTag> {
+    use libra_types::{
+        identifier::Identifier,
+        transaction::{Script, TransactionArgument},
+    };
+    use sgchain::client_state_view::ClientStateView;
+    use sgcompiler::{Compiler, StateViewModuleLoader};
+    use sgwallet::scripts::ChannelAsset;
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../sgwallet/test_case/test_point_asset");
+    let module = {
+        let client_state_view = ClientStateView::new(None, deployer.client());
+        let module_loader = StateViewModuleLoader::new(&client_state_view);
+        let compiler = Compiler::new_with_module_loader(deployer.account(), &module_loader);
+        compiler.compile_module(std::fs::read_to_string(path.join("module.mvir"))?.as_str())?
+    };
+    deployer.deploy_module(module).await?;
+    // wait the module is visible to the module loader.
+    _delay(Duration::from_millis(1000)).await;
+
+    let client_state_view = ClientStateView::new(None, deployer.client());
+    let module_loader = StateViewModuleLoader::new(&client_state_view);
+    let compiler = Compiler::new_with_module_loader(deployer.account(), &module_loader);
+    let package = compiler.compile_package(path.join("scripts"))?;
+    let mint =
+        compiler.compile_script(std::fs::read_to_string(path.join("mint.mvir"))?.as_str())?;
+    let tag = |name: &str| -> Result<StructTag> {
+        Ok(StructTag {
+            address: deployer.account(),
+            module: Identifier::new("Point")?,
+            name: Identifier::new(name)?,
+            type_params: vec![],
+        })
+    };
+    let asset = ChannelAsset::new(tag("T")?, "scripts".to_string(), tag("Balance")?);
+    for wallet in wallets {
+        wallet.install_package(package.clone()).await?;
+        wallet.register_asset(asset.clone())?;
+        wallet
+            .run_script(Script::new(
+                mint.clone(),
+                vec![TransactionArgument::U64(1000)],
+            ))
+            .await?;
+    }
+    Ok(asset.asset)
+}
+
 #[allow(dead_code)]
 async fn _gen_wallet(
     client: Arc<MockChainClient>,
@@ -773,6 +1388,33 @@ async fn _open_channel(
     Ok(sender_gas)
 }
 
+/// Open channel of libra and `asset`, both participants deposit the same amount.
+async fn _open_channel_with_asset(
+    sender_wallet: Arc<WalletHandle>,
+    receiver_wallet: Arc<WalletHandle>,
+    asset: &StructTag,
+    amount: u64,
+    asset_amount: u64,
+) -> Result<u64> {
+    let sender = sender_wallet.account();
+    let receiver = receiver_wallet.account();
+    let req = sender_wallet
+        .open_with_asset(receiver, asset, amount, amount, asset_amount, asset_amount)
+        .await?;
+    let resp = match receiver_wallet.verify_txn(sender, &req).await? {
+        Some(t) => t,
+        None => {
+            receiver_wallet
+                .approve_txn(sender, req.request_id())
+                .await?
+        }
+    };
+    let _ = sender_wallet.verify_txn_response(receiver, &resp).await?;
+    let sender_gas = sender_wallet.apply_txn(receiver, &resp).await?;
+    let _receiver_gas = receiver_wallet.apply_txn(sender, &resp).await?;
+    Ok(sender_gas)
+}
+
 fn _get_unix_ts() -> u64 {
     let start = SystemTime::now();
     let since_the_epoch = start
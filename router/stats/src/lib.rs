@@ -8,8 +8,16 @@ use libra_types::account_address::AccountAddress;
 use sgtypes::system_event::Event;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::runtime::Handle;
 
+/// probability of a channel forwarding a payment when nothing is known about it.
+pub const APRIORI_PROBABILITY: f64 = 0.6;
+/// probability of a channel forwarding no more than it forwarded last time.
+pub const SUCCESS_PROBABILITY: f64 = 0.95;
+/// a failed channel recovers half of its probability in this many seconds.
+pub const PENALTY_HALF_LIFE: u64 = 300;
+
 pub struct Stats {
     executor: Handle,
     inner: Arc<StatsInner>,
@@ -21,6 +29,7 @@ pub struct Stats {
 
 struct StatsInner {
     user_channel_stats: Mutex<HashMap<DirectedChannel, ChannelStats>>,
+    channel_history: Mutex<HashMap<DirectedChannel, ChannelHistory>>,
 }
 struct ChannelStats {
     payment_data: Mutex<HashMap<HashValue, u64>>,
@@ -35,6 +44,68 @@ pub type DirectedChannel = (AccountAddress, AccountAddress);
 
 pub type PaymentInfo = (HashValue, u64, PayEnum);
 
+/// Result of sending `amount` over `route`. Channels before `failed_hop` forwarded it,
+/// and the one from `route[failed_hop]` to `route[failed_hop + 1]` failed it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentOutcome {
+    pub route: Vec<AccountAddress>,
+    pub amount: u64,
+    pub failed_hop: Option<usize>,
+}
+
+impl PaymentOutcome {
+    pub fn new(route: Vec<AccountAddress>, amount: u64, failed_hop: Option<usize>) -> Self {
+        Self {
+            route,
+            amount,
+            failed_hop,
+        }
+    }
+}
+
+/// Last success and failure of a directed channel, as (amount, unix timestamp in seconds).
+#[derive(Clone, Debug, Default)]
+pub struct ChannelHistory {
+    last_success: Option<(u64, u64)>,
+    last_failure: Option<(u64, u64)>,
+}
+
+impl ChannelHistory {
+    pub fn record_success(&mut self, amount: u64, now: u64) {
+        self.last_success = Some((amount, now));
+        if let Some((failed_amount, _)) = self.last_failure {
+            if failed_amount <= amount {
+                self.last_failure = None;
+            }
+        }
+    }
+
+    pub fn record_failure(&mut self, amount: u64, now: u64) {
+        self.last_failure = Some((amount, now));
+        if let Some((succeeded_amount, _)) = self.last_success {
+            if succeeded_amount >= amount {
+                self.last_success = None;
+            }
+        }
+    }
+
+    /// Probability of forwarding `amount`. A failure makes amounts no less than the failed one
+    /// unlikely, and the penalty decays with time.
+    pub fn probability(&self, amount: u64, now: u64) -> f64 {
+        if let Some((failed_amount, failed_at)) = self.last_failure {
+            if amount >= failed_amount {
+                let elapsed = now.saturating_sub(failed_at) as f64;
+                let penalty = 0.5f64.powf(elapsed / PENALTY_HALF_LIFE as f64);
+                return APRIORI_PROBABILITY * (1.0 - penalty);
+            }
+        }
+        match self.last_success {
+            Some((succeeded_amount, _)) if amount <= succeeded_amount => SUCCESS_PROBABILITY,
+            _ => APRIORI_PROBABILITY,
+        }
+    }
+}
+
 impl ChannelStats {
     fn new() -> Self {
         Self {
@@ -67,6 +138,7 @@ impl Stats {
 
         let inner = StatsInner {
             user_channel_stats: Mutex::new(HashMap::new()),
+            channel_history: Mutex::new(HashMap::new()),
         };
         Self {
             data_sender,
@@ -82,6 +154,29 @@ impl Stats {
         self.inner.payment_pressure(channel).await
     }
 
+    /// Probability of `channel` forwarding `amount`, from its payment history.
+    pub async fn probability(&self, channel: &DirectedChannel, amount: u64) -> f64 {
+        match self.inner.channel_history.lock().await.get(channel) {
+            Some(history) => history.probability(amount, now_secs()),
+            None => APRIORI_PROBABILITY,
+        }
+    }
+
+    pub async fn report(&self, outcome: PaymentOutcome) {
+        let now = now_secs();
+        let mut channel_history = self.inner.channel_history.lock().await;
+        for (index, hop) in outcome.route.windows(2).enumerate() {
+            let history = channel_history.entry((hop[0], hop[1])).or_default();
+            match outcome.failed_hop {
+                Some(failed_hop) if failed_hop == index => {
+                    history.record_failure(outcome.amount, now);
+                    break;
+                }
+                _ => history.record_success(outcome.amount, now),
+            }
+        }
+    }
+
     pub fn start(&mut self) -> Result<()> {
         let data_receiver = self.data_receiver.take().expect("already taken");
         let control_receiver = self.control_receiver.take().expect("already taken");
@@ -167,3 +262,61 @@ impl StatsInner {
         }
     }
 }
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_history() {
+        let mut history = ChannelHistory::default();
+        assert_eq!(APRIORI_PROBABILITY, history.probability(100, 0));
+
+        history.record_success(100, 0);
+        assert_eq!(SUCCESS_PROBABILITY, history.probability(100, 0));
+        assert_eq!(APRIORI_PROBABILITY, history.probability(101, 0));
+
+        history.record_failure(50, 10);
+        assert_eq!(0.0, history.probability(50, 10));
+        assert_eq!(APRIORI_PROBABILITY, history.probability(49, 10));
+        let half = history.probability(50, 10 + PENALTY_HALF_LIFE);
+        assert!((half - APRIORI_PROBABILITY / 2.0).abs() < 1e-9);
+        assert!(history.probability(50, 10 + 20 * PENALTY_HALF_LIFE) > half);
+
+        history.record_success(60, 20);
+        assert_eq!(SUCCESS_PROBABILITY, history.probability(50, 20));
+    }
+
+    #[test]
+    fn test_report_outcome() {
+        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let stats = Stats::new(rt.handle().clone());
+        let route = vec![
+            AccountAddress::random(),
+            AccountAddress::random(),
+            AccountAddress::random(),
+            AccountAddress::random(),
+        ];
+        rt.block_on(async {
+            stats
+                .report(PaymentOutcome::new(route.clone(), 100, Some(1)))
+                .await;
+            assert_eq!(
+                SUCCESS_PROBABILITY,
+                stats.probability(&(route[0], route[1]), 100).await
+            );
+            assert!(stats.probability(&(route[1], route[2]), 100).await < 0.01);
+            assert_eq!(
+                APRIORI_PROBABILITY,
+                stats.probability(&(route[2], route[3]), 100).await
+            );
+        });
+    }
+}
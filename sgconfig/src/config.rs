@@ -19,6 +19,14 @@ pub struct WalletConfig {
     pub chain_address: String,
     pub chain_port: u16,
     pub store_dir: String,
+    /// path of the encrypted static channel backup, kept out of `store_dir`.
+    pub channel_backup: Option<String>,
+    /// number of channel txns between two checkpoints, 0 to disable checkpoints,
+    /// wallet default is used if it's not set.
+    pub checkpoint_interval: Option<u64>,
+    /// unix socket of a signer process holding the account key,
+    /// the key is loaded by the node itself if it's not set.
+    pub remote_signer: Option<String>,
 }
 
 impl Default for WalletConfig {
@@ -27,6 +35,9 @@ impl Default for WalletConfig {
             chain_address: "localhost".to_string(),
             chain_port: 8000,
             store_dir: "sgstore".to_string(),
+            channel_backup: None,
+            checkpoint_interval: None,
+            remote_signer: None,
         }
     }
 }
@@ -34,6 +45,8 @@ impl Default for WalletConfig {
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct NetworkConfig {
     pub listen: String,
+    /// `{address}/p2p/{peer}`, peer is the hex address derived from the network key of the seed,
+    /// which the node logs at startup, not its account.
     pub seeds: Vec<String>,
 }
 
@@ -45,6 +58,38 @@ pub struct RpcConfig {
     pub auto_approve: bool,
     pub router_type: String,
     pub path: Option<String>,
+    /// rules deciding proposals which need approval, `auto_approve` is used if it's not set.
+    pub approval_policy: Option<ApprovalPolicyConfig>,
+}
+
+/// Rules are checked in order, the first matched one decides the proposal.
+/// Decisions are one of "approve", "reject" and "ask".
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApprovalPolicyConfig {
+    /// decision when no rule matches.
+    pub default_decision: String,
+    #[serde(default)]
+    pub rules: Vec<ApprovalRuleConfig>,
+}
+
+/// Unset conditions match any proposal.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApprovalRuleConfig {
+    pub decision: String,
+    /// package name, or module name of an action.
+    pub package: Option<String>,
+    /// script name, or function name of an action.
+    pub script: Option<String>,
+    /// address of the proposer, in hex with 0x prefix.
+    pub peer: Option<String>,
+    /// package name of the asset whose amount is bounded, the default asset if unset.
+    /// Approving rules don't match proposals taking other assets.
+    pub asset: Option<String>,
+    /// bounds of the amount the proposal takes from our channel balance.
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    /// max total amount approved by the rule in a day.
+    pub daily_volume: Option<u64>,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -83,6 +128,7 @@ pub fn get_test_config(
         auto_approve,
         router_type,
         path,
+        approval_policy: None,
     };
     let rest = RestConfig {
         address: addr.clone(),
@@ -9,6 +9,8 @@ mod tests;
 pub use helper::{
     convert_account_address_to_peer_id, convert_peer_id_to_account_address, get_unix_ts,
 };
-pub use message::{Message, NetworkMessage};
-pub use net::{build_network_service, NetworkComponent, NetworkService};
+pub use message::{Message, NetworkMessage, PeerEvent};
+pub use net::{
+    build_network_service, build_network_service_with_identity, NetworkComponent, NetworkService,
+};
 pub use network_libp2p::PeerId;
@@ -24,10 +24,19 @@ impl CryptoHash for InnerMessage {
     }
 }
 
+/// Connection change of a peer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PeerEvent {
+    Open(AccountAddress),
+    Close(AccountAddress),
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
 pub enum Message {
     ACK(u128),
     Payload(PayloadMsg),
+    /// `SignedNetworkIdentity` of the sender, sent first on a new connection.
+    Identity(Vec<u8>),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
@@ -10,7 +10,8 @@ use libra_crypto::{
     test_utils::KeyPair,
 };
 
-use crate::message::{Message, NetworkMessage};
+use crate::message::{Message, NetworkMessage, PeerEvent};
+use anyhow::{ensure, format_err, Result};
 use futures::{
     future,
     stream::{self, Stream},
@@ -23,11 +24,12 @@ use futures::{
 use libra_logger::prelude::*;
 use libra_types::account_address::AccountAddress;
 use network_libp2p::{
-    identity, start_service, NetworkConfiguration, NodeKeyConfig, Secret, Service as Libp2pService,
-    ServiceEvent,
+    identity, start_service, NetworkConfiguration, NodeKeyConfig, PeerId, Secret,
+    Service as Libp2pService, ServiceEvent,
 };
 use parking_lot::Mutex;
 use sg_config::config::NetworkConfig;
+use sgtypes::network_identity::{NetworkIdentity, SignedNetworkIdentity};
 use std::{collections::HashMap, io, sync::Arc, thread};
 use tokio::prelude::task::AtomicTask;
 
@@ -35,8 +37,70 @@ use tokio::prelude::task::AtomicTask;
 pub struct NetworkService {
     pub libp2p_service: Arc<Mutex<Libp2pService>>,
     acks: Arc<Mutex<HashMap<u128, Sender<()>>>>,
+    peer_event_senders: PeerEventSenders,
+    peers: SharedPeers,
+    account: AccountAddress,
 }
 
+/// Accounts of connected peers, learned from the network identities they sent.
+#[derive(Default)]
+struct Peers {
+    accounts: HashMap<PeerId, AccountAddress>,
+    peer_ids: HashMap<AccountAddress, PeerId>,
+}
+
+type SharedPeers = Arc<Mutex<Peers>>;
+
+impl Peers {
+    fn insert(&mut self, peer_id: PeerId, account: AccountAddress) {
+        if let Some(old) = self.peer_ids.insert(account, peer_id.clone()) {
+            self.accounts.remove(&old);
+        }
+        self.accounts.insert(peer_id, account);
+    }
+
+    fn remove(&mut self, peer_id: &PeerId) -> Option<AccountAddress> {
+        let account = self.accounts.remove(peer_id)?;
+        if self.peer_ids.get(&account) == Some(peer_id) {
+            self.peer_ids.remove(&account);
+        }
+        Some(account)
+    }
+
+    fn account(&self, peer_id: &PeerId) -> Option<AccountAddress> {
+        self.accounts.get(peer_id).cloned()
+    }
+
+    fn peer_id(&self, account: &AccountAddress) -> Option<PeerId> {
+        self.peer_ids.get(account).cloned()
+    }
+}
+
+/// Account of `peer_id` proved by the identity it sent.
+fn verify_identity(peer_id: &PeerId, bytes: &[u8]) -> Result<AccountAddress> {
+    let identity = SignedNetworkIdentity::from_bytes(bytes)?;
+    identity.verify()?;
+    let identity_peer_id = convert_account_address_to_peer_id(identity.peer_address())
+        .map_err(|_| format_err!("invalid network key in identity"))?;
+    ensure!(
+        &identity_peer_id == peer_id,
+        "identity of another peer {} is sent by {}",
+        identity_peer_id,
+        peer_id
+    );
+    Ok(identity.account())
+}
+
+type PeerEventSenders = Arc<Mutex<Vec<mpsc::UnboundedSender<PeerEvent>>>>;
+
+/// Tell subscribers about `event`, and drop those who are gone.
+fn notify_peer_event(senders: &PeerEventSenders, event: PeerEvent) {
+    senders
+        .lock()
+        .retain(|sender| sender.unbounded_send(event.clone()).is_ok());
+}
+
+/// Build the network service of the account whose key is `key_pair`.
 pub fn build_network_service(
     cfg: &NetworkConfig,
     key_pair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
@@ -46,6 +110,26 @@ pub fn build_network_service(
     mpsc::UnboundedReceiver<NetworkMessage>,
     oneshot::Sender<()>,
 ) {
+    let identity = NetworkIdentity::new(key_pair.public_key.clone()).sign(&key_pair.private_key);
+    build_network_service_with_identity(cfg, key_pair, identity)
+}
+
+/// Build the network service by its own `key_pair`, which is not the account key.
+/// Peers learn the account of this node from `identity` signed by the account key.
+pub fn build_network_service_with_identity(
+    cfg: &NetworkConfig,
+    key_pair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+    identity: SignedNetworkIdentity,
+) -> (
+    NetworkService,
+    mpsc::UnboundedSender<NetworkMessage>,
+    mpsc::UnboundedReceiver<NetworkMessage>,
+    oneshot::Sender<()>,
+) {
+    assert_eq!(
+        identity.identity.network_public_key, key_pair.public_key,
+        "identity should be of the network key"
+    );
     let config = NetworkConfiguration {
         listen_addresses: vec![cfg.listen.parse().expect("Failed to parse network config")],
         boot_nodes: convert_boot_nodes(cfg.seeds.clone()),
@@ -57,7 +141,7 @@ pub fn build_network_service(
         },
         ..NetworkConfiguration::default()
     };
-    NetworkService::new(config)
+    NetworkService::new(config, identity)
 }
 
 fn build_libp2p_service(cfg: NetworkConfiguration) -> Result<Arc<Mutex<Libp2pService>>, io::Error> {
@@ -71,6 +155,9 @@ fn build_libp2p_service(cfg: NetworkConfiguration) -> Result<Arc<Mutex<Libp2pSer
 fn run_network(
     net_srv: Arc<Mutex<Libp2pService>>,
     acks: Arc<Mutex<HashMap<u128, Sender<()>>>>,
+    peer_event_senders: PeerEventSenders,
+    peers: SharedPeers,
+    identity: Vec<u8>,
 ) -> (
     mpsc::UnboundedSender<NetworkMessage>,
     mpsc::UnboundedReceiver<NetworkMessage>,
@@ -79,12 +166,16 @@ fn run_network(
     let (mut _tx, net_rx) = mpsc::unbounded();
     let (net_tx, mut _rx) = mpsc::unbounded::<NetworkMessage>();
     let net_srv_1 = net_srv.clone();
+    let identity_1 = identity.clone();
     let connected_fut = future::poll_fn(move || {
-        match try_ready!(net_srv_1.lock().poll()) {
+        let event = try_ready!(net_srv_1.lock().poll());
+        match event {
             Some(ServiceEvent::OpenedCustomProtocol { peer_id, .. }) => {
-                debug!(
-                    "Connected peer: {}",
-                    convert_peer_id_to_account_address(&peer_id).unwrap()
+                debug!("Connected peer: {}", peer_id);
+                // the peer is told to other components once its identity arrives.
+                net_srv_1.lock().send_custom_message(
+                    &peer_id,
+                    Message::Identity(identity_1.clone()).into_bytes(),
                 );
             }
             _ => {
@@ -96,6 +187,7 @@ fn run_network(
 
     let net_srv_2 = net_srv.clone();
     let ack_sender = net_srv.clone();
+    let peers_1 = peers.clone();
     let task_notify = Arc::new(AtomicTask::new());
     let notify = task_notify.clone();
     let network_fut = stream::poll_fn(move || {
@@ -111,7 +203,13 @@ fn run_network(
                     Message::Payload(payload) => {
                         //receive message
                         info!("Receive message with peer_id:{:?}", &peer_id);
-                        let address = convert_peer_id_to_account_address(&peer_id).unwrap();
+                        let address = match peers_1.lock().account(&peer_id) {
+                            Some(address) => address,
+                            None => {
+                                warn!("Drop message of peer {} without identity", peer_id);
+                                return Ok(());
+                            }
+                        };
                         let user_msg = NetworkMessage {
                             peer_id: address,
                             data: payload.data,
@@ -135,6 +233,14 @@ fn run_network(
                             );
                         }
                     }
+                    Message::Identity(bytes) => match verify_identity(&peer_id, &bytes) {
+                        Ok(address) => {
+                            info!("Connected peer {:?} of account {:?}", peer_id, address);
+                            peers_1.lock().insert(peer_id, address);
+                            notify_peer_event(&peer_event_senders, PeerEvent::Open(address));
+                        }
+                        Err(e) => warn!("Invalid identity of peer {}, {}", peer_id, e),
+                    },
                 }
             }
             ServiceEvent::OpenedCustomProtocol {
@@ -142,15 +248,22 @@ fn run_network(
                 version: _,
                 debug_info: _,
             } => {
-                info!(
-                    "Connected peer {:?}",
-                    convert_peer_id_to_account_address(&peer_id).unwrap()
+                debug!("Connected peer {:?}, send identity", peer_id);
+                ack_sender.lock().send_custom_message(
+                    &peer_id,
+                    Message::Identity(identity.clone()).into_bytes(),
                 );
             }
             ServiceEvent::ClosedCustomProtocol {
-                peer_id: _,
+                peer_id,
                 debug_info: _,
-            } => debug!("Network close custom protol"),
+            } => {
+                debug!("Network close custom protol");
+                let address = peers_1.lock().remove(&peer_id);
+                if let Some(address) = address {
+                    notify_peer_event(&peer_event_senders, PeerEvent::Close(address));
+                }
+            }
             ServiceEvent::Clogged {
                 peer_id: _,
                 messages: _,
@@ -165,15 +278,24 @@ fn run_network(
 
     let protocol_fut = stream::poll_fn(move || _rx.poll())
         .for_each(move |message| {
-            let peer_id = convert_account_address_to_peer_id(message.peer_id).unwrap();
+            let peer_id = match peers.lock().peer_id(&message.peer_id) {
+                Some(peer_id) => peer_id,
+                None => {
+                    error!(
+                        "Message send to account {} is not connected",
+                        message.peer_id
+                    );
+                    return Ok(());
+                }
+            };
             net_srv
                 .lock()
                 .send_custom_message(&peer_id, Message::new_message(message.data).into_bytes());
             task_notify.notify();
             if net_srv.lock().is_open(&peer_id) == false {
                 error!(
-                    "Message send to peer :{} is not connected",
-                    convert_peer_id_to_account_address(&peer_id).unwrap()
+                    "Message send to peer :{} of account {} is not connected",
+                    peer_id, message.peer_id
                 );
             }
             info!("Already send message {:?}", &peer_id);
@@ -208,12 +330,16 @@ fn run_network(
 fn spawn_network(
     libp2p_service: Arc<Mutex<Libp2pService>>,
     acks: Arc<Mutex<HashMap<u128, Sender<()>>>>,
+    peer_event_senders: PeerEventSenders,
+    peers: SharedPeers,
+    identity: Vec<u8>,
     close_rx: oneshot::Receiver<()>,
 ) -> (
     mpsc::UnboundedSender<NetworkMessage>,
     mpsc::UnboundedReceiver<NetworkMessage>,
 ) {
-    let (network_sender, network_receiver, network_future) = run_network(libp2p_service, acks);
+    let (network_sender, network_receiver, network_future) =
+        run_network(libp2p_service, acks, peer_event_senders, peers, identity);
     let fut = network_future
         .select(close_rx.then(|_| {
             debug!("Shutdown the network");
@@ -236,6 +362,7 @@ fn spawn_network(
 impl NetworkService {
     fn new(
         cfg: NetworkConfiguration,
+        identity: SignedNetworkIdentity,
     ) -> (
         NetworkService,
         mpsc::UnboundedSender<NetworkMessage>,
@@ -245,8 +372,18 @@ impl NetworkService {
         let (close_tx, close_rx) = oneshot::channel::<()>();
         let libp2p_service = build_libp2p_service(cfg).unwrap();
         let acks = Arc::new(Mutex::new(HashMap::new()));
-        let (network_sender, network_receiver) =
-            spawn_network(libp2p_service.clone(), acks.clone(), close_rx);
+        let peer_event_senders = Arc::new(Mutex::new(Vec::new()));
+        let peers = Arc::new(Mutex::new(Peers::default()));
+        let (network_sender, network_receiver) = spawn_network(
+            libp2p_service.clone(),
+            acks.clone(),
+            peer_event_senders.clone(),
+            peers.clone(),
+            identity
+                .to_bytes()
+                .expect("Failed to encode network identity"),
+            close_rx,
+        );
         info!("Network started, connected peers:");
         for p in libp2p_service.lock().connected_peers() {
             info!("peer_id:{}", p);
@@ -256,6 +393,9 @@ impl NetworkService {
             Self {
                 libp2p_service,
                 acks,
+                peer_event_senders,
+                peers,
+                account: identity.account(),
             },
             network_sender,
             network_receiver,
@@ -263,25 +403,47 @@ impl NetworkService {
         )
     }
 
+    /// Whether the node of account `address` is connected and has told us its identity.
     pub fn is_connected(&self, address: AccountAddress) -> bool {
-        self.libp2p_service
-            .lock()
-            .is_open(&convert_account_address_to_peer_id(address).unwrap())
+        let peer_id = self.peers.lock().peer_id(&address);
+        match peer_id {
+            Some(peer_id) => self.libp2p_service.lock().is_open(&peer_id),
+            None => false,
+        }
     }
 
+    /// Stream peers connected or disconnected from now on.
+    pub fn subscribe_peer_events(&self) -> mpsc::UnboundedReceiver<PeerEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.peer_event_senders.lock().push(sender);
+        receiver
+    }
+
+    /// Address of the network peer derived from the network key, seeds refer to it.
     pub fn identify(&self) -> AccountAddress {
         convert_peer_id_to_account_address(self.libp2p_service.lock().peer_id()).unwrap()
     }
 
+    /// Account of the node, which may differ from the network peer address.
+    pub fn account(&self) -> AccountAddress {
+        self.account
+    }
+
     pub fn send_message(
         &mut self,
         account_address: AccountAddress,
         message: Vec<u8>,
     ) -> impl Future<Item = (), Error = Canceled> {
         let (tx, rx) = oneshot::channel::<()>();
+        let peer_id = match self.peers.lock().peer_id(&account_address) {
+            Some(peer_id) => peer_id,
+            None => {
+                warn!("Account {} is not connected", account_address);
+                // the receiver is canceled by dropping the sender.
+                return rx;
+            }
+        };
         let (protocol_msg, message_id) = Message::new_payload(message);
-        let peer_id =
-            convert_account_address_to_peer_id(account_address).expect("Invalid account address");
 
         self.libp2p_service
             .lock()
@@ -31,9 +31,11 @@ mod tests {
     use network_libp2p::{identity, NodeKeyConfig, PeerId, PublicKey, Secret};
 
     use crate::{
-        build_network_service, convert_account_address_to_peer_id, helper::convert_boot_nodes,
-        NetworkComponent, NetworkService,
+        build_network_service, build_network_service_with_identity,
+        convert_account_address_to_peer_id, helper::convert_boot_nodes, NetworkComponent,
+        NetworkService,
     };
+    use sgtypes::network_identity::NetworkIdentity;
 
     use crate::message::NetworkMessage;
     use futures::sync::oneshot;
@@ -227,6 +229,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_network_identity() {
+        let _rt = Runtime::new().unwrap();
+        let mut rng: StdRng = SeedableRng::seed_from_u64(100);
+        let account_key_pair =
+            KeyPair::<Ed25519PrivateKey, Ed25519PublicKey>::generate_for_testing(&mut rng);
+        let network_key_pair = Arc::new(
+            KeyPair::<Ed25519PrivateKey, Ed25519PublicKey>::generate_for_testing(&mut rng),
+        );
+        let identity = NetworkIdentity::new(network_key_pair.public_key.clone())
+            .sign(&account_key_pair.private_key);
+        let account = AccountAddress::from_public_key(&account_key_pair.public_key);
+
+        let config1 = sg_config::config::NetworkConfig {
+            listen: "/ip4/127.0.0.1/tcp/50500".to_string(),
+            seeds: vec![],
+        };
+        let (service1, _tx1, _rx1, _close_tx1) =
+            build_network_service_with_identity(&config1, network_key_pair, identity);
+        assert_eq!(account, service1.account());
+        assert_ne!(account, service1.identify());
+
+        let key_pair = {
+            let mut rng: StdRng = SeedableRng::seed_from_u64(101);
+            Arc::new(KeyPair::<Ed25519PrivateKey, Ed25519PublicKey>::generate_for_testing(&mut rng))
+        };
+        let config2 = sg_config::config::NetworkConfig {
+            listen: "/ip4/127.0.0.1/tcp/50501".to_string(),
+            seeds: vec![format!(
+                "{}/p2p/{}",
+                config1.listen,
+                hex::encode(service1.identify())
+            )],
+        };
+        let (service2, _tx2, _rx2, _close_tx2) = build_network_service(&config2, key_pair);
+        thread::sleep(Duration::from_secs(2));
+
+        // peers know each other by accounts, not by network keys.
+        assert!(service2.is_connected(account));
+        assert!(!service2.is_connected(service1.identify()));
+        assert!(service1.is_connected(service2.account()));
+    }
+
     #[test]
     fn test_convert_address_peer_id() {
         let (private_key, public_key) = compat::generate_keypair(Option::None);
@@ -0,0 +1,119 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines approval store APIs that are used by node to keep an audit log of
+//! decisions made on incoming proposals, and amounts approved by every rule in a day.
+
+use crate::schema::{
+    approval_audit_schema::ApprovalAuditSchema, approval_volume_schema::ApprovalVolumeSchema,
+};
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use libra_crypto::HashValue;
+use schemadb::ReadOptions;
+use sgtypes::approval::ApprovalAuditRecord;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct ApprovalStore<S> {
+    db: S,
+}
+
+impl<S> ApprovalStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> ApprovalStore<S>
+where
+    S: SchemaDB,
+{
+    /// Append `record` to the audit log, return its index.
+    /// Caller should serialize appends, as the index is derived from the last record.
+    pub fn append_audit(&self, record: &ApprovalAuditRecord) -> Result<u64> {
+        let index = {
+            let mut iter = self
+                .db
+                .iter::<ApprovalAuditSchema>(ReadOptions::default())?;
+            iter.seek_to_last();
+            iter.next()
+                .transpose()?
+                .map(|(index, _)| index + 1)
+                .unwrap_or(0)
+        };
+        self.db.put::<ApprovalAuditSchema>(&index, record)?;
+        Ok(index)
+    }
+
+    /// Return audit records starting from `start_index`, in the order they are appended.
+    pub fn list_audits(
+        &self,
+        start_index: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, ApprovalAuditRecord)>> {
+        let mut iter = self
+            .db
+            .iter::<ApprovalAuditSchema>(ReadOptions::default())?;
+        iter.seek(&start_index)?;
+        iter.take(limit).collect::<Result<Vec<_>>>()
+    }
+
+    /// Amounts approved by every rule in `day` by rule id, empty if nothing is approved yet.
+    pub fn get_volumes(&self, day: u64) -> Result<BTreeMap<HashValue, u64>> {
+        Ok(self
+            .db
+            .get::<ApprovalVolumeSchema>(&day)?
+            .unwrap_or_default())
+    }
+
+    pub fn put_volumes(&self, day: u64, volumes: &BTreeMap<HashValue, u64>) -> Result<()> {
+        self.db.put::<ApprovalVolumeSchema>(&day, volumes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel_db::ChannelDB, storage::SgStorage};
+    use libra_types::account_address::AccountAddress;
+    use sgtypes::approval::ApprovalDecision;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_approval_store() -> Result<()> {
+        let owner = AccountAddress::random();
+        let storage = Arc::new(SgStorage::new(owner, libra_tools::tempdir::TempPath::new()));
+        let store = ApprovalStore::new(ChannelDB::new(owner, storage));
+
+        for decision in &[ApprovalDecision::Approve, ApprovalDecision::Reject] {
+            let audit = ApprovalAuditRecord {
+                request_id: HashValue::random(),
+                peer: AccountAddress::random(),
+                channel_address: AccountAddress::random(),
+                operator: "transfer".to_string(),
+                amount: 100,
+                decision: *decision,
+                timestamp: 1,
+                reason: String::new(),
+            };
+            store.append_audit(&audit)?;
+        }
+        let audits = store.list_audits(0, 10)?;
+        assert_eq!(
+            vec![0, 1],
+            audits.iter().map(|(i, _)| *i).collect::<Vec<_>>()
+        );
+        assert_eq!(ApprovalDecision::Reject, audits[1].1.decision);
+        assert_eq!(1, store.list_audits(1, 10)?.len());
+
+        assert!(store.get_volumes(1)?.is_empty());
+        let mut volumes = BTreeMap::new();
+        volumes.insert(HashValue::random(), 100);
+        volumes.insert(HashValue::random(), 0);
+        store.put_volumes(1, &volumes)?;
+        assert_eq!(volumes, store.get_volumes(1)?);
+        assert!(store.get_volumes(2)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines checkpoint store APIs that are used by channel to keep
+//! the checkpoints signed by all participants, indexed by version.
+
+use crate::schema::channel_checkpoint_schema::ChannelCheckpointSchema;
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use schemadb::{ReadOptions, SchemaBatch};
+use sgtypes::channel_checkpoint::SignedChannelCheckpoint;
+
+#[derive(Debug, Clone)]
+pub struct ChannelCheckpointStore<S> {
+    db: S,
+}
+
+impl<S> ChannelCheckpointStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> ChannelCheckpointStore<S>
+where
+    S: SchemaDB,
+{
+    pub fn get_latest_checkpoint(&self) -> Result<Option<SignedChannelCheckpoint>> {
+        let mut iter = self
+            .db
+            .iter::<ChannelCheckpointSchema>(ReadOptions::default())?;
+        iter.seek_to_last();
+        Ok(iter.next().transpose()?.map(|(_, checkpoint)| checkpoint))
+    }
+
+    pub fn put_checkpoint(
+        &self,
+        checkpoint: &SignedChannelCheckpoint,
+        batch: &mut SchemaBatch,
+    ) -> Result<()> {
+        batch.put::<ChannelCheckpointSchema>(&checkpoint.version(), checkpoint)
+    }
+}
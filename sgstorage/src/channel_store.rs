@@ -1,27 +1,35 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::channel_checkpoint_store::ChannelCheckpointStore;
 use crate::channel_transaction_store::ChannelTransactionStore;
 use crate::channel_write_set_store::ChannelWriteSetStore;
 use crate::ledger_info_store::LedgerStore;
 use crate::pending_txn_store::PendingTxnStore;
+use crate::schema::channel_transaction_schema::AppliedChannelTransactionSchema;
+use crate::schema::channel_txn_meta_schema::ChannelTxnMetaSchema;
+use crate::schema::channel_write_set_accumulator_schema::ChannelWriteSetAccumulatorSchema;
+use crate::schema::channel_write_set_schema::ChannelWriteSetSchema;
 use crate::schema::participant_public_key_schema::ParticipantPublicKeySchema;
 use crate::schema_db::SchemaDB;
 
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 use itertools::Itertools;
 use libra_crypto::ed25519::Ed25519PublicKey;
 use libra_crypto::hash::CryptoHash;
 use libra_crypto::HashValue;
 use libra_types::account_address::AccountAddress;
 use libra_types::channel::{Witness, WitnessData};
+use libra_types::proof::position::Position;
 use libra_types::transaction::Version;
 use libra_types::write_set::WriteSet;
 use rocksdb::ReadOptions;
 use schemadb::SchemaBatch;
 use sgtypes::applied_channel_txn::AppliedChannelTxn;
+use sgtypes::channel_checkpoint::{ChannelCheckpoint, SignedChannelCheckpoint};
 use sgtypes::channel_transaction_info::ChannelTransactionInfo;
 use sgtypes::channel_transaction_to_commit::*;
+use sgtypes::channel_txn_history::ChannelTxnMeta;
 use sgtypes::ledger_info::LedgerInfo;
 use sgtypes::pending_txn::PendingTransaction;
 use sgtypes::proof::signed_channel_transaction_proof::SignedChannelTransactionProof;
@@ -39,8 +47,11 @@ pub struct ChannelStore<S> {
     transaction_store: ChannelTransactionStore<S>,
     write_set_store: ChannelWriteSetStore<S>,
     pending_txn_store: PendingTxnStore<S>,
+    checkpoint_store: ChannelCheckpointStore<S>,
     latest_witness: Arc<RwLock<Option<Witness>>>,
     pending_txn: Arc<RwLock<Option<PendingTransaction>>>,
+    travelling_txn: Arc<RwLock<Option<PendingTransaction>>>,
+    speculative_txns: Arc<RwLock<Vec<PendingTransaction>>>,
     participant_keys: Arc<RwLock<BTreeMap<AccountAddress, Option<Ed25519PublicKey>>>>,
 }
 
@@ -63,6 +74,19 @@ impl<S> ChannelStore<S> {
         let mut write_guard = self.pending_txn.write().expect("should get write lock");
         *write_guard = pending_txn;
     }
+
+    fn set_travelling_txn(&self, travelling_txn: Option<PendingTransaction>) {
+        let mut write_guard = self.travelling_txn.write().expect("should get write lock");
+        *write_guard = travelling_txn;
+    }
+
+    fn set_speculative_txns(&self, speculative_txns: Vec<PendingTransaction>) {
+        let mut write_guard = self
+            .speculative_txns
+            .write()
+            .expect("should get write lock");
+        *write_guard = speculative_txns;
+    }
 }
 
 impl<S> ChannelStore<S>
@@ -76,8 +100,11 @@ where
             transaction_store: ChannelTransactionStore::new(db.clone()),
             write_set_store: ChannelWriteSetStore::new(db.clone()),
             pending_txn_store: PendingTxnStore::new(db.clone()),
+            checkpoint_store: ChannelCheckpointStore::new(db.clone()),
             latest_witness: Arc::new(RwLock::new(None)),
             pending_txn: Arc::new(RwLock::new(None)),
+            travelling_txn: Arc::new(RwLock::new(None)),
+            speculative_txns: Arc::new(RwLock::new(vec![])),
             participant_keys: Arc::new(RwLock::new(BTreeMap::new())),
         };
         store.bootstrap(participants)?;
@@ -144,6 +171,8 @@ where
     fn load_pending_txn(&self) -> Result<()> {
         let pending_txn_opt = self.pending_txn_store.get_pending_txn()?;
         self.set_pending_txn(pending_txn_opt);
+        self.set_travelling_txn(self.pending_txn_store.get_travelling_txn()?);
+        self.set_speculative_txns(self.pending_txn_store.get_speculative_txns()?);
         Ok(())
     }
 
@@ -227,6 +256,7 @@ where
             }
         }
 
+        let travel_op = txn_to_commit.travel_op.clone();
         let new_ledger_hash = self.save_tx_impl(txn_to_commit, version, &mut schema_batch)?;
 
         if let Some(x) = ledger_info {
@@ -238,6 +268,10 @@ where
                 expected_root_hash,
             );
             self.ledger_store.put_ledger_info(x, &mut schema_batch)?;
+            // the txn is applied at the time of the ledger info it produces.
+            let meta = ChannelTxnMeta::new(x.timestamp_usecs() / 1_000_000, travel_op);
+            self.transaction_store
+                .put_txn_meta(version, &meta, &mut schema_batch)?;
         }
 
         if clear_pending_txn {
@@ -289,6 +323,97 @@ where
         Ok(())
     }
 
+    /// Move the agreed pending txn `travelling_txn` out of pending while it's travelling,
+    /// so that offchain txns can be agreed on top of it.
+    pub fn start_travelling(&self, travelling_txn: PendingTransaction) -> Result<()> {
+        let mut sb = SchemaBatch::new();
+        self.pending_txn_store
+            .save_travelling_txn(&travelling_txn, &mut sb)?;
+        self.pending_txn_store.clear(&mut sb)?;
+        self.commit(sb)?;
+        self.set_travelling_txn(Some(travelling_txn));
+        self.set_pending_txn(None);
+        Ok(())
+    }
+
+    /// Queue the agreed offchain txn `speculative_txn` after the travelling txn,
+    /// it's applied once the travelling txn is committed onchain.
+    pub fn save_speculative_txn(&self, speculative_txn: PendingTransaction) -> Result<()> {
+        let mut speculative_txns = self.get_speculative_txns();
+        let mut sb = SchemaBatch::new();
+        self.pending_txn_store.save_speculative_txn(
+            speculative_txns.len(),
+            &speculative_txn,
+            &mut sb,
+        )?;
+        self.pending_txn_store.clear(&mut sb)?;
+        self.commit(sb)?;
+        speculative_txns.push(speculative_txn);
+        self.set_speculative_txns(speculative_txns);
+        self.set_pending_txn(None);
+        Ok(())
+    }
+
+    /// Clear the travelling txn and offchain txns queued after it,
+    /// once they are applied, or dropped as the travelling txn fails.
+    pub fn finish_travelling(&self) -> Result<()> {
+        let mut sb = SchemaBatch::new();
+        self.pending_txn_store.clear_travelling_txn(&mut sb)?;
+        self.pending_txn_store
+            .clear_speculative_txns(self.get_speculative_txns().len(), &mut sb)?;
+        self.commit(sb)?;
+        self.set_travelling_txn(None);
+        self.set_speculative_txns(vec![]);
+        Ok(())
+    }
+
+    /// Save `checkpoint` which is already signed by all participants,
+    /// and prune txns and write sets before it.
+    /// Checkpoint not newer than the latest one is ignored.
+    pub fn save_checkpoint(&self, checkpoint: SignedChannelCheckpoint) -> Result<()> {
+        if let Some(latest) = self.checkpoint_store.get_latest_checkpoint()? {
+            if checkpoint.version() <= latest.version() {
+                return Ok(());
+            }
+        }
+        let expected =
+            self.build_checkpoint(checkpoint.checkpoint.channel_address, checkpoint.version())?;
+        ensure!(
+            checkpoint.checkpoint == expected,
+            "checkpoint at {} mismatch local state",
+            checkpoint.version()
+        );
+        let mut sb = SchemaBatch::new();
+        self.checkpoint_store.put_checkpoint(&checkpoint, &mut sb)?;
+        self.commit(sb)?;
+        self.prune(checkpoint.version())
+    }
+
+    /// Delete txns and write sets before `version`, except the last travel txn before it,
+    /// which is the base state of the checkpoint.
+    /// Txn infos and the accumulator are kept, so proofs are still available.
+    fn prune(&self, version: Version) -> Result<()> {
+        let travel_version = self.last_travel_version(version)?;
+        let ranges = vec![(0, travel_version), (travel_version + 1, version)];
+        for (begin, end) in ranges {
+            if begin >= end {
+                continue;
+            }
+            self.db
+                .range_delete::<AppliedChannelTransactionSchema, Version>(&begin, &end)?;
+            self.db
+                .range_delete::<ChannelWriteSetSchema, (Version, u64)>(&(begin, 0), &(end, 0))?;
+            self.db
+                .range_delete::<ChannelTxnMetaSchema, Version>(&begin, &end)?;
+            self.db
+                .range_delete::<ChannelWriteSetAccumulatorSchema, (Version, Position)>(
+                    &(begin, Position::from_inorder_index(0)),
+                    &(end, Position::from_inorder_index(0)),
+                )?;
+        }
+        Ok(())
+    }
+
     fn save_tx_impl(
         &self,
         tx: ChannelTransactionToCommit,
@@ -366,9 +491,78 @@ where
     }
 
     pub fn get_write_set_by_version(&self, version: u64) -> Result<WriteSet> {
+        self.ensure_not_pruned(version)?;
         self.write_set_store.get_write_set_by_version(version)
     }
 
+    /// Build checkpoint of the channel state at `version`, to be signed by participants.
+    pub fn build_checkpoint(
+        &self,
+        channel_address: AccountAddress,
+        version: Version,
+    ) -> Result<ChannelCheckpoint> {
+        let (latest_version, _) = self.ledger_store.get_latest_transaction_info()?;
+        ensure!(
+            version <= latest_version,
+            "txn {} is not applied yet, latest is {}",
+            version,
+            latest_version
+        );
+        let state_root = self
+            .ledger_store
+            .get_transaction_info(version)?
+            .write_set_hash();
+        let accumulator_root = self.ledger_store.get_root_hash(version)?;
+        Ok(ChannelCheckpoint::new(
+            channel_address,
+            version,
+            state_root,
+            accumulator_root,
+        ))
+    }
+
+    pub fn get_latest_checkpoint(&self) -> Result<Option<SignedChannelCheckpoint>> {
+        self.checkpoint_store.get_latest_checkpoint()
+    }
+
+    /// The first txn version not pruned by checkpoints,
+    /// the last travel txn before it is kept as well.
+    pub fn first_unpruned_version(&self) -> Result<Version> {
+        Ok(self
+            .get_latest_checkpoint()?
+            .map(|c| c.version())
+            .unwrap_or(0))
+    }
+
+    /// Version of the last travel txn at or before `version`, it's found by txn infos,
+    /// so it works even if the txns are pruned.
+    pub fn last_travel_version(&self, version: Version) -> Result<Version> {
+        let mut travel_version = version;
+        loop {
+            if self
+                .ledger_store
+                .get_transaction_info(travel_version)?
+                .travel()
+            {
+                return Ok(travel_version);
+            }
+            ensure!(travel_version > 0, "channel should be opened by travel txn");
+            travel_version -= 1;
+        }
+    }
+
+    fn ensure_not_pruned(&self, version: Version) -> Result<()> {
+        let first_unpruned = self.first_unpruned_version()?;
+        if version < first_unpruned && version != self.last_travel_version(first_unpruned)? {
+            bail!(
+                "txn {} is pruned by checkpoint at {}",
+                version,
+                first_unpruned
+            );
+        }
+        Ok(())
+    }
+
     pub fn get_pending_txn(&self) -> Option<PendingTransaction> {
         self.pending_txn
             .read()
@@ -377,6 +571,22 @@ where
             .clone()
     }
 
+    pub fn get_travelling_txn(&self) -> Option<PendingTransaction> {
+        self.travelling_txn
+            .read()
+            .expect("should get read lock")
+            .deref()
+            .clone()
+    }
+
+    pub fn get_speculative_txns(&self) -> Vec<PendingTransaction> {
+        self.speculative_txns
+            .read()
+            .expect("should get read lock")
+            .deref()
+            .clone()
+    }
+
     pub fn get_latest_witness(&self) -> Option<Witness> {
         self.latest_witness
             .read()
@@ -406,6 +616,11 @@ where
             .collect()
     }
 
+    /// Meta of the txn at `version`, None if it's applied before metas are kept.
+    pub fn get_txn_meta(&self, version: Version) -> Result<Option<ChannelTxnMeta>> {
+        self.transaction_store.get_txn_meta(version)
+    }
+
     pub fn get_transaction_by_channel_seq_number(
         &self,
         channel_sequence_number: u64,
@@ -417,7 +632,9 @@ where
         self.get_txn_with_proof(channel_sequence_number, ledger_version, fetch_events)
     }
 
-    fn get_txn_with_proof(
+    /// Get txn at `version` with proof towards root of ledger at `ledger_version`,
+    /// use version of a checkpoint as `ledger_version` to verify it against the checkpoint.
+    pub fn get_txn_with_proof(
         &self,
         version: u64,
         ledger_version: u64,
@@ -427,6 +644,7 @@ where
             .ledger_store
             .get_transaction_info_with_proof(version, ledger_version)?;
         let proof = SignedChannelTransactionProof::new(txn_info_accumulator_proof, txn_info);
+        self.ensure_not_pruned(version)?;
         let signed_transaction = self.transaction_store.get_transaction(version)?;
         // TODO(caojiafeng): impl me
         let events = if fetch_events { None } else { None };
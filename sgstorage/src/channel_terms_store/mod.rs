@@ -0,0 +1,57 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines channel terms store APIs that are used by channels to keep
+//! the reserve and packages agreed when they are opened.
+
+use crate::schema::channel_terms_schema::ChannelTermsSchema;
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use libra_types::account_address::AccountAddress;
+use sgtypes::open_channel_policy::ChannelTerms;
+
+#[derive(Debug, Clone)]
+pub struct ChannelTermsStore<S> {
+    db: S,
+}
+
+impl<S> ChannelTermsStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> ChannelTermsStore<S>
+where
+    S: SchemaDB,
+{
+    /// Terms of the channel, None if it's opened without negotiation.
+    pub fn get_terms(&self, channel_address: AccountAddress) -> Result<Option<ChannelTerms>> {
+        self.db.get::<ChannelTermsSchema>(&channel_address)
+    }
+
+    pub fn save_terms(&self, channel_address: AccountAddress, terms: &ChannelTerms) -> Result<()> {
+        self.db.put::<ChannelTermsSchema>(&channel_address, terms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel_db::ChannelDB, storage::SgStorage};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_channel_terms_store() -> Result<()> {
+        let owner = AccountAddress::random();
+        let channel_address = AccountAddress::random();
+        let storage = Arc::new(SgStorage::new(owner, libra_tools::tempdir::TempPath::new()));
+        let store = ChannelTermsStore::new(ChannelDB::new(channel_address, storage));
+        assert_eq!(None, store.get_terms(channel_address)?);
+
+        let terms = ChannelTerms::new(100, vec!["scripts".to_string()]);
+        store.save_terms(channel_address, &terms)?;
+        assert_eq!(Some(terms), store.get_terms(channel_address)?);
+        Ok(())
+    }
+}
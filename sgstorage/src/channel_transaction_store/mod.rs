@@ -8,12 +8,14 @@
 
 use crate::error::SgStorageError;
 use crate::schema::channel_transaction_schema::*;
+use crate::schema::channel_txn_meta_schema::ChannelTxnMetaSchema;
 use crate::schema_db::SchemaDB;
 use anyhow::Result;
 use libra_types::{account_address::AccountAddress, transaction::Version};
 use libradb::schema::transaction_by_account::*;
 use schemadb::SchemaBatch;
 use sgtypes::applied_channel_txn::AppliedChannelTxn;
+use sgtypes::channel_txn_history::ChannelTxnMeta;
 
 #[derive(Clone)]
 pub struct ChannelTransactionStore<S> {
@@ -72,4 +74,19 @@ where
 
         Ok(())
     }
+
+    /// Get meta of the transaction at `version`,
+    /// None if it's applied before metas are kept.
+    pub fn get_txn_meta(&self, version: Version) -> Result<Option<ChannelTxnMeta>> {
+        self.db.get::<ChannelTxnMetaSchema>(&version)
+    }
+
+    pub fn put_txn_meta(
+        &self,
+        version: Version,
+        meta: &ChannelTxnMeta,
+        cs: &mut SchemaBatch,
+    ) -> Result<()> {
+        cs.put::<ChannelTxnMetaSchema>(&version, meta)
+    }
 }
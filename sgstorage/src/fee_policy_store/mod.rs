@@ -0,0 +1,69 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines fee policy store APIs that are used by wallet to keep
+//! what it charges to forward htlc over its channels.
+
+use crate::schema::fee_policy_schema::FeePolicySchema;
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use libra_types::account_address::AccountAddress;
+use sgtypes::fee_policy::FeePolicy;
+
+#[derive(Debug, Clone)]
+pub struct FeePolicyStore<S> {
+    db: S,
+}
+
+impl<S> FeePolicyStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> FeePolicyStore<S>
+where
+    S: SchemaDB,
+{
+    /// Policy of channel with `participant`, falls back to the default policy.
+    pub fn get_fee_policy(&self, participant: AccountAddress) -> Result<FeePolicy> {
+        if let Some(policy) = self.db.get::<FeePolicySchema>(&Some(participant))? {
+            return Ok(policy);
+        }
+        Ok(self.db.get::<FeePolicySchema>(&None)?.unwrap_or_default())
+    }
+
+    /// Set policy of channel with `participant`, or the default policy if it's None.
+    pub fn save_fee_policy(
+        &self,
+        participant: Option<AccountAddress>,
+        policy: &FeePolicy,
+    ) -> Result<()> {
+        self.db.put::<FeePolicySchema>(&participant, policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel_db::ChannelDB, storage::SgStorage};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_fee_policy_store() -> Result<()> {
+        let owner = AccountAddress::random();
+        let storage = Arc::new(SgStorage::new(owner, libra_tools::tempdir::TempPath::new()));
+        let store = FeePolicyStore::new(ChannelDB::new(owner, storage));
+
+        let (participant1, participant2) = (AccountAddress::random(), AccountAddress::random());
+        assert_eq!(FeePolicy::default(), store.get_fee_policy(participant1)?);
+
+        let default_policy = FeePolicy::new(1, 10, 30);
+        store.save_fee_policy(None, &default_policy)?;
+        let policy = FeePolicy::new(5, 100, 40);
+        store.save_fee_policy(Some(participant1), &policy)?;
+        assert_eq!(policy, store.get_fee_policy(participant1)?);
+        assert_eq!(default_policy, store.get_fee_policy(participant2)?);
+        Ok(())
+    }
+}
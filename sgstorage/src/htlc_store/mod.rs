@@ -0,0 +1,105 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines htlc store APIs that are used by wallet to track outgoing htlc payments,
+//! and to keep an audit log of what is done on them.
+
+use crate::schema::{htlc_audit_schema::HtlcAuditSchema, htlc_schema::HtlcSchema};
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use libra_crypto::HashValue;
+use schemadb::ReadOptions;
+use sgtypes::htlc::{HtlcAuditRecord, HtlcRecord};
+
+#[derive(Debug, Clone)]
+pub struct HtlcStore<S> {
+    db: S,
+}
+
+impl<S> HtlcStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> HtlcStore<S>
+where
+    S: SchemaDB,
+{
+    pub fn get_htlc(&self, hash_lock: &HashValue) -> Result<Option<HtlcRecord>> {
+        self.db.get::<HtlcSchema>(hash_lock)
+    }
+
+    pub fn save_htlc(&self, record: &HtlcRecord) -> Result<()> {
+        self.db.put::<HtlcSchema>(record.hash_lock(), record)
+    }
+
+    /// Return all tracked htlcs, ordered by hash lock.
+    pub fn list_htlcs(&self) -> Result<Vec<HtlcRecord>> {
+        let iter = self.db.iter::<HtlcSchema>(ReadOptions::default())?;
+        iter.map(|kv| Ok(kv?.1)).collect::<Result<Vec<_>>>()
+    }
+
+    /// Append `record` to the audit log, return its index.
+    /// Caller should serialize appends, as the index is derived from the last record.
+    pub fn append_audit(&self, record: &HtlcAuditRecord) -> Result<u64> {
+        let index = {
+            let mut iter = self.db.iter::<HtlcAuditSchema>(ReadOptions::default())?;
+            iter.seek_to_last();
+            iter.next()
+                .transpose()?
+                .map(|(index, _)| index + 1)
+                .unwrap_or(0)
+        };
+        self.db.put::<HtlcAuditSchema>(&index, record)?;
+        Ok(index)
+    }
+
+    /// Return audit records starting from `start_index`, in the order they are appended.
+    pub fn list_audits(
+        &self,
+        start_index: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, HtlcAuditRecord)>> {
+        let mut iter = self.db.iter::<HtlcAuditSchema>(ReadOptions::default())?;
+        iter.seek(&start_index)?;
+        iter.take(limit).collect::<Result<Vec<_>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel_db::ChannelDB, storage::SgStorage};
+    use libra_types::account_address::AccountAddress;
+    use sgtypes::htlc::{HtlcAction, HtlcPayment, HtlcState};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_htlc_store() -> Result<()> {
+        let owner = AccountAddress::random();
+        let storage = Arc::new(SgStorage::new(owner, libra_tools::tempdir::TempPath::new()));
+        let store = HtlcStore::new(ChannelDB::new(owner, storage));
+
+        let payment = HtlcPayment::new(HashValue::random(), 1000, 10);
+        let mut record = HtlcRecord::new(AccountAddress::random(), payment);
+        store.save_htlc(&record)?;
+        record.set_state(HtlcState::Settled);
+        store.save_htlc(&record)?;
+        assert_eq!(Some(record.clone()), store.get_htlc(record.hash_lock())?);
+        assert_eq!(1, store.list_htlcs()?.len());
+
+        for action in &[HtlcAction::Track, HtlcAction::Settle] {
+            let audit = HtlcAuditRecord::new(&record, *action, None, 1, String::new());
+            store.append_audit(&audit)?;
+        }
+        let audits = store.list_audits(0, 10)?;
+        assert_eq!(
+            vec![0, 1],
+            audits.iter().map(|(i, _)| *i).collect::<Vec<_>>()
+        );
+        assert_eq!(HtlcAction::Settle, audits[1].1.action);
+        assert_eq!(1, store.list_audits(1, 10)?.len());
+        Ok(())
+    }
+}
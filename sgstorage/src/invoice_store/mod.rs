@@ -0,0 +1,66 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines invoice store APIs that are used by node to keep preimages
+//! and forwarding records of htlc payments across restarts.
+
+use crate::schema::{invoice_schema::InvoiceSchema, previous_hop_schema::PreviousHopSchema};
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use schemadb::{ReadOptions, SchemaBatch};
+use sgtypes::invoice::InvoiceRecord;
+
+#[derive(Debug, Clone)]
+pub struct InvoiceStore<S> {
+    db: S,
+}
+
+impl<S> InvoiceStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> InvoiceStore<S>
+where
+    S: SchemaDB,
+{
+    pub fn get_invoice(&self, r_hash: &HashValue) -> Result<Option<InvoiceRecord>> {
+        self.db.get::<InvoiceSchema>(r_hash)
+    }
+
+    pub fn save_invoice(&self, invoice: &InvoiceRecord) -> Result<()> {
+        self.db.put::<InvoiceSchema>(invoice.r_hash(), invoice)
+    }
+
+    /// Return all invoices, ordered by r_hash.
+    pub fn list_invoices(&self) -> Result<Vec<InvoiceRecord>> {
+        let iter = self.db.iter::<InvoiceSchema>(ReadOptions::default())?;
+        iter.map(|kv| Ok(kv?.1)).collect::<Result<Vec<_>>>()
+    }
+
+    /// Previous hops of all htlcs locked by `r_hash` we forwarded, one per htlc.
+    pub fn get_previous_hops(&self, r_hash: &HashValue) -> Result<Vec<AccountAddress>> {
+        Ok(self
+            .db
+            .get::<PreviousHopSchema>(r_hash)?
+            .unwrap_or_default())
+    }
+
+    pub fn save_previous_hops(
+        &self,
+        r_hash: &HashValue,
+        previous_hops: &[AccountAddress],
+    ) -> Result<()> {
+        self.db
+            .put::<PreviousHopSchema>(r_hash, &previous_hops.to_vec())
+    }
+
+    pub fn delete_previous_hops(&self, r_hash: &HashValue) -> Result<()> {
+        let mut write_batch = SchemaBatch::new();
+        write_batch.delete::<PreviousHopSchema>(r_hash)?;
+        self.db.write_schemas(write_batch)
+    }
+}
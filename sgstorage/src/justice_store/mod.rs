@@ -0,0 +1,55 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines justice store APIs that are used by watchtower to keep
+//! encrypted justice data of its users, indexed by hint.
+
+use crate::schema::justice_schema::JusticeSchema;
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use sgtypes::justice::JusticeBlob;
+
+#[derive(Debug, Clone)]
+pub struct JusticeStore<S> {
+    db: S,
+}
+
+impl<S> JusticeStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> JusticeStore<S>
+where
+    S: SchemaDB,
+{
+    pub fn save_justice(&self, blob: &JusticeBlob) -> Result<()> {
+        self.db.put::<JusticeSchema>(&blob.hint, blob)
+    }
+
+    pub fn get_justice(&self, hint: &Vec<u8>) -> Result<Option<JusticeBlob>> {
+        self.db.get::<JusticeSchema>(hint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel_db::ChannelDB, storage::SgStorage};
+    use libra_types::account_address::AccountAddress;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_justice_store() -> Result<()> {
+        let owner = AccountAddress::random();
+        let storage = Arc::new(SgStorage::new(owner, libra_tools::tempdir::TempPath::new()));
+        let store = JusticeStore::new(ChannelDB::new(owner, storage));
+
+        let blob = JusticeBlob::new(vec![1u8; 16], vec![2u8; 100]);
+        assert_eq!(None, store.get_justice(&blob.hint)?);
+        store.save_justice(&blob)?;
+        assert_eq!(Some(blob.clone()), store.get_justice(&blob.hint)?);
+        Ok(())
+    }
+}
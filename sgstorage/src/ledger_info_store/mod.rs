@@ -155,6 +155,11 @@ where
     ) -> Result<AccumulatorConsistencyProof> {
         Accumulator::get_consistency_proof(self, ledger_version + 1, client_known_version + 1)
     }
+    /// Get root hash of the transaction accumulator at `version`.
+    pub fn get_root_hash(&self, version: Version) -> Result<HashValue> {
+        Accumulator::get_root_hash(self, version + 1)
+    }
+
     /// From left to right, get frozen subtree root hashes of the transaction accumulator.
     pub fn get_ledger_frozen_subtree_hashes(&self, version: Version) -> Result<Vec<HashValue>> {
         Accumulator::get_frozen_subtree_hashes(self, version + 1)
@@ -8,16 +8,25 @@ use libra_types::account_address::AccountAddress;
 use std::collections::BTreeSet;
 use std::sync::Arc;
 
+pub mod approval_store;
+pub mod channel_checkpoint_store;
 pub mod channel_db;
 pub mod channel_store;
+pub mod channel_terms_store;
 pub mod channel_transaction_store;
 pub mod channel_write_set_store;
 pub mod error;
+pub mod fee_policy_store;
+pub mod htlc_store;
+pub mod invoice_store;
+pub mod justice_store;
 pub mod ledger_info_store;
+pub mod payment_store;
 pub mod pending_txn_store;
 pub mod rocksdb_utils;
 pub mod schema;
 pub mod schema_db;
+pub mod script_package_store;
 pub mod storage;
 pub mod utils;
 
@@ -0,0 +1,42 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines payment store APIs that are used by node to keep the status
+//! and attempts of outgoing payments.
+
+use crate::schema::payment_schema::PaymentSchema;
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use libra_crypto::HashValue;
+use schemadb::ReadOptions;
+use sgtypes::payment::PaymentRecord;
+
+#[derive(Debug, Clone)]
+pub struct PaymentStore<S> {
+    db: S,
+}
+
+impl<S> PaymentStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> PaymentStore<S>
+where
+    S: SchemaDB,
+{
+    pub fn get_payment(&self, r_hash: &HashValue) -> Result<Option<PaymentRecord>> {
+        self.db.get::<PaymentSchema>(r_hash)
+    }
+
+    pub fn save_payment(&self, payment: &PaymentRecord) -> Result<()> {
+        self.db.put::<PaymentSchema>(payment.r_hash(), payment)
+    }
+
+    /// Return all payments, ordered by r_hash.
+    pub fn list_payments(&self) -> Result<Vec<PaymentRecord>> {
+        let iter = self.db.iter::<PaymentSchema>(ReadOptions::default())?;
+        iter.map(|kv| Ok(kv?.1)).collect::<Result<Vec<_>>>()
+    }
+}
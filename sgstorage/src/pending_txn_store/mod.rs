@@ -22,6 +22,9 @@ where
     S: SchemaDB,
 {
     const PENDING_TXN_KEY: &'static str = "pending";
+    const TRAVELLING_TXN_KEY: &'static str = "travelling";
+    const SPECULATIVE_TXN_KEY_PREFIX: &'static str = "speculative";
+
     pub fn get_pending_txn(&self) -> Result<Option<PendingTransaction>> {
         self.db
             .get::<PendingTransactionSchema>(&Self::PENDING_TXN_KEY.to_string())
@@ -36,4 +39,56 @@ where
     ) -> Result<()> {
         write_batch.put::<PendingTransactionSchema>(&Self::PENDING_TXN_KEY.to_string(), pending_txn)
     }
+
+    /// The agreed travel txn which is waiting to be committed onchain.
+    pub fn get_travelling_txn(&self) -> Result<Option<PendingTransaction>> {
+        self.db
+            .get::<PendingTransactionSchema>(&Self::TRAVELLING_TXN_KEY.to_string())
+    }
+    pub fn save_travelling_txn(
+        &self,
+        travelling_txn: &PendingTransaction,
+        write_batch: &mut SchemaBatch,
+    ) -> Result<()> {
+        write_batch
+            .put::<PendingTransactionSchema>(&Self::TRAVELLING_TXN_KEY.to_string(), travelling_txn)
+    }
+    pub fn clear_travelling_txn(&self, write_batch: &mut SchemaBatch) -> Result<()> {
+        write_batch.delete::<PendingTransactionSchema>(&Self::TRAVELLING_TXN_KEY.to_string())
+    }
+
+    /// Offchain txns agreed on top of the travelling txn, in the order they are agreed.
+    pub fn get_speculative_txns(&self) -> Result<Vec<PendingTransaction>> {
+        let mut txns = vec![];
+        while let Some(txn) = self
+            .db
+            .get::<PendingTransactionSchema>(&Self::speculative_txn_key(txns.len()))?
+        {
+            txns.push(txn);
+        }
+        Ok(txns)
+    }
+    pub fn save_speculative_txn(
+        &self,
+        index: usize,
+        speculative_txn: &PendingTransaction,
+        write_batch: &mut SchemaBatch,
+    ) -> Result<()> {
+        write_batch
+            .put::<PendingTransactionSchema>(&Self::speculative_txn_key(index), speculative_txn)
+    }
+    pub fn clear_speculative_txns(
+        &self,
+        count: usize,
+        write_batch: &mut SchemaBatch,
+    ) -> Result<()> {
+        for index in 0..count {
+            write_batch.delete::<PendingTransactionSchema>(&Self::speculative_txn_key(index))?;
+        }
+        Ok(())
+    }
+
+    fn speculative_txn_key(index: usize) -> String {
+        format!("{}-{}", Self::SPECULATIVE_TXN_KEY_PREFIX, index)
+    }
 }
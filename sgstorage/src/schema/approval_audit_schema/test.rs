@@ -0,0 +1,23 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use schemadb::schema::assert_encode_decode;
+use sgtypes::approval::ApprovalDecision;
+
+#[test]
+fn test_encode_decode() {
+    let audit = ApprovalAuditRecord {
+        request_id: HashValue::random(),
+        peer: AccountAddress::random(),
+        channel_address: AccountAddress::random(),
+        operator: "transfer".to_string(),
+        amount: 100,
+        decision: ApprovalDecision::Approve,
+        timestamp: 1,
+        reason: "matched rule 0".to_string(),
+    };
+    assert_encode_decode::<ApprovalAuditSchema>(&1, &audit);
+}
@@ -0,0 +1,54 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for amounts approved by approval rules in a day.
+//!
+//! ```text
+//! |<--key-->|<-----value----->|
+//! |   day   | volume of rules |
+//! ```
+//!
+//! `day` is days since unix epoch, serialized in big endian. The value keeps approved amount of
+//! every rule by rule id, the hash of the rule, so it follows the rule when rules are reordered.
+
+use crate::schema::{ensure_slice_len_eq, APPROVAL_VOLUME_CF_NAME};
+use anyhow::Result;
+use byteorder::{BigEndian, ReadBytesExt};
+use libra_crypto::HashValue;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+define_schema!(
+    ApprovalVolumeSchema,
+    u64,
+    BTreeMap<HashValue, u64>,
+    APPROVAL_VOLUME_CF_NAME
+);
+
+impl KeyCodec<ApprovalVolumeSchema> for u64 {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_be_bytes().to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        ensure_slice_len_eq(data, size_of::<u64>())?;
+        Ok((&data[..]).read_u64::<BigEndian>()?)
+    }
+}
+
+impl ValueCodec<ApprovalVolumeSchema> for BTreeMap<HashValue, u64> {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,13 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use schemadb::schema::assert_encode_decode;
+
+#[test]
+fn test_encode_decode() {
+    let mut volumes = BTreeMap::new();
+    volumes.insert(HashValue::random(), 100);
+    volumes.insert(HashValue::random(), 2000);
+    assert_encode_decode::<ApprovalVolumeSchema>(&18553, &volumes);
+}
@@ -0,0 +1,47 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for assets registered by wallet.
+//!
+//! ```text
+//! |<------key------>|<----value---->|
+//! | asset tag bytes |  asset bytes  |
+//! ```
+use crate::schema::CHANNEL_ASSET_CF_NAME;
+use anyhow::Result;
+use libra_types::language_storage::StructTag;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::script_package::ChannelAsset;
+
+define_schema!(
+    ChannelAssetSchema,
+    StructTag,
+    ChannelAsset,
+    CHANNEL_ASSET_CF_NAME
+);
+
+impl KeyCodec<ChannelAssetSchema> for StructTag {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+impl ValueCodec<ChannelAssetSchema> for ChannelAsset {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
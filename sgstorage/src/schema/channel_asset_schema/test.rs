@@ -0,0 +1,18 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_types::{account_address::AccountAddress, identifier::Identifier};
+use schemadb::schema::assert_encode_decode;
+
+#[test]
+fn test_encode_decode() {
+    let tag = |name: &str| StructTag {
+        address: AccountAddress::random(),
+        module: Identifier::new("Point").unwrap(),
+        name: Identifier::new(name).unwrap(),
+        type_params: vec![],
+    };
+    let asset = ChannelAsset::new(tag("T"), "point".to_string(), tag("Balance"));
+    assert_encode_decode::<ChannelAssetSchema>(&asset.asset.clone(), &asset);
+}
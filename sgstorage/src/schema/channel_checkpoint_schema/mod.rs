@@ -0,0 +1,50 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for checkpoints of a channel.
+//!
+//! ```text
+//! |<--key-->|<--------value-------->|
+//! | version | signed checkpoint     |
+//! ```
+use crate::schema::{ensure_slice_len_eq, CHANNEL_CHECKPOINT_CF_NAME};
+use anyhow::Result;
+use byteorder::{BigEndian, ReadBytesExt};
+use libra_types::transaction::Version;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::channel_checkpoint::SignedChannelCheckpoint;
+use std::mem::size_of;
+
+define_schema!(
+    ChannelCheckpointSchema,
+    Version,
+    SignedChannelCheckpoint,
+    CHANNEL_CHECKPOINT_CF_NAME
+);
+
+impl KeyCodec<ChannelCheckpointSchema> for Version {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_be_bytes().to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        ensure_slice_len_eq(data, size_of::<Version>())?;
+        Ok((&data[..]).read_u64::<BigEndian>()?)
+    }
+}
+
+impl ValueCodec<ChannelCheckpointSchema> for SignedChannelCheckpoint {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,19 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use schemadb::schema::assert_encode_decode;
+use sgtypes::channel_checkpoint::ChannelCheckpoint;
+
+#[test]
+fn test_encode_decode() {
+    let checkpoint = SignedChannelCheckpoint::new(ChannelCheckpoint::new(
+        AccountAddress::random(),
+        100,
+        HashValue::random(),
+        HashValue::random(),
+    ));
+    assert_encode_decode::<ChannelCheckpointSchema>(&checkpoint.version(), &checkpoint);
+}
@@ -0,0 +1,49 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for terms agreed when a channel is opened.
+//!
+//! ```text
+//! |<-------key------->|<----value---->|
+//! |  channel address  |  terms bytes  |
+//! ```
+use crate::schema::{ensure_slice_len_eq, CHANNEL_TERMS_CF_NAME};
+use anyhow::Result;
+use libra_types::account_address::{AccountAddress, ADDRESS_LENGTH};
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::open_channel_policy::ChannelTerms;
+use std::convert::TryFrom;
+
+define_schema!(
+    ChannelTermsSchema,
+    AccountAddress,
+    ChannelTerms,
+    CHANNEL_TERMS_CF_NAME
+);
+
+impl KeyCodec<ChannelTermsSchema> for AccountAddress {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        ensure_slice_len_eq(data, ADDRESS_LENGTH)?;
+        Ok(AccountAddress::try_from(data)?)
+    }
+}
+
+impl ValueCodec<ChannelTermsSchema> for ChannelTerms {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,11 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use schemadb::schema::assert_encode_decode;
+
+#[test]
+fn test_encode_decode() {
+    let terms = ChannelTerms::new(100, vec!["libra".to_string()]);
+    assert_encode_decode::<ChannelTermsSchema>(&AccountAddress::random(), &terms);
+}
@@ -0,0 +1,51 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for what a channel knows about its applied
+//! transactions besides the transactions themselves.
+//!
+//! ```text
+//! |<--key-->|<--value-->|
+//! | version | txn meta  |
+//! ```
+use crate::schema::{ensure_slice_len_eq, CHANNEL_TXN_META_CF_NAME};
+use anyhow::Result;
+use byteorder::{BigEndian, ReadBytesExt};
+use libra_types::transaction::Version;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::channel_txn_history::ChannelTxnMeta;
+use std::mem::size_of;
+
+define_schema!(
+    ChannelTxnMetaSchema,
+    Version,
+    ChannelTxnMeta,
+    CHANNEL_TXN_META_CF_NAME
+);
+
+impl KeyCodec<ChannelTxnMetaSchema> for Version {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_be_bytes().to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        ensure_slice_len_eq(data, size_of::<Version>())?;
+        Ok((&data[..]).read_u64::<BigEndian>()?)
+    }
+}
+
+impl ValueCodec<ChannelTxnMetaSchema> for ChannelTxnMeta {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,20 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_types::{account_address::AccountAddress, transaction::TransactionArgument};
+use schemadb::schema::assert_encode_decode;
+use sgtypes::channel_transaction::ChannelOp;
+
+#[test]
+fn test_encode_decode() {
+    let meta = ChannelTxnMeta::new(
+        100,
+        Some((
+            ChannelOp::Close,
+            vec![TransactionArgument::Address(AccountAddress::random())],
+        )),
+    );
+    assert_encode_decode::<ChannelTxnMetaSchema>(&1, &meta);
+    assert_encode_decode::<ChannelTxnMetaSchema>(&2, &ChannelTxnMeta::new(100, None));
+}
@@ -0,0 +1,52 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for fee policies of channels.
+//!
+//! The policy of channel with a participant, or the default one if key is empty.
+//! ```text
+//! |<--------key-------->|<----value---->|
+//! | participant address | policy bytes  |
+//! ```
+use crate::schema::FEE_POLICY_CF_NAME;
+use anyhow::Result;
+use libra_types::account_address::AccountAddress;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::fee_policy::FeePolicy;
+use std::convert::TryFrom;
+
+define_schema!(
+    FeePolicySchema,
+    Option<AccountAddress>,
+    FeePolicy,
+    FEE_POLICY_CF_NAME
+);
+
+impl KeyCodec<FeePolicySchema> for Option<AccountAddress> {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.map(|addr| addr.to_vec()).unwrap_or_default())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        if data.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(AccountAddress::try_from(data)?))
+    }
+}
+
+impl ValueCodec<FeePolicySchema> for FeePolicy {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
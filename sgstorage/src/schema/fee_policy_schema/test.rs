@@ -0,0 +1,12 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use schemadb::schema::assert_encode_decode;
+
+#[test]
+fn test_encode_decode() {
+    let policy = FeePolicy::new(1, 100, 20);
+    assert_encode_decode::<FeePolicySchema>(&Some(AccountAddress::random()), &policy);
+    assert_encode_decode::<FeePolicySchema>(&None, &policy);
+}
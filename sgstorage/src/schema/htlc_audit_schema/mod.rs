@@ -0,0 +1,47 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for the audit log of htlc actions.
+//!
+//! ```text
+//! |<--key-->|<----value---->|
+//! |  index  | record bytes  |
+//! ```
+//!
+//! `index` is serialized in big endian so that records are kept in the order they are appended.
+
+use crate::schema::{ensure_slice_len_eq, HTLC_AUDIT_CF_NAME};
+use anyhow::Result;
+use byteorder::{BigEndian, ReadBytesExt};
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::htlc::HtlcAuditRecord;
+use std::mem::size_of;
+
+define_schema!(HtlcAuditSchema, u64, HtlcAuditRecord, HTLC_AUDIT_CF_NAME);
+
+impl KeyCodec<HtlcAuditSchema> for u64 {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_be_bytes().to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        ensure_slice_len_eq(data, size_of::<u64>())?;
+        Ok((&data[..]).read_u64::<BigEndian>()?)
+    }
+}
+
+impl ValueCodec<HtlcAuditSchema> for HtlcAuditRecord {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,22 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use schemadb::schema::assert_encode_decode;
+use sgtypes::htlc::{HtlcAction, HtlcPayment, HtlcRecord};
+
+#[test]
+fn test_encode_decode() {
+    let payment = HtlcPayment::new(HashValue::random(), 1000, 10);
+    let record = HtlcRecord::new(AccountAddress::random(), payment);
+    let audit = HtlcAuditRecord::new(
+        &record,
+        HtlcAction::Fail,
+        Some(100),
+        1,
+        "channel is travelling".to_string(),
+    );
+    assert_encode_decode::<HtlcAuditSchema>(&1, &audit);
+}
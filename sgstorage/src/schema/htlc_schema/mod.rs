@@ -0,0 +1,42 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for outgoing htlc tracked by wallet.
+//!
+//! ```text
+//! |<---key--->|<----value---->|
+//! | hash_lock | record bytes  |
+//! ```
+use crate::schema::HTLC_CF_NAME;
+use anyhow::Result;
+use libra_crypto::HashValue;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::htlc::HtlcRecord;
+
+define_schema!(HtlcSchema, HashValue, HtlcRecord, HTLC_CF_NAME);
+
+impl KeyCodec<HtlcSchema> for HashValue {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        HashValue::from_slice(data)
+    }
+}
+
+impl ValueCodec<HtlcSchema> for HtlcRecord {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,16 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_types::account_address::AccountAddress;
+use schemadb::schema::assert_encode_decode;
+use sgtypes::htlc::{HtlcPayment, HtlcState};
+
+#[test]
+fn test_encode_decode() {
+    let payment = HtlcPayment::new(HashValue::random(), 1000, 10);
+    let mut record = HtlcRecord::new(AccountAddress::random(), payment);
+    record.set_expire_at(100);
+    record.set_state(HtlcState::Recalling { proposed_at: 101 });
+    assert_encode_decode::<HtlcSchema>(record.hash_lock(), &record);
+}
@@ -0,0 +1,29 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_crypto::ed25519::{Ed25519PrivateKey, Ed25519PublicKey};
+use libra_types::account_address::AccountAddress;
+use schemadb::schema::assert_encode_decode;
+use sgtypes::invoice::Invoice;
+use std::convert::TryFrom;
+
+#[test]
+fn test_encode_decode() {
+    let private_key = Ed25519PrivateKey::try_from([1u8; 32].as_ref()).unwrap();
+    let preimage = HashValue::random().to_vec();
+    let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+    let invoice = Invoice {
+        r_hash: r_hash.to_vec(),
+        amount: 1000,
+        receiver: AccountAddress::from_public_key(&Ed25519PublicKey::from(&private_key)),
+        timestamp: 1,
+        expiry: 3_600_000,
+        description: "coffee".to_string(),
+        min_final_timeout: 100,
+        route_hints: vec![],
+    }
+    .sign(&private_key);
+    let record = InvoiceRecord::new(r_hash, preimage, invoice);
+    assert_encode_decode::<InvoiceSchema>(&r_hash, &record);
+}
@@ -0,0 +1,41 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for justice blobs kept by watchtower.
+//!
+//! ```text
+//! |<--key-->|<-----value----->|
+//! |  hint   | justice blob    |
+//! ```
+use crate::schema::JUSTICE_CF_NAME;
+use anyhow::Result;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::justice::JusticeBlob;
+
+define_schema!(JusticeSchema, Vec<u8>, JusticeBlob, JUSTICE_CF_NAME);
+
+impl KeyCodec<JusticeSchema> for Vec<u8> {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.clone())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        Ok(data.to_vec())
+    }
+}
+
+impl ValueCodec<JusticeSchema> for JusticeBlob {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
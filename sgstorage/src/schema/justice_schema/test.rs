@@ -0,0 +1,11 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use schemadb::schema::assert_encode_decode;
+
+#[test]
+fn test_encode_decode() {
+    let blob = JusticeBlob::new(vec![1u8; 16], vec![2u8; 100]);
+    assert_encode_decode::<JusticeSchema>(&blob.hint, &blob);
+}
@@ -1,14 +1,28 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod approval_audit_schema;
+pub mod approval_volume_schema;
+pub mod channel_asset_schema;
+pub mod channel_checkpoint_schema;
+pub mod channel_terms_schema;
 pub mod channel_transaction_accumulator;
 pub mod channel_transaction_info;
 pub mod channel_transaction_schema;
+pub mod channel_txn_meta_schema;
 pub mod channel_write_set_accumulator_schema;
 pub mod channel_write_set_schema;
+pub mod fee_policy_schema;
+pub mod htlc_audit_schema;
+pub mod htlc_schema;
+pub mod invoice_schema;
+pub mod justice_schema;
 pub mod ledger_info_schema;
 pub mod participant_public_key_schema;
+pub mod payment_schema;
 pub mod pending_transaction_schema;
+pub mod previous_hop_schema;
+pub mod script_package_schema;
 use anyhow::{ensure, Result};
 use schemadb::ColumnFamilyName;
 
@@ -28,6 +42,20 @@ pub const CHANNEL_WRITE_SET_CF_NAME: ColumnFamilyName = "channel_write_set";
 pub const CHANNEL_WRITE_SET_ACCUMULATOR_CF_NAME: ColumnFamilyName = "channel_write_set_accumulator";
 pub const PENDING_CHANNEL_TRANSACTION_CF_NAME: ColumnFamilyName = "pending_channel_transaction";
 pub const PARTICIPANT_PUBLIC_KEY_CF_NAME: ColumnFamilyName = "participant_public_key";
+pub const INVOICE_CF_NAME: ColumnFamilyName = "invoice";
+pub const PREVIOUS_HOP_CF_NAME: ColumnFamilyName = "previous_hop";
+pub const HTLC_CF_NAME: ColumnFamilyName = "htlc";
+pub const HTLC_AUDIT_CF_NAME: ColumnFamilyName = "htlc_audit";
+pub const PAYMENT_CF_NAME: ColumnFamilyName = "payment";
+pub const FEE_POLICY_CF_NAME: ColumnFamilyName = "fee_policy";
+pub const JUSTICE_CF_NAME: ColumnFamilyName = "justice";
+pub const CHANNEL_CHECKPOINT_CF_NAME: ColumnFamilyName = "channel_checkpoint";
+pub const APPROVAL_AUDIT_CF_NAME: ColumnFamilyName = "approval_audit";
+pub const CHANNEL_TXN_META_CF_NAME: ColumnFamilyName = "channel_txn_meta";
+pub const APPROVAL_VOLUME_CF_NAME: ColumnFamilyName = "approval_volume";
+pub const SCRIPT_PACKAGE_CF_NAME: ColumnFamilyName = "script_package";
+pub const CHANNEL_ASSET_CF_NAME: ColumnFamilyName = "channel_asset";
+pub const CHANNEL_TERMS_CF_NAME: ColumnFamilyName = "channel_terms";
 
 pub fn ensure_slice_len_eq(data: &[u8], len: usize) -> Result<()> {
     ensure!(
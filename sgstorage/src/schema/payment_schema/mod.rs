@@ -0,0 +1,43 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for `PaymentRecord` structure.
+//!
+//! Serialized outgoing payment identified by its hash lock.
+//! ```text
+//! |<--key-->|<-----value---->|
+//! | r_hash  | payment bytes  |
+//! ```
+use crate::schema::PAYMENT_CF_NAME;
+use anyhow::Result;
+use libra_crypto::HashValue;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::payment::PaymentRecord;
+
+define_schema!(PaymentSchema, HashValue, PaymentRecord, PAYMENT_CF_NAME);
+
+impl KeyCodec<PaymentSchema> for HashValue {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        HashValue::from_slice(data)
+    }
+}
+
+impl ValueCodec<PaymentSchema> for PaymentRecord {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
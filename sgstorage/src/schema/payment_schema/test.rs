@@ -0,0 +1,25 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use libra_types::account_address::AccountAddress;
+use schemadb::schema::assert_encode_decode;
+use sgtypes::{
+    payment::{AttemptFailure, PaymentAttempt},
+    sg_error::{SgError, SgErrorCode},
+};
+
+#[test]
+fn test_encode_decode() {
+    let (payer, receiver) = (AccountAddress::random(), AccountAddress::random());
+    let r_hash = HashValue::random();
+    let mut record = PaymentRecord::new(r_hash, receiver, 1000, 1);
+    let request_id = HashValue::random();
+    record.add_attempt(
+        PaymentAttempt::new(request_id, vec![payer, receiver], 1000, 0),
+        2,
+    );
+    let error = SgError::new(SgErrorCode::TIMEOUT, "future time out".to_string());
+    record.fail_attempt(&request_id, AttemptFailure::new(&error, None), 3);
+    assert_encode_decode::<PaymentSchema>(&r_hash, &record);
+}
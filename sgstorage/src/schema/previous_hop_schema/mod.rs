@@ -0,0 +1,46 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for previous hops of forwarded htlc payment.
+//! A multi-path payment may pass through us in several htlcs, so one address is kept per
+//! incoming htlc.
+//!
+//! ```text
+//! |<--key-->|<--------value--------->|
+//! | r_hash  | previous hop addresses |
+//! ```
+use crate::schema::PREVIOUS_HOP_CF_NAME;
+use anyhow::Result;
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+
+define_schema!(
+    PreviousHopSchema,
+    HashValue,
+    Vec<AccountAddress>,
+    PREVIOUS_HOP_CF_NAME
+);
+
+impl KeyCodec<PreviousHopSchema> for HashValue {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        HashValue::from_slice(data)
+    }
+}
+
+impl ValueCodec<PreviousHopSchema> for Vec<AccountAddress> {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
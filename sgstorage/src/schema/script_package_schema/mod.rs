@@ -0,0 +1,46 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines physical storage schema for script packages installed by wallet.
+//!
+//! ```text
+//! |<-----key---->|<----value---->|
+//! | package name | package bytes |
+//! ```
+use crate::schema::SCRIPT_PACKAGE_CF_NAME;
+use anyhow::Result;
+use schemadb::{
+    define_schema,
+    schema::{KeyCodec, ValueCodec},
+};
+use sgtypes::script_package::ChannelScriptPackage;
+
+define_schema!(
+    ScriptPackageSchema,
+    String,
+    ChannelScriptPackage,
+    SCRIPT_PACKAGE_CF_NAME
+);
+
+impl KeyCodec<ScriptPackageSchema> for String {
+    fn encode_key(&self) -> Result<Vec<u8>> {
+        Ok(self.as_bytes().to_vec())
+    }
+
+    fn decode_key(data: &[u8]) -> Result<Self> {
+        Ok(String::from_utf8(data.to_vec())?)
+    }
+}
+
+impl ValueCodec<ScriptPackageSchema> for ChannelScriptPackage {
+    fn encode_value(&self) -> Result<Vec<u8>> {
+        lcs::to_bytes(self).map_err(Into::into)
+    }
+
+    fn decode_value(data: &[u8]) -> Result<Self> {
+        lcs::from_bytes(data).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,19 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use super::*;
+use schemadb::schema::assert_encode_decode;
+use sgtypes::script_package::ScriptCode;
+
+#[test]
+fn test_encode_decode() {
+    let package = ChannelScriptPackage::new(
+        "point".to_string(),
+        vec![ScriptCode::new(
+            "transfer".to_string(),
+            "main() { return; }".to_string(),
+            vec![1, 2, 3],
+        )],
+    );
+    assert_encode_decode::<ScriptPackageSchema>(&"point".to_string(), &package);
+}
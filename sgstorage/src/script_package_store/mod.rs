@@ -0,0 +1,100 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This file defines script package store APIs that are used by wallet to keep
+//! packages installed and assets registered by user, so they survive a restart.
+
+use crate::schema::{
+    channel_asset_schema::ChannelAssetSchema, script_package_schema::ScriptPackageSchema,
+};
+use crate::schema_db::SchemaDB;
+use anyhow::Result;
+use schemadb::ReadOptions;
+use sgtypes::script_package::{ChannelAsset, ChannelScriptPackage};
+
+#[derive(Debug, Clone)]
+pub struct ScriptPackageStore<S> {
+    db: S,
+}
+
+impl<S> ScriptPackageStore<S> {
+    pub fn new(db: S) -> Self {
+        Self { db }
+    }
+}
+
+impl<S> ScriptPackageStore<S>
+where
+    S: SchemaDB,
+{
+    pub fn save_package(&self, package: &ChannelScriptPackage) -> Result<()> {
+        self.db
+            .put::<ScriptPackageSchema>(&package.package_name().to_string(), package)
+    }
+
+    pub fn list_packages(&self) -> Result<Vec<ChannelScriptPackage>> {
+        let mut iter = self
+            .db
+            .iter::<ScriptPackageSchema>(ReadOptions::default())?;
+        iter.seek_to_first();
+        iter.map(|item| item.map(|(_, package)| package))
+            .collect::<Result<Vec<_>>>()
+    }
+
+    pub fn save_asset(&self, asset: &ChannelAsset) -> Result<()> {
+        self.db.put::<ChannelAssetSchema>(&asset.asset, asset)
+    }
+
+    pub fn list_assets(&self) -> Result<Vec<ChannelAsset>> {
+        let mut iter = self.db.iter::<ChannelAssetSchema>(ReadOptions::default())?;
+        iter.seek_to_first();
+        iter.map(|item| item.map(|(_, asset)| asset))
+            .collect::<Result<Vec<_>>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{channel_db::ChannelDB, storage::SgStorage};
+    use libra_types::{
+        account_address::AccountAddress, identifier::Identifier, language_storage::StructTag,
+    };
+    use sgtypes::script_package::ScriptCode;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_script_package_store() -> Result<()> {
+        let owner = AccountAddress::random();
+        let path = libra_tools::tempdir::TempPath::new();
+        let storage = Arc::new(SgStorage::new(owner, path.path()));
+        let store = ScriptPackageStore::new(ChannelDB::new(owner, storage));
+        assert!(store.list_packages()?.is_empty());
+        assert!(store.list_assets()?.is_empty());
+
+        let package = ChannelScriptPackage::new(
+            "point".to_string(),
+            vec![ScriptCode::new(
+                "transfer".to_string(),
+                "main() { return; }".to_string(),
+                vec![1, 2, 3],
+            )],
+        );
+        store.save_package(&package)?;
+        let tag = |name: &str| StructTag {
+            address: owner,
+            module: Identifier::new("Point").unwrap(),
+            name: Identifier::new(name).unwrap(),
+            type_params: vec![],
+        };
+        let asset = ChannelAsset::new(tag("T"), "point".to_string(), tag("Balance"));
+        store.save_asset(&asset)?;
+        drop(store);
+
+        let storage = Arc::new(SgStorage::new(owner, path.path()));
+        let store = ScriptPackageStore::new(ChannelDB::new(owner, storage));
+        assert_eq!(vec![package], store.list_packages()?);
+        assert_eq!(vec![asset], store.list_assets()?);
+        Ok(())
+    }
+}
@@ -8,7 +8,12 @@ use super::schema::{
     STALE_NODE_INDEX_CF_NAME, TRANSACTION_BY_ACCOUNT_CF_NAME,
 };
 use crate::rocksdb_utils::FixedPrefixSliceTransform;
-use crate::schema::PARTICIPANT_PUBLIC_KEY_CF_NAME;
+use crate::schema::{
+    APPROVAL_AUDIT_CF_NAME, APPROVAL_VOLUME_CF_NAME, CHANNEL_ASSET_CF_NAME,
+    CHANNEL_CHECKPOINT_CF_NAME, CHANNEL_TERMS_CF_NAME, CHANNEL_TXN_META_CF_NAME,
+    FEE_POLICY_CF_NAME, HTLC_AUDIT_CF_NAME, HTLC_CF_NAME, INVOICE_CF_NAME, JUSTICE_CF_NAME,
+    PARTICIPANT_PUBLIC_KEY_CF_NAME, PAYMENT_CF_NAME, PREVIOUS_HOP_CF_NAME, SCRIPT_PACKAGE_CF_NAME,
+};
 use anyhow::{format_err, Error, Result};
 use libra_logger::prelude::*;
 use libra_types::account_address::{AccountAddress, ADDRESS_LENGTH};
@@ -94,6 +99,20 @@ impl SgStorage {
                 PARTICIPANT_PUBLIC_KEY_CF_NAME,
                 default_column_family_options(),
             ),
+            (INVOICE_CF_NAME, default_column_family_options()),
+            (PREVIOUS_HOP_CF_NAME, default_column_family_options()),
+            (HTLC_CF_NAME, default_column_family_options()),
+            (HTLC_AUDIT_CF_NAME, default_column_family_options()),
+            (PAYMENT_CF_NAME, default_column_family_options()),
+            (FEE_POLICY_CF_NAME, default_column_family_options()),
+            (JUSTICE_CF_NAME, default_column_family_options()),
+            (CHANNEL_CHECKPOINT_CF_NAME, default_column_family_options()),
+            (APPROVAL_AUDIT_CF_NAME, default_column_family_options()),
+            (CHANNEL_TXN_META_CF_NAME, default_column_family_options()),
+            (APPROVAL_VOLUME_CF_NAME, default_column_family_options()),
+            (SCRIPT_PACKAGE_CF_NAME, default_column_family_options()),
+            (CHANNEL_ASSET_CF_NAME, default_column_family_options()),
+            (CHANNEL_TERMS_CF_NAME, default_column_family_options()),
         ]
         .iter()
         .cloned()
@@ -0,0 +1,354 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rules deciding incoming proposals which need approval of the operator.
+
+use crate::channel_transaction::ChannelOp;
+use crate::impl_hash;
+use anyhow::{bail, Error, Result};
+use libra_crypto::{hash::CryptoHash, HashValue};
+use libra_crypto_derive::CryptoHasher;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ApprovalDecision {
+    Approve,
+    Reject,
+    /// leave the proposal to the operator.
+    Ask,
+}
+
+impl FromStr for ApprovalDecision {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "approve" => ApprovalDecision::Approve,
+            "reject" => ApprovalDecision::Reject,
+            "ask" => ApprovalDecision::Ask,
+            _ => bail!("invalid approval decision {}", s),
+        })
+    }
+}
+
+impl Display for ApprovalDecision {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let s = match self {
+            ApprovalDecision::Approve => "approve",
+            ApprovalDecision::Reject => "reject",
+            ApprovalDecision::Ask => "ask",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// What a rule knows about an incoming proposal.
+#[derive(Clone, Debug)]
+pub struct ProposalFacts {
+    pub peer: AccountAddress,
+    pub operator: ChannelOp,
+    /// amount of the default asset the proposal takes from our channel balance.
+    pub amount: u64,
+    /// amount of other assets the proposal takes from our channel balance, by package name.
+    pub asset_amounts: Vec<(String, u64)>,
+}
+
+impl ProposalFacts {
+    pub fn new(peer: AccountAddress, operator: ChannelOp, amount: u64) -> Self {
+        Self {
+            peer,
+            operator,
+            amount,
+            asset_amounts: vec![],
+        }
+    }
+
+    pub fn with_asset_amount(mut self, asset: String, amount: u64) -> Self {
+        self.asset_amounts.push((asset, amount));
+        self
+    }
+
+    /// Amount of `asset` the proposal takes, `None` for the default asset.
+    pub fn amount_of(&self, asset: Option<&str>) -> u64 {
+        match asset {
+            None => self.amount,
+            Some(asset) => self
+                .asset_amounts
+                .iter()
+                .filter(|(name, _)| name == asset)
+                .map(|(_, amount)| *amount)
+                .sum(),
+        }
+    }
+
+    /// Assets the proposal takes, other than `asset`.
+    fn other_assets(&self, asset: Option<&str>) -> Vec<&str> {
+        let mut others = vec![];
+        if asset.is_some() && self.amount > 0 {
+            others.push("default");
+        }
+        for (name, amount) in &self.asset_amounts {
+            if *amount > 0 && Some(name.as_str()) != asset {
+                others.push(name.as_str());
+            }
+        }
+        others
+    }
+
+    /// (package, script) of every op, module and function name are used for actions.
+    fn scripts(&self) -> Vec<(Option<&str>, Option<&str>)> {
+        fn script_of(op: &ChannelOp) -> (Option<&str>, Option<&str>) {
+            match op {
+                ChannelOp::Execute {
+                    package_name,
+                    script_name,
+                } => (Some(package_name), Some(script_name)),
+                ChannelOp::Action {
+                    module_name,
+                    function_name,
+                    ..
+                } => (Some(module_name), Some(function_name)),
+                _ => (None, None),
+            }
+        }
+        match &self.operator {
+            ChannelOp::Batch { ops } => ops.iter().map(|op| script_of(&op.operator)).collect(),
+            op => vec![script_of(op)],
+        }
+    }
+}
+
+/// A rule matches a proposal if all its conditions are met, unset conditions match anything.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, CryptoHasher)]
+pub struct ApprovalRule {
+    pub decision: ApprovalDecision,
+    pub package: Option<String>,
+    pub script: Option<String>,
+    pub peer: Option<AccountAddress>,
+    /// package name of the asset whose amount is bounded by the rule, the default asset if unset.
+    /// Rules approving proposals don't match the ones taking other assets.
+    pub asset: Option<String>,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    /// max total amount approved by the rule in a day.
+    pub daily_volume: Option<u64>,
+}
+impl_hash!(ApprovalRule, ApprovalRuleHasher);
+
+impl ApprovalRule {
+    pub fn new(decision: ApprovalDecision) -> Self {
+        Self {
+            decision,
+            package: None,
+            script: None,
+            peer: None,
+            asset: None,
+            min_amount: None,
+            max_amount: None,
+            daily_volume: None,
+        }
+    }
+
+    /// Id of the rule which keys its daily volume, the hash of its conditions.
+    /// It stays the same when rules are reordered, a changed rule starts with no volume.
+    pub fn id(&self) -> HashValue {
+        self.hash()
+    }
+
+    /// Check the rule against `facts`, `volume` is the amount approved by the rule today.
+    /// Return why it doesn't match if so.
+    pub fn check(&self, facts: &ProposalFacts, volume: u64) -> std::result::Result<(), String> {
+        if let Some(peer) = &self.peer {
+            if peer != &facts.peer {
+                return Err(format!("peer is not {}", peer));
+            }
+        }
+        for (package, script) in facts.scripts() {
+            if let Some(expected) = &self.package {
+                if package != Some(expected.as_str()) {
+                    return Err(format!("package is not {}", expected));
+                }
+            }
+            if let Some(expected) = &self.script {
+                if script != Some(expected.as_str()) {
+                    return Err(format!("script is not {}", expected));
+                }
+            }
+        }
+        let asset = self.asset.as_ref().map(String::as_str);
+        if self.decision == ApprovalDecision::Approve {
+            let others = facts.other_assets(asset);
+            if !others.is_empty() {
+                return Err(format!("asset {} is not bounded", others.join(", ")));
+            }
+        }
+        let amount = facts.amount_of(asset);
+        if let Some(min_amount) = self.min_amount {
+            if amount < min_amount {
+                return Err(format!("amount {} < {}", amount, min_amount));
+            }
+        }
+        if let Some(max_amount) = self.max_amount {
+            if amount > max_amount {
+                return Err(format!("amount {} > {}", amount, max_amount));
+            }
+        }
+        if let Some(daily_volume) = self.daily_volume {
+            if volume.saturating_add(amount) > daily_volume {
+                return Err(format!(
+                    "daily volume {} is exceeded, {} is used",
+                    daily_volume, volume
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rules are checked in order, the first matched one decides.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApprovalPolicy {
+    pub rules: Vec<ApprovalRule>,
+    /// decision when no rule matches.
+    pub default_decision: ApprovalDecision,
+}
+
+impl ApprovalPolicy {
+    pub fn new(rules: Vec<ApprovalRule>, default_decision: ApprovalDecision) -> Self {
+        Self {
+            rules,
+            default_decision,
+        }
+    }
+
+    /// Decide the proposal by `facts`, `volumes` is the amount approved today by rule id.
+    /// Return the decision, index of the matched rule, and the reason.
+    pub fn decide(
+        &self,
+        facts: &ProposalFacts,
+        volumes: &BTreeMap<HashValue, u64>,
+    ) -> (ApprovalDecision, Option<usize>, String) {
+        let mut mismatches = vec![];
+        for (i, rule) in self.rules.iter().enumerate() {
+            let volume = volumes.get(&rule.id()).cloned().unwrap_or(0);
+            match rule.check(facts, volume) {
+                Ok(()) => return (rule.decision, Some(i), format!("matched rule {}", i)),
+                Err(reason) => mismatches.push(format!("rule {}: {}", i, reason)),
+            }
+        }
+        let reason = if mismatches.is_empty() {
+            "no rule".to_string()
+        } else {
+            format!("no rule matched, {}", mismatches.join("; "))
+        };
+        (self.default_decision, None, reason)
+    }
+}
+
+/// Decision made on an incoming proposal, kept for audit.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalAuditRecord {
+    pub request_id: HashValue,
+    pub peer: AccountAddress,
+    pub channel_address: AccountAddress,
+    pub operator: String,
+    pub amount: u64,
+    pub decision: ApprovalDecision,
+    /// unix timestamp in milliseconds.
+    pub timestamp: u64,
+    pub reason: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer() -> ChannelOp {
+        ChannelOp::Action {
+            module_address: AccountAddress::default(),
+            module_name: "ChannelScript".to_string(),
+            function_name: "transfer".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_approval_policy() {
+        let peer = AccountAddress::random();
+        let mut small_transfer = ApprovalRule::new(ApprovalDecision::Approve);
+        small_transfer.script = Some("transfer".to_string());
+        small_transfer.max_amount = Some(100);
+        small_transfer.daily_volume = Some(150);
+        let mut blocked = ApprovalRule::new(ApprovalDecision::Reject);
+        blocked.peer = Some(peer);
+        let small_transfer_id = small_transfer.id();
+        let policy = ApprovalPolicy::new(vec![blocked, small_transfer], ApprovalDecision::Ask);
+
+        let facts = ProposalFacts::new(peer, transfer(), 10);
+        assert_eq!(
+            ApprovalDecision::Reject,
+            policy.decide(&facts, &BTreeMap::new()).0
+        );
+
+        let facts = ProposalFacts::new(AccountAddress::random(), transfer(), 100);
+        let mut volumes = BTreeMap::new();
+        volumes.insert(small_transfer_id, 0);
+        let (decision, rule, _) = policy.decide(&facts, &volumes);
+        assert_eq!((ApprovalDecision::Approve, Some(1)), (decision, rule));
+        // daily volume is used up.
+        volumes.insert(small_transfer_id, 100);
+        let (decision, rule, reason) = policy.decide(&facts, &volumes);
+        assert_eq!((ApprovalDecision::Ask, None), (decision, rule));
+        assert!(reason.contains("daily volume"));
+        // the volume follows the rule when rules are reordered.
+        let mut reordered = policy.clone();
+        reordered.rules.reverse();
+        let (decision, rule, _) = reordered.decide(&facts, &volumes);
+        assert_eq!((ApprovalDecision::Ask, None), (decision, rule));
+
+        let facts = ProposalFacts::new(AccountAddress::random(), transfer(), 101);
+        assert_eq!(
+            ApprovalDecision::Ask,
+            policy.decide(&facts, &BTreeMap::new()).0
+        );
+        let facts = ProposalFacts::new(AccountAddress::random(), ChannelOp::Close, 0);
+        assert_eq!(
+            ApprovalDecision::Ask,
+            policy.decide(&facts, &BTreeMap::new()).0
+        );
+    }
+
+    #[test]
+    fn test_approval_policy_assets() {
+        let mut small_transfer = ApprovalRule::new(ApprovalDecision::Approve);
+        small_transfer.max_amount = Some(100);
+        let mut small_point_transfer = ApprovalRule::new(ApprovalDecision::Approve);
+        small_point_transfer.asset = Some("point".to_string());
+        small_point_transfer.max_amount = Some(10);
+        let policy = ApprovalPolicy::new(
+            vec![small_transfer, small_point_transfer],
+            ApprovalDecision::Ask,
+        );
+
+        let peer = AccountAddress::random();
+        let facts =
+            ProposalFacts::new(peer, transfer(), 0).with_asset_amount("point".to_string(), 10);
+        let (decision, rule, _) = policy.decide(&facts, &BTreeMap::new());
+        assert_eq!((ApprovalDecision::Approve, Some(1)), (decision, rule));
+        // the default asset is bounded, but not the point.
+        let facts =
+            ProposalFacts::new(peer, transfer(), 10).with_asset_amount("point".to_string(), 1000);
+        let (decision, _, reason) = policy.decide(&facts, &BTreeMap::new());
+        assert_eq!(ApprovalDecision::Ask, decision);
+        assert!(reason.contains("asset point is not bounded"));
+        let facts =
+            ProposalFacts::new(peer, transfer(), 0).with_asset_amount("other".to_string(), 1);
+        assert_eq!(
+            ApprovalDecision::Ask,
+            policy.decide(&facts, &BTreeMap::new()).0
+        );
+    }
+}
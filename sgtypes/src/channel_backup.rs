@@ -0,0 +1,120 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Static channel backup, enough to close channels after local storage is lost,
+//! with their participants or by force.
+//!
+//! The backup is encrypted by a key derived from the account private key with a fresh nonce
+//! on every save, and prefixed by its version, so it can be kept anywhere outside the wallet store.
+
+use crate::{
+    encryption::{decrypt, encrypt, KEY_SIZE},
+    onion::hmac,
+};
+use anyhow::{ensure, Result};
+use libra_crypto::ed25519::{Ed25519PrivateKey, Ed25519PublicKey};
+use libra_types::{account_address::AccountAddress, channel::Witness};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const CHANNEL_BACKUP_VERSION: u8 = 2;
+
+const BACKUP_KEY: &[u8] = b"channel backup";
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelBackup {
+    pub channel_address: AccountAddress,
+    /// all participants, including the owner.
+    pub participants: BTreeSet<AccountAddress>,
+    pub participant_keys: BTreeMap<AccountAddress, Ed25519PublicKey>,
+    /// witness of the latest applied state.
+    pub witness: Witness,
+}
+
+impl ChannelBackup {
+    pub fn new(
+        channel_address: AccountAddress,
+        participants: BTreeSet<AccountAddress>,
+        participant_keys: BTreeMap<AccountAddress, Ed25519PublicKey>,
+        witness: Witness,
+    ) -> Self {
+        Self {
+            channel_address,
+            participants,
+            participant_keys,
+            witness,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StaticChannelBackup {
+    pub account: AccountAddress,
+    pub channels: Vec<ChannelBackup>,
+}
+
+impl StaticChannelBackup {
+    pub fn new(account: AccountAddress, channels: Vec<ChannelBackup>) -> Self {
+        Self { account, channels }
+    }
+
+    /// Encrypt the backup by `key`, which is derived by `backup_key`.
+    pub fn encrypt(&self, key: &[u8; KEY_SIZE]) -> Result<Vec<u8>> {
+        let data = lcs::to_bytes(self)?;
+        let mut bytes = vec![CHANNEL_BACKUP_VERSION];
+        bytes.extend(encrypt(key, &data)?);
+        Ok(bytes)
+    }
+
+    pub fn decrypt(bytes: &[u8], key: &[u8; KEY_SIZE]) -> Result<Self> {
+        ensure!(!bytes.is_empty(), "channel backup is empty");
+        ensure!(
+            bytes[0] == CHANNEL_BACKUP_VERSION,
+            "unsupported channel backup version {}",
+            bytes[0]
+        );
+        let data = decrypt(key, &bytes[1..])?;
+        Ok(lcs::from_bytes(&data)?)
+    }
+}
+
+/// Key to encrypt the backup of the account of `private_key`.
+pub fn backup_key(private_key: &Ed25519PrivateKey) -> [u8; KEY_SIZE] {
+    hmac(BACKUP_KEY, &[&private_key.to_bytes()[..]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::{test_utils::KeyPair, Uniform};
+    use rand::prelude::*;
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let mut rng: StdRng = SeedableRng::seed_from_u64(1);
+        let keypair: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let other: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let account = AccountAddress::from_public_key(&keypair.public_key);
+        let participant = AccountAddress::from_public_key(&other.public_key);
+
+        let mut participant_keys = BTreeMap::new();
+        participant_keys.insert(account, keypair.public_key.clone());
+        participant_keys.insert(participant, other.public_key.clone());
+        let channel = ChannelBackup::new(
+            AccountAddress::random(),
+            participant_keys.keys().cloned().collect(),
+            participant_keys,
+            Witness::default(),
+        );
+        let backup = StaticChannelBackup::new(account, vec![channel]);
+
+        let key = backup_key(&keypair.private_key);
+        let bytes = backup.encrypt(&key).unwrap();
+        assert_eq!(CHANNEL_BACKUP_VERSION, bytes[0]);
+        assert_ne!(bytes, backup.encrypt(&key).unwrap());
+        assert_eq!(backup, StaticChannelBackup::decrypt(&bytes, &key).unwrap());
+        assert!(StaticChannelBackup::decrypt(&bytes, &backup_key(&other.private_key)).is_err());
+    }
+}
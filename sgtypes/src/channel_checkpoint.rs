@@ -0,0 +1,225 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checkpoint of offchain channel state signed by all participants.
+//!
+//! Once a checkpoint is signed, txns and write sets before it can be pruned,
+//! proofs of txns up to it stay verifiable against its accumulator root.
+
+use crate::impl_hash;
+use anyhow::{ensure, format_err, Error, Result};
+use libra_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    hash::CryptoHash,
+    HashValue, SigningKey, VerifyingKey,
+};
+use libra_crypto_derive::CryptoHasher;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, CryptoHasher)]
+pub struct ChannelCheckpoint {
+    pub channel_address: AccountAddress,
+    /// channel sequence number of the last txn covered by the checkpoint.
+    pub version: u64,
+    /// root hash of the channel write set at `version`.
+    pub state_root: HashValue,
+    /// root hash of the channel txn accumulator at `version`.
+    pub accumulator_root: HashValue,
+}
+impl_hash!(ChannelCheckpoint, ChannelCheckpointHasher);
+
+impl ChannelCheckpoint {
+    pub fn new(
+        channel_address: AccountAddress,
+        version: u64,
+        state_root: HashValue,
+        accumulator_root: HashValue,
+    ) -> Self {
+        Self {
+            channel_address,
+            version,
+            state_root,
+            accumulator_root,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::ChannelCheckpoint> for ChannelCheckpoint {
+    type Error = Error;
+
+    fn try_from(proto: crate::proto::sgtypes::ChannelCheckpoint) -> Result<Self> {
+        Ok(Self::new(
+            AccountAddress::try_from(proto.channel_address)?,
+            proto.version,
+            HashValue::from_slice(&proto.state_root)?,
+            HashValue::from_slice(&proto.accumulator_root)?,
+        ))
+    }
+}
+
+impl From<ChannelCheckpoint> for crate::proto::sgtypes::ChannelCheckpoint {
+    fn from(checkpoint: ChannelCheckpoint) -> Self {
+        Self {
+            channel_address: checkpoint.channel_address.to_vec(),
+            version: checkpoint.version,
+            state_root: checkpoint.state_root.to_vec(),
+            accumulator_root: checkpoint.accumulator_root.to_vec(),
+        }
+    }
+}
+
+/// Checkpoint with signatures of participants, it's valid once all of them signed.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedChannelCheckpoint {
+    pub checkpoint: ChannelCheckpoint,
+    pub signatures: BTreeMap<AccountAddress, Ed25519Signature>,
+}
+
+impl SignedChannelCheckpoint {
+    pub fn new(checkpoint: ChannelCheckpoint) -> Self {
+        Self {
+            checkpoint,
+            signatures: BTreeMap::new(),
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.checkpoint.version
+    }
+
+    /// Add signature of `signer` to the checkpoint.
+    pub fn sign(&mut self, signer: AccountAddress, private_key: &Ed25519PrivateKey) {
+        let signature = private_key.sign_message(&CryptoHash::hash(&self.checkpoint));
+        self.add_signature(signer, signature);
+    }
+
+    /// Add `signature` of `signer` made on the checkpoint elsewhere.
+    pub fn add_signature(&mut self, signer: AccountAddress, signature: Ed25519Signature) {
+        self.signatures.insert(signer, signature);
+    }
+
+    /// Check the signature of `signer`.
+    pub fn verify_signer(
+        &self,
+        signer: AccountAddress,
+        public_key: &Ed25519PublicKey,
+    ) -> Result<()> {
+        let signature = self
+            .signatures
+            .get(&signer)
+            .ok_or_else(|| format_err!("checkpoint is not signed by {}", signer))?;
+        public_key.verify_signature(&CryptoHash::hash(&self.checkpoint), signature)?;
+        Ok(())
+    }
+
+    /// Check the checkpoint is signed by all `participant_keys`, and nobody else.
+    pub fn verify(
+        &self,
+        participant_keys: &BTreeMap<AccountAddress, Ed25519PublicKey>,
+    ) -> Result<()> {
+        ensure!(
+            self.signatures.len() == participant_keys.len(),
+            "checkpoint should be signed by {} participants, got {}",
+            participant_keys.len(),
+            self.signatures.len()
+        );
+        for (signer, public_key) in participant_keys {
+            self.verify_signer(*signer, public_key)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::SignedChannelCheckpoint> for SignedChannelCheckpoint {
+    type Error = Error;
+
+    fn try_from(proto: crate::proto::sgtypes::SignedChannelCheckpoint) -> Result<Self> {
+        let checkpoint = proto
+            .checkpoint
+            .ok_or_else(|| format_err!("Missing checkpoint"))?;
+        let mut signatures = BTreeMap::new();
+        for s in proto.signatures {
+            signatures.insert(
+                AccountAddress::try_from(s.signer)?,
+                Ed25519Signature::try_from(s.signature.as_slice())?,
+            );
+        }
+        Ok(Self {
+            checkpoint: ChannelCheckpoint::try_from(checkpoint)?,
+            signatures,
+        })
+    }
+}
+
+impl From<SignedChannelCheckpoint> for crate::proto::sgtypes::SignedChannelCheckpoint {
+    fn from(signed: SignedChannelCheckpoint) -> Self {
+        Self {
+            checkpoint: Some(signed.checkpoint.into()),
+            signatures: signed
+                .signatures
+                .into_iter()
+                .map(
+                    |(signer, signature)| crate::proto::sgtypes::ChannelCheckpointSignature {
+                        signer: signer.to_vec(),
+                        signature: signature.to_bytes().to_vec(),
+                    },
+                )
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::{test_utils::KeyPair, Uniform};
+    use rand::prelude::*;
+
+    #[test]
+    fn test_sign_checkpoint() -> Result<()> {
+        let mut rng: StdRng = SeedableRng::from_seed([0; 32]);
+        let keypairs: Vec<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> = (0..2)
+            .map(|_| KeyPair::generate_for_testing(&mut rng))
+            .collect();
+        let keys = keypairs
+            .iter()
+            .map(|k| {
+                (
+                    AccountAddress::from_public_key(&k.public_key),
+                    k.public_key.clone(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let mut signed = SignedChannelCheckpoint::new(ChannelCheckpoint::new(
+            AccountAddress::random(),
+            10,
+            HashValue::random(),
+            HashValue::random(),
+        ));
+        let first = &keypairs[0];
+        signed.sign(
+            AccountAddress::from_public_key(&first.public_key),
+            &first.private_key,
+        );
+        assert!(signed.verify(&keys).is_err());
+
+        let second = &keypairs[1];
+        signed.sign(
+            AccountAddress::from_public_key(&second.public_key),
+            &second.private_key,
+        );
+        signed.verify(&keys)?;
+
+        let proto: crate::proto::sgtypes::SignedChannelCheckpoint = signed.clone().into();
+        assert_eq!(signed, SignedChannelCheckpoint::try_from(proto)?);
+
+        let mut tampered = signed;
+        tampered.checkpoint.version += 1;
+        assert!(tampered.verify(&keys).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! State of a channel exchanged by participants when they reconnect.
+//!
+//! A connection may drop in the middle of a proposal, leaving participants with different
+//! channel sequence numbers, or one of them without the signatures it needs to apply the
+//! pending txn. Each side reports what it has, and `reconcile` tells the other how to catch up.
+
+use crate::channel_transaction_sigs::ChannelTransactionSigs;
+use anyhow::{Error, Result};
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use std::convert::TryFrom;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ChannelReestablish {
+    pub channel_address: AccountAddress,
+    /// number of txns applied in the channel, which is also the sequence number of the pending one.
+    pub channel_sequence_number: u64,
+    /// hash of the pending proposal with signatures collected of it.
+    pub pending_txn: Option<(HashValue, Vec<ChannelTransactionSigs>)>,
+    /// hash of the latest txn applied offchain with its signatures,
+    /// a participant who missed some of them can still apply it.
+    pub latest_txn: Option<(HashValue, Vec<ChannelTransactionSigs>)>,
+}
+
+/// What a participant should do to catch up with the state reported by its peer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReestablishAction {
+    /// nothing to do, or it's the peer who needs to catch up.
+    None,
+    /// collect signatures of the pending txn, and apply it once all participants signed.
+    CollectSignatures(Vec<ChannelTransactionSigs>),
+    /// the peer already applied the pending txn, collect its signatures and apply it too.
+    CatchUp(Vec<ChannelTransactionSigs>),
+    /// the peer doesn't have the pending txn, cancel it.
+    Rollback(HashValue),
+    /// states diverge too far to be fixed offchain, the channel should be resolved onchain.
+    Unrecoverable(String),
+}
+
+impl ChannelReestablish {
+    pub fn new(
+        channel_address: AccountAddress,
+        channel_sequence_number: u64,
+        pending_txn: Option<(HashValue, Vec<ChannelTransactionSigs>)>,
+        latest_txn: Option<(HashValue, Vec<ChannelTransactionSigs>)>,
+    ) -> Self {
+        Self {
+            channel_address,
+            channel_sequence_number,
+            pending_txn,
+            latest_txn,
+        }
+    }
+
+    /// Decide how to catch up with `remote` reported by the peer, `self` is the local state.
+    pub fn reconcile(&self, remote: &ChannelReestablish) -> ReestablishAction {
+        let local_seq = self.channel_sequence_number;
+        let remote_seq = remote.channel_sequence_number;
+        if local_seq > remote_seq {
+            return ReestablishAction::None;
+        }
+        if local_seq + 1 < remote_seq {
+            return ReestablishAction::Unrecoverable(format!(
+                "peer is ahead by {} txns",
+                remote_seq - local_seq
+            ));
+        }
+        let (pending_id, local_sigs) = match &self.pending_txn {
+            Some((id, sigs)) => (id, sigs),
+            None if local_seq == remote_seq => return ReestablishAction::None,
+            None => {
+                return match &remote.latest_txn {
+                    Some(_) => ReestablishAction::Unrecoverable(
+                        "peer applied a txn which is not pending locally".to_string(),
+                    ),
+                    // it's applied onchain, and will be synced from chain.
+                    None => ReestablishAction::None,
+                };
+            }
+        };
+        let missing = |remote_sigs: &Vec<ChannelTransactionSigs>| {
+            remote_sigs
+                .iter()
+                .filter(|s| !local_sigs.iter().any(|l| l.address == s.address))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        if local_seq == remote_seq {
+            match &remote.pending_txn {
+                Some((id, remote_sigs)) if id == pending_id => {
+                    let missing = missing(remote_sigs);
+                    if missing.is_empty() {
+                        ReestablishAction::None
+                    } else {
+                        ReestablishAction::CollectSignatures(missing)
+                    }
+                }
+                _ => ReestablishAction::Rollback(*pending_id),
+            }
+        } else {
+            match &remote.latest_txn {
+                Some((id, remote_sigs)) if id == pending_id => {
+                    ReestablishAction::CatchUp(missing(remote_sigs))
+                }
+                Some(_) => ReestablishAction::Unrecoverable(
+                    "peer applied a txn different from the pending one".to_string(),
+                ),
+                None => ReestablishAction::None,
+            }
+        }
+    }
+}
+
+fn txn_from_proto(
+    id: Vec<u8>,
+    sigs: Vec<crate::proto::sgtypes::ChannelTransactionSigs>,
+) -> Result<Option<(HashValue, Vec<ChannelTransactionSigs>)>> {
+    if id.is_empty() {
+        return Ok(None);
+    }
+    let sigs = sigs
+        .into_iter()
+        .map(ChannelTransactionSigs::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some((HashValue::from_slice(&id)?, sigs)))
+}
+
+fn txn_into_proto(
+    txn: Option<(HashValue, Vec<ChannelTransactionSigs>)>,
+) -> (Vec<u8>, Vec<crate::proto::sgtypes::ChannelTransactionSigs>) {
+    match txn {
+        Some((id, sigs)) => (id.to_vec(), sigs.into_iter().map(Into::into).collect()),
+        None => (vec![], vec![]),
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::ChannelReestablish> for ChannelReestablish {
+    type Error = Error;
+
+    fn try_from(proto: crate::proto::sgtypes::ChannelReestablish) -> Result<Self> {
+        Ok(Self::new(
+            AccountAddress::try_from(proto.channel_address)?,
+            proto.channel_sequence_number,
+            txn_from_proto(proto.pending_txn_id, proto.pending_signatures)?,
+            txn_from_proto(proto.latest_txn_id, proto.latest_signatures)?,
+        ))
+    }
+}
+
+impl From<ChannelReestablish> for crate::proto::sgtypes::ChannelReestablish {
+    fn from(reestablish: ChannelReestablish) -> Self {
+        let (pending_txn_id, pending_signatures) = txn_into_proto(reestablish.pending_txn);
+        let (latest_txn_id, latest_signatures) = txn_into_proto(reestablish.latest_txn);
+        Self {
+            channel_address: reestablish.channel_address.to_vec(),
+            channel_sequence_number: reestablish.channel_sequence_number,
+            pending_txn_id,
+            pending_signatures,
+            latest_txn_id,
+            latest_signatures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::{
+        ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+        test_utils::KeyPair,
+        SigningKey,
+    };
+    use rand::prelude::*;
+
+    fn sigs_of(keypair: &KeyPair<Ed25519PrivateKey, Ed25519PublicKey>) -> ChannelTransactionSigs {
+        let witness_data_hash = HashValue::random();
+        ChannelTransactionSigs::new(
+            AccountAddress::from_public_key(&keypair.public_key),
+            keypair.public_key.clone(),
+            keypair.private_key.sign_message(&HashValue::random()),
+            witness_data_hash,
+            keypair.private_key.sign_message(&witness_data_hash),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_reconcile_channel_reestablish() -> Result<()> {
+        let mut rng: StdRng = SeedableRng::from_seed([0; 32]);
+        let sender: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let receiver: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let channel_address = AccountAddress::random();
+        let txn_id = HashValue::random();
+        let sender_sigs = sigs_of(&sender);
+        let receiver_sigs = sigs_of(&receiver);
+
+        // the response of receiver is lost after it applied the txn.
+        let local = ChannelReestablish::new(
+            channel_address,
+            5,
+            Some((txn_id, vec![sender_sigs.clone()])),
+            None,
+        );
+        let remote = ChannelReestablish::new(
+            channel_address,
+            6,
+            None,
+            Some((txn_id, vec![sender_sigs.clone(), receiver_sigs.clone()])),
+        );
+        assert_eq!(
+            ReestablishAction::CatchUp(vec![receiver_sigs.clone()]),
+            local.reconcile(&remote)
+        );
+        assert_eq!(ReestablishAction::None, remote.reconcile(&local));
+
+        // both are waiting for the txn to be agreed.
+        let remote = ChannelReestablish::new(
+            channel_address,
+            5,
+            Some((txn_id, vec![receiver_sigs.clone()])),
+            None,
+        );
+        assert_eq!(
+            ReestablishAction::CollectSignatures(vec![receiver_sigs.clone()]),
+            local.reconcile(&remote)
+        );
+
+        // the proposal never reached the receiver.
+        let remote = ChannelReestablish::new(channel_address, 5, None, None);
+        assert_eq!(
+            ReestablishAction::Rollback(txn_id),
+            local.reconcile(&remote)
+        );
+        assert_eq!(ReestablishAction::None, remote.reconcile(&local));
+
+        let remote = ChannelReestablish::new(channel_address, 7, None, None);
+        match local.reconcile(&remote) {
+            ReestablishAction::Unrecoverable(_) => {}
+            action => panic!("expect unrecoverable, got {:?}", action),
+        }
+
+        let proto: crate::proto::sgtypes::ChannelReestablish = local.clone().into();
+        assert_eq!(local, ChannelReestablish::try_from(proto)?);
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@
 
 use crate::channel_transaction_sigs::ChannelTransactionSigs;
 use crate::impl_hash;
-use anyhow::{Error, Result};
+use anyhow::{format_err, Error, Result};
 use bytes::IntoBuf;
 use libra_crypto::{
     ed25519::{Ed25519PublicKey, Ed25519Signature},
@@ -176,6 +176,11 @@ pub enum ChannelOp {
         function_name: String,
     },
     Close,
+    /// ops executed in order under one channel sequence number,
+    /// the channel txn itself carries no args.
+    Batch {
+        ops: Vec<BatchedChannelOp>,
+    },
 }
 
 impl ChannelOp {
@@ -186,6 +191,13 @@ impl ChannelOp {
         }
     }
 
+    pub fn is_batch(&self) -> bool {
+        match self {
+            ChannelOp::Batch { .. } => true,
+            _ => false,
+        }
+    }
+
     pub fn to_string(&self) -> String {
         format!("{}", self)
     }
@@ -205,6 +217,14 @@ impl Display for ChannelOp {
                 function_name,
             } => write!(f, "{}.{}.{}", module_address, module_name, function_name),
             ChannelOp::Close => write!(f, "close"),
+            ChannelOp::Batch { ops } => write!(
+                f,
+                "batch[{}]",
+                ops.iter()
+                    .map(|op| op.operator.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
@@ -232,6 +252,13 @@ impl TryFrom<crate::proto::sgtypes::ChannelOp> for ChannelOp {
                 function_name: proto.function_name,
             },
             ProtoChannelOpType::Close => ChannelOp::Close,
+            ProtoChannelOpType::Batch => ChannelOp::Batch {
+                ops: proto
+                    .batch
+                    .into_iter()
+                    .map(BatchedChannelOp::try_from)
+                    .collect::<Result<Vec<_>>>()?,
+            },
         };
         Ok(ret)
     }
@@ -267,11 +294,55 @@ impl From<ChannelOp> for crate::proto::sgtypes::ChannelOp {
             ChannelOp::Close => {
                 channel_op.set_op_type(ProtoChannelOpType::Close);
             }
+            ChannelOp::Batch { ops } => {
+                channel_op.set_op_type(ProtoChannelOpType::Batch);
+                channel_op.batch = ops.into_iter().map(Into::into).collect();
+            }
         };
         channel_op
     }
 }
 
+/// One op of a `ChannelOp::Batch`, with its own args.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct BatchedChannelOp {
+    pub operator: ChannelOp,
+    pub args: Vec<TransactionArgument>,
+}
+
+impl BatchedChannelOp {
+    pub fn new(operator: ChannelOp, args: Vec<TransactionArgument>) -> Self {
+        Self { operator, args }
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::BatchedChannelOp> for BatchedChannelOp {
+    type Error = Error;
+
+    fn try_from(proto: crate::proto::sgtypes::BatchedChannelOp) -> Result<Self> {
+        let operator = ChannelOp::try_from(
+            proto
+                .operator
+                .ok_or_else(|| format_err!("batched op should have operator"))?,
+        )?;
+        let args = proto
+            .args
+            .into_iter()
+            .map(TransactionArgument::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { operator, args })
+    }
+}
+
+impl From<BatchedChannelOp> for crate::proto::sgtypes::BatchedChannelOp {
+    fn from(op: BatchedChannelOp) -> Self {
+        Self {
+            operator: Some(op.operator.into()),
+            args: op.args.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ChannelTransactionProposal {
     pub channel_txn: ChannelTransaction,
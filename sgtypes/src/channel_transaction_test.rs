@@ -13,8 +13,12 @@ use libra_types::channel::Witness;
 use libra_types::identifier::Identifier;
 use libra_types::language_storage::ModuleId;
 use libra_types::transaction::{ChannelTransactionPayloadBody, ScriptAction};
-use libra_types::{account_address::AccountAddress, transaction::Script};
+use libra_types::{
+    account_address::AccountAddress,
+    transaction::{Script, TransactionArgument},
+};
 use rand::prelude::*;
+use std::convert::TryFrom;
 use std::time::Duration;
 
 //TODO(jole) use Arbitrary
@@ -79,3 +83,43 @@ fn request_roundtrip_canonical_serialization() {
         assert_eq!(request, output);
     }
 }
+
+#[test]
+fn batch_op_proto_roundtrip() {
+    let transfer = ChannelOp::Action {
+        module_address: AccountAddress::default(),
+        module_name: "ChannelScript".to_string(),
+        function_name: "transfer".to_string(),
+    };
+    let op = ChannelOp::Batch {
+        ops: vec![
+            BatchedChannelOp::new(
+                transfer,
+                vec![
+                    TransactionArgument::Address(AccountAddress::random()),
+                    TransactionArgument::U64(100),
+                ],
+            ),
+            BatchedChannelOp::new(
+                ChannelOp::Execute {
+                    package_name: "game".to_string(),
+                    script_name: "move".to_string(),
+                },
+                vec![TransactionArgument::U64(3)],
+            ),
+        ],
+    };
+    assert!(op.is_batch());
+    assert_eq!(
+        format!(
+            "batch[{}.ChannelScript.transfer, game.move]",
+            AccountAddress::default()
+        ),
+        op.to_string()
+    );
+
+    let proto: crate::proto::sgtypes::ChannelOp = op.clone().into();
+    assert_eq!(op, ChannelOp::try_from(proto).unwrap());
+    let bytes = lcs::to_bytes(&op).unwrap();
+    assert_eq!(op, lcs::from_bytes::<ChannelOp>(bytes.as_slice()).unwrap());
+}
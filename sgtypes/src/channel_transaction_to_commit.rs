@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::applied_channel_txn::AppliedChannelTxn;
+use crate::channel_transaction::ChannelOp;
 
 use libra_types::contract_event::ContractEvent;
+use libra_types::transaction::TransactionArgument;
 
 use libra_types::vm_error::StatusCode;
 use libra_types::write_set::WriteSet;
@@ -17,6 +19,8 @@ pub struct ChannelTransactionToCommit {
     pub events: Vec<ContractEvent>,
     pub major_status: StatusCode,
     pub gas_used: u64,
+    /// operator and args of a travel txn, which the onchain txn doesn't keep.
+    pub travel_op: Option<(ChannelOp, Vec<TransactionArgument>)>,
 }
 
 impl ChannelTransactionToCommit {
@@ -26,6 +30,7 @@ impl ChannelTransactionToCommit {
         events: Vec<ContractEvent>,
         major_status: StatusCode,
         gas_used: u64,
+        travel_op: Option<(ChannelOp, Vec<TransactionArgument>)>,
     ) -> Self {
         Self {
             signed_channel_txn,
@@ -33,6 +38,7 @@ impl ChannelTransactionToCommit {
             events,
             major_status,
             gas_used,
+            travel_op,
         }
     }
     pub fn transaction(&self) -> &AppliedChannelTxn {
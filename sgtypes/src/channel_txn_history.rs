@@ -0,0 +1,184 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! History of applied channel txns, for reconciliation.
+
+use crate::channel_transaction::ChannelOp;
+use libra_types::{account_address::AccountAddress, transaction::TransactionArgument};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Range and filters of a channel txn history query.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelTxnQuery {
+    /// channel sequence number to start scanning from, inclusive.
+    pub start: u64,
+    /// max number of txns in a page, 0 means no limit.
+    pub limit: u64,
+    /// only txns of the operator, or batches containing it.
+    /// solo txns of other participants have no known operator, so they never match.
+    pub operator: Option<ChannelOp>,
+    pub proposer: Option<AccountAddress>,
+    /// only txns applied in `[start_time, end_time)`, in unix seconds.
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+}
+
+impl ChannelTxnQuery {
+    pub fn new(start: u64, limit: u64) -> Self {
+        Self {
+            start,
+            limit,
+            ..Default::default()
+        }
+    }
+
+    pub fn operator(mut self, operator: ChannelOp) -> Self {
+        self.operator = Some(operator);
+        self
+    }
+
+    pub fn proposer(mut self, proposer: AccountAddress) -> Self {
+        self.proposer = Some(proposer);
+        self
+    }
+
+    pub fn time_window(mut self, start_time: Option<u64>, end_time: Option<u64>) -> Self {
+        self.start_time = start_time;
+        self.end_time = end_time;
+        self
+    }
+
+    pub fn matches(&self, record: &ChannelTxnRecord) -> bool {
+        if let Some(operator) = &self.operator {
+            let matched = record.operator.as_ref() == Some(operator)
+                || match &record.operator {
+                    Some(ChannelOp::Batch { ops }) => ops.iter().any(|op| &op.operator == operator),
+                    _ => false,
+                };
+            if !matched {
+                return false;
+            }
+        }
+        if let Some(proposer) = &self.proposer {
+            if &record.proposer != proposer {
+                return false;
+            }
+        }
+        if let Some(start_time) = self.start_time {
+            if record.applied_time < start_time {
+                return false;
+            }
+        }
+        if let Some(end_time) = self.end_time {
+            if record.applied_time >= end_time {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// An applied channel txn, with balance changes of participants it made.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelTxnRecord {
+    pub channel_sequence_number: u64,
+    pub proposer: AccountAddress,
+    pub travel: bool,
+    /// None if the txn is a solo txn of other participants.
+    pub operator: Option<ChannelOp>,
+    pub args: Vec<TransactionArgument>,
+    /// in unix seconds.
+    pub expiration_time: u64,
+    /// when the txn is applied into local store, in unix seconds.
+    /// 0 if it's applied before the time is kept.
+    pub applied_time: u64,
+    pub gas_used: u64,
+    /// channel balance changes of participants, unchanged ones are omitted.
+    pub balance_deltas: BTreeMap<AccountAddress, i64>,
+}
+
+/// What the channel knows about an applied txn besides the txn itself.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelTxnMeta {
+    /// in unix seconds.
+    pub applied_time: u64,
+    /// operator and args of a travel txn, which the onchain txn doesn't keep.
+    /// None for offchain txns, and solo txns of other participants.
+    pub travel_op: Option<(ChannelOp, Vec<TransactionArgument>)>,
+}
+
+impl ChannelTxnMeta {
+    pub fn new(
+        applied_time: u64,
+        travel_op: Option<(ChannelOp, Vec<TransactionArgument>)>,
+    ) -> Self {
+        Self {
+            applied_time,
+            travel_op,
+        }
+    }
+}
+
+/// A page of channel txn history.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelTxnPage {
+    pub txns: Vec<ChannelTxnRecord>,
+    /// where the next page starts, None if there are no more txns.
+    pub next_start: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel_transaction::BatchedChannelOp;
+
+    fn record(operator: Option<ChannelOp>, proposer: AccountAddress) -> ChannelTxnRecord {
+        ChannelTxnRecord {
+            channel_sequence_number: 1,
+            proposer,
+            travel: operator.is_none(),
+            operator,
+            args: vec![],
+            expiration_time: 100,
+            applied_time: 200,
+            gas_used: 0,
+            balance_deltas: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let proposer = AccountAddress::random();
+        let transfer = ChannelOp::Action {
+            module_address: AccountAddress::default(),
+            module_name: "ChannelScript".to_string(),
+            function_name: "transfer".to_string(),
+        };
+        let offchain = record(Some(transfer.clone()), proposer);
+        let travel = record(None, proposer);
+
+        let query = ChannelTxnQuery::new(0, 10);
+        assert!(query.matches(&offchain) && query.matches(&travel));
+
+        let batch = ChannelOp::Batch {
+            ops: vec![BatchedChannelOp::new(transfer.clone(), vec![])],
+        };
+        let query = ChannelTxnQuery::new(0, 10).operator(transfer);
+        assert!(query.matches(&offchain));
+        assert!(!query.matches(&travel));
+        assert!(!query.matches(&record(Some(ChannelOp::Close), proposer)));
+        assert!(query.matches(&record(Some(batch), proposer)));
+
+        let query = ChannelTxnQuery::new(0, 10).proposer(AccountAddress::random());
+        assert!(!query.matches(&offchain));
+
+        let query = ChannelTxnQuery::new(0, 10).time_window(Some(200), Some(201));
+        assert!(query.matches(&offchain));
+        let query = ChannelTxnQuery::new(0, 10).time_window(None, Some(200));
+        assert!(!query.matches(&offchain));
+        // expiration time is not what the window filters on.
+        let query = ChannelTxnQuery::new(0, 10).time_window(Some(100), Some(101));
+        assert!(!query.matches(&offchain));
+    }
+}
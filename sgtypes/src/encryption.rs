@@ -0,0 +1,62 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Authenticated encryption of data kept outside the wallet store, by ChaCha20-Poly1305.
+//!
+//! A random nonce is drawn for every encryption and prefixes the cipher text,
+//! so a key can encrypt many times without reusing the key stream.
+
+use anyhow::{ensure, format_err, Result};
+use chacha20poly1305::{
+    aead::{Aead, NewAead},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use rand::RngCore;
+
+pub const KEY_SIZE: usize = 32;
+
+const NONCE_SIZE: usize = 12;
+
+/// Encrypt `data` by `key`, the output is nonce followed by cipher text and tag.
+pub fn encrypt(key: &[u8; KEY_SIZE], data: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let sealed = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(&nonce), data)
+        .map_err(|_| format_err!("fail to encrypt data"))?;
+    let mut bytes = nonce.to_vec();
+    bytes.extend(sealed);
+    Ok(bytes)
+}
+
+pub fn decrypt(key: &[u8; KEY_SIZE], bytes: &[u8]) -> Result<Vec<u8>> {
+    ensure!(bytes.len() >= NONCE_SIZE, "encrypted data is too short");
+    let (nonce, sealed) = bytes.split_at(NONCE_SIZE);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(Nonce::from_slice(nonce), sealed)
+        .map_err(|_| format_err!("fail to decrypt data, wrong key or data is corrupted"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let key = [1u8; KEY_SIZE];
+        let data = b"channel backup".to_vec();
+        let first = encrypt(&key, &data).unwrap();
+        let second = encrypt(&key, &data).unwrap();
+        // nonce is not reused, neither is the key stream.
+        assert_ne!(first[..NONCE_SIZE], second[..NONCE_SIZE]);
+        assert_ne!(first[NONCE_SIZE..], second[NONCE_SIZE..]);
+        assert_eq!(data, decrypt(&key, &first).unwrap());
+        assert_eq!(data, decrypt(&key, &second).unwrap());
+
+        assert!(decrypt(&[2u8; KEY_SIZE], &first).is_err());
+        let mut tampered = first.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&key, &tampered).is_err());
+        assert!(decrypt(&key, &first[..NONCE_SIZE - 1]).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{format_err, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// fee rate is in parts per million of the forwarded amount.
+pub const FEE_RATE_DENOMINATOR: u64 = 1_000_000;
+/// blocks a hop keeps between the timeout of incoming htlc and the forwarded one by default.
+pub const DEFAULT_TIMELOCK_DELTA: u64 = 10;
+
+/// What a node charges to forward htlc over one of its channels.
+/// Channels forward for free unless the operator sets a policy.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub struct FeePolicy {
+    pub base_fee: u64,
+    /// proportional fee, in parts per million.
+    pub fee_rate: u64,
+    /// the forwarded htlc times out this many blocks earlier than the incoming one,
+    /// so that the hop has time to recall it before the incoming one is recalled.
+    pub timelock_delta: u64,
+}
+
+impl FeePolicy {
+    pub fn new(base_fee: u64, fee_rate: u64, timelock_delta: u64) -> Self {
+        Self {
+            base_fee,
+            fee_rate,
+            timelock_delta,
+        }
+    }
+
+    /// Fee to forward `amount`, saturated at u64::MAX.
+    pub fn fee(&self, amount: u64) -> u64 {
+        let proportional = amount as u128 * self.fee_rate as u128 / FEE_RATE_DENOMINATOR as u128;
+        let fee = self.base_fee as u128 + proportional;
+        if fee > std::u64::MAX as u128 {
+            std::u64::MAX
+        } else {
+            fee as u64
+        }
+    }
+
+    /// A conservative max amount can be forwarded out of `incoming`, after the fee is paid.
+    pub fn max_forward(&self, incoming: u64) -> u64 {
+        if incoming <= self.base_fee {
+            return 0;
+        }
+        let amount = (incoming - self.base_fee) as u128 * FEE_RATE_DENOMINATOR as u128
+            / (FEE_RATE_DENOMINATOR as u128 + self.fee_rate as u128);
+        amount as u64
+    }
+}
+
+impl Default for FeePolicy {
+    fn default() -> Self {
+        Self::new(0, 0, DEFAULT_TIMELOCK_DELTA)
+    }
+}
+
+/// Amount and timeout of the htlc every hop of a route receives, so that the last hop
+/// receives `amount` with `timeout`. `policies` are of the intermediate hops in route order,
+/// each of them keeps its fee and timelock delta out of what it receives.
+pub fn route_amounts(amount: u64, timeout: u64, policies: &[FeePolicy]) -> Result<Vec<(u64, u64)>> {
+    let mut result = vec![(amount, timeout)];
+    let (mut amount, mut timeout) = (amount, timeout);
+    for policy in policies.iter().rev() {
+        amount = amount
+            .checked_add(policy.fee(amount))
+            .ok_or_else(|| format_err!("route fee overflow"))?;
+        timeout = timeout
+            .checked_add(policy.timelock_delta)
+            .ok_or_else(|| format_err!("route timeout overflow"))?;
+        result.push((amount, timeout));
+    }
+    result.reverse();
+    Ok(result)
+}
+
+/// Total fee paid to intermediate hops for delivering `amount`.
+pub fn route_fee(amount: u64, policies: &[FeePolicy]) -> Result<u64> {
+    let amounts = route_amounts(amount, 0, policies)?;
+    Ok(amounts[0].0 - amount)
+}
+
+impl TryFrom<crate::proto::sgtypes::FeePolicy> for FeePolicy {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::FeePolicy) -> Result<Self> {
+        Ok(Self::new(
+            value.base_fee,
+            value.fee_rate,
+            value.timelock_delta,
+        ))
+    }
+}
+
+impl From<FeePolicy> for crate::proto::sgtypes::FeePolicy {
+    fn from(value: FeePolicy) -> Self {
+        Self {
+            base_fee: value.base_fee,
+            fee_rate: value.fee_rate,
+            timelock_delta: value.timelock_delta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee() {
+        let policy = FeePolicy::new(10, 1_000, 5);
+        assert_eq!(10, policy.fee(0));
+        assert_eq!(11, policy.fee(1_000));
+        assert_eq!(
+            std::u64::MAX,
+            FeePolicy::new(std::u64::MAX, 1, 0).fee(1_000_000)
+        );
+
+        for incoming in [0u64, 10, 11, 1_011, 123_456_789].iter() {
+            let amount = policy.max_forward(*incoming);
+            assert!(amount == 0 || amount + policy.fee(amount) <= *incoming);
+        }
+        assert_eq!(1_000, policy.max_forward(1_011));
+    }
+
+    #[test]
+    fn test_route_amounts() {
+        let policies = vec![FeePolicy::new(10, 1_000, 5), FeePolicy::new(1, 0, 20)];
+        let amounts = route_amounts(1_000, 100, &policies).unwrap();
+        // the first hop pays the second one 1, and keeps 10 + 1001 / 1000 for itself.
+        assert_eq!(vec![(1_012, 125), (1_001, 120), (1_000, 100)], amounts);
+        assert_eq!(12, route_fee(1_000, &policies).unwrap());
+        assert_eq!(vec![(1_000, 100)], route_amounts(1_000, 100, &[]).unwrap());
+        assert!(route_amounts(std::u64::MAX, 100, &policies).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::fee_policy::FeePolicy;
+use crate::impl_hash;
+use anyhow::{ensure, format_err, Error, Result};
+use libra_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    hash::CryptoHash,
+    HashValue, SigningKey, VerifyingKey,
+};
+use libra_crypto_derive::CryptoHasher;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::convert::{TryFrom, TryInto};
+
+/// Announce that a channel exists between `participants`.
+/// Receivers check it against the channel resource on chain before adding it to their graph.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelAnnouncement {
+    pub channel_address: AccountAddress,
+    pub participants: Vec<AccountAddress>,
+    pub timestamp: u64,
+}
+
+impl ChannelAnnouncement {
+    pub fn new(
+        channel_address: AccountAddress,
+        participants: Vec<AccountAddress>,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            channel_address,
+            participants,
+            timestamp,
+        }
+    }
+
+    /// channel address is derived from its participants.
+    pub fn check_channel_address(&self) -> Result<()> {
+        let participants = self.participants.iter().cloned().collect::<BTreeSet<_>>();
+        ensure!(
+            participants.len() == self.participants.len(),
+            "duplicated participant in channel announcement"
+        );
+        ensure!(
+            AccountAddress::from(&participants) == self.channel_address,
+            "channel address {} does not match participants",
+            self.channel_address
+        );
+        Ok(())
+    }
+}
+
+/// Direction of a channel from `local_addr` to `remote_addr`, announced by `local_addr`.
+/// `capacity` is the total balance of the channel.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelUpdate {
+    pub channel_address: AccountAddress,
+    pub local_addr: AccountAddress,
+    pub remote_addr: AccountAddress,
+    pub capacity: u64,
+    pub fee_policy: FeePolicy,
+    pub disabled: bool,
+    pub timestamp: u64,
+}
+
+impl ChannelUpdate {
+    pub fn new(
+        channel_address: AccountAddress,
+        local_addr: AccountAddress,
+        remote_addr: AccountAddress,
+        capacity: u64,
+        fee_policy: FeePolicy,
+        disabled: bool,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            channel_address,
+            local_addr,
+            remote_addr,
+            capacity,
+            fee_policy,
+            disabled,
+            timestamp,
+        }
+    }
+}
+
+/// A node tells it's alive.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NodeAnnouncement {
+    pub node: AccountAddress,
+    pub timestamp: u64,
+}
+
+impl NodeAnnouncement {
+    pub fn new(node: AccountAddress, timestamp: u64) -> Self {
+        Self { node, timestamp }
+    }
+}
+
+/// `timestamp` of all gossip is in seconds, a newer one replaces the older.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, CryptoHasher)]
+pub enum Gossip {
+    ChannelAnnouncement(ChannelAnnouncement),
+    ChannelUpdate(ChannelUpdate),
+    NodeAnnouncement(NodeAnnouncement),
+}
+impl_hash!(Gossip, GossipHasher);
+
+impl Gossip {
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            Gossip::ChannelAnnouncement(m) => m.timestamp,
+            Gossip::ChannelUpdate(m) => m.timestamp,
+            Gossip::NodeAnnouncement(m) => m.timestamp,
+        }
+    }
+
+    /// whether `signer` is allowed to sign the gossip.
+    /// A channel is announced by any of its participants, others by the node they are about.
+    pub fn is_signed_by(&self, signer: &AccountAddress) -> bool {
+        match self {
+            Gossip::ChannelAnnouncement(m) => m.participants.contains(signer),
+            Gossip::ChannelUpdate(m) => &m.local_addr == signer,
+            Gossip::NodeAnnouncement(m) => &m.node == signer,
+        }
+    }
+
+    pub fn sign(self, private_key: &Ed25519PrivateKey) -> SignedGossip {
+        let signature = private_key.sign_message(&CryptoHash::hash(&self));
+        self.with_signature(Ed25519PublicKey::from(private_key), signature)
+    }
+
+    /// Attach `signature` made on the gossip elsewhere.
+    pub fn with_signature(
+        self,
+        public_key: Ed25519PublicKey,
+        signature: Ed25519Signature,
+    ) -> SignedGossip {
+        SignedGossip {
+            gossip: self,
+            public_key,
+            signature,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedGossip {
+    pub gossip: Gossip,
+    pub public_key: Ed25519PublicKey,
+    pub signature: Ed25519Signature,
+}
+
+impl SignedGossip {
+    pub fn signer(&self) -> AccountAddress {
+        AccountAddress::from_public_key(&self.public_key)
+    }
+
+    /// hash of the signed gossip, used to dedup messages.
+    pub fn id(&self) -> HashValue {
+        CryptoHash::hash(&self.gossip)
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        let signer = self.signer();
+        ensure!(
+            self.gossip.is_signed_by(&signer),
+            "gossip should not be signed by {}",
+            signer
+        );
+        self.public_key
+            .verify_signature(&CryptoHash::hash(&self.gossip), &self.signature)?;
+        Ok(())
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::ChannelAnnouncement> for ChannelAnnouncement {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::ChannelAnnouncement) -> Result<Self> {
+        let participants: Result<Vec<AccountAddress>> = value
+            .participants
+            .into_iter()
+            .map(AccountAddress::try_from)
+            .collect();
+        Ok(Self::new(
+            value.channel_address.try_into()?,
+            participants?,
+            value.timestamp,
+        ))
+    }
+}
+
+impl From<ChannelAnnouncement> for crate::proto::sgtypes::ChannelAnnouncement {
+    fn from(value: ChannelAnnouncement) -> Self {
+        Self {
+            channel_address: value.channel_address.to_vec(),
+            participants: value.participants.iter().map(|p| p.to_vec()).collect(),
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::ChannelUpdate> for ChannelUpdate {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::ChannelUpdate) -> Result<Self> {
+        Ok(Self::new(
+            value.channel_address.try_into()?,
+            value.local_addr.try_into()?,
+            value.remote_addr.try_into()?,
+            value.capacity,
+            value
+                .fee_policy
+                .ok_or_else(|| format_err!("Missing fee_policy"))?
+                .try_into()?,
+            value.disabled,
+            value.timestamp,
+        ))
+    }
+}
+
+impl From<ChannelUpdate> for crate::proto::sgtypes::ChannelUpdate {
+    fn from(value: ChannelUpdate) -> Self {
+        Self {
+            channel_address: value.channel_address.to_vec(),
+            local_addr: value.local_addr.to_vec(),
+            remote_addr: value.remote_addr.to_vec(),
+            capacity: value.capacity,
+            fee_policy: Some(value.fee_policy.into()),
+            disabled: value.disabled,
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::NodeAnnouncement> for NodeAnnouncement {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::NodeAnnouncement) -> Result<Self> {
+        Ok(Self::new(value.node.try_into()?, value.timestamp))
+    }
+}
+
+impl From<NodeAnnouncement> for crate::proto::sgtypes::NodeAnnouncement {
+    fn from(value: NodeAnnouncement) -> Self {
+        Self {
+            node: value.node.to_vec(),
+            timestamp: value.timestamp,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::SignedGossip> for SignedGossip {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::SignedGossip) -> Result<Self> {
+        use crate::proto::sgtypes::signed_gossip::GossipItems;
+
+        let gossip = match value
+            .gossip_items
+            .ok_or_else(|| format_err!("Missing gossip_items"))?
+        {
+            GossipItems::ChannelAnnouncement(m) => Gossip::ChannelAnnouncement(m.try_into()?),
+            GossipItems::ChannelUpdate(m) => Gossip::ChannelUpdate(m.try_into()?),
+            GossipItems::NodeAnnouncement(m) => Gossip::NodeAnnouncement(m.try_into()?),
+        };
+        Ok(Self {
+            gossip,
+            public_key: Ed25519PublicKey::try_from(value.public_key.as_slice())?,
+            signature: Ed25519Signature::try_from(value.signature.as_slice())?,
+        })
+    }
+}
+
+impl From<SignedGossip> for crate::proto::sgtypes::SignedGossip {
+    fn from(value: SignedGossip) -> Self {
+        use crate::proto::sgtypes::signed_gossip::GossipItems;
+
+        let gossip_items = match value.gossip {
+            Gossip::ChannelAnnouncement(m) => GossipItems::ChannelAnnouncement(m.into()),
+            Gossip::ChannelUpdate(m) => GossipItems::ChannelUpdate(m.into()),
+            Gossip::NodeAnnouncement(m) => GossipItems::NodeAnnouncement(m.into()),
+        };
+        Self {
+            gossip_items: Some(gossip_items),
+            public_key: value.public_key.to_bytes().to_vec(),
+            signature: value.signature.to_bytes().to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::{test_utils::KeyPair, Uniform};
+    use rand::prelude::*;
+
+    #[test]
+    fn test_signed_gossip() {
+        let mut rng: StdRng = SeedableRng::seed_from_u64(1);
+        let keypair: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let local = AccountAddress::from_public_key(&keypair.public_key);
+        let remote = AccountAddress::random();
+        let participants = vec![local, remote].into_iter().collect::<BTreeSet<_>>();
+        let channel_address = AccountAddress::from(&participants);
+
+        let announcement =
+            ChannelAnnouncement::new(channel_address, participants.into_iter().collect(), 1);
+        announcement.check_channel_address().unwrap();
+        let signed = Gossip::ChannelAnnouncement(announcement).sign(&keypair.private_key);
+        signed.verify().unwrap();
+        assert_eq!(local, signed.signer());
+
+        let update = ChannelUpdate::new(
+            channel_address,
+            local,
+            remote,
+            100,
+            FeePolicy::default(),
+            false,
+            2,
+        );
+        let signed = Gossip::ChannelUpdate(update.clone()).sign(&keypair.private_key);
+        signed.verify().unwrap();
+        let proto: crate::proto::sgtypes::SignedGossip = signed.clone().into();
+        assert_eq!(signed, SignedGossip::try_from(proto).unwrap());
+
+        let mut tampered = signed.clone();
+        tampered.gossip = Gossip::ChannelUpdate(ChannelUpdate {
+            capacity: 1000,
+            ..update.clone()
+        });
+        assert!(tampered.verify().is_err());
+
+        // update of the other direction can only be signed by the remote.
+        let reverse = ChannelUpdate {
+            local_addr: remote,
+            remote_addr: local,
+            ..update
+        };
+        assert!(Gossip::ChannelUpdate(reverse)
+            .sign(&keypair.private_key)
+            .verify()
+            .is_err());
+
+        let wrong_address =
+            ChannelAnnouncement::new(AccountAddress::random(), vec![local, remote], 1);
+        assert!(wrong_address.check_channel_address().is_err());
+    }
+}
@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct HtlcPayment {
     hash_lock: HashValue,
     amount: u64,
@@ -29,3 +31,172 @@ impl HtlcPayment {
         self.timeout
     }
 }
+
+/// every outgoing htlc goes through these states:
+/// 1. Pending, funds are locked in the channel, waiting for the preimage.
+/// 2. Recalling, the htlc is expired, and a recall is proposed to the participant.
+/// 3. Settled if the participant released the preimage, or Recalled if the funds are back.
+/// A htlc whose recall keeps failing is given up as Failed, and left to the operator.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum HtlcState {
+    Pending,
+    Recalling { proposed_at: u64 },
+    Settled,
+    Recalled,
+    Failed,
+}
+
+impl HtlcState {
+    pub fn is_final(&self) -> bool {
+        match self {
+            HtlcState::Pending | HtlcState::Recalling { .. } => false,
+            _ => true,
+        }
+    }
+}
+
+/// An outgoing htlc tracked by the wallet until it's settled or recalled.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HtlcRecord {
+    participant: AccountAddress,
+    payment: HtlcPayment,
+    /// block height after which the payment can be recalled.
+    /// It's unknown until the watcher sees the htlc for the first time.
+    expire_at: Option<u64>,
+    recall_attempts: u32,
+    state: HtlcState,
+}
+
+impl HtlcRecord {
+    pub fn new(participant: AccountAddress, payment: HtlcPayment) -> Self {
+        Self {
+            participant,
+            payment,
+            expire_at: None,
+            recall_attempts: 0,
+            state: HtlcState::Pending,
+        }
+    }
+
+    pub fn participant(&self) -> AccountAddress {
+        self.participant
+    }
+    pub fn payment(&self) -> &HtlcPayment {
+        &self.payment
+    }
+    pub fn hash_lock(&self) -> &HashValue {
+        self.payment.hash_lock()
+    }
+    pub fn expire_at(&self) -> Option<u64> {
+        self.expire_at
+    }
+    pub fn set_expire_at(&mut self, expire_at: u64) {
+        self.expire_at = Some(expire_at);
+    }
+    pub fn recall_attempts(&self) -> u32 {
+        self.recall_attempts
+    }
+    pub fn inc_recall_attempts(&mut self) -> u32 {
+        self.recall_attempts += 1;
+        self.recall_attempts
+    }
+    pub fn state(&self) -> HtlcState {
+        self.state
+    }
+    pub fn set_state(&mut self, state: HtlcState) {
+        self.state = state;
+    }
+
+    /// whether the htlc is still pending at `block_height`, but its timeout is reached.
+    pub fn is_expired(&self, block_height: u64) -> bool {
+        self.state == HtlcState::Pending
+            && self
+                .expire_at
+                .map(|expire_at| block_height > expire_at)
+                .unwrap_or(false)
+    }
+
+    /// whether the participant didn't answer the recall for more than `force_after` blocks.
+    pub fn is_recall_stuck(&self, block_height: u64, force_after: u64) -> bool {
+        match self.state {
+            HtlcState::Recalling { proposed_at } => {
+                block_height > proposed_at.saturating_add(force_after)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Actions taken on a tracked htlc, kept for audit.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum HtlcAction {
+    /// the htlc is applied to the channel and tracked.
+    Track,
+    /// the participant released the preimage.
+    Settle,
+    /// a recall is proposed to the participant.
+    ProposeRecall,
+    /// the recall is submitted onchain without the participant's signature.
+    ForceRecall,
+    /// the recall is applied to the channel.
+    Recall,
+    /// the recall failed, see detail for the reason.
+    Fail,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HtlcAuditRecord {
+    pub hash_lock: HashValue,
+    pub participant: AccountAddress,
+    pub action: HtlcAction,
+    /// block height when the action is taken, if known.
+    pub block_height: Option<u64>,
+    /// unix timestamp in milliseconds.
+    pub timestamp: u64,
+    pub detail: String,
+}
+
+impl HtlcAuditRecord {
+    pub fn new(
+        record: &HtlcRecord,
+        action: HtlcAction,
+        block_height: Option<u64>,
+        timestamp: u64,
+        detail: String,
+    ) -> Self {
+        Self {
+            hash_lock: *record.hash_lock(),
+            participant: record.participant(),
+            action,
+            block_height,
+            timestamp,
+            detail,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_htlc_record_timeout() {
+        let payment = HtlcPayment::new(HashValue::random(), 100, 10);
+        let mut record = HtlcRecord::new(AccountAddress::random(), payment);
+        assert!(!record.is_expired(std::u64::MAX));
+
+        record.set_expire_at(20);
+        assert!(!record.is_expired(20));
+        assert!(record.is_expired(21));
+        assert!(!record.is_recall_stuck(21, 5));
+
+        record.set_state(HtlcState::Recalling { proposed_at: 21 });
+        assert!(!record.is_expired(21));
+        assert!(!record.is_recall_stuck(26, 5));
+        assert!(record.is_recall_stuck(27, 5));
+
+        record.set_state(HtlcState::Recalled);
+        assert!(record.state().is_final());
+        assert!(!record.is_recall_stuck(27, 5));
+    }
+}
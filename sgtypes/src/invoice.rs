@@ -0,0 +1,289 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::impl_hash;
+use anyhow::{ensure, Error, Result};
+use bech32::{FromBase32, ToBase32};
+use libra_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    hash::CryptoHash,
+    HashValue, SigningKey, VerifyingKey,
+};
+use libra_crypto_derive::CryptoHasher;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+/// human readable prefix of encoded invoice.
+pub const INVOICE_HRP: &str = "sgi";
+/// version of the invoice encoding, bumped on incompatible change.
+pub const INVOICE_VERSION: u8 = 1;
+
+/// A private route to the receiver which is not announced in the channel graph.
+/// `hops` starts from a node reachable in the graph, and ends at the direct peer of
+/// the receiver, the receiver itself is not included.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RouteHint {
+    pub hops: Vec<AccountAddress>,
+}
+
+impl RouteHint {
+    pub fn new(hops: Vec<AccountAddress>) -> Self {
+        Self { hops }
+    }
+}
+
+/// Payment request created by the receiver.
+/// `timestamp` and `expiry` are in milliseconds, `min_final_timeout` is in blocks.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Serialize, Deserialize, CryptoHasher)]
+pub struct Invoice {
+    pub r_hash: Vec<u8>,
+    pub amount: u64,
+    pub receiver: AccountAddress,
+    pub timestamp: u64,
+    pub expiry: u64,
+    pub description: String,
+    pub min_final_timeout: u64,
+    pub route_hints: Vec<RouteHint>,
+}
+impl_hash!(Invoice, InvoiceHasher);
+
+impl Invoice {
+    pub fn expires_at(&self) -> u64 {
+        self.timestamp.saturating_add(self.expiry)
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at()
+    }
+
+    /// Sign the invoice by receiver's key.
+    pub fn sign(self, private_key: &Ed25519PrivateKey) -> SignedInvoice {
+        let signature = private_key.sign_message(&CryptoHash::hash(&self));
+        self.with_signature(Ed25519PublicKey::from(private_key), signature)
+    }
+
+    /// Attach `signature` made on the invoice by receiver's key elsewhere.
+    pub fn with_signature(
+        self,
+        public_key: Ed25519PublicKey,
+        signature: Ed25519Signature,
+    ) -> SignedInvoice {
+        SignedInvoice {
+            invoice: self,
+            public_key,
+            signature,
+        }
+    }
+}
+
+/// Invoice with the receiver's signature, it's encoded as
+/// `INVOICE_HRP` + bech32 data of `INVOICE_VERSION || lcs(SignedInvoice)`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedInvoice {
+    pub invoice: Invoice,
+    pub public_key: Ed25519PublicKey,
+    pub signature: Ed25519Signature,
+}
+
+impl SignedInvoice {
+    /// Check the invoice is signed by its receiver.
+    pub fn verify(&self) -> Result<()> {
+        ensure!(
+            AccountAddress::from_public_key(&self.public_key) == self.invoice.receiver,
+            "invoice is not signed by receiver {}",
+            self.invoice.receiver
+        );
+        self.public_key
+            .verify_signature(&CryptoHash::hash(&self.invoice), &self.signature)?;
+        Ok(())
+    }
+}
+
+impl TryFrom<Vec<u8>> for SignedInvoice {
+    type Error = Error;
+
+    fn try_from(value: Vec<u8>) -> Result<Self> {
+        ensure!(!value.is_empty(), "invoice is empty");
+        ensure!(
+            value[0] == INVOICE_VERSION,
+            "unsupported invoice version {}",
+            value[0]
+        );
+        let invoice: SignedInvoice = lcs::from_bytes(&value[1..])?;
+        invoice.verify()?;
+        Ok(invoice)
+    }
+}
+
+impl From<SignedInvoice> for Vec<u8> {
+    fn from(value: SignedInvoice) -> Self {
+        let mut result = vec![INVOICE_VERSION];
+        result.extend(lcs::to_bytes(&value).expect("Serialization should work."));
+        result
+    }
+}
+
+impl TryFrom<String> for SignedInvoice {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        let (hrp, data) = bech32::decode(value.as_str())?;
+        ensure!(hrp == INVOICE_HRP, "invalid invoice prefix {}", hrp);
+        SignedInvoice::try_from(Vec::<u8>::from_base32(&data)?)
+    }
+}
+
+impl From<SignedInvoice> for String {
+    fn from(value: SignedInvoice) -> Self {
+        let bytes: Vec<u8> = value.into();
+        bech32::encode(INVOICE_HRP, bytes.to_base32()).expect("hrp should be valid")
+    }
+}
+
+/// every invoice goes through these states:
+/// 1. Open, waiting for the htlc payment.
+/// 2. Accepted, htlc payment is received and preimage is about to be released.
+/// 3. Settled, preimage is released to the payer.
+/// An open invoice can also be Canceled by user, or Expired by the sweeper.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum InvoiceState {
+    Open = 0,
+    Accepted,
+    Settled,
+    Canceled,
+    Expired,
+}
+
+impl InvoiceState {
+    /// Invoice in final state can not be paid anymore.
+    pub fn is_final(&self) -> bool {
+        match self {
+            InvoiceState::Open | InvoiceState::Accepted => false,
+            _ => true,
+        }
+    }
+}
+
+/// Persistent record of an invoice created by this node.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceRecord {
+    r_hash: HashValue,
+    preimage: Vec<u8>,
+    invoice: SignedInvoice,
+    state: InvoiceState,
+}
+
+impl InvoiceRecord {
+    pub fn new(r_hash: HashValue, preimage: Vec<u8>, invoice: SignedInvoice) -> Self {
+        Self {
+            r_hash,
+            preimage,
+            invoice,
+            state: InvoiceState::Open,
+        }
+    }
+
+    pub fn r_hash(&self) -> &HashValue {
+        &self.r_hash
+    }
+    pub fn preimage(&self) -> &[u8] {
+        self.preimage.as_slice()
+    }
+    pub fn invoice(&self) -> &SignedInvoice {
+        &self.invoice
+    }
+    pub fn amount(&self) -> u64 {
+        self.invoice.invoice.amount
+    }
+    pub fn receiver(&self) -> AccountAddress {
+        self.invoice.invoice.receiver
+    }
+    pub fn description(&self) -> &str {
+        self.invoice.invoice.description.as_str()
+    }
+    pub fn created_at(&self) -> u64 {
+        self.invoice.invoice.timestamp
+    }
+    pub fn expiry(&self) -> u64 {
+        self.invoice.invoice.expiry
+    }
+    pub fn expires_at(&self) -> u64 {
+        self.invoice.invoice.expires_at()
+    }
+    pub fn min_final_timeout(&self) -> u64 {
+        self.invoice.invoice.min_final_timeout
+    }
+    pub fn state(&self) -> InvoiceState {
+        self.state
+    }
+    pub fn set_state(&mut self, state: InvoiceState) {
+        self.state = state;
+    }
+
+    /// whether the invoice is still open at `now`, but its expiry is reached.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.state == InvoiceState::Open && self.invoice.invoice.is_expired(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn new_signed_invoice() -> SignedInvoice {
+        let private_key = Ed25519PrivateKey::try_from([1u8; 32].as_ref()).unwrap();
+        let receiver = AccountAddress::from_public_key(&Ed25519PublicKey::from(&private_key));
+        Invoice {
+            r_hash: HashValue::random().to_vec(),
+            amount: 1000,
+            receiver,
+            timestamp: 1,
+            expiry: 3_600_000,
+            description: "coffee".to_string(),
+            min_final_timeout: 100,
+            route_hints: vec![RouteHint::new(vec![AccountAddress::random()])],
+        }
+        .sign(&private_key)
+    }
+
+    #[test]
+    fn test_invoice_encode_decode() {
+        let invoice = new_signed_invoice();
+        let encoded: String = invoice.clone().into();
+        assert!(encoded.starts_with(INVOICE_HRP));
+        let decoded: SignedInvoice = encoded.try_into().unwrap();
+        assert_eq!(invoice, decoded);
+    }
+
+    #[test]
+    fn test_invoice_decode_invalid() {
+        assert!(SignedInvoice::try_from(Vec::<u8>::new()).is_err());
+        assert!(SignedInvoice::try_from(vec![INVOICE_VERSION]).is_err());
+        assert!(SignedInvoice::try_from(vec![INVOICE_VERSION + 1, 0, 0]).is_err());
+
+        let mut bytes: Vec<u8> = new_signed_invoice().into();
+        bytes.truncate(bytes.len() / 2);
+        assert!(SignedInvoice::try_from(bytes).is_err());
+
+        let encoded: String = new_signed_invoice().into();
+        let mut corrupted = encoded.into_bytes();
+        let last = corrupted.len() - 1;
+        corrupted[last] = if corrupted[last] == b'q' { b'p' } else { b'q' };
+        assert!(SignedInvoice::try_from(String::from_utf8(corrupted).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_invoice_tampered() {
+        let mut invoice = new_signed_invoice();
+        invoice.invoice.amount = 1;
+        assert!(invoice.verify().is_err());
+        let bytes: Vec<u8> = invoice.into();
+        assert!(SignedInvoice::try_from(bytes).is_err());
+
+        let mut invoice = new_signed_invoice();
+        invoice.invoice.receiver = AccountAddress::random();
+        assert!(invoice.verify().is_err());
+    }
+}
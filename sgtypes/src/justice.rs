@@ -0,0 +1,111 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Justice data lets a watchtower challenge stale channel states for offline users.
+//!
+//! When a channel state is revoked by a newer one, the user hands a challenge txn of the
+//! newer state to the tower, encrypted by the hash of the revoked witness.
+//! The witness is only known to participants until someone submits the revoked state onchain,
+//! then the tower can find the blob by its hint, decrypt it and submit the challenge.
+
+use crate::onion::{hmac, open, seal};
+use anyhow::{ensure, Result};
+use libra_crypto::{hash::CryptoHash, HashValue};
+use libra_types::{
+    account_address::AccountAddress,
+    channel::{Witness, WitnessData},
+    transaction::SignedTransaction,
+};
+use serde::{Deserialize, Serialize};
+
+pub const HINT_SIZE: usize = 16;
+
+const HINT: &[u8] = b"hint";
+
+/// What the tower needs to challenge a revoked state of the channel.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct JusticeData {
+    pub channel_address: AccountAddress,
+    /// channel sequence number of the state proved by `challenge_txn`.
+    pub channel_sequence_number: u64,
+    /// signed by the user, but not submitted.
+    pub challenge_txn: SignedTransaction,
+}
+
+impl JusticeData {
+    pub fn new(
+        channel_address: AccountAddress,
+        channel_sequence_number: u64,
+        challenge_txn: SignedTransaction,
+    ) -> Self {
+        Self {
+            channel_address,
+            channel_sequence_number,
+            challenge_txn,
+        }
+    }
+
+    /// Encrypt the data by hash of the witness it revokes.
+    pub fn encrypt(&self, revoked_witness: &HashValue) -> Result<JusticeBlob> {
+        let data = lcs::to_bytes(self)?;
+        Ok(JusticeBlob {
+            hint: justice_hint(revoked_witness),
+            data: seal(&revoked_witness.to_vec(), data),
+        })
+    }
+}
+
+/// Encrypted justice data, the tower can only read it after the revoked state goes onchain.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct JusticeBlob {
+    pub hint: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+impl JusticeBlob {
+    pub fn new(hint: Vec<u8>, data: Vec<u8>) -> Self {
+        Self { hint, data }
+    }
+
+    pub fn decrypt(&self, revoked_witness: &HashValue) -> Result<JusticeData> {
+        ensure!(
+            self.hint == justice_hint(revoked_witness),
+            "justice blob is not for the witness"
+        );
+        let data = open(&revoked_witness.to_vec(), &self.data)?;
+        Ok(lcs::from_bytes(&data)?)
+    }
+}
+
+/// Hash of the witness data signed by all participants.
+pub fn witness_hash(witness: &Witness) -> HashValue {
+    CryptoHash::hash(&WitnessData::new(
+        witness.channel_sequence_number(),
+        witness.write_set().clone(),
+    ))
+}
+
+/// Index of justice blob, which doesn't reveal the witness.
+pub fn justice_hint(revoked_witness: &HashValue) -> Vec<u8> {
+    hmac(HINT, &[&revoked_witness.to_vec()[..]])[..HINT_SIZE].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let secret = HashValue::random();
+        let data = b"challenge txn".to_vec();
+        let sealed = seal(&secret.to_vec(), data.clone());
+        assert_ne!(&sealed[..data.len()], &data[..]);
+        assert_eq!(data, open(&secret.to_vec(), &sealed).unwrap());
+        assert!(open(&HashValue::random().to_vec(), &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert!(open(&secret.to_vec(), &tampered).is_err());
+        assert_ne!(justice_hint(&secret), justice_hint(&HashValue::random()));
+    }
+}
@@ -0,0 +1,446 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Encrypted keystore of account keys.
+//!
+//! Keys are encrypted by a key derived from a passphrase with scrypt, and a fresh salt is used
+//! every time the keystore is saved, so keys never touch disk unencrypted.
+//!
+//! An account key is rotated by replacing it with a new key, the old one is kept as retiring
+//! until funds of the old account are moved to the new one, then it's retired for good.
+//!
+//! The network identity key is not an account key, it's kept next to the keystore unencrypted.
+
+use crate::encryption::{decrypt, encrypt, KEY_SIZE};
+use anyhow::{ensure, format_err, Result};
+use libra_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+    test_utils::KeyPair,
+    PrivateKey,
+};
+use libra_types::account_address::AccountAddress;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::TryFrom,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+pub const KEYSTORE_VERSION: u8 = 2;
+
+const SALT_SIZE: usize = 32;
+
+/// scrypt parameters, the default costs about 32MB memory.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    fn derive_key(&self, passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_SIZE]> {
+        let params = scrypt::ScryptParams::new(self.log_n, self.r, self.p)
+            .map_err(|e| format_err!("invalid scrypt params {:?}: {}", self, e))?;
+        let mut key = [0u8; KEY_SIZE];
+        scrypt::scrypt(passphrase.as_bytes(), salt, &params, &mut key)
+            .map_err(|e| format_err!("fail to derive keystore key: {}", e))?;
+        Ok(key)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedKeyStore {
+    kdf: KdfParams,
+    salt: Vec<u8>,
+    sealed: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyStoreData {
+    keys: Vec<Vec<u8>>,
+    /// retiring keys, with the account replacing each of them.
+    retiring: Vec<(Vec<u8>, AccountAddress)>,
+}
+
+pub struct KeyStore {
+    path: PathBuf,
+    passphrase: String,
+    kdf: KdfParams,
+    /// private keys of accounts, the first one is the default account.
+    keys: Vec<Ed25519PrivateKey>,
+    /// rotated keys kept until funds of their accounts are moved to the replacing accounts.
+    retiring: Vec<(Ed25519PrivateKey, AccountAddress)>,
+}
+
+impl KeyStore {
+    /// Create an empty keystore at `path`, which should not exist.
+    pub fn create<P: AsRef<Path>>(path: P, passphrase: &str) -> Result<Self> {
+        Self::create_with_kdf(path, passphrase, KdfParams::default())
+    }
+
+    pub fn create_with_kdf<P: AsRef<Path>>(
+        path: P,
+        passphrase: &str,
+        kdf: KdfParams,
+    ) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        ensure!(!path.exists(), "keystore {:?} already exists", path);
+        ensure!(!passphrase.is_empty(), "passphrase should not be empty");
+        let keystore = Self {
+            path,
+            passphrase: passphrase.to_string(),
+            kdf,
+            keys: vec![],
+            retiring: vec![],
+        };
+        keystore.save()?;
+        Ok(keystore)
+    }
+
+    /// Decrypt the keystore at `path` by `passphrase`.
+    pub fn unlock<P: AsRef<Path>>(path: P, passphrase: &str) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let bytes = fs::read(&path)?;
+        ensure!(!bytes.is_empty(), "keystore {:?} is empty", path);
+        ensure!(
+            bytes[0] == KEYSTORE_VERSION,
+            "unsupported keystore version {}",
+            bytes[0]
+        );
+        let sealed: SealedKeyStore = lcs::from_bytes(&bytes[1..])?;
+        let key = sealed.kdf.derive_key(passphrase, &sealed.salt)?;
+        let data = decrypt(&key, &sealed.sealed)
+            .map_err(|_| format_err!("wrong passphrase or keystore {:?} is corrupted", path))?;
+        let data: KeyStoreData = lcs::from_bytes(&data)?;
+        let keys = data
+            .keys
+            .iter()
+            .map(|k| Ok(Ed25519PrivateKey::try_from(k.as_slice())?))
+            .collect::<Result<Vec<_>>>()?;
+        let retiring = data
+            .retiring
+            .iter()
+            .map(|(k, successor)| Ok((Ed25519PrivateKey::try_from(k.as_slice())?, *successor)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            path,
+            passphrase: passphrase.to_string(),
+            kdf: sealed.kdf,
+            keys,
+            retiring,
+        })
+    }
+
+    pub fn accounts(&self) -> Vec<AccountAddress> {
+        self.keys
+            .iter()
+            .map(|k| AccountAddress::from_public_key(&k.public_key()))
+            .collect()
+    }
+
+    /// Retiring accounts, with the accounts replacing them.
+    pub fn retiring_accounts(&self) -> Vec<(AccountAddress, AccountAddress)> {
+        self.retiring
+            .iter()
+            .map(|(k, successor)| (AccountAddress::from_public_key(&k.public_key()), *successor))
+            .collect()
+    }
+
+    /// Keypair of `account`, or the default account if it's None.
+    /// A retiring account is found too, so that its funds can be moved.
+    pub fn keypair(
+        &self,
+        account: Option<AccountAddress>,
+    ) -> Result<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> {
+        let private_key = match account {
+            Some(account) => self.find(account)?,
+            None => self
+                .keys
+                .first()
+                .ok_or(format_err!("keystore has no account"))?,
+        };
+        let private_key = copy_key(private_key)?;
+        let public_key = private_key.public_key();
+        Ok(KeyPair {
+            private_key,
+            public_key,
+        })
+    }
+
+    /// Generate a new account and save it.
+    pub fn create_account(&mut self) -> Result<AccountAddress> {
+        self.import_account(generate_key()?)
+    }
+
+    /// Replace key of `account` by a new one, at the same place so the default account stays
+    /// the default. The old key is kept retiring until `retire_account`.
+    /// If `account` is retiring already, the account replacing it is returned.
+    pub fn rotate_account(&mut self, account: AccountAddress) -> Result<AccountAddress> {
+        if let Some((_, successor)) = self.find_retiring(account) {
+            return Ok(*successor);
+        }
+        let index = self
+            .keys
+            .iter()
+            .position(|k| AccountAddress::from_public_key(&k.public_key()) == account)
+            .ok_or(format_err!("account {} not found in keystore", account))?;
+        let new_key = generate_key()?;
+        let successor = AccountAddress::from_public_key(&new_key.public_key());
+        let old_key = std::mem::replace(&mut self.keys[index], new_key);
+        self.retiring.push((old_key, successor));
+        self.save()?;
+        Ok(successor)
+    }
+
+    /// Put the key of a retiring `account` back in place of the account replacing it, and drop
+    /// the replacing key, if funds fail to be moved to it.
+    pub fn undo_rotation(&mut self, account: AccountAddress) -> Result<()> {
+        let position = self
+            .retiring
+            .iter()
+            .position(|(k, _)| AccountAddress::from_public_key(&k.public_key()) == account)
+            .ok_or(format_err!("account {} is not retiring", account))?;
+        let successor = self.retiring[position].1;
+        let index = self
+            .keys
+            .iter()
+            .position(|k| AccountAddress::from_public_key(&k.public_key()) == successor)
+            .ok_or(format_err!("account {} not found in keystore", successor))?;
+        let (old_key, _) = self.retiring.remove(position);
+        self.keys[index] = old_key;
+        self.save()
+    }
+
+    /// Drop the key of a retiring `account` for good, after its funds are moved.
+    pub fn retire_account(&mut self, account: AccountAddress) -> Result<()> {
+        let before = self.retiring.len();
+        self.retiring
+            .retain(|(k, _)| AccountAddress::from_public_key(&k.public_key()) != account);
+        ensure!(
+            self.retiring.len() < before,
+            "account {} is not retiring",
+            account
+        );
+        self.save()
+    }
+
+    pub fn import_account(&mut self, private_key: Ed25519PrivateKey) -> Result<AccountAddress> {
+        let account = AccountAddress::from_public_key(&private_key.public_key());
+        ensure!(
+            self.find(account).is_err(),
+            "account {} already exists in keystore",
+            account
+        );
+        self.keys.push(private_key);
+        self.save()?;
+        Ok(account)
+    }
+
+    pub fn export_account(&self, account: AccountAddress) -> Result<Ed25519PrivateKey> {
+        copy_key(self.find(account)?)
+    }
+
+    /// Re-encrypt the keystore by `new_passphrase`.
+    pub fn change_passphrase(&mut self, new_passphrase: &str) -> Result<()> {
+        ensure!(!new_passphrase.is_empty(), "passphrase should not be empty");
+        self.passphrase = new_passphrase.to_string();
+        self.save()
+    }
+
+    fn find(&self, account: AccountAddress) -> Result<&Ed25519PrivateKey> {
+        self.keys
+            .iter()
+            .find(|k| AccountAddress::from_public_key(&k.public_key()) == account)
+            .or_else(|| self.find_retiring(account).map(|(k, _)| k))
+            .ok_or(format_err!("account {} not found in keystore", account))
+    }
+
+    fn find_retiring(
+        &self,
+        account: AccountAddress,
+    ) -> Option<&(Ed25519PrivateKey, AccountAddress)> {
+        self.retiring
+            .iter()
+            .find(|(k, _)| AccountAddress::from_public_key(&k.public_key()) == account)
+    }
+
+    /// Encrypt all keys with a fresh salt, and replace the keystore file atomically.
+    fn save(&self) -> Result<()> {
+        let data = lcs::to_bytes(&KeyStoreData {
+            keys: self.keys.iter().map(|k| k.to_bytes().to_vec()).collect(),
+            retiring: self
+                .retiring
+                .iter()
+                .map(|(k, successor)| (k.to_bytes().to_vec(), *successor))
+                .collect(),
+        })?;
+        let mut salt = vec![0u8; SALT_SIZE];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let key = self.kdf.derive_key(&self.passphrase, &salt)?;
+        let sealed = SealedKeyStore {
+            kdf: self.kdf,
+            salt,
+            sealed: encrypt(&key, &data)?,
+        };
+        let mut bytes = vec![KEYSTORE_VERSION];
+        bytes.extend(lcs::to_bytes(&sealed)?);
+
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = open_private_file(&tmp_path)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Path of the network key kept next to the keystore at `keystore_path`.
+pub fn network_key_path<P: AsRef<Path>>(keystore_path: P) -> PathBuf {
+    let mut path = keystore_path.as_ref().as_os_str().to_owned();
+    path.push(".network");
+    PathBuf::from(path)
+}
+
+/// Network identity key at `path`, generated at first use. It's not an account key and the
+/// node holds it in process, so it's kept unencrypted, readable by the owner only.
+pub fn load_or_create_network_key<P: AsRef<Path>>(path: P) -> Result<Ed25519PrivateKey> {
+    let path = path.as_ref();
+    if path.exists() {
+        return Ok(Ed25519PrivateKey::try_from(&fs::read(path)?[..])?);
+    }
+    let private_key = generate_key()?;
+    let tmp_path = path.with_extension("tmp");
+    let mut file = open_private_file(&tmp_path)?;
+    file.write_all(&private_key.to_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(private_key)
+}
+
+fn generate_key() -> Result<Ed25519PrivateKey> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Ok(Ed25519PrivateKey::try_from(&bytes[..])?)
+}
+
+fn copy_key(private_key: &Ed25519PrivateKey) -> Result<Ed25519PrivateKey> {
+    Ok(Ed25519PrivateKey::try_from(&private_key.to_bytes()[..])?)
+}
+
+#[cfg(unix)]
+fn open_private_file(path: &Path) -> Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    Ok(fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?)
+}
+
+#[cfg(not(unix))]
+fn open_private_file(path: &Path) -> Result<fs::File> {
+    Ok(fs::File::create(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_key() -> Result<()> {
+        let keystore_path =
+            std::env::temp_dir().join(format!("keystore-{}", AccountAddress::random()));
+        let path = network_key_path(&keystore_path);
+        let network_key = load_or_create_network_key(&path)?;
+        assert_eq!(
+            network_key.to_bytes(),
+            load_or_create_network_key(&path)?.to_bytes()
+        );
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_keystore() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("keystore-{}", AccountAddress::random()));
+        let kdf = KdfParams {
+            log_n: 4,
+            r: 8,
+            p: 1,
+        };
+        let mut keystore = KeyStore::create_with_kdf(&path, "passphrase", kdf)?;
+        let first = keystore.create_account()?;
+        let second = keystore.create_account()?;
+        assert_ne!(first, second);
+        assert!(KeyStore::create_with_kdf(&path, "passphrase", kdf).is_err());
+
+        let bytes = fs::read(&path)?;
+        let exported = keystore.export_account(second)?;
+        assert!(!bytes.windows(32).any(|w| w == &exported.to_bytes()[..]));
+
+        assert!(KeyStore::unlock(&path, "wrong").is_err());
+        let keystore = KeyStore::unlock(&path, "passphrase")?;
+        assert_eq!(vec![first, second], keystore.accounts());
+        assert_eq!(
+            first,
+            AccountAddress::from_public_key(&keystore.keypair(None)?.public_key)
+        );
+
+        let mut other = KeyStore::create_with_kdf(path.with_extension("other"), "passphrase", kdf)?;
+        assert_eq!(second, other.import_account(exported)?);
+        assert!(other
+            .import_account(keystore.export_account(second)?)
+            .is_err());
+
+        other.change_passphrase("new passphrase")?;
+        assert!(KeyStore::unlock(path.with_extension("other"), "passphrase").is_err());
+        let other = KeyStore::unlock(path.with_extension("other"), "new passphrase")?;
+        assert_eq!(vec![second], other.accounts());
+
+        let mut keystore = keystore;
+        let rotated = keystore.rotate_account(first)?;
+        assert_eq!(rotated, keystore.rotate_account(first)?);
+        assert!(keystore.rotate_account(AccountAddress::random()).is_err());
+        let keystore = KeyStore::unlock(&path, "passphrase")?;
+        assert_eq!(vec![rotated, second], keystore.accounts());
+        assert_eq!(vec![(first, rotated)], keystore.retiring_accounts());
+        // the old key is still there to move funds.
+        assert_eq!(
+            first,
+            AccountAddress::from_public_key(&keystore.keypair(Some(first))?.public_key)
+        );
+
+        let mut keystore = keystore;
+        assert!(keystore.undo_rotation(second).is_err());
+        keystore.undo_rotation(first)?;
+        let mut keystore = KeyStore::unlock(&path, "passphrase")?;
+        assert_eq!(vec![first, second], keystore.accounts());
+        assert!(keystore.retiring_accounts().is_empty());
+        assert!(keystore.keypair(Some(rotated)).is_err());
+
+        let rotated = keystore.rotate_account(first)?;
+        assert!(keystore.retire_account(second).is_err());
+        keystore.retire_account(first)?;
+        let keystore = KeyStore::unlock(&path, "passphrase")?;
+        assert!(keystore.retiring_accounts().is_empty());
+        assert!(keystore.keypair(Some(first)).is_err());
+
+        fs::remove_file(&path)?;
+        fs::remove_file(path.with_extension("other"))?;
+        Ok(())
+    }
+}
@@ -3,18 +3,33 @@
 pub mod account_resource_ext;
 pub mod account_state;
 pub mod channel;
+pub mod channel_backup;
+pub mod channel_checkpoint;
+pub mod channel_reestablish;
 pub mod channel_transaction;
 pub mod channel_transaction_info;
 pub mod channel_transaction_sigs;
 #[cfg(test)]
 mod channel_transaction_test;
 pub mod channel_transaction_to_commit;
+pub mod channel_txn_history;
+pub mod encryption;
+pub mod fee_policy;
+pub mod gossip;
 #[macro_use]
 pub mod hash;
 pub mod applied_channel_txn;
+pub mod approval;
 pub mod htlc;
+pub mod invoice;
+pub mod justice;
+pub mod keystore;
 pub mod ledger_info;
 pub mod message;
+pub mod network_identity;
+pub mod onion;
+pub mod open_channel_policy;
+pub mod payment;
 pub mod pending_txn;
 pub mod proof;
 pub mod proto;
@@ -1,45 +1,120 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::channel_checkpoint::SignedChannelCheckpoint;
+use crate::channel_reestablish::ChannelReestablish;
 use crate::channel_transaction::ChannelTransactionRequest;
+use crate::fee_policy::FeePolicy;
+use crate::gossip::SignedGossip;
+use crate::onion::OnionPacket;
 use crate::s_value::SValue;
 use crate::sg_error::SgError;
-use anyhow::{bail, format_err, Error, Result};
+use anyhow::{bail, ensure, format_err, Error, Result};
 use bytes::IntoBuf;
 use libra_crypto::{
-    ed25519::Ed25519Signature,
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
     hash::{CryptoHash, CryptoHasher, DefaultHasher},
-    HashValue,
+    HashValue, SigningKey, VerifyingKey,
 };
 use libra_prost_ext::MessageExt;
 use libra_types::account_address::AccountAddress;
 use parity_multiaddr::Multiaddr;
 use prost::Message;
+use serde::{Deserialize, Serialize};
 use std::convert::{TryFrom, TryInto};
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NegotiateAction {
+    /// terms proposed by the channel opener.
+    Propose,
+    /// terms proposed back by the receiver, instead of the proposed ones.
+    Counter,
+    Accept,
+    Reject,
+}
+
+impl NegotiateAction {
+    pub fn get_type(self) -> u32 {
+        match self {
+            NegotiateAction::Propose => 0,
+            NegotiateAction::Counter => 1,
+            NegotiateAction::Accept => 2,
+            NegotiateAction::Reject => 3,
+        }
+    }
+
+    pub fn from_type(action: u32) -> Result<Self> {
+        match action {
+            0 => Ok(NegotiateAction::Propose),
+            1 => Ok(NegotiateAction::Counter),
+            2 => Ok(NegotiateAction::Accept),
+            3 => Ok(NegotiateAction::Reject),
+            _ => bail!("no such negotiate action {}", action),
+        }
+    }
+}
+
+/// Signature of a participant who agrees with the terms.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NegotiateSignature {
+    pub public_key: Ed25519PublicKey,
+    pub signature: Ed25519Signature,
+}
+
+impl TryFrom<crate::proto::sgtypes::NegotiateSignature> for NegotiateSignature {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::NegotiateSignature) -> Result<Self> {
+        Ok(Self {
+            public_key: Ed25519PublicKey::try_from(value.public_key.as_slice())?,
+            signature: Ed25519Signature::try_from(value.signature.as_slice())?,
+        })
+    }
+}
+
+impl From<NegotiateSignature> for crate::proto::sgtypes::NegotiateSignature {
+    fn from(value: NegotiateSignature) -> Self {
+        Self {
+            public_key: value.public_key.to_bytes().to_vec(),
+            signature: value.signature.to_bytes().to_vec(),
+        }
+    }
+}
+
+/// One step of the open channel negotiation, see `NegotiateAction`.
+/// The channel is opened with the raw message once both participants signed it.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct OpenChannelNodeNegotiateMessage {
     pub raw_negotiate_message: RawNegotiateMessage,
-    pub sender_sign: Ed25519Signature,
-    pub receiver_sign: Option<Ed25519Signature>,
+    pub action: NegotiateAction,
+    pub sender_sign: Option<NegotiateSignature>,
+    pub receiver_sign: Option<NegotiateSignature>,
+    /// why the terms are rejected or countered, empty otherwise.
+    pub reason: String,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct RawNegotiateMessage {
     pub sender_addr: AccountAddress,
     pub resource_type: StructTag,
-    pub sender_amount: i64,
+    pub sender_amount: u64,
     pub receiver_addr: AccountAddress,
-    pub receiver_amount: i64,
+    pub receiver_amount: u64,
+    /// min amount each participant deposits into the channel.
+    pub min_reserve: u64,
+    /// script packages which can be executed in the channel.
+    pub packages: Vec<String>,
 }
 
 impl RawNegotiateMessage {
     pub fn new(
         sender_addr: AccountAddress,
         resource_type: StructTag,
-        sender_amount: i64,
+        sender_amount: u64,
         receiver_addr: AccountAddress,
-        receiver_amount: i64,
+        receiver_amount: u64,
+        min_reserve: u64,
+        packages: Vec<String>,
     ) -> Self {
         RawNegotiateMessage {
             sender_addr,
@@ -47,6 +122,8 @@ impl RawNegotiateMessage {
             sender_amount,
             receiver_addr,
             receiver_amount,
+            min_reserve,
+            packages,
         }
     }
 
@@ -60,6 +137,64 @@ impl RawNegotiateMessage {
     pub fn into_proto_bytes(self) -> Result<Vec<u8>> {
         Ok(TryInto::<crate::proto::sgtypes::RawNegotiateMessage>::try_into(self)?.to_vec()?)
     }
+
+    /// Check terms countered by the receiver are acceptable for the sender who proposed `self`.
+    /// The sender is not asked to deposit more, or to allow packages it didn't propose,
+    /// and both deposits should meet the reserve.
+    pub fn check_counter(&self, counter: &RawNegotiateMessage) -> Result<()> {
+        ensure!(
+            counter.sender_addr == self.sender_addr
+                && counter.receiver_addr == self.receiver_addr
+                && counter.resource_type == self.resource_type,
+            "counter is not for the same channel"
+        );
+        ensure!(
+            counter.sender_amount <= self.sender_amount,
+            "sender amount {} > proposed {}",
+            counter.sender_amount,
+            self.sender_amount
+        );
+        if let Some(package) = counter
+            .packages
+            .iter()
+            .find(|package| !self.packages.contains(package))
+        {
+            bail!("package {} is not proposed", package);
+        }
+        counter.check_reserve()
+    }
+
+    /// Check both deposits meet the reserve.
+    pub fn check_reserve(&self) -> Result<()> {
+        ensure!(
+            self.sender_amount >= self.min_reserve,
+            "sender amount {} < reserve {}",
+            self.sender_amount,
+            self.min_reserve
+        );
+        ensure!(
+            self.receiver_amount >= self.min_reserve,
+            "receiver amount {} < reserve {}",
+            self.receiver_amount,
+            self.min_reserve
+        );
+        Ok(())
+    }
+}
+
+impl CryptoHash for RawNegotiateMessage {
+    type Hasher = DefaultHasher;
+
+    fn hash(&self) -> HashValue {
+        let mut state = Self::Hasher::default();
+        state.write(
+            &self
+                .clone()
+                .into_proto_bytes()
+                .expect("Serialization should work."),
+        );
+        state.finish()
+    }
 }
 
 impl TryFrom<crate::proto::sgtypes::RawNegotiateMessage> for RawNegotiateMessage {
@@ -75,6 +210,8 @@ impl TryFrom<crate::proto::sgtypes::RawNegotiateMessage> for RawNegotiateMessage
             value.sender_amount,
             value.receiver_addr.try_into()?,
             value.receiver_amount,
+            value.min_reserve,
+            value.packages,
         ))
     }
 }
@@ -87,6 +224,8 @@ impl From<RawNegotiateMessage> for crate::proto::sgtypes::RawNegotiateMessage {
             sender_amount: value.sender_amount,
             receiver_addr: value.receiver_addr.to_vec(),
             receiver_amount: value.receiver_amount,
+            min_reserve: value.min_reserve,
+            packages: value.packages,
         }
     }
 }
@@ -94,16 +233,90 @@ impl From<RawNegotiateMessage> for crate::proto::sgtypes::RawNegotiateMessage {
 impl OpenChannelNodeNegotiateMessage {
     pub fn new(
         raw_negotiate_message: RawNegotiateMessage,
-        sender_sign: Ed25519Signature,
-        receiver_sign: Option<Ed25519Signature>,
+        action: NegotiateAction,
+        sender_sign: Option<NegotiateSignature>,
+        receiver_sign: Option<NegotiateSignature>,
+        reason: String,
     ) -> Self {
         OpenChannelNodeNegotiateMessage {
             raw_negotiate_message,
+            action,
             sender_sign,
             receiver_sign,
+            reason,
         }
     }
 
+    /// Reject `raw_negotiate_message` for `reason`.
+    pub fn new_reject(raw_negotiate_message: RawNegotiateMessage, reason: String) -> Self {
+        Self::new(
+            raw_negotiate_message,
+            NegotiateAction::Reject,
+            None,
+            None,
+            reason,
+        )
+    }
+
+    /// Sign the terms as the sender or the receiver, whose address is of `public_key`.
+    pub fn sign(
+        &mut self,
+        private_key: &Ed25519PrivateKey,
+        public_key: Ed25519PublicKey,
+    ) -> Result<()> {
+        let signature = private_key.sign_message(&CryptoHash::hash(&self.raw_negotiate_message));
+        self.add_signature(public_key, signature)
+    }
+
+    /// Same as `sign`, by `signature` made on the terms elsewhere.
+    pub fn add_signature(
+        &mut self,
+        public_key: Ed25519PublicKey,
+        signature: Ed25519Signature,
+    ) -> Result<()> {
+        let signer = AccountAddress::from_public_key(&public_key);
+        let sign = Some(NegotiateSignature {
+            signature,
+            public_key,
+        });
+        if signer == self.raw_negotiate_message.sender_addr {
+            self.sender_sign = sign;
+        } else if signer == self.raw_negotiate_message.receiver_addr {
+            self.receiver_sign = sign;
+        } else {
+            bail!("{} is not a participant of the negotiation", signer);
+        }
+        Ok(())
+    }
+
+    /// Check present signatures are of the participants and valid for the terms.
+    pub fn verify(&self) -> Result<()> {
+        let hash = CryptoHash::hash(&self.raw_negotiate_message);
+        let signs = vec![
+            (self.raw_negotiate_message.sender_addr, &self.sender_sign),
+            (
+                self.raw_negotiate_message.receiver_addr,
+                &self.receiver_sign,
+            ),
+        ];
+        for (participant, sign) in signs {
+            if let Some(sign) = sign {
+                ensure!(
+                    AccountAddress::from_public_key(&sign.public_key) == participant,
+                    "negotiation is not signed by {}",
+                    participant
+                );
+                sign.public_key.verify_signature(&hash, &sign.signature)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether both participants agree with the terms, signatures should be verified before.
+    pub fn is_agreed(&self) -> bool {
+        self.sender_sign.is_some() && self.receiver_sign.is_some()
+    }
+
     pub fn from_proto_bytes<B>(buf: B) -> Result<Self>
     where
         B: IntoBuf,
@@ -129,17 +342,12 @@ impl TryFrom<crate::proto::sgtypes::OpenChannelNodeNegotiateMessage>
             .raw_message
             .ok_or_else(|| format_err!("Missing raw_message"))?
             .try_into()?;
-        let sender_sign = Ed25519Signature::try_from(value.sender_sign.as_slice())?;
-        let receiver_sign = if value.receiver_sign.len() > 0 {
-            Option::Some(Ed25519Signature::try_from(value.receiver_sign.as_slice())?)
-        } else {
-            Option::None
-        };
-
         Ok(OpenChannelNodeNegotiateMessage::new(
             raw,
-            sender_sign,
-            receiver_sign,
+            NegotiateAction::from_type(value.action)?,
+            value.sender_sign.map(TryInto::try_into).transpose()?,
+            value.receiver_sign.map(TryInto::try_into).transpose()?,
+            value.reason,
         ))
     }
 }
@@ -150,10 +358,10 @@ impl From<OpenChannelNodeNegotiateMessage>
     fn from(value: OpenChannelNodeNegotiateMessage) -> Self {
         Self {
             raw_message: Some(value.raw_negotiate_message.into()),
-            sender_sign: value.sender_sign.to_bytes().to_vec(),
-            receiver_sign: value
-                .receiver_sign
-                .map_or(vec![], |sign| sign.to_bytes().to_vec()),
+            sender_sign: value.sender_sign.map(Into::into),
+            receiver_sign: value.receiver_sign.map(Into::into),
+            action: value.action.get_type(),
+            reason: value.reason,
         }
     }
 }
@@ -253,6 +461,10 @@ impl From<AddressMessage> for crate::proto::sgtypes::AddressMessage {
 pub struct ErrorMessage {
     pub raw_transaction_hash: HashValue,
     pub error: SgError,
+    /// failure of multi hop payment encrypted for the sender, empty if none.
+    pub onion_error: Vec<u8>,
+    /// channel of the txn, None if it's unknown to the sender of the error.
+    pub channel_address: Option<AccountAddress>,
 }
 
 impl ErrorMessage {
@@ -260,9 +472,21 @@ impl ErrorMessage {
         Self {
             raw_transaction_hash,
             error,
+            onion_error: vec![],
+            channel_address: None,
         }
     }
 
+    pub fn with_onion_error(mut self, onion_error: Vec<u8>) -> Self {
+        self.onion_error = onion_error;
+        self
+    }
+
+    pub fn with_channel_address(mut self, channel_address: AccountAddress) -> Self {
+        self.channel_address = Some(channel_address);
+        self
+    }
+
     pub fn from_proto_bytes<B>(buf: B) -> Result<Self>
     where
         B: IntoBuf,
@@ -284,9 +508,16 @@ impl TryFrom<crate::proto::sgtypes::ErrorMessage> for ErrorMessage {
             error_code: value.error_code.try_into()?,
             error_message: value.error_message,
         };
+        let channel_address = if value.channel_address.is_empty() {
+            None
+        } else {
+            Some(AccountAddress::try_from(value.channel_address)?)
+        };
         Ok(Self {
             raw_transaction_hash,
             error,
+            onion_error: value.onion_error,
+            channel_address,
         })
     }
 }
@@ -297,6 +528,11 @@ impl From<ErrorMessage> for crate::proto::sgtypes::ErrorMessage {
             raw_transaction_hash: value.raw_transaction_hash.to_vec(),
             error_code: value.error.error_code.into(),
             error_message: value.error.error_message,
+            onion_error: value.onion_error,
+            channel_address: value
+                .channel_address
+                .map(|a| a.to_vec())
+                .unwrap_or_default(),
         }
     }
 }
@@ -309,6 +545,7 @@ pub enum MessageType {
     ErrorMessage,
     MultiHopChannelTransactionRequest,
     RouterMessage,
+    NodeMessage,
 }
 
 impl MessageType {
@@ -320,6 +557,7 @@ impl MessageType {
             MessageType::ErrorMessage => 4,
             MessageType::MultiHopChannelTransactionRequest => 5,
             MessageType::RouterMessage => 6,
+            MessageType::NodeMessage => 7,
         }
     }
 
@@ -331,6 +569,7 @@ impl MessageType {
             4 => Ok(MessageType::ErrorMessage),
             5 => Ok(MessageType::MultiHopChannelTransactionRequest),
             6 => Ok(MessageType::RouterMessage),
+            7 => Ok(MessageType::NodeMessage),
             _ => bail!("no such type"),
         }
     }
@@ -340,6 +579,8 @@ impl MessageType {
 pub struct BalanceQueryRequest {
     pub local_addr: AccountAddress,
     pub remote_addr: AccountAddress,
+    /// asset to query, None for the default asset.
+    pub asset: Option<libra_types::language_storage::StructTag>,
 }
 
 impl BalanceQueryRequest {
@@ -347,6 +588,19 @@ impl BalanceQueryRequest {
         Self {
             local_addr,
             remote_addr,
+            asset: None,
+        }
+    }
+
+    pub fn new_with_asset(
+        local_addr: AccountAddress,
+        remote_addr: AccountAddress,
+        asset: libra_types::language_storage::StructTag,
+    ) -> Self {
+        Self {
+            local_addr,
+            remote_addr,
+            asset: Some(asset),
         }
     }
 
@@ -366,10 +620,16 @@ impl TryFrom<crate::proto::sgtypes::BalanceQueryRequest> for BalanceQueryRequest
     type Error = Error;
 
     fn try_from(value: crate::proto::sgtypes::BalanceQueryRequest) -> Result<Self> {
-        Ok(Self::new(
-            value.local_addr.try_into()?,
-            value.remote_addr.try_into()?,
-        ))
+        let asset = if value.asset.is_empty() {
+            None
+        } else {
+            Some(lcs::from_bytes(&value.asset)?)
+        };
+        Ok(Self {
+            local_addr: value.local_addr.try_into()?,
+            remote_addr: value.remote_addr.try_into()?,
+            asset,
+        })
     }
 }
 
@@ -378,6 +638,9 @@ impl From<BalanceQueryRequest> for crate::proto::sgtypes::BalanceQueryRequest {
         Self {
             local_addr: value.local_addr.to_vec(),
             remote_addr: value.remote_addr.to_vec(),
+            asset: value.asset.map_or(vec![], |asset| {
+                lcs::to_bytes(&asset).expect("Serialization should work.")
+            }),
         }
     }
 }
@@ -389,6 +652,8 @@ pub struct BalanceQueryResponse {
     pub local_balance: u64,
     pub remote_balance: u64,
     pub total_pay_amount: u64,
+    /// policy of `local_addr` to forward htlc to `remote_addr`, None if unknown.
+    pub fee_policy: Option<FeePolicy>,
 }
 
 impl BalanceQueryResponse {
@@ -398,6 +663,7 @@ impl BalanceQueryResponse {
         local_balance: u64,
         remote_balance: u64,
         total_pay_amount: u64,
+        fee_policy: Option<FeePolicy>,
     ) -> Self {
         Self {
             local_addr,
@@ -405,6 +671,7 @@ impl BalanceQueryResponse {
             local_balance,
             remote_balance,
             total_pay_amount,
+            fee_policy,
         }
     }
 
@@ -419,6 +686,7 @@ impl BalanceQueryResponse {
         Ok(TryInto::<crate::proto::sgtypes::BalanceQueryResponse>::try_into(self)?.to_vec()?)
     }
 
+    /// The same channel seen from the other side, whose fee policy is unknown.
     pub fn revert(&self) -> Self {
         Self {
             local_addr: self.remote_addr.clone(),
@@ -426,6 +694,7 @@ impl BalanceQueryResponse {
             local_balance: self.remote_balance,
             remote_balance: self.local_balance,
             total_pay_amount: self.total_pay_amount,
+            fee_policy: None,
         }
     }
 }
@@ -440,6 +709,7 @@ impl TryFrom<crate::proto::sgtypes::BalanceQueryResponse> for BalanceQueryRespon
             value.local_balance,
             value.remote_balance,
             value.total_pay_amount,
+            value.fee_policy.map(FeePolicy::try_from).transpose()?,
         ))
     }
 }
@@ -452,6 +722,7 @@ impl From<BalanceQueryResponse> for crate::proto::sgtypes::BalanceQueryResponse
             local_balance: value.local_balance,
             remote_balance: value.remote_balance,
             total_pay_amount: value.total_pay_amount,
+            fee_policy: value.fee_policy.map(Into::into),
         }
     }
 }
@@ -522,17 +793,21 @@ impl From<AntQueryMessage> for crate::proto::sgtypes::AntQueryMessage {
     }
 }
 
+/// What a hop of multi hop payment learns from the onion, the htlc of `amount` and `timeout`
+/// should be forwarded to `remote_addr`, which is the hop itself for the receiver.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct NextHop {
     pub remote_addr: AccountAddress,
     pub amount: u64,
+    pub timeout: u64,
 }
 
 impl NextHop {
-    pub fn new(remote_addr: AccountAddress, amount: u64) -> Self {
+    pub fn new(remote_addr: AccountAddress, amount: u64, timeout: u64) -> Self {
         Self {
             remote_addr,
             amount,
+            timeout,
         }
     }
 
@@ -552,7 +827,11 @@ impl TryFrom<crate::proto::sgtypes::NextHop> for NextHop {
     type Error = Error;
 
     fn try_from(value: crate::proto::sgtypes::NextHop) -> Result<Self> {
-        Ok(Self::new(value.remote_addr.try_into()?, value.amount))
+        Ok(Self::new(
+            value.remote_addr.try_into()?,
+            value.amount,
+            value.timeout,
+        ))
     }
 }
 
@@ -561,6 +840,7 @@ impl From<NextHop> for crate::proto::sgtypes::NextHop {
         Self {
             remote_addr: value.remote_addr.to_vec(),
             amount: value.amount,
+            timeout: value.timeout,
         }
     }
 }
@@ -568,12 +848,12 @@ impl From<NextHop> for crate::proto::sgtypes::NextHop {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MultiHopChannelRequest {
     pub request: ChannelTransactionRequest,
-    pub hops: Vec<NextHop>,
+    pub onion: OnionPacket,
 }
 
 impl MultiHopChannelRequest {
-    pub fn new(request: ChannelTransactionRequest, hops: Vec<NextHop>) -> Self {
-        Self { request, hops }
+    pub fn new(request: ChannelTransactionRequest, onion: OnionPacket) -> Self {
+        Self { request, onion }
     }
 
     pub fn from_proto_bytes<B>(buf: B) -> Result<Self>
@@ -592,19 +872,15 @@ impl TryFrom<crate::proto::sgtypes::MultiHopChannelRequest> for MultiHopChannelR
     type Error = Error;
 
     fn try_from(value: crate::proto::sgtypes::MultiHopChannelRequest) -> Result<Self> {
-        let hops: Result<Vec<NextHop>> = value
-            .hops
-            .iter()
-            .clone()
-            .map(|v| NextHop::try_from(v.clone()))
-            .collect();
-
         Ok(Self::new(
             value
                 .request
                 .ok_or_else(|| format_err!("Missing request"))?
                 .try_into()?,
-            hops?,
+            value
+                .onion
+                .ok_or_else(|| format_err!("Missing onion"))?
+                .try_into()?,
         ))
     }
 }
@@ -613,7 +889,7 @@ impl From<MultiHopChannelRequest> for crate::proto::sgtypes::MultiHopChannelRequ
     fn from(value: MultiHopChannelRequest) -> Self {
         Self {
             request: Some(value.request.into()),
-            hops: value.hops.iter().cloned().map(|v| v.into()).collect(),
+            onion: Some(value.onion.into()),
         }
     }
 }
@@ -796,7 +1072,175 @@ impl From<AntFinalMessage> for crate::proto::sgtypes::AntFinalMessage {
     }
 }
 
-pub enum NodeNetworkMessage {}
+/// Opaque payload a DApp sends to a peer it has a channel with, routed by `topic`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DappMessage {
+    /// id of the message, echoed back by its receipt.
+    pub id: HashValue,
+    pub topic: String,
+    pub payload: Vec<u8>,
+    /// whether the receiver should answer with a receipt.
+    pub need_receipt: bool,
+}
+
+impl DappMessage {
+    pub fn new(id: HashValue, topic: String, payload: Vec<u8>, need_receipt: bool) -> Self {
+        Self {
+            id,
+            topic,
+            payload,
+            need_receipt,
+        }
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::DappMessage> for DappMessage {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::DappMessage) -> Result<Self> {
+        Ok(Self::new(
+            HashValue::from_slice(&value.id)?,
+            value.topic,
+            value.payload,
+            value.need_receipt,
+        ))
+    }
+}
+
+impl From<DappMessage> for crate::proto::sgtypes::DappMessage {
+    fn from(value: DappMessage) -> Self {
+        Self {
+            id: value.id.to_vec(),
+            topic: value.topic,
+            payload: value.payload,
+            need_receipt: value.need_receipt,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DappMessageReceipt {
+    pub id: HashValue,
+    /// whether the message reached any subscriber of its topic.
+    pub delivered: bool,
+}
+
+impl DappMessageReceipt {
+    pub fn new(id: HashValue, delivered: bool) -> Self {
+        Self { id, delivered }
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::DappMessageReceipt> for DappMessageReceipt {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::DappMessageReceipt) -> Result<Self> {
+        Ok(Self::new(
+            HashValue::from_slice(&value.id)?,
+            value.delivered,
+        ))
+    }
+}
+
+impl From<DappMessageReceipt> for crate::proto::sgtypes::DappMessageReceipt {
+    fn from(value: DappMessageReceipt) -> Self {
+        Self {
+            id: value.id.to_vec(),
+            delivered: value.delivered,
+        }
+    }
+}
+
+/// A dapp message received from `sender`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InboundDappMessage {
+    pub sender: AccountAddress,
+    pub message: DappMessage,
+}
+
+impl InboundDappMessage {
+    pub fn new(sender: AccountAddress, message: DappMessage) -> Self {
+        Self { sender, message }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NodeNetworkMessage {
+    DappMessage(DappMessage),
+    DappMessageReceipt(DappMessageReceipt),
+    ChannelCheckpointRequest(SignedChannelCheckpoint),
+    ChannelCheckpointResponse(SignedChannelCheckpoint),
+    ChannelReestablish(ChannelReestablish),
+}
+
+impl NodeNetworkMessage {
+    pub fn from_proto_bytes<B>(buf: B) -> Result<Self>
+    where
+        B: IntoBuf,
+    {
+        crate::proto::sgtypes::NodeNetworkMessage::decode(buf)?.try_into()
+    }
+
+    pub fn into_proto_bytes(self) -> Result<Vec<u8>> {
+        Ok(TryInto::<crate::proto::sgtypes::NodeNetworkMessage>::try_into(self)?.to_vec()?)
+    }
+}
+
+impl TryFrom<crate::proto::sgtypes::NodeNetworkMessage> for NodeNetworkMessage {
+    type Error = anyhow::Error;
+
+    fn try_from(proto: crate::proto::sgtypes::NodeNetworkMessage) -> Result<Self> {
+        use crate::proto::sgtypes::node_network_message::NodeMessageItems;
+
+        let item = proto
+            .node_message_items
+            .ok_or_else(|| format_err!("Missing node_message_items"))?;
+
+        let message = match item {
+            NodeMessageItems::DappMessage(m) => {
+                NodeNetworkMessage::DappMessage(DappMessage::try_from(m)?)
+            }
+            NodeMessageItems::DappMessageReceipt(m) => {
+                NodeNetworkMessage::DappMessageReceipt(DappMessageReceipt::try_from(m)?)
+            }
+            NodeMessageItems::CheckpointRequest(m) => {
+                NodeNetworkMessage::ChannelCheckpointRequest(SignedChannelCheckpoint::try_from(m)?)
+            }
+            NodeMessageItems::CheckpointResponse(m) => {
+                NodeNetworkMessage::ChannelCheckpointResponse(SignedChannelCheckpoint::try_from(m)?)
+            }
+            NodeMessageItems::ChannelReestablish(m) => {
+                NodeNetworkMessage::ChannelReestablish(ChannelReestablish::try_from(m)?)
+            }
+        };
+        Ok(message)
+    }
+}
+
+impl From<NodeNetworkMessage> for crate::proto::sgtypes::NodeNetworkMessage {
+    fn from(message: NodeNetworkMessage) -> Self {
+        use crate::proto::sgtypes::node_network_message::NodeMessageItems;
+
+        let item = match message {
+            NodeNetworkMessage::DappMessage(m) => NodeMessageItems::DappMessage(m.into()),
+            NodeNetworkMessage::DappMessageReceipt(m) => {
+                NodeMessageItems::DappMessageReceipt(m.into())
+            }
+            NodeNetworkMessage::ChannelCheckpointRequest(m) => {
+                NodeMessageItems::CheckpointRequest(m.into())
+            }
+            NodeNetworkMessage::ChannelCheckpointResponse(m) => {
+                NodeMessageItems::CheckpointResponse(m.into())
+            }
+            NodeNetworkMessage::ChannelReestablish(m) => {
+                NodeMessageItems::ChannelReestablish(m.into())
+            }
+        };
+        Self {
+            node_message_items: Some(item),
+        }
+    }
+}
 
 #[allow(clippy::large_enum_variant)]
 #[derive(Clone, Eq, PartialEq)]
@@ -807,6 +1251,7 @@ pub enum RouterNetworkMessage {
     AntFinalMessage(AntFinalMessage),
     BalanceQueryRequest(BalanceQueryRequest),
     BalanceQueryResponse(BalanceQueryResponse),
+    Gossip(SignedGossip),
 }
 
 impl RouterNetworkMessage {
@@ -855,6 +1300,9 @@ impl TryFrom<crate::proto::sgtypes::RouterNetworkMessage> for RouterNetworkMessa
             RouterMessageItems::BalanceQueryResponse(resp) => {
                 RouterNetworkMessage::BalanceQueryResponse(BalanceQueryResponse::try_from(resp)?)
             }
+            RouterMessageItems::Gossip(resp) => {
+                RouterNetworkMessage::Gossip(SignedGossip::try_from(resp)?)
+            }
         };
 
         Ok(response)
@@ -884,6 +1332,7 @@ impl From<RouterNetworkMessage> for crate::proto::sgtypes::RouterNetworkMessage
             RouterNetworkMessage::BalanceQueryResponse(r) => {
                 RouterMessageItems::BalanceQueryResponse(r.into())
             }
+            RouterNetworkMessage::Gossip(r) => RouterMessageItems::Gossip(r.into()),
         };
 
         Self {
@@ -0,0 +1,108 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::impl_hash;
+use anyhow::Result;
+use libra_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    hash::CryptoHash,
+    SigningKey, VerifyingKey,
+};
+use libra_crypto_derive::CryptoHasher;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+
+/// Network key of a node, which is not its account key. The account key signs it, so that peers
+/// know which account is behind the network peer.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, CryptoHasher)]
+pub struct NetworkIdentity {
+    pub network_public_key: Ed25519PublicKey,
+}
+impl_hash!(NetworkIdentity, NetworkIdentityHasher);
+
+impl NetworkIdentity {
+    pub fn new(network_public_key: Ed25519PublicKey) -> Self {
+        Self { network_public_key }
+    }
+
+    pub fn sign(self, private_key: &Ed25519PrivateKey) -> SignedNetworkIdentity {
+        let signature = private_key.sign_message(&CryptoHash::hash(&self));
+        self.with_signature(Ed25519PublicKey::from(private_key), signature)
+    }
+
+    /// Attach `signature` made on the identity elsewhere.
+    pub fn with_signature(
+        self,
+        account_public_key: Ed25519PublicKey,
+        signature: Ed25519Signature,
+    ) -> SignedNetworkIdentity {
+        SignedNetworkIdentity {
+            identity: self,
+            account_public_key,
+            signature,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedNetworkIdentity {
+    pub identity: NetworkIdentity,
+    pub account_public_key: Ed25519PublicKey,
+    pub signature: Ed25519Signature,
+}
+
+impl SignedNetworkIdentity {
+    pub fn account(&self) -> AccountAddress {
+        AccountAddress::from_public_key(&self.account_public_key)
+    }
+
+    /// Address of the network peer, derived from the network key the same way as accounts.
+    pub fn peer_address(&self) -> AccountAddress {
+        AccountAddress::from_public_key(&self.identity.network_public_key)
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        self.account_public_key
+            .verify_signature(&CryptoHash::hash(&self.identity), &self.signature)?;
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(lcs::to_bytes(self)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        Ok(lcs::from_bytes(bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::{test_utils::KeyPair, Uniform};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_network_identity() -> Result<()> {
+        let mut rng: StdRng = SeedableRng::from_seed([0; 32]);
+        let account: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let network = Ed25519PrivateKey::generate_for_testing(&mut rng);
+
+        let identity =
+            NetworkIdentity::new(Ed25519PublicKey::from(&network)).sign(&account.private_key);
+        identity.verify()?;
+        assert_eq!(
+            AccountAddress::from_public_key(&account.public_key),
+            identity.account()
+        );
+        let decoded = SignedNetworkIdentity::from_bytes(&identity.to_bytes()?)?;
+        assert_eq!(identity, decoded);
+
+        // the signature doesn't hold for another network key.
+        let mut forged = identity;
+        forged.identity = NetworkIdentity::new(account.public_key.clone());
+        assert!(forged.verify().is_err());
+        Ok(())
+    }
+}
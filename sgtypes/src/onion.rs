@@ -0,0 +1,444 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Sphinx style onion for multi hop htlc payments.
+//!
+//! The sender wraps what every hop needs to know in layers encrypted by secrets shared
+//! with the hops, so a hop learns only its predecessor, its successor, the amount to forward
+//! and the timeout. Failures travel back in layers encrypted by the same secrets,
+//! and only the sender can tell which hop failed.
+
+use crate::message::NextHop;
+use crate::sg_error::{SgError, SgErrorCode};
+use anyhow::{bail, ensure, format_err, Error, Result};
+use curve25519_dalek::{
+    constants::X25519_BASEPOINT, edwards::CompressedEdwardsY, montgomery::MontgomeryPoint,
+    scalar::Scalar,
+};
+use hmac::{Hmac, Mac};
+use libra_crypto::ed25519::{Ed25519PrivateKey, Ed25519PublicKey};
+use libra_types::account_address::{AccountAddress, ADDRESS_LENGTH};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use std::convert::{TryFrom, TryInto};
+
+/// max hops of a route, including the receiver.
+pub const MAX_HOPS: usize = 20;
+pub const KEY_SIZE: usize = 32;
+pub const HMAC_SIZE: usize = 32;
+/// address of next hop, amount and timeout to forward.
+const PAYLOAD_SIZE: usize = ADDRESS_LENGTH + 8 + 8;
+const HOP_DATA_SIZE: usize = PAYLOAD_SIZE + HMAC_SIZE;
+pub const ROUTING_INFO_SIZE: usize = MAX_HOPS * HOP_DATA_SIZE;
+/// failure is padded to this size, so its length tells nothing about the failed hop.
+pub const FAILURE_SIZE: usize = 256;
+
+const RHO: &[u8] = b"rho";
+const MU: &[u8] = b"mu";
+const UM: &[u8] = b"um";
+const AMMAG: &[u8] = b"ammag";
+
+/// Secret shared by the sender and a hop.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SharedSecret([u8; KEY_SIZE]);
+
+impl SharedSecret {
+    fn from_point(point: &MontgomeryPoint) -> Result<Self> {
+        ensure!(
+            point.to_bytes() != [0u8; 32],
+            "shared secret is low order point"
+        );
+        let mut secret = [0u8; KEY_SIZE];
+        secret.copy_from_slice(&Sha256::digest(&point.to_bytes()));
+        Ok(Self(secret))
+    }
+
+    fn key(&self, key_type: &[u8]) -> [u8; KEY_SIZE] {
+        hmac(key_type, &[&self.0[..]])
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OnionPacket {
+    pub ephemeral_key: [u8; 32],
+    pub routing_info: Vec<u8>,
+    pub hmac: [u8; HMAC_SIZE],
+}
+
+impl OnionPacket {
+    /// Wrap `payloads` for hops owning `public_keys` in layers, `associated_data` is bound to
+    /// the packet, so it can't be moved to another payment.
+    /// Return the packet for the first hop, and secrets shared with every hop.
+    pub fn new(
+        public_keys: &[Ed25519PublicKey],
+        payloads: &[NextHop],
+        associated_data: &[u8],
+    ) -> Result<(Self, Vec<SharedSecret>)> {
+        let session_key = Scalar::from_bytes_mod_order(rand::thread_rng().gen());
+        Self::new_with_session_key(session_key, public_keys, payloads, associated_data)
+    }
+
+    fn new_with_session_key(
+        session_key: Scalar,
+        public_keys: &[Ed25519PublicKey],
+        payloads: &[NextHop],
+        associated_data: &[u8],
+    ) -> Result<(Self, Vec<SharedSecret>)> {
+        let num_hops = public_keys.len();
+        ensure!(
+            num_hops > 0 && num_hops <= MAX_HOPS,
+            "onion should have 1 to {} hops",
+            MAX_HOPS
+        );
+        ensure!(
+            payloads.len() == num_hops,
+            "should have payload of every hop"
+        );
+
+        // ephemeral key is blinded at every hop, so hops can't link the packets they see.
+        let ephemeral_key = X25519_BASEPOINT * session_key;
+        let mut alpha = ephemeral_key;
+        let mut blinding = session_key;
+        let mut secrets = Vec::with_capacity(num_hops);
+        for public_key in public_keys {
+            let secret = SharedSecret::from_point(&(to_montgomery(public_key)? * blinding))?;
+            let factor = blinding_factor(&alpha, &secret);
+            alpha = alpha * factor;
+            blinding *= factor;
+            secrets.push(secret);
+        }
+
+        // what the hops before the last one append to the routing info when they shift it.
+        let mut filler = vec![];
+        for secret in secrets[..num_hops - 1].iter() {
+            filler.extend_from_slice(&[0u8; HOP_DATA_SIZE]);
+            let stream = generate_stream(&secret.key(RHO), ROUTING_INFO_SIZE + HOP_DATA_SIZE);
+            let offset = stream.len() - filler.len();
+            xor(&mut filler, &stream[offset..]);
+        }
+
+        let mut routing_info = vec![0u8; ROUTING_INFO_SIZE];
+        let mut next_hmac = [0u8; HMAC_SIZE];
+        for (index, (secret, payload)) in secrets.iter().zip(payloads).enumerate().rev() {
+            routing_info.truncate(ROUTING_INFO_SIZE - HOP_DATA_SIZE);
+            let mut hop_data = encode_payload(payload);
+            hop_data.extend_from_slice(&next_hmac);
+            hop_data.append(&mut routing_info);
+            routing_info = hop_data;
+            xor(
+                &mut routing_info,
+                &generate_stream(&secret.key(RHO), ROUTING_INFO_SIZE),
+            );
+            if index == num_hops - 1 {
+                let offset = ROUTING_INFO_SIZE - filler.len();
+                routing_info[offset..].copy_from_slice(&filler);
+            }
+            next_hmac = hmac(&secret.key(MU), &[&routing_info[..], associated_data]);
+        }
+
+        let packet = Self {
+            ephemeral_key: ephemeral_key.to_bytes(),
+            routing_info,
+            hmac: next_hmac,
+        };
+        Ok((packet, secrets))
+    }
+
+    /// Peel the outer layer by hop's key, return its payload, packet for the next hop,
+    /// which is None for the last hop, and the secret shared with sender.
+    pub fn peel(
+        &self,
+        private_key: &Ed25519PrivateKey,
+        associated_data: &[u8],
+    ) -> Result<(NextHop, Option<OnionPacket>, SharedSecret)> {
+        let secret = shared_secret(private_key, &self.ephemeral_key)?;
+        self.peel_with_secret(secret, associated_data)
+    }
+
+    /// Same as `peel`, by the secret the hop derived from `ephemeral_key` of the packet.
+    pub fn peel_with_secret(
+        &self,
+        secret: SharedSecret,
+        associated_data: &[u8],
+    ) -> Result<(NextHop, Option<OnionPacket>, SharedSecret)> {
+        let alpha = MontgomeryPoint(self.ephemeral_key);
+        ensure!(
+            verify_hmac(
+                &secret.key(MU),
+                &[&self.routing_info[..], associated_data],
+                &self.hmac
+            ),
+            "onion hmac mismatch"
+        );
+
+        let mut bytes = self.routing_info.clone();
+        bytes.extend_from_slice(&[0u8; HOP_DATA_SIZE]);
+        xor(
+            &mut bytes,
+            &generate_stream(&secret.key(RHO), ROUTING_INFO_SIZE + HOP_DATA_SIZE),
+        );
+        let payload = decode_payload(&bytes[..PAYLOAD_SIZE])?;
+        let mut next_hmac = [0u8; HMAC_SIZE];
+        next_hmac.copy_from_slice(&bytes[PAYLOAD_SIZE..HOP_DATA_SIZE]);
+        if next_hmac == [0u8; HMAC_SIZE] {
+            return Ok((payload, None, secret));
+        }
+        let next = Self {
+            ephemeral_key: (alpha * blinding_factor(&alpha, &secret)).to_bytes(),
+            routing_info: bytes[HOP_DATA_SIZE..].to_vec(),
+            hmac: next_hmac,
+        };
+        Ok((payload, Some(next), secret))
+    }
+}
+
+/// Secret shared by the hop of `private_key` with the sender of a packet whose ephemeral key
+/// is `ephemeral_key`.
+pub fn shared_secret(
+    private_key: &Ed25519PrivateKey,
+    ephemeral_key: &[u8; 32],
+) -> Result<SharedSecret> {
+    SharedSecret::from_point(&(MontgomeryPoint(*ephemeral_key) * to_scalar(private_key)))
+}
+
+/// Failure created by the hop sharing `secret` with the sender.
+pub fn create_failure(secret: &SharedSecret, error: &SgError) -> Vec<u8> {
+    let message = error.error_message.as_bytes();
+    let len = std::cmp::min(message.len(), FAILURE_SIZE - 6);
+    let mut data = Vec::with_capacity(FAILURE_SIZE);
+    data.extend_from_slice(&Into::<u32>::into(error.error_code).to_le_bytes());
+    data.extend_from_slice(&(len as u16).to_le_bytes());
+    data.extend_from_slice(&message[..len]);
+    data.resize(FAILURE_SIZE, 0);
+
+    let mut failure = hmac(&secret.key(UM), &[&data[..]]).to_vec();
+    failure.append(&mut data);
+    wrap_failure(secret, failure)
+}
+
+/// Every hop on the way back encrypts the failure once more.
+pub fn wrap_failure(secret: &SharedSecret, mut failure: Vec<u8>) -> Vec<u8> {
+    let stream = generate_stream(&secret.key(AMMAG), failure.len());
+    xor(&mut failure, &stream);
+    failure
+}
+
+/// Peel the failure by `secrets` shared with hops in the order of the route,
+/// return index of the hop which created it, and the error.
+pub fn decode_failure(secrets: &[SharedSecret], failure: &[u8]) -> Result<(usize, SgError)> {
+    ensure!(
+        failure.len() == HMAC_SIZE + FAILURE_SIZE,
+        "failure should be {} bytes",
+        HMAC_SIZE + FAILURE_SIZE
+    );
+    let mut failure = failure.to_vec();
+    for (index, secret) in secrets.iter().enumerate() {
+        failure = wrap_failure(secret, failure);
+        let (mac, data) = failure.split_at(HMAC_SIZE);
+        if verify_hmac(&secret.key(UM), &[data], mac) {
+            let code = u32::from_le_bytes(data[..4].try_into()?);
+            let len = u16::from_le_bytes(data[4..6].try_into()?) as usize;
+            ensure!(len <= FAILURE_SIZE - 6, "failure message is too long");
+            let error = SgError::new(
+                SgErrorCode::try_from(code).unwrap_or(SgErrorCode::UNKNOWN),
+                String::from_utf8_lossy(&data[6..6 + len]).into_owned(),
+            );
+            return Ok((index, error));
+        }
+    }
+    bail!("failure is not created by any hop of the route")
+}
+
+fn encode_payload(payload: &NextHop) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(PAYLOAD_SIZE);
+    bytes.extend_from_slice(&payload.remote_addr.to_vec());
+    bytes.extend_from_slice(&payload.amount.to_le_bytes());
+    bytes.extend_from_slice(&payload.timeout.to_le_bytes());
+    bytes
+}
+
+fn decode_payload(bytes: &[u8]) -> Result<NextHop> {
+    let remote_addr = AccountAddress::try_from(&bytes[..ADDRESS_LENGTH])?;
+    let amount = u64::from_le_bytes(bytes[ADDRESS_LENGTH..ADDRESS_LENGTH + 8].try_into()?);
+    let timeout = u64::from_le_bytes(bytes[ADDRESS_LENGTH + 8..PAYLOAD_SIZE].try_into()?);
+    Ok(NextHop::new(remote_addr, amount, timeout))
+}
+
+/// x25519 key of a node is derived from its ed25519 key, the same way as ed25519 does.
+fn to_scalar(private_key: &Ed25519PrivateKey) -> Scalar {
+    let hash = Sha512::digest(&private_key.to_bytes());
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&hash[..32]);
+    bytes[0] &= 248;
+    bytes[31] &= 127;
+    bytes[31] |= 64;
+    Scalar::from_bits(bytes)
+}
+
+fn to_montgomery(public_key: &Ed25519PublicKey) -> Result<MontgomeryPoint> {
+    CompressedEdwardsY(public_key.to_bytes())
+        .decompress()
+        .map(|point| point.to_montgomery())
+        .ok_or_else(|| format_err!("invalid ed25519 public key"))
+}
+
+fn blinding_factor(alpha: &MontgomeryPoint, secret: &SharedSecret) -> Scalar {
+    let mut hasher = Sha256::new();
+    hasher.input(alpha.as_bytes());
+    hasher.input(&secret.0);
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&hasher.result());
+    Scalar::from_bytes_mod_order(bytes)
+}
+
+pub(crate) fn hmac(key: &[u8], data: &[&[u8]]) -> [u8; HMAC_SIZE] {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("hmac can take key of any size");
+    for d in data {
+        mac.input(d);
+    }
+    let mut result = [0u8; HMAC_SIZE];
+    result.copy_from_slice(&mac.result().code());
+    result
+}
+
+pub(crate) fn verify_hmac(key: &[u8], data: &[&[u8]], expected: &[u8]) -> bool {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("hmac can take key of any size");
+    for d in data {
+        mac.input(d);
+    }
+    mac.verify(expected).is_ok()
+}
+
+/// pseudo random stream from hmac of a counter.
+pub(crate) fn generate_stream(key: &[u8], len: usize) -> Vec<u8> {
+    let mut stream = Vec::with_capacity(len + HMAC_SIZE);
+    let mut counter = 0u64;
+    while stream.len() < len {
+        stream.extend_from_slice(&hmac(key, &[&counter.to_le_bytes()[..]]));
+        counter += 1;
+    }
+    stream.truncate(len);
+    stream
+}
+
+pub(crate) fn xor(data: &mut [u8], stream: &[u8]) {
+    for (d, s) in data.iter_mut().zip(stream) {
+        *d ^= s;
+    }
+}
+
+/// Encrypt `data` by `secret`, and append a mac of the cipher text.
+pub(crate) fn seal(secret: &[u8], mut data: Vec<u8>) -> Vec<u8> {
+    let stream = generate_stream(&hmac(RHO, &[secret]), data.len());
+    xor(&mut data, &stream);
+    let mac = hmac(&hmac(MU, &[secret]), &[&data]);
+    data.extend_from_slice(&mac);
+    data
+}
+
+pub(crate) fn open(secret: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+    ensure!(sealed.len() >= HMAC_SIZE, "sealed data is too short");
+    let (data, mac) = sealed.split_at(sealed.len() - HMAC_SIZE);
+    ensure!(
+        verify_hmac(&hmac(MU, &[secret]), &[data], mac),
+        "sealed data hmac mismatched"
+    );
+    let mut data = data.to_vec();
+    let stream = generate_stream(&hmac(RHO, &[secret]), data.len());
+    xor(&mut data, &stream);
+    Ok(data)
+}
+
+impl TryFrom<crate::proto::sgtypes::OnionPacket> for OnionPacket {
+    type Error = Error;
+
+    fn try_from(value: crate::proto::sgtypes::OnionPacket) -> Result<Self> {
+        ensure!(
+            value.routing_info.len() == ROUTING_INFO_SIZE,
+            "routing info should be {} bytes",
+            ROUTING_INFO_SIZE
+        );
+        Ok(Self {
+            ephemeral_key: value.ephemeral_key.as_slice().try_into()?,
+            routing_info: value.routing_info,
+            hmac: value.hmac.as_slice().try_into()?,
+        })
+    }
+}
+
+impl From<OnionPacket> for crate::proto::sgtypes::OnionPacket {
+    fn from(value: OnionPacket) -> Self {
+        Self {
+            ephemeral_key: value.ephemeral_key.to_vec(),
+            routing_info: value.routing_info,
+            hmac: value.hmac.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::{test_utils::KeyPair, Uniform};
+    use rand::prelude::*;
+
+    #[test]
+    fn test_onion_packet() {
+        let mut rng: StdRng = SeedableRng::seed_from_u64(1);
+        let keypairs: Vec<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> = (0..3)
+            .map(|_| KeyPair::generate_for_testing(&mut rng))
+            .collect();
+        let addrs: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| AccountAddress::from_public_key(&keypair.public_key))
+            .collect();
+        let payloads = vec![
+            NextHop::new(addrs[1], 102, 30),
+            NextHop::new(addrs[2], 100, 20),
+            NextHop::new(addrs[2], 100, 20),
+        ];
+        let public_keys: Vec<_> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let hash_lock = b"hash lock";
+        let (packet, secrets) = OnionPacket::new(&public_keys, &payloads, hash_lock).unwrap();
+
+        let proto: crate::proto::sgtypes::OnionPacket = packet.clone().into();
+        let mut packet = OnionPacket::try_from(proto).unwrap();
+        for (index, keypair) in keypairs.iter().enumerate() {
+            assert!(packet.peel(&keypair.private_key, b"other lock").is_err());
+            let (payload, next, secret) = packet.peel(&keypair.private_key, hash_lock).unwrap();
+            assert_eq!(payloads[index], payload);
+            assert_eq!(secrets[index], secret);
+            match next {
+                Some(next) => {
+                    assert_ne!(packet.ephemeral_key, next.ephemeral_key);
+                    packet = next;
+                }
+                None => assert_eq!(keypairs.len() - 1, index),
+            }
+        }
+
+        let mut tampered = OnionPacket::new(&public_keys, &payloads, hash_lock)
+            .unwrap()
+            .0;
+        tampered.routing_info[0] ^= 1;
+        assert!(tampered.peel(&keypairs[0].private_key, hash_lock).is_err());
+    }
+
+    #[test]
+    fn test_onion_failure() {
+        let secrets: Vec<_> = (0..3u8).map(|i| SharedSecret([i; KEY_SIZE])).collect();
+        let error = SgError::new(SgErrorCode::FEE_INSUFFICIENT, "fee".to_string());
+
+        let failure = create_failure(&secrets[1], &error);
+        assert_eq!(HMAC_SIZE + FAILURE_SIZE, failure.len());
+        let failure = wrap_failure(&secrets[0], failure);
+        assert_eq!((1, error), decode_failure(&secrets, &failure).unwrap());
+
+        let mut tampered = failure.clone();
+        tampered[HMAC_SIZE] ^= 1;
+        assert!(decode_failure(&secrets, &tampered).is_err());
+        // hops after the failed one can't tell which hop it is from.
+        assert!(decode_failure(&secrets[2..], &failure).is_err());
+    }
+}
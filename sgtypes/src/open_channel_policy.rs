@@ -0,0 +1,220 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Terms a node accepts for channels opened by others, see `OpenChannelNodeNegotiateMessage`.
+
+use crate::message::RawNegotiateMessage;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpenChannelPolicy {
+    /// max amount we deposit into a channel opened by others.
+    pub max_contribution: u64,
+    /// min reserve we require of both participants.
+    pub min_reserve: u64,
+    /// packages allowed in channels, None to allow all installed ones.
+    pub packages: Option<Vec<String>>,
+}
+
+impl Default for OpenChannelPolicy {
+    fn default() -> Self {
+        Self {
+            max_contribution: std::u64::MAX,
+            min_reserve: 0,
+            packages: None,
+        }
+    }
+}
+
+/// Terms agreed by participants when the channel is opened, proposals breaking them are rejected.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelTerms {
+    /// min balance of the default asset each participant keeps in the channel.
+    pub min_reserve: u64,
+    /// script packages which can be executed in the channel.
+    pub packages: Vec<String>,
+}
+
+impl ChannelTerms {
+    pub fn new(min_reserve: u64, packages: Vec<String>) -> Self {
+        Self {
+            min_reserve,
+            packages,
+        }
+    }
+}
+
+impl From<&RawNegotiateMessage> for ChannelTerms {
+    fn from(terms: &RawNegotiateMessage) -> Self {
+        Self::new(terms.min_reserve, terms.packages.clone())
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NegotiateResponse {
+    Accept,
+    /// terms we would accept instead, with why the proposed ones are not.
+    Counter(RawNegotiateMessage, String),
+    Reject(String),
+}
+
+impl OpenChannelPolicy {
+    pub fn new(max_contribution: u64, min_reserve: u64, packages: Option<Vec<String>>) -> Self {
+        Self {
+            max_contribution,
+            min_reserve,
+            packages,
+        }
+    }
+
+    /// Respond to `proposal` as its receiver, `installed` tells whether a package is installed.
+    pub fn respond<F>(&self, proposal: &RawNegotiateMessage, installed: F) -> NegotiateResponse
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut counter = proposal.clone();
+        let mut reasons = vec![];
+
+        let (accepted, refused): (Vec<String>, Vec<String>) =
+            proposal.packages.iter().cloned().partition(|package| {
+                installed(package)
+                    && self
+                        .packages
+                        .as_ref()
+                        .map_or(true, |packages| packages.contains(package))
+            });
+        if !refused.is_empty() {
+            counter.packages = accepted;
+            reasons.push(format!("packages {:?} are not accepted", refused));
+        }
+        if counter.min_reserve < self.min_reserve {
+            reasons.push(format!(
+                "reserve {} < {}",
+                counter.min_reserve, self.min_reserve
+            ));
+            counter.min_reserve = self.min_reserve;
+        }
+        if counter.min_reserve > self.max_contribution {
+            return NegotiateResponse::Reject(format!(
+                "reserve {} > max contribution {}",
+                counter.min_reserve, self.max_contribution
+            ));
+        }
+        if counter.sender_amount < counter.min_reserve {
+            return NegotiateResponse::Reject(format!(
+                "sender amount {} < reserve {}",
+                counter.sender_amount, counter.min_reserve
+            ));
+        }
+        if counter.receiver_amount > self.max_contribution {
+            reasons.push(format!(
+                "receiver amount {} > max contribution {}",
+                counter.receiver_amount, self.max_contribution
+            ));
+            counter.receiver_amount = self.max_contribution;
+        } else if counter.receiver_amount < counter.min_reserve {
+            reasons.push(format!(
+                "receiver amount {} < reserve {}",
+                counter.receiver_amount, counter.min_reserve
+            ));
+            counter.receiver_amount = counter.min_reserve;
+        }
+
+        if reasons.is_empty() {
+            NegotiateResponse::Accept
+        } else {
+            NegotiateResponse::Counter(counter, reasons.join("; "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{NegotiateAction, OpenChannelNodeNegotiateMessage, StructTag};
+    use libra_crypto::{
+        ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+        test_utils::KeyPair,
+    };
+    use libra_types::account_address::AccountAddress;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_negotiate_open_channel() {
+        let mut rng: StdRng = SeedableRng::from_seed([0; 32]);
+        let sender: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let receiver: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        let proposal = RawNegotiateMessage::new(
+            AccountAddress::from_public_key(&sender.public_key),
+            StructTag::new(
+                AccountAddress::default(),
+                "LibraCoin".to_string(),
+                "T".to_string(),
+                vec![],
+            ),
+            1000,
+            AccountAddress::from_public_key(&receiver.public_key),
+            1000,
+            10,
+            vec!["libra".to_string(), "gobang".to_string()],
+        );
+        let installed = |package: &str| package == "libra";
+
+        // receiver deposits at most 500, and needs a higher reserve.
+        let policy = OpenChannelPolicy::new(500, 100, None);
+        let counter = match policy.respond(&proposal, installed) {
+            NegotiateResponse::Counter(counter, _) => counter,
+            response => panic!("expect counter, got {:?}", response),
+        };
+        assert_eq!(500, counter.receiver_amount);
+        assert_eq!(100, counter.min_reserve);
+        assert_eq!(vec!["libra".to_string()], counter.packages);
+        assert!(proposal.check_counter(&counter).is_ok());
+        assert_eq!(
+            NegotiateResponse::Accept,
+            policy.respond(&counter, installed)
+        );
+
+        let mut greedy = counter.clone();
+        greedy.sender_amount += 1;
+        assert!(proposal.check_counter(&greedy).is_err());
+
+        let policy = OpenChannelPolicy::new(50, 100, None);
+        match policy.respond(&proposal, installed) {
+            NegotiateResponse::Reject(_) => {}
+            response => panic!("expect reject, got {:?}", response),
+        }
+
+        let mut message = OpenChannelNodeNegotiateMessage::new(
+            counter,
+            NegotiateAction::Counter,
+            None,
+            None,
+            String::new(),
+        );
+        message
+            .sign(&receiver.private_key, receiver.public_key.clone())
+            .unwrap();
+        message.verify().unwrap();
+        assert!(!message.is_agreed());
+        message
+            .sign(&sender.private_key, sender.public_key.clone())
+            .unwrap();
+        assert!(message.is_agreed());
+        let decoded = OpenChannelNodeNegotiateMessage::from_proto_bytes(
+            message.clone().into_proto_bytes().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(message, decoded);
+
+        message.raw_negotiate_message.sender_amount = 1;
+        assert!(message.verify().is_err());
+        let stranger: KeyPair<Ed25519PrivateKey, Ed25519PublicKey> =
+            KeyPair::generate_for_testing(&mut rng);
+        assert!(message
+            .sign(&stranger.private_key, stranger.public_key.clone())
+            .is_err());
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::sg_error::{SgError, SgErrorCode};
+use libra_crypto::HashValue;
+use libra_types::account_address::AccountAddress;
+use serde::{Deserialize, Serialize};
+
+/// A payment is InFlight until the preimage comes back (Succeeded),
+/// or no route is found, or one of its attempts fails (Failed).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PaymentStatus {
+    InFlight,
+    Succeeded,
+    Failed,
+}
+
+impl PaymentStatus {
+    pub fn is_final(&self) -> bool {
+        *self != PaymentStatus::InFlight
+    }
+}
+
+/// Why an attempt failed, `hop` is the node reported the error, if known.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AttemptFailure {
+    pub code: SgErrorCode,
+    pub message: String,
+    pub hop: Option<AccountAddress>,
+}
+
+impl AttemptFailure {
+    pub fn new(error: &SgError, hop: Option<AccountAddress>) -> Self {
+        Self {
+            code: error.error_code,
+            message: error.error_message.clone(),
+            hop,
+        }
+    }
+}
+
+/// A htlc sent over one route, a multi-path payment has one attempt per shard.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PaymentAttempt {
+    /// id of the channel txn request sent to the first hop.
+    pub request_id: HashValue,
+    /// vertexes of the route, starting from the payer and ending at the receiver.
+    pub route: Vec<AccountAddress>,
+    pub amount: u64,
+    pub fee: u64,
+    pub status: PaymentStatus,
+    pub failure: Option<AttemptFailure>,
+}
+
+impl PaymentAttempt {
+    pub fn new(request_id: HashValue, route: Vec<AccountAddress>, amount: u64, fee: u64) -> Self {
+        Self {
+            request_id,
+            route,
+            amount,
+            fee,
+            status: PaymentStatus::InFlight,
+            failure: None,
+        }
+    }
+}
+
+/// An outgoing htlc payment with all its attempts, identified by the hash lock.
+/// `created_at` and `updated_at` are unix timestamps in milliseconds.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRecord {
+    r_hash: HashValue,
+    receiver: AccountAddress,
+    amount: u64,
+    status: PaymentStatus,
+    attempts: Vec<PaymentAttempt>,
+    preimage: Option<Vec<u8>>,
+    /// why the payment failed before any attempt is made, e.g. no route.
+    failure: Option<AttemptFailure>,
+    created_at: u64,
+    updated_at: u64,
+}
+
+impl PaymentRecord {
+    pub fn new(r_hash: HashValue, receiver: AccountAddress, amount: u64, now: u64) -> Self {
+        Self {
+            r_hash,
+            receiver,
+            amount,
+            status: PaymentStatus::InFlight,
+            attempts: vec![],
+            preimage: None,
+            failure: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn r_hash(&self) -> &HashValue {
+        &self.r_hash
+    }
+    pub fn receiver(&self) -> AccountAddress {
+        self.receiver
+    }
+    pub fn amount(&self) -> u64 {
+        self.amount
+    }
+    pub fn status(&self) -> PaymentStatus {
+        self.status
+    }
+    pub fn attempts(&self) -> &[PaymentAttempt] {
+        self.attempts.as_slice()
+    }
+    pub fn preimage(&self) -> Option<&[u8]> {
+        self.preimage.as_ref().map(|p| p.as_slice())
+    }
+    pub fn failure(&self) -> Option<&AttemptFailure> {
+        self.failure.as_ref()
+    }
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+    pub fn updated_at(&self) -> u64 {
+        self.updated_at
+    }
+
+    /// total fees of the attempts which are not failed.
+    pub fn fee(&self) -> u64 {
+        self.attempts
+            .iter()
+            .filter(|attempt| attempt.status != PaymentStatus::Failed)
+            .map(|attempt| attempt.fee)
+            .sum()
+    }
+
+    /// Add an attempt, a failed payment is in flight again.
+    pub fn add_attempt(&mut self, attempt: PaymentAttempt, now: u64) {
+        self.attempts.push(attempt);
+        self.status = PaymentStatus::InFlight;
+        self.updated_at = now;
+    }
+
+    /// Mark attempt `request_id` as failed, so is the payment, as the receiver
+    /// needs all shards to release the preimage.
+    /// Return false if there is no such attempt in flight.
+    pub fn fail_attempt(
+        &mut self,
+        request_id: &HashValue,
+        failure: AttemptFailure,
+        now: u64,
+    ) -> bool {
+        let attempt = match self
+            .attempts
+            .iter_mut()
+            .find(|attempt| &attempt.request_id == request_id)
+        {
+            Some(attempt) if attempt.status == PaymentStatus::InFlight => attempt,
+            _ => return false,
+        };
+        attempt.status = PaymentStatus::Failed;
+        attempt.failure = Some(failure);
+        if self.status == PaymentStatus::InFlight {
+            self.status = PaymentStatus::Failed;
+        }
+        self.updated_at = now;
+        true
+    }
+
+    /// The payment failed without sending an attempt.
+    pub fn fail(&mut self, failure: AttemptFailure, now: u64) {
+        self.failure = Some(failure);
+        self.status = PaymentStatus::Failed;
+        self.updated_at = now;
+    }
+
+    /// The preimage is released by the receiver, all attempts in flight are settled.
+    pub fn succeed(&mut self, preimage: Vec<u8>, now: u64) {
+        for attempt in self.attempts.iter_mut() {
+            if attempt.status == PaymentStatus::InFlight {
+                attempt.status = PaymentStatus::Succeeded;
+            }
+        }
+        self.preimage = Some(preimage);
+        self.status = PaymentStatus::Succeeded;
+        self.updated_at = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payment_status() {
+        let (payer, receiver) = (AccountAddress::random(), AccountAddress::random());
+        let preimage = HashValue::random().to_vec();
+        let r_hash = HashValue::from_sha3_256(preimage.as_slice());
+        let mut record = PaymentRecord::new(r_hash, receiver, 1000, 1);
+
+        let (id1, id2) = (HashValue::random(), HashValue::random());
+        record.add_attempt(PaymentAttempt::new(id1, vec![payer, receiver], 600, 0), 2);
+        record.add_attempt(PaymentAttempt::new(id2, vec![payer, receiver], 400, 0), 2);
+        assert_eq!(PaymentStatus::InFlight, record.status());
+
+        let error = SgError::new(SgErrorCode::BALANCE_NOT_ENOUGH, "no money".to_string());
+        assert!(record.fail_attempt(&id2, AttemptFailure::new(&error, Some(receiver)), 3));
+        assert!(!record.fail_attempt(&id2, AttemptFailure::new(&error, None), 4));
+        assert_eq!(PaymentStatus::Failed, record.status());
+        assert_eq!(
+            Some(receiver),
+            record.attempts()[1].failure.as_ref().unwrap().hop
+        );
+
+        let id3 = HashValue::random();
+        let error = SgError::new(SgErrorCode::NOT_PATH, "no path".to_string());
+        record.fail(AttemptFailure::new(&error, None), 5);
+        assert_eq!(PaymentStatus::Failed, record.status());
+        assert_eq!(SgErrorCode::NOT_PATH, record.failure().unwrap().code);
+
+        record.add_attempt(PaymentAttempt::new(id3, vec![payer, receiver], 400, 0), 5);
+        assert_eq!(PaymentStatus::InFlight, record.status());
+        record.succeed(preimage.clone(), 6);
+        assert_eq!(PaymentStatus::Succeeded, record.status());
+        assert_eq!(Some(preimage.as_slice()), record.preimage());
+        assert_eq!(PaymentStatus::Succeeded, record.attempts()[0].status);
+        assert_eq!(PaymentStatus::Failed, record.attempts()[1].status);
+        assert_eq!(6, record.updated_at());
+    }
+}
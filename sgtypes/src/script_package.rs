@@ -4,7 +4,10 @@
 use anyhow::{Error, Result};
 use libra_crypto::hash::{CryptoHash, CryptoHasher, TestOnlyHasher};
 use libra_crypto::HashValue;
-use libra_types::transaction::{Script, TransactionArgument};
+use libra_types::{
+    language_storage::StructTag,
+    transaction::{Script, TransactionArgument},
+};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::{
@@ -153,3 +156,23 @@ impl From<ChannelScriptPackage> for crate::proto::sgtypes::ChannelScriptPackage
         }
     }
 }
+
+/// An asset which can be held in channels, moved by scripts of its package.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ChannelAsset {
+    pub asset: StructTag,
+    pub package_name: String,
+    /// channel resource of each participant holding its balance of the asset,
+    /// whose layout should be the same as `ChannelParticipantAccountResource`.
+    pub balance_tag: StructTag,
+}
+
+impl ChannelAsset {
+    pub fn new(asset: StructTag, package_name: String, balance_tag: StructTag) -> Self {
+        Self {
+            asset,
+            package_name,
+            balance_tag,
+        }
+    }
+}
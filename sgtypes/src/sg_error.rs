@@ -4,6 +4,7 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
 
+use libra_crypto::HashValue;
 use libra_types::account_address::AccountAddress;
 
 use crate::channel::ChannelStage;
@@ -37,6 +38,10 @@ pub enum SgErrorCode {
     REJECT = 5,
     NOT_PATH = 6,
     BALANCE_NOT_ENOUGH = 7,
+    FEE_INSUFFICIENT = 8,
+    TIMELOCK_TOO_SHORT = 9,
+    PROPOSAL_EXPIRED = 10,
+    MULTI_PATH_TIMEOUT = 11,
 }
 
 impl std::fmt::Display for SgErrorCode {
@@ -75,4 +80,11 @@ impl SgError {
             format!("Channel at stage: {:?}, unsupported this operator.", stage),
         )
     }
+
+    pub fn new_proposal_expired_error(channel_txn_id: &HashValue) -> Self {
+        Self::new(
+            SgErrorCode::PROPOSAL_EXPIRED,
+            format!("Proposal {} is expired.", channel_txn_id),
+        )
+    }
 }
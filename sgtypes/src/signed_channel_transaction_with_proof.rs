@@ -1,7 +1,9 @@
 use crate::applied_channel_txn::AppliedChannelTxn;
+use crate::channel_checkpoint::ChannelCheckpoint;
 use crate::proof::signed_channel_transaction_proof::SignedChannelTransactionProof;
 
 use anyhow::{ensure, Result};
+use libra_crypto::hash::CryptoHash;
 use libra_types::account_address::AccountAddress;
 use libra_types::contract_event::ContractEvent;
 use libra_types::ledger_info::LedgerInfo;
@@ -73,4 +75,27 @@ impl SignedChannelTransactionWithProof {
 
         Ok(())
     }
+
+    /// Verifies the signed transaction exists in the ledger committed by `checkpoint`,
+    /// the proof should be built towards the ledger at version of the checkpoint.
+    pub fn verify_with_checkpoint(&self, checkpoint: &ChannelCheckpoint) -> Result<()> {
+        ensure!(
+            self.version <= checkpoint.version,
+            "Version ({}) is newer than checkpoint ({}).",
+            self.version,
+            checkpoint.version,
+        );
+        let txn_info = self.proof.transaction_info();
+        ensure!(
+            txn_info.signed_transaction_hash() == self.signed_transaction.hash(),
+            "Transaction hash ({}) not expected ({}).",
+            self.signed_transaction.hash(),
+            txn_info.signed_transaction_hash(),
+        );
+        self.proof.ledger_info_to_transaction_info_proof().verify(
+            checkpoint.accumulator_root,
+            txn_info.hash(),
+            self.version,
+        )
+    }
 }
@@ -28,6 +28,7 @@ use std::{any::type_name, time::Duration};
 use tokio::time::interval;
 use uuid::Uuid;
 
+pub(crate) use txn_stream::get_block_height;
 pub use txn_stream::TransactionWithInfo;
 
 pub type Interest = Box<dyn Fn(&TransactionWithInfo) -> bool + Send + Sync>;
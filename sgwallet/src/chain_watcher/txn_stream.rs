@@ -46,40 +46,48 @@ pub(crate) fn build_request(
         .into()
 }
 
-pub(super) struct TxnQuerier(Arc<dyn ChainClient>);
+/// Get block height of the chain from `BlockMetaResource` of association account.
+/// `known_version` is the ledger version already known by the caller.
+pub(crate) async fn get_block_height(
+    chain_client: &dyn ChainClient,
+    known_version: Option<u64>,
+) -> Result<u64> {
+    let ri = RequestItem::GetAccountState {
+        address: association_address(),
+    };
 
-impl TxnQuerier {
-    async fn get_block_hight(&self, start_version: u64) -> Result<u64> {
-        let ri = RequestItem::GetAccountState {
-            address: association_address(),
-        };
+    let resp: libra_types::get_with_proof::ResponseItem = chain_client
+        .update_to_latest_ledger_async(&build_request(ri, known_version))
+        .await?
+        .response_items
+        .remove(0)
+        .try_into()?;
 
-        let resp: libra_types::get_with_proof::ResponseItem = self
-            .0
-            .update_to_latest_ledger_async(&build_request(ri, Some(start_version)))
-            .await?
-            .response_items
-            .remove(0)
-            .try_into()?;
+    let AccountStateWithProof {
+        blob,
+        version,
+        proof,
+    } = resp.into_get_account_state_response()?;
 
-        let AccountStateWithProof {
-            blob,
-            version,
-            proof,
-        } = resp.into_get_account_state_response()?;
+    let blob = blob.ok_or(format_err!("association account not exists!"))?;
+    let resp = AccountState::from_account_state_blob(
+        version,
+        blob.into(),
+        proof.transaction_info_to_account_proof().clone(),
+    )?;
+    let block_meta = resp
+        .get_resource::<BlockMetaResource>(&DataPath::onchain_resource_path(
+            BlockMetaResource::struct_tag(),
+        ))?
+        .ok_or(format_err!("block meta resource should exists"))?;
+    Ok(block_meta.height)
+}
+
+pub(super) struct TxnQuerier(Arc<dyn ChainClient>);
 
-        let blob = blob.ok_or(format_err!("association account not exists!"))?;
-        let resp = AccountState::from_account_state_blob(
-            version,
-            blob.into(),
-            proof.transaction_info_to_account_proof().clone(),
-        )?;
-        let block_meta = resp
-            .get_resource::<BlockMetaResource>(&DataPath::onchain_resource_path(
-                BlockMetaResource::struct_tag(),
-            ))?
-            .ok_or(format_err!("block meta resource should exists"))?;
-        Ok(block_meta.height)
+impl TxnQuerier {
+    async fn get_block_hight(&self, start_version: u64) -> Result<u64> {
+        get_block_height(self.0.as_ref(), Some(start_version)).await
     }
 }
 
@@ -3,13 +3,17 @@
 use crate::{
     channel::{
         access_local, channel_event_stream::ChannelEventStream, AccessingResource, ApplyPendingTxn,
-        CancelPendingTxn, Channel, ChannelEvent, CollectProposalWithSigs, Execute, GetPendingTxn,
-        GrantProposal,
+        BuildChallengeTxn, BuildReestablish, CancelPendingTxn, Channel, ChannelEvent,
+        CollectProposalWithSigs, Execute, ExpirePendingTxn, GetPendingTxn, GrantProposal,
+        Reestablish,
     },
-    utils::contract::{
-        channel_challenge_name, channel_close_name, channel_resolve_name, parse_channel_event,
+    utils::{
+        actor_timer::Timer,
+        contract::{
+            channel_challenge_name, channel_close_name, channel_resolve_name, parse_channel_event,
+        },
     },
-    wallet::{submit_transaction, watch_transaction, ChannelNotifyEvent},
+    wallet::{is_txn_expired, submit_transaction, watch_transaction, ChannelNotifyEvent},
 };
 use anyhow::{bail, ensure, format_err, Result};
 use async_trait::async_trait;
@@ -19,31 +23,38 @@ use coerce_rt::actor::{
     Actor, ActorRef, GetActorRef,
 };
 use futures::{future::abortable, StreamExt};
-use libra_crypto::hash::CryptoHash;
+use libra_crypto::{hash::CryptoHash, HashValue};
 use libra_logger::prelude::*;
 use libra_types::{
     account_address::AccountAddress,
     account_config::{account_module_name, core_code_address},
-    channel::ChannelResource,
+    channel::{ChannelResource, Witness, WitnessData},
     contract_event::{ContractEvent, EventWithProof},
     transaction::{
         SignedTransaction, Transaction, TransactionArgument, TransactionInfo,
         TransactionListWithProof, TransactionOutput, TransactionPayload, TransactionWithProof,
     },
+    vm_error::StatusCode,
     write_set::WriteSet,
 };
+use sgstorage::channel_terms_store::ChannelTermsStore;
 use sgtypes::{
     account_state::AccountState,
     applied_channel_txn::AppliedChannelTxn,
     channel::ChannelState,
+    channel_reestablish::{ChannelReestablish, ReestablishAction},
     channel_transaction::{ChannelOp, ChannelTransaction},
     channel_transaction_sigs::ChannelTransactionSigs,
     channel_transaction_to_commit::ChannelTransactionToCommit,
+    justice::witness_hash,
     pending_txn::{PendingTransaction, ProposalLifecycle},
     signed_channel_transaction::SignedChannelTransaction,
     signed_channel_transaction_with_proof::SignedChannelTransactionWithProof,
 };
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
+
+/// how often the channel checks whether its pending txn is expired.
+const PROPOSAL_EXPIRATION_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 /// Sync with layer1
 /// First, get current state of channel in layer1,
@@ -96,6 +107,11 @@ impl Channel {
                 }
             }
         }
+        // offchain txns may be agreed on top of the travelling txn.
+        if let Some(witness) = self.speculative_witness() {
+            self.stm
+                .advance_state(None, witness, self.store.get_participant_keys());
+        }
 
         Ok(())
     }
@@ -104,6 +120,10 @@ impl Channel {
 #[async_trait]
 impl Actor for Channel {
     async fn started(&mut self, ctx: &mut ActorHandlerContext) {
+        if let Some(travelling_txn) = self.store.get_travelling_txn() {
+            let channel_txn = &travelling_txn.proposal().channel_txn;
+            self.watch_travelling_txn(ctx, channel_txn.proposer(), channel_txn.sequence_number());
+        }
         // check pending state
         if let Some(pending_proposal) = self.pending_txn() {
             match pending_proposal.lifecycle() {
@@ -113,7 +133,9 @@ impl Actor for Channel {
                         ctx.set_status(ActorStatus::Stopping);
                     }
                     Ok(Some((txn_sender, seq_number))) => {
-                        if let Err(e) = self
+                        if self.store.get_travelling_txn().is_some() {
+                            self.watch_travelling_txn(ctx, txn_sender, seq_number);
+                        } else if let Err(e) = self
                             .handle(
                                 WatchAndApplyTravelTxn {
                                     sender: txn_sender,
@@ -150,6 +172,16 @@ impl Actor for Channel {
             }
         }
 
+        let myself = self.get_ref(ctx);
+        self.expiration_timer = Some(Timer::start(
+            myself,
+            std::cmp::min(
+                PROPOSAL_EXPIRATION_CHECK_INTERVAL,
+                self.stm.proposal_expiration(),
+            ),
+            ExpirePendingTxn,
+        ));
+
         let start_number = self
             .stm
             .channel_resource()
@@ -171,6 +203,9 @@ impl Actor for Channel {
 
     async fn stopped(&mut self, _ctx: &mut ActorHandlerContext) {
         self.abort_sub_tasks();
+        if let Some(timer) = self.expiration_timer.take() {
+            timer.stop();
+        }
         if let Err(e) = self
             .channel_event_sender
             .notify(ChannelNotifyEvent {
@@ -199,6 +234,8 @@ impl Handler<Execute> for Channel {
         self.stm.handle_new_proposal(&mut pending_txn, proposal)?;
         debug_assert!(pending_txn.is_some());
         let mut pending_txn = pending_txn.unwrap();
+        self.check_travelling(&pending_txn)?;
+        self.check_terms(&pending_txn)?;
         let my_signature = self
             .stm
             .generate_txn_sigs(&pending_txn.proposal().channel_txn, pending_txn.output())?;
@@ -253,11 +290,32 @@ impl Handler<CollectProposalWithSigs> for Channel {
                 }
             }
         }
+        // or it's the travelling txn, or already applied on top of it.
+        if let Some(speculative_txn) = self
+            .store
+            .get_travelling_txn()
+            .into_iter()
+            .chain(self.store.get_speculative_txns())
+            .find(|t| {
+                t.proposal().channel_txn.channel_sequence_number()
+                    == proposal.channel_txn.channel_sequence_number()
+            })
+        {
+            ensure!(
+                CryptoHash::hash(&proposal.channel_txn)
+                    == CryptoHash::hash(&speculative_txn.proposal().channel_txn),
+                "invalid proposal, channel already applied a different proposal with same channel seq number {}",
+                proposal.channel_txn.channel_sequence_number()
+            );
+            return Ok(speculative_txn.get_signature(&self.account_address()));
+        }
         let mut pending_txn = self.pending_txn();
         self.stm.handle_new_proposal(&mut pending_txn, proposal)?;
         debug_assert!(pending_txn.is_some());
 
         let mut pending_txn = pending_txn.unwrap();
+        self.check_travelling(&pending_txn)?;
+        self.check_terms(&pending_txn)?;
         let txn_hash = CryptoHash::hash(&pending_txn.proposal().channel_txn);
         self.stm
             .handle_proposal_signature(&mut pending_txn, txn_hash, sigs)?;
@@ -351,6 +409,45 @@ impl Handler<CancelPendingTxn> for Channel {
     }
 }
 
+/// Cancel the pending txn if it's still negotiating after its deadline,
+/// participants are told by the wallet, and their late signatures are rejected.
+#[async_trait]
+impl Handler<ExpirePendingTxn> for Channel {
+    async fn handle(
+        &mut self,
+        _message: ExpirePendingTxn,
+        ctx: &mut ActorHandlerContext,
+    ) -> <ExpirePendingTxn as Message>::Result {
+        let pending_txn = match self.pending_txn() {
+            Some(p) => p,
+            None => return,
+        };
+        match pending_txn.lifecycle() {
+            ProposalLifecycle::Created | ProposalLifecycle::Negotiating => {}
+            _ => return,
+        }
+        let channel_txn = &pending_txn.proposal().channel_txn;
+        if !is_txn_expired(channel_txn) {
+            return;
+        }
+        let channel_txn_id = CryptoHash::hash(channel_txn);
+        warn!("{} pending txn {} is expired", &self.stm, channel_txn_id);
+        if let Err(e) = self.clear_pending_txn() {
+            error!("{} fail to clear expired pending txn, {}", &self.stm, e);
+            return;
+        }
+        self.emit_event(ChannelEvent::ProposalExpired {
+            channel_address: self.channel_address().clone(),
+            channel_txn_id,
+            participants: self.participant_addresses().clone(),
+        })
+        .await;
+        if channel_txn.operator().is_open() {
+            ctx.set_status(ActorStatus::Stopping);
+        }
+    }
+}
+
 #[async_trait]
 impl Handler<ApplyPendingTxn> for Channel {
     async fn handle(
@@ -374,8 +471,14 @@ impl Handler<ApplyPendingTxn> for Channel {
         let proposal_lifecycle = pending_txn.lifecycle();
         match proposal_lifecycle {
             ProposalLifecycle::Applying => {
-                let (proposal, output, signatures) = pending_txn.into();
-                self.apply(proposal.channel_txn, output, signatures)?;
+                if self.store.get_travelling_txn().is_some() {
+                    // it's applied into store after the travelling txn is committed onchain.
+                    let witness = offchain_witness(&pending_txn);
+                    self.store.save_speculative_txn(pending_txn)?;
+                    self.stm.advance_state(None, witness, BTreeMap::new());
+                } else {
+                    self.apply_offchain(pending_txn).await?;
+                }
                 return Ok(None);
             }
             ProposalLifecycle::Traveling => {
@@ -387,7 +490,13 @@ impl Handler<ApplyPendingTxn> for Channel {
                 let txn_sender = channel_txn.proposer();
                 let seq_number = channel_txn.sequence_number();
 
-                self.save_pending_txn(pending_txn)?;
+                if self.can_travel_in_background(&pending_txn) {
+                    let witness = travel_output_witness(&pending_txn);
+                    self.store.start_travelling(pending_txn)?;
+                    self.stm.advance_state(None, witness, BTreeMap::new());
+                } else {
+                    self.save_pending_txn(pending_txn)?;
+                }
                 Ok(Some((txn_sender, seq_number)))
             }
             _ => unreachable!(),
@@ -413,6 +522,19 @@ impl Handler<AccessingResource> for Channel {
     }
 }
 
+#[async_trait]
+impl Handler<BuildChallengeTxn> for Channel {
+    async fn handle(
+        &mut self,
+        _message: BuildChallengeTxn,
+        _ctx: &mut ActorHandlerContext,
+    ) -> <BuildChallengeTxn as Message>::Result {
+        debug!("{} build challenge txn", &self.stm);
+        let challenge_txn = self.stm.build_challenge_txn()?;
+        Ok((self.stm.channel_sequence_number(), challenge_txn))
+    }
+}
+
 #[async_trait]
 impl Handler<GetPendingTxn> for Channel {
     async fn handle(
@@ -425,6 +547,73 @@ impl Handler<GetPendingTxn> for Channel {
     }
 }
 
+#[async_trait]
+impl Handler<BuildReestablish> for Channel {
+    async fn handle(
+        &mut self,
+        _message: BuildReestablish,
+        _ctx: &mut ActorHandlerContext,
+    ) -> <BuildReestablish as Message>::Result {
+        debug!("{} build reestablish", &self.stm);
+        self.build_reestablish()
+    }
+}
+
+#[async_trait]
+impl Handler<Reestablish> for Channel {
+    async fn handle(
+        &mut self,
+        message: Reestablish,
+        ctx: &mut ActorHandlerContext,
+    ) -> <Reestablish as Message>::Result {
+        let Reestablish { remote } = message;
+        ensure!(
+            &remote.channel_address == self.channel_address(),
+            "reestablish is not of channel {}",
+            self.channel_address()
+        );
+        let action = self.build_reestablish()?.reconcile(&remote);
+        debug!("{} reestablish, {:?}", &self.stm, &action);
+        let agreed = match &action {
+            ReestablishAction::None | ReestablishAction::Unrecoverable(_) => false,
+            ReestablishAction::CollectSignatures(sigs) => {
+                let mut pending_txn = self
+                    .pending_txn()
+                    .ok_or_else(|| format_err!("no pending txn"))?;
+                let txn_hash = CryptoHash::hash(&pending_txn.proposal().channel_txn);
+                for s in sigs {
+                    self.stm
+                        .handle_proposal_signature(&mut pending_txn, txn_hash, s.clone())?;
+                }
+                self.save_pending_txn(pending_txn.clone())?;
+                pending_txn.lifecycle() == ProposalLifecycle::Agreed
+            }
+            ReestablishAction::CatchUp(sigs) => {
+                let mut pending_txn = self
+                    .pending_txn()
+                    .ok_or_else(|| format_err!("no pending txn"))?;
+                for s in sigs {
+                    self.stm
+                        .handle_applied_proposal_signature(&mut pending_txn, s.clone())?;
+                }
+                self.save_pending_txn(pending_txn.clone())?;
+                pending_txn.lifecycle() == ProposalLifecycle::Agreed
+            }
+            ReestablishAction::Rollback(channel_txn_id) => {
+                self.handle(
+                    CancelPendingTxn {
+                        channel_txn_id: *channel_txn_id,
+                    },
+                    ctx,
+                )
+                .await?;
+                false
+            }
+        };
+        Ok((action, agreed))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct WatchAndApplyTravelTxn {
     pub sender: AccountAddress,
@@ -443,17 +632,39 @@ impl Handler<WatchAndApplyTravelTxn> for Channel {
     ) -> <WatchAndApplyTravelTxn as Message>::Result {
         debug!("{} handle {:?}", &self.stm, &message);
         let WatchAndApplyTravelTxn { sender, seq_number } = message;
+        let txn_with_proof =
+            watch_transaction(self.chain_client.clone(), sender, seq_number).await?;
+        self.handle(ApplyTravelTxn { txn_with_proof }, ctx).await
+    }
+}
+
+/// Apply a travel txn of the channel which is committed onchain.
+#[derive(Debug)]
+pub(crate) struct ApplyTravelTxn {
+    pub txn_with_proof: TransactionWithProof,
+}
+impl Message for ApplyTravelTxn {
+    type Result = Result<u64>;
+}
+
+#[async_trait]
+impl Handler<ApplyTravelTxn> for Channel {
+    async fn handle(
+        &mut self,
+        message: ApplyTravelTxn,
+        ctx: &mut ActorHandlerContext,
+    ) -> <ApplyTravelTxn as Message>::Result {
         let TransactionWithProof {
             version,
             transaction,
             events,
             proof,
-        } = watch_transaction(self.chain_client.clone(), sender, seq_number).await?;
+        } = message.txn_with_proof;
 
         let gas_used = proof.transaction_info().gas_used();
         debug!(
-            "channel travel txn {}-{} gas used: {}",
-            sender, seq_number, gas_used
+            "{} apply travel txn at version {}, gas used: {}",
+            &self.stm, version, gas_used
         );
 
         let txn_info = proof.transaction_info().clone();
@@ -542,6 +753,8 @@ impl Handler<ApplyCoSignedTxn> for Channel {
         debug_assert!(self.participant_addresses().contains(&txn_sender));
         debug_assert!(self.channel_address() == &channel_txn_payload.channel_address());
         debug_assert!(channel_txn_payload.is_authorized());
+        let travel_op = self.travel_op_of(&signed_txn);
+        let speculative_state = self.end_travelling(&signed_txn, &txn_info)?;
 
         let txn_channel_seq_number = channel_txn_payload.witness().channel_sequence_number();
         let local_channel_seq_number = self.stm.channel_sequence_number();
@@ -563,6 +776,7 @@ impl Handler<ApplyCoSignedTxn> for Channel {
                 AppliedChannelTxn::Travel(s) => {
                     if s.raw_txn().hash() == raw_txn.hash() {
                         // it's ok, it may be a late message.
+                        self.apply_speculative_state(speculative_state).await?;
                         return Ok(applied_txn.proof.transaction_info().gas_used());
                     } else {
                         // FIXME: what happened, why I apply a travel txn different from on chain.
@@ -588,7 +802,12 @@ impl Handler<ApplyCoSignedTxn> for Channel {
         let gas_used = txn_info.gas_used();
 
         // 1. I trust the txn and apply it into local.
-        self.apply_travel(version, signed_txn.clone(), txn_info, events)?;
+        self.apply_travel(version, signed_txn.clone(), txn_info, events, travel_op)?;
+        self.emit_event(ChannelEvent::StateApplied {
+            channel_address: self.channel_address().clone(),
+        })
+        .await;
+        self.apply_speculative_state(speculative_state).await?;
         // 2. after apply, check channel state
         let channel_resource: ChannelResource = self
             .stm
@@ -679,6 +898,9 @@ impl Handler<ApplySoloTxn> for Channel {
         debug_assert!(self.participant_addresses().contains(&txn_sender));
         debug_assert!(self.channel_address() == &channel_txn_payload.channel_address());
         debug_assert!(!channel_txn_payload.is_authorized());
+        let travel_op = self.travel_op_of(&signed_txn);
+        // a solo txn is never the travelling txn, offchain txns made on it are dropped.
+        let _ = self.end_travelling(&signed_txn, &txn_info)?;
 
         let txn_channel_seq_number = channel_txn_payload.witness().channel_sequence_number();
         let local_channel_seq_number = self.stm.channel_sequence_number();
@@ -743,7 +965,11 @@ impl Handler<ApplySoloTxn> for Channel {
         debug_assert!(txn_channel_seq_number == local_channel_seq_number);
         let gas_used = txn_info.gas_used();
         // 1. I trust the txn and apply it into local.
-        self.apply_travel(version, signed_txn.clone(), txn_info, events)?;
+        self.apply_travel(version, signed_txn.clone(), txn_info, events, travel_op)?;
+        self.emit_event(ChannelEvent::StateApplied {
+            channel_address: self.channel_address().clone(),
+        })
+        .await;
         // 2. after apply, check channel state
         let channel_resource: ChannelResource = self
             .stm
@@ -854,6 +1080,47 @@ impl Handler<ChannelLockTimeout> for Channel {
     }
 }
 
+/// Offchain txns made on top of the travelling txn, applied after it's committed onchain.
+struct SpeculativeState {
+    txns: Vec<PendingTransaction>,
+    /// the txn in negotiation when the travelling txn is committed.
+    pending_txn: Option<PendingTransaction>,
+}
+
+/// Witness of the output of an agreed travel txn, signed by all participants.
+fn travel_output_witness(travel_txn: &PendingTransaction) -> Witness {
+    Witness::new(
+        WitnessData::new(
+            travel_txn.proposal().channel_txn.channel_sequence_number() + 1,
+            travel_txn.output().write_set().clone(),
+        ),
+        travel_txn
+            .signatures()
+            .values()
+            .filter_map(|s| s.travel_output_witness_signature.clone())
+            .collect(),
+    )
+}
+
+/// Witness of an agreed offchain txn, same as the one stored after it's applied.
+fn offchain_witness(offchain_txn: &PendingTransaction) -> Witness {
+    Witness::new(
+        WitnessData::new(
+            offchain_txn
+                .proposal()
+                .channel_txn
+                .channel_sequence_number()
+                + 1,
+            offchain_txn.output().write_set().clone(),
+        ),
+        offchain_txn
+            .signatures()
+            .values()
+            .map(|s| s.witness_data_signature.clone())
+            .collect(),
+    )
+}
+
 async fn channel_event_loop<A>(
     mut channel_event_stream: ChannelEventStream,
     mut actor_ref: ActorRef<A>,
@@ -1010,6 +1277,7 @@ impl Channel {
         signed_txn: SignedTransaction,
         txn_info: TransactionInfo,
         events: Vec<ContractEvent>,
+        travel_op: Option<(ChannelOp, Vec<TransactionArgument>)>,
     ) -> Result<()> {
         let txn_to_commit = ChannelTransactionToCommit {
             signed_channel_txn: AppliedChannelTxn::Travel(signed_txn),
@@ -1017,6 +1285,7 @@ impl Channel {
             major_status: txn_info.major_status(),
             write_set: WriteSet::default(),
             gas_used: txn_info.gas_used(),
+            travel_op,
         };
         let channel_address_state = self
             .chain_client
@@ -1036,6 +1305,24 @@ impl Channel {
         Ok(())
     }
 
+    /// Operator and args of the travel txn, if it's proposed in this channel.
+    /// Solo txns of other participants are not, as they are never proposed.
+    fn travel_op_of(
+        &self,
+        signed_txn: &SignedTransaction,
+    ) -> Option<(ChannelOp, Vec<TransactionArgument>)> {
+        self.store
+            .get_travelling_txn()
+            .into_iter()
+            .chain(self.pending_txn())
+            .map(|t| t.proposal().channel_txn.clone())
+            .find(|t| {
+                t.proposer() == signed_txn.sender()
+                    && t.sequence_number() == signed_txn.sequence_number()
+            })
+            .map(|t| (t.operator().clone(), t.args().to_vec()))
+    }
+
     /// apply data into local channel storage
     fn apply(
         &mut self,
@@ -1052,6 +1339,7 @@ impl Channel {
             major_status: txn_output.status().vm_status().major_status,
             write_set: txn_output.write_set().clone(),
             gas_used: txn_output.gas_used(),
+            travel_op: None,
         };
 
         // apply txn  also delete pending txn from db
@@ -1066,6 +1354,215 @@ impl Channel {
         Ok(())
     }
 
+    /// apply the agreed offchain txn, and revoke the state it replaces.
+    async fn apply_offchain(&mut self, pending_txn: PendingTransaction) -> Result<()> {
+        let revoked_witness = self.stm.witness();
+        let revoked_witness = if revoked_witness.channel_sequence_number() > 0 {
+            Some(witness_hash(revoked_witness))
+        } else {
+            None
+        };
+        let (proposal, output, signatures) = pending_txn.into();
+        self.apply(proposal.channel_txn, output, signatures)?;
+        if let Some(revoked_witness) = revoked_witness {
+            self.emit_event(ChannelEvent::StateRevoked {
+                channel_address: self.channel_address().clone(),
+                revoked_witness,
+            })
+            .await;
+        }
+        self.emit_event(ChannelEvent::StateApplied {
+            channel_address: self.channel_address().clone(),
+        })
+        .await;
+        Ok(())
+    }
+
+    /// An agreed travel txn of an opened channel travels in background,
+    /// participants go on with offchain txns on top of its output until it's committed onchain.
+    /// Txns opening or closing the channel, and solo txns, are waited in place.
+    fn can_travel_in_background(&self, pending_txn: &PendingTransaction) -> bool {
+        let keeps_channel_open = match pending_txn.proposal().channel_txn.operator() {
+            ChannelOp::Execute { .. } => true,
+            ChannelOp::Action { function_name, .. } => {
+                function_name.as_str() != channel_close_name().as_str()
+            }
+            _ => false,
+        };
+        keeps_channel_open
+            && pending_txn.consensus_reached()
+            && self
+                .stm
+                .channel_resource()
+                .map(|r| r.opened())
+                .unwrap_or(false)
+    }
+
+    /// Only offchain txns can be made while a travel txn is waiting to be committed onchain.
+    fn check_travelling(&self, pending_txn: &PendingTransaction) -> Result<()> {
+        ensure!(
+            self.store.get_travelling_txn().is_none() || !pending_txn.output().is_travel_txn(),
+            "{} has a travel txn waiting to be committed onchain, only offchain txns are allowed",
+            &self.stm
+        );
+        Ok(())
+    }
+
+    /// Proposals must keep the terms agreed when the channel was opened, if any.
+    fn check_terms(&self, pending_txn: &PendingTransaction) -> Result<()> {
+        match ChannelTermsStore::new(self.store.db()).get_terms(*self.channel_address())? {
+            Some(terms) => self.stm.check_terms(&terms, pending_txn),
+            None => Ok(()),
+        }
+    }
+
+    /// Witness after the travelling txn and offchain txns queued on it, if a txn is travelling.
+    fn speculative_witness(&self) -> Option<Witness> {
+        let travelling_txn = self.store.get_travelling_txn()?;
+        Some(match self.store.get_speculative_txns().last() {
+            Some(txn) => offchain_witness(txn),
+            None => travel_output_witness(&travelling_txn),
+        })
+    }
+
+    /// Watch the travelling txn in background, and apply it once it's committed onchain.
+    fn watch_travelling_txn(
+        &mut self,
+        ctx: &mut ActorHandlerContext,
+        sender: AccountAddress,
+        seq_number: u64,
+    ) {
+        let chain_client = self.chain_client.clone();
+        let mut myself = self.get_ref(ctx);
+        let (task, abort_handle) = abortable(async move {
+            let result = match watch_transaction(chain_client, sender, seq_number).await {
+                Ok(txn_with_proof) => myself
+                    .send(ApplyTravelTxn { txn_with_proof })
+                    .await
+                    .map_err(|_| format_err!("channel actor gone"))
+                    .and_then(|r| r),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                error!(
+                    "fail to apply travelling txn {}-{}, {}",
+                    sender, seq_number, e
+                );
+            }
+        });
+        tokio::task::spawn(task);
+        self.sub_tasks.push(abort_handle);
+    }
+
+    /// Rewind to the state in local store, as a travel txn is committed onchain while a txn is
+    /// travelling.
+    /// Return offchain txns made on top of the travelling txn, to apply after it.
+    /// If it's not the travelling txn or it fails, they are dropped, and None is returned.
+    fn end_travelling(
+        &mut self,
+        signed_txn: &SignedTransaction,
+        txn_info: &TransactionInfo,
+    ) -> Result<Option<SpeculativeState>> {
+        let travelling_txn = match self.store.get_travelling_txn() {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let txns = self.store.get_speculative_txns();
+        let pending_txn = self.pending_txn();
+        self.stm.advance_state(
+            None,
+            self.store.get_latest_witness().unwrap_or_default(),
+            BTreeMap::new(),
+        );
+        let channel_txn = &travelling_txn.proposal().channel_txn;
+        if signed_txn.sender() == channel_txn.proposer()
+            && signed_txn.sequence_number() == channel_txn.sequence_number()
+            && txn_info.major_status() == StatusCode::EXECUTED
+        {
+            return Ok(Some(SpeculativeState { txns, pending_txn }));
+        }
+        warn!(
+            "{} travelling txn is not committed, drop {} offchain txns made on it",
+            &self.stm,
+            txns.len()
+        );
+        self.store.finish_travelling()?;
+        if pending_txn.is_some() {
+            self.clear_pending_txn()?;
+        }
+        Ok(None)
+    }
+
+    /// Apply offchain txns made on top of the travelling txn, after it's applied.
+    async fn apply_speculative_state(&mut self, state: Option<SpeculativeState>) -> Result<()> {
+        let SpeculativeState { txns, pending_txn } = match state {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        for txn in txns {
+            // some may be applied before a restart.
+            let channel_seq_number = txn.proposal().channel_txn.channel_sequence_number();
+            if self.check_applied(channel_seq_number)?.is_none() {
+                self.apply_offchain(txn).await?;
+            }
+        }
+        self.store.finish_travelling()?;
+        // the txn in negotiation is made on top of them too, keep it.
+        if let Some(pending_txn) = pending_txn {
+            self.save_pending_txn(pending_txn)?;
+        }
+        Ok(())
+    }
+
+    async fn emit_event(&mut self, channel_event: ChannelEvent) {
+        if let Err(e) = self
+            .channel_event_sender
+            .notify(ChannelNotifyEvent { channel_event })
+            .await
+        {
+            error!("{}, fail to emit channel event, error: {:?}", &self.stm, e);
+        }
+    }
+
+    fn build_reestablish(&self) -> Result<ChannelReestablish> {
+        let pending_txn = self.pending_txn().map(|p| {
+            (
+                CryptoHash::hash(&p.proposal().channel_txn),
+                p.signatures().values().cloned().collect(),
+            )
+        });
+        let latest_txn = match self.store.get_speculative_txns().last() {
+            Some(t) => Some((
+                CryptoHash::hash(&t.proposal().channel_txn),
+                t.signatures().values().cloned().collect(),
+            )),
+            None => self.latest_applied_txn()?,
+        };
+        Ok(ChannelReestablish::new(
+            self.channel_address().clone(),
+            self.stm.channel_sequence_number(),
+            pending_txn,
+            latest_txn,
+        ))
+    }
+
+    fn latest_applied_txn(&self) -> Result<Option<(HashValue, Vec<ChannelTransactionSigs>)>> {
+        let latest_txn = match self.store.get_startup_info()? {
+            Some(info) => match self.check_applied(info.latest_version)? {
+                Some(t) => match t.signed_transaction {
+                    AppliedChannelTxn::Offchain(t) => Some((
+                        CryptoHash::hash(&t.raw_tx),
+                        t.signatures.into_iter().map(|(_, s)| s).collect(),
+                    )),
+                    AppliedChannelTxn::Travel(_) => None,
+                },
+                None => None,
+            },
+            None => None,
+        };
+        Ok(latest_txn)
+    }
+
     fn pending_txn(&self) -> Option<PendingTransaction> {
         self.store.get_pending_txn()
     }
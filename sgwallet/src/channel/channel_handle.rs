@@ -1,13 +1,14 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::channel::{AccessingResource, Channel, GetPendingTxn};
+use crate::channel::{AccessingResource, BuildChallengeTxn, Channel, GetPendingTxn};
 use anyhow::Result;
 use coerce_rt::actor::ActorRef;
 use libra_types::{
     access_path::{AccessPath, DataPath},
     account_address::AccountAddress,
     libra_resource::{make_resource, LibraResource},
+    transaction::SignedTransaction,
 };
 use serde::de::DeserializeOwned;
 use sgtypes::pending_txn::PendingTransaction;
@@ -61,6 +62,11 @@ impl ChannelHandle {
         Ok(self.channel_ref.clone().send(GetPendingTxn).await?)
     }
 
+    /// Return a challenge txn of current state, with its channel sequence number.
+    pub async fn build_challenge_txn(&self) -> Result<(u64, SignedTransaction)> {
+        self.channel_ref.clone().send(BuildChallengeTxn).await?
+    }
+
     pub async fn get_channel_resource<R: LibraResource + DeserializeOwned>(
         &self,
         data_path: DataPath,
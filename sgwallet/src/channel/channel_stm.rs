@@ -3,25 +3,27 @@
 
 use crate::{
     channel::{access_local, channel::is_participant_channel_resource_modified},
-    scripts::PackageRegistry,
+    scripts::{PackageRegistry, ASSET_OPEN_SCRIPT, DEFAULT_PACKAGE},
+    signer::Signer,
+    utils::contract::channel_challenge_name,
     wallet::{
-        execute_transaction, txn_expiration, GAS_UNIT_PRICE, MAX_GAS_AMOUNT_OFFCHAIN,
-        MAX_GAS_AMOUNT_ONCHAIN,
+        execute_transaction, is_txn_expired, txn_expiration_after, GAS_UNIT_PRICE,
+        MAX_GAS_AMOUNT_OFFCHAIN, MAX_GAS_AMOUNT_ONCHAIN,
     },
     ChannelStateView,
 };
 use anyhow::{bail, ensure, format_err, Result};
 use libra_crypto::{
-    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    ed25519::{Ed25519PublicKey, Ed25519Signature},
     hash::CryptoHash,
-    test_utils::KeyPair,
-    HashValue, SigningKey, VerifyingKey,
+    HashValue, VerifyingKey,
 };
 use libra_logger::prelude::*;
 use libra_state_view::StateView;
 use libra_types::{
     access_path::{AccessPath, DataPath},
     account_address::AccountAddress,
+    account_config::{account_module_name, core_code_address},
     channel::{
         ChannelChallengeBy, ChannelLockedBy, ChannelMirrorResource,
         ChannelParticipantAccountResource, ChannelResource, Witness, WitnessData,
@@ -33,15 +35,19 @@ use libra_types::{
         ChannelTransactionPayload, ChannelTransactionPayloadBody, RawTransaction, ScriptAction,
         SignedTransaction, TransactionArgument, TransactionOutput, TransactionPayload,
     },
-    write_set::WriteSet,
+    write_set::{WriteOp, WriteSet, WriteSetMut},
 };
 use serde::de::DeserializeOwned;
 use sgchain::star_chain_client::ChainClient;
 use sgtypes::{
     channel::ChannelState,
-    channel_transaction::{ChannelOp, ChannelTransaction, ChannelTransactionProposal},
+    channel_transaction::{
+        BatchedChannelOp, ChannelOp, ChannelTransaction, ChannelTransactionProposal,
+    },
     channel_transaction_sigs::ChannelTransactionSigs,
+    open_channel_policy::ChannelTerms,
     pending_txn::{PendingTransaction, ProposalLifecycle},
+    sg_error::SgError,
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -63,9 +69,11 @@ pub struct ChannelStm {
     channel_state: ChannelState,
     witness: Witness,
 
-    keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+    signer: Arc<dyn Signer>,
     script_registry: Arc<PackageRegistry>,
     chain_client: Arc<dyn ChainClient>,
+    /// how long a proposal of mine waits for signatures of participants.
+    proposal_expiration: Duration,
 }
 
 impl std::fmt::Display for ChannelStm {
@@ -86,17 +94,19 @@ impl ChannelStm {
         channel_address: AccountAddress,
         account_address: AccountAddress,
         participant_addresses: BTreeSet<AccountAddress>,
-        keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+        signer: Arc<dyn Signer>,
         script_registry: Arc<PackageRegistry>,
         chain_client: Arc<dyn ChainClient>,
+        proposal_expiration: Duration,
     ) -> Self {
         Self {
             channel_address,
             account_address,
             participant_addresses,
-            keypair,
+            signer,
             script_registry,
             chain_client,
+            proposal_expiration,
 
             channel_state: ChannelState::empty(channel_address),
             witness: Witness::default(),
@@ -111,6 +121,10 @@ impl ChannelStm {
         data.map(make_resource).transpose()
     }
 
+    pub(crate) fn proposal_expiration(&self) -> Duration {
+        self.proposal_expiration
+    }
+
     pub(crate) fn channel_sequence_number(&self) -> u64 {
         let channel_mirror_resource = self
             .get_local::<ChannelMirrorResource>(&AccessPath::new_for_data_path(
@@ -198,6 +212,7 @@ impl ChannelStm {
         }
         match pending_txn {
             None => {
+                check_expiration(&proposal.channel_txn)?;
                 // TODO: if I propose this, don't verify.
                 self.verify_proposal(&proposal)?;
 
@@ -208,8 +223,7 @@ impl ChannelStm {
                     proposal.channel_txn.args()
                 );
                 // execute proposal to get txn payload and txn witness data for later use
-                let (_payload_body, _payload_body_signature, output) =
-                    self.execute_proposal(&proposal)?;
+                let output = self.execute_proposal(&proposal)?;
 
                 let mut p = PendingTransaction::new(proposal, output, BTreeMap::new());
                 p.set_lifecycle(ProposalLifecycle::Created);
@@ -243,8 +257,15 @@ impl ChannelStm {
             proposal.channel_txn.hash() == proposal_id,
             "proposal txn hash mismatched"
         );
+        ensure!(
+            self.participant_addresses.contains(&sigs.address),
+            "signer {} is not a participant of the channel",
+            sigs.address
+        );
 
         if pending_txn.get_signature(&sigs.address).is_none() {
+            // late signatures can't revive an expired proposal.
+            check_expiration(&proposal.channel_txn)?;
             self.verify_txn_sigs(&pending_txn, &sigs)?;
             pending_txn.add_signature(sigs);
 
@@ -260,6 +281,30 @@ impl ChannelStm {
         }
         return Ok(());
     }
+    /// Collect signature of the pending txn which the signer already applied.
+    /// Unlike `handle_proposal_signature`, it's not checked against expiration,
+    /// as the txn is agreed by all participants before it's applied.
+    pub fn handle_applied_proposal_signature(
+        &self,
+        pending_txn: &mut PendingTransaction,
+        sigs: ChannelTransactionSigs,
+    ) -> Result<()> {
+        ensure!(
+            self.participant_addresses.contains(&sigs.address),
+            "signer {} is not a participant of the channel",
+            sigs.address
+        );
+        if pending_txn.get_signature(&sigs.address).is_none() {
+            self.verify_txn_sigs(&pending_txn, &sigs)?;
+            pending_txn.add_signature(sigs);
+            if pending_txn.lifecycle() == ProposalLifecycle::Created {
+                pending_txn.set_lifecycle(ProposalLifecycle::Negotiating);
+            }
+            pending_txn.try_reach_consensus(&self.participant_addresses);
+        }
+        Ok(())
+    }
+
     pub fn handle_apply_proposal(&self, pending_txn: &mut PendingTransaction) -> Result<()> {
         let can_be_offchain =
             pending_txn.consensus_reached() && !pending_txn.output().is_travel_txn();
@@ -267,11 +312,70 @@ impl ChannelStm {
             // can directly apply offchain
             pending_txn.set_applying();
         } else {
+            ensure!(
+                !pending_txn.proposal().channel_txn.operator().is_batch(),
+                "batch txn can not travel, it needs all participants' signatures"
+            );
             pending_txn.set_travelling();
         }
         Ok(())
     }
 
+    /// Check `pending_txn` keeps `terms` agreed when the channel is opened: it only runs agreed
+    /// packages, and takes no participant's balance below the reserve, except closing the channel.
+    pub(crate) fn check_terms(
+        &self,
+        terms: &ChannelTerms,
+        pending_txn: &PendingTransaction,
+    ) -> Result<()> {
+        let channel_txn = &pending_txn.proposal().channel_txn;
+        let ops = match channel_txn.operator() {
+            ChannelOp::Batch { ops } => ops.iter().map(|op| &op.operator).collect::<Vec<_>>(),
+            op => vec![op],
+        };
+        for op in ops.iter() {
+            match op {
+                ChannelOp::Close => return Ok(()),
+                ChannelOp::Execute { package_name, .. } => ensure!(
+                    package_name == DEFAULT_PACKAGE || terms.packages.contains(package_name),
+                    "package {} is not agreed in channel {}",
+                    package_name,
+                    self.channel_address
+                ),
+                _ => {}
+            }
+        }
+        for participant in self.participant_addresses.iter() {
+            let access_path = AccessPath::new_for_data_path(
+                self.channel_address,
+                DataPath::channel_resource_path(
+                    *participant,
+                    ChannelParticipantAccountResource::struct_tag(),
+                ),
+            );
+            let after = match pending_txn.output().write_set().get(&access_path) {
+                Some(WriteOp::Value(value)) => {
+                    make_resource::<ChannelParticipantAccountResource>(value)?.balance()
+                }
+                Some(WriteOp::Deletion) => 0,
+                None => continue,
+            };
+            let before = self
+                .get_local::<ChannelParticipantAccountResource>(&access_path)?
+                .map(|r| r.balance())
+                .unwrap_or(0);
+            ensure!(
+                after >= before || after >= terms.min_reserve,
+                "balance of {} in channel {} would be {}, below the reserve {}",
+                participant,
+                self.channel_address,
+                after,
+                terms.min_reserve
+            );
+        }
+        Ok(())
+    }
+
     pub fn can_auto_sign_pending_proposal(&self, pending_txn: &PendingTransaction) -> Result<bool> {
         let can_auto_signed = !is_participant_channel_resource_modified(
             self.witness.write_set(),
@@ -286,18 +390,63 @@ impl ChannelStm {
         &self,
         channel_txn: &ChannelTransaction,
     ) -> Result<(ChannelTransactionPayloadBody, Ed25519Signature)> {
-        let action =
-            self.channel_op_to_action(channel_txn.operator(), channel_txn.args().to_vec())?;
+        let body = self.build_channel_txn_payload_body(
+            channel_txn.operator(),
+            channel_txn.args().to_vec(),
+            channel_txn.proposer(),
+            self.witness.clone(),
+        )?;
+        let sig = self.signer.sign_payload_body(&body)?;
+        Ok((body, sig))
+    }
 
-        let body = ChannelTransactionPayloadBody::new(
+    fn build_channel_txn_payload_body(
+        &self,
+        channel_op: &ChannelOp,
+        args: Vec<TransactionArgument>,
+        proposer: AccountAddress,
+        witness: Witness,
+    ) -> Result<ChannelTransactionPayloadBody> {
+        let action = self.channel_op_to_action(channel_op, args)?;
+        Ok(ChannelTransactionPayloadBody::new(
             self.channel_address,
-            channel_txn.proposer(),
+            proposer,
             action,
+            witness,
+        ))
+    }
+
+    /// The hash participants sign as channel payload.
+    /// A batch never goes onchain as a whole, so its payload signature is on the channel txn,
+    /// and the batch result is bound by the witness signature.
+    fn channel_txn_payload_hash(&self, channel_txn: &ChannelTransaction) -> Result<HashValue> {
+        if channel_txn.operator().is_batch() {
+            return Ok(CryptoHash::hash(channel_txn));
+        }
+        let payload_body = self.build_channel_txn_payload_body(
+            channel_txn.operator(),
+            channel_txn.args().to_vec(),
+            channel_txn.proposer(),
             self.witness.clone(),
-        );
-        let body_hash = CryptoHash::hash(&body);
-        let sig = self.keypair.private_key.sign_message(&body_hash);
-        Ok((body, sig))
+        )?;
+        Ok(CryptoHash::hash(&payload_body))
+    }
+
+    /// sign what `channel_txn_payload_hash` hashes.
+    fn sign_channel_txn_payload(
+        &self,
+        channel_txn: &ChannelTransaction,
+    ) -> Result<Ed25519Signature> {
+        if channel_txn.operator().is_batch() {
+            return self.signer.sign_channel_txn(channel_txn);
+        }
+        let payload_body = self.build_channel_txn_payload_body(
+            channel_txn.operator(),
+            channel_txn.args().to_vec(),
+            channel_txn.proposer(),
+            self.witness.clone(),
+        )?;
+        self.signer.sign_payload_body(&payload_body)
     }
 
     pub fn build_signed_txn(&self, pending_txn: &PendingTransaction) -> Result<SignedTransaction> {
@@ -318,6 +467,45 @@ impl ChannelStm {
         Ok(signed_txn)
     }
 
+    /// Build a solo challenge txn proving the current state, signed only by me.
+    /// It's not submitted, but kept by watchtower in case a revoked state goes onchain.
+    pub fn build_challenge_txn(&self) -> Result<SignedTransaction> {
+        self.build_solo_txn(
+            ChannelOp::Action {
+                module_address: core_code_address(),
+                module_name: account_module_name().as_str().to_string(),
+                function_name: channel_challenge_name().as_str().to_string(),
+            },
+            vec![],
+        )
+    }
+
+    /// Build a solo txn of current state signed only by me, without executing it offchain.
+    pub fn build_solo_txn(
+        &self,
+        channel_op: ChannelOp,
+        args: Vec<TransactionArgument>,
+    ) -> Result<SignedTransaction> {
+        let proposal = self.generate_proposal(channel_op, args)?;
+        let channel_txn = &proposal.channel_txn;
+        let (payload_body, payload_signature) =
+            self.build_and_sign_channel_txn_payload_body(channel_txn)?;
+        let mut signatures = BTreeMap::new();
+        signatures.insert(
+            self.account_address,
+            (self.signer.public_key(), payload_signature),
+        );
+        // the txn may be submitted at any time later.
+        self.build_chain_txn(
+            payload_body,
+            Some(signatures),
+            channel_txn.proposer(),
+            channel_txn.sequence_number(),
+            MAX_GAS_AMOUNT_ONCHAIN,
+            Duration::from_secs(u64::max_value()),
+        )
+    }
+
     fn build_raw_txn_from_channel_txn(
         &self,
         channel_payload_body: ChannelTransactionPayloadBody,
@@ -393,9 +581,7 @@ impl ChannelStm {
             GAS_UNIT_PRICE,
             expiration_time,
         );
-        Ok(raw_txn
-            .sign(&self.keypair.private_key, self.keypair.public_key.clone())?
-            .into_inner())
+        self.signer.sign_txn(raw_txn)
     }
 
     fn channel_op_to_action(
@@ -405,6 +591,56 @@ impl ChannelStm {
     ) -> Result<ScriptAction> {
         match op {
             ChannelOp::Open => {
+                // args start with addresses of other participants.
+                let participants = args
+                    .iter()
+                    .take_while(|arg| match arg {
+                        TransactionArgument::Address(_) => true,
+                        _ => false,
+                    })
+                    .count()
+                    + 1;
+                // open of a two-party channel depositing an asset too, by the asset's open script,
+                // the package name comes after the amounts of the default asset.
+                let package_name = args.iter().find_map(|arg| match arg {
+                    TransactionArgument::ByteArray(bytes) => Some(bytes.as_bytes().to_vec()),
+                    _ => None,
+                });
+                if let Some(package_name) = package_name {
+                    ensure!(
+                        participants == 2,
+                        "only two-party channel can be opened with an asset"
+                    );
+                    let package_name = String::from_utf8(package_name)?;
+                    let script_code = self
+                        .script_registry
+                        .get_script(&package_name, ASSET_OPEN_SCRIPT)
+                        .ok_or(format_err!("package {} can not open channel", package_name))?;
+                    let args = args
+                        .into_iter()
+                        .filter(|arg| match arg {
+                            TransactionArgument::ByteArray(_) => false,
+                            _ => true,
+                        })
+                        .collect();
+                    return Ok(ScriptAction::new_code(
+                        script_code.byte_code().clone(),
+                        args,
+                    ));
+                }
+                if participants > 2 {
+                    let script_code = self
+                        .script_registry
+                        .multi_party_open_script(participants)
+                        .ok_or(format_err!(
+                            "channel of {} participants is not supported",
+                            participants
+                        ))?;
+                    return Ok(ScriptAction::new_code(
+                        script_code.byte_code().clone(),
+                        args,
+                    ));
+                }
                 let module_id =
                     ModuleId::new(AccountAddress::default(), Identifier::new("ChannelScript")?);
 
@@ -456,9 +692,27 @@ impl ChannelStm {
                     args,
                 ))
             }
+            ChannelOp::Batch { .. } => bail!("batch op can not be built into one script action"),
         }
     }
 
+    fn check_batch(&self, channel_txn: &ChannelTransaction) -> Result<()> {
+        if let ChannelOp::Batch { ops } = channel_txn.operator() {
+            ensure!(!ops.is_empty(), "batch should contain at least one op");
+            ensure!(
+                channel_txn.args().is_empty(),
+                "batch args should be carried by its ops"
+            );
+            for op in ops {
+                match &op.operator {
+                    ChannelOp::Execute { .. } | ChannelOp::Action { .. } => {}
+                    other => bail!("op {} is not allowed in a batch", other),
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn generate_proposal(
         &self,
         channel_op: ChannelOp,
@@ -490,14 +744,14 @@ impl ChannelStm {
             args,
             self.account_address,
             account_seq_number,
-            txn_expiration(),
+            txn_expiration_after(self.proposal_expiration),
         );
-        let channel_txn_hash = CryptoHash::hash(&channel_txn);
-        let channel_txn_signature = self.keypair.private_key.sign_message(&channel_txn_hash);
+        self.check_batch(&channel_txn)?;
+        let channel_txn_signature = self.signer.sign_channel_txn(&channel_txn)?;
 
         let proposal = ChannelTransactionProposal {
             channel_txn,
-            proposer_public_key: self.keypair.public_key.clone(),
+            proposer_public_key: self.signer.public_key(),
             proposer_signature: channel_txn_signature,
         };
         Ok(proposal)
@@ -506,29 +760,16 @@ impl ChannelStm {
     pub fn execute_proposal(
         &self,
         proposal: &ChannelTransactionProposal,
-    ) -> Result<(
-        ChannelTransactionPayloadBody,
-        Ed25519Signature,
-        TransactionOutput,
-    )> {
+    ) -> Result<TransactionOutput> {
         let channel_txn = &proposal.channel_txn;
-        let (payload_body, payload_body_signature) =
-            self.build_and_sign_channel_txn_payload_body(channel_txn)?;
-
-        let output = {
-            // create mocked txn to execute
-            // execute txn on offchain vm, should mock sender and receiver signature with a local
-            // keypair. the vm will skip signature check on offchain vm.
-            let txn = self.build_raw_txn_from_channel_txn(
-                payload_body.clone(),
+        let output = match channel_txn.operator() {
+            ChannelOp::Batch { ops } => self.execute_batch(channel_txn, ops)?,
+            channel_op => self.execute_channel_op(
                 channel_txn,
-                None,
-                MAX_GAS_AMOUNT_OFFCHAIN,
-            )?;
-
-            let version = channel_txn.version();
-            let state_view = self.channel_view(Some(version))?;
-            execute_transaction(&state_view, txn)?
+                channel_op,
+                channel_txn.args().to_vec(),
+                self.witness.clone(),
+            )?,
         };
 
         // check output gas
@@ -547,7 +788,80 @@ impl ChannelStm {
             &output.write_set()
         );
 
-        Ok((payload_body, payload_body_signature, output))
+        Ok(output)
+    }
+
+    /// execute `channel_op` of `channel_txn` on offchain vm, with channel resources read from `witness`.
+    fn execute_channel_op(
+        &self,
+        channel_txn: &ChannelTransaction,
+        channel_op: &ChannelOp,
+        args: Vec<TransactionArgument>,
+        witness: Witness,
+    ) -> Result<TransactionOutput> {
+        let payload_body =
+            self.build_channel_txn_payload_body(channel_op, args, channel_txn.proposer(), witness)?;
+        // create mocked txn to execute
+        // execute txn on offchain vm, should mock sender and receiver signature with a local
+        // keypair. the vm will skip signature check on offchain vm.
+        let txn = self.build_raw_txn_from_channel_txn(
+            payload_body.clone(),
+            channel_txn,
+            None,
+            MAX_GAS_AMOUNT_OFFCHAIN,
+        )?;
+
+        let state_view = ChannelStateView::new(
+            self.account_address,
+            &self.channel_state,
+            payload_body.witness().write_set(),
+            Some(channel_txn.version()),
+            self.chain_client.as_ref(),
+        )?;
+        execute_transaction(&state_view, txn)
+    }
+
+    /// Execute ops of a batch in order, each one on top of the write sets of the ops before it.
+    /// All ops run against the same channel sequence number,
+    /// and their outputs are merged into one offchain output.
+    fn execute_batch(
+        &self,
+        channel_txn: &ChannelTransaction,
+        ops: &[BatchedChannelOp],
+    ) -> Result<TransactionOutput> {
+        let mut latest_write_set = self.witness.write_set().clone();
+        let mut batch_write_set = WriteSet::default();
+        let mut events = vec![];
+        let mut gas_used = 0u64;
+        let mut status = None;
+        for op in ops {
+            let witness = Witness::new(
+                WitnessData::new(
+                    self.witness.channel_sequence_number(),
+                    latest_write_set.clone(),
+                ),
+                vec![],
+            );
+            let output =
+                self.execute_channel_op(channel_txn, &op.operator, op.args.clone(), witness)?;
+            ensure!(
+                !output.is_travel_txn(),
+                "op {} of batch needs to travel, batch can only contain offchain ops",
+                op.operator
+            );
+            latest_write_set = merge_write_set(&latest_write_set, output.write_set())?;
+            batch_write_set = merge_write_set(&batch_write_set, output.write_set())?;
+            events.extend_from_slice(output.events());
+            gas_used += output.gas_used();
+            status = Some(output.status().clone());
+        }
+        let status = status.ok_or(format_err!("batch should contain at least one op"))?;
+        Ok(TransactionOutput::new(
+            batch_write_set,
+            events,
+            gas_used,
+            status,
+        ))
     }
 
     pub fn generate_txn_sigs(
@@ -555,8 +869,7 @@ impl ChannelStm {
         channel_txn: &ChannelTransaction,
         output: &TransactionOutput,
     ) -> Result<ChannelTransactionSigs> {
-        let (_, payload_body_signature) =
-            self.build_and_sign_channel_txn_payload_body(channel_txn)?;
+        let payload_body_signature = self.sign_channel_txn_payload(channel_txn)?;
 
         let ws = if output.is_travel_txn() {
             WriteSet::default()
@@ -571,25 +884,21 @@ impl ChannelStm {
             &witness_data_hash,
             self.channel_sequence_number() + 1
         );
-        let witness_data_signature = self.keypair.private_key.sign_message(&witness_data_hash);
+        let witness_data_signature = self.signer.sign_witness_data(&witness_data)?;
 
         let travel_output_witness_signature = if output.is_travel_txn() {
             let txn_output_witness_data = WitnessData::new(
                 self.channel_sequence_number() + 1,
                 output.write_set().clone(),
             );
-            Some(
-                self.keypair
-                    .private_key
-                    .sign_message(&CryptoHash::hash(&txn_output_witness_data)),
-            )
+            Some(self.signer.sign_witness_data(&txn_output_witness_data)?)
         } else {
             None
         };
 
         let generated_sigs = ChannelTransactionSigs::new(
             self.account_address,
-            self.keypair.public_key.clone(),
+            self.signer.public_key(),
             payload_body_signature,
             witness_data_hash,
             witness_data_signature,
@@ -617,6 +926,8 @@ impl ChannelStm {
             .proposer_public_key
             .verify_signature(&CryptoHash::hash(channel_txn), &proposal.proposer_signature)?;
 
+        self.check_batch(channel_txn)?;
+
         // TODO: check public key match proposer address
         if !channel_txn.operator().is_open() {
             ensure!(
@@ -632,13 +943,10 @@ impl ChannelStm {
         pending_txn: &PendingTransaction,
         channel_txn_sigs: &ChannelTransactionSigs,
     ) -> Result<()> {
-        let (payload_body, _) =
-            self.build_and_sign_channel_txn_payload_body(&pending_txn.proposal().channel_txn)?;
-
-        channel_txn_sigs.public_key.verify_signature(
-            &CryptoHash::hash(&payload_body),
-            &channel_txn_sigs.channel_payload_signature,
-        )?;
+        let payload_hash = self.channel_txn_payload_hash(&pending_txn.proposal().channel_txn)?;
+        channel_txn_sigs
+            .public_key
+            .verify_signature(&payload_hash, &channel_txn_sigs.channel_payload_signature)?;
 
         let output = pending_txn.output();
         let ws = if output.is_travel_txn() {
@@ -695,3 +1003,23 @@ impl ChannelStm {
         )
     }
 }
+
+/// Fail with a typed error once `channel_txn` is past its deadline.
+fn check_expiration(channel_txn: &ChannelTransaction) -> Result<()> {
+    if is_txn_expired(channel_txn) {
+        return Err(SgError::new_proposal_expired_error(&CryptoHash::hash(channel_txn)).into());
+    }
+    Ok(())
+}
+
+/// merge `top` over `base`, ops in `top` win.
+fn merge_write_set(base: &WriteSet, top: &WriteSet) -> Result<WriteSet> {
+    let mut merged = base
+        .iter()
+        .cloned()
+        .collect::<BTreeMap<AccessPath, WriteOp>>();
+    for (ap, op) in top {
+        merged.insert(ap.clone(), op.clone());
+    }
+    WriteSetMut::new(merged.into_iter().collect()).freeze()
+}
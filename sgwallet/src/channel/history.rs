@@ -0,0 +1,223 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{bail, format_err, Result};
+use libra_types::{
+    access_path::{AccessPath, DataPath},
+    account_address::AccountAddress,
+    channel::ChannelParticipantAccountResource,
+    libra_resource::{make_resource, LibraResource},
+    transaction::SignedTransaction,
+    write_set::WriteOp,
+};
+use sgchain::star_chain_client::ChainClient;
+use sgstorage::{channel_db::ChannelDB, channel_store::ChannelStore};
+use sgtypes::{
+    applied_channel_txn::AppliedChannelTxn,
+    channel::ChannelState,
+    channel_txn_history::{ChannelTxnMeta, ChannelTxnPage, ChannelTxnQuery, ChannelTxnRecord},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+type Balances = BTreeMap<AccountAddress, u64>;
+
+/// Scan applied txns of the channel from `query.start`, until a page is filled.
+/// Balances after a travel txn are read from chain at the txn version,
+/// balances after an offchain txn are the ones in its witness, over the last travel state.
+pub(crate) async fn list_channel_txns(
+    channel_address: AccountAddress,
+    store: &ChannelStore<ChannelDB>,
+    chain_client: Arc<dyn ChainClient>,
+    query: &ChannelTxnQuery,
+) -> Result<ChannelTxnPage> {
+    let latest_version = match store.get_startup_info()? {
+        Some(startup_info) => startup_info.latest_version,
+        None => return Ok(ChannelTxnPage::default()),
+    };
+    let first_unpruned = store.first_unpruned_version()?;
+    let history = History {
+        channel_address,
+        participants: store.participant_addresses(),
+        store,
+        chain_client,
+    };
+    // balances before the checkpoint txn are pruned, so it can not be listed.
+    let start = if first_unpruned > 0 {
+        query.start.max(first_unpruned + 1)
+    } else {
+        query.start
+    };
+    let mut page = ChannelTxnPage::default();
+    if start > latest_version {
+        return Ok(page);
+    }
+    let (mut base, mut balances) = if start == 0 {
+        (Balances::new(), Balances::new())
+    } else {
+        history.balances_at(start - 1).await?
+    };
+
+    for version in start..=latest_version {
+        if query.limit > 0 && page.txns.len() as u64 >= query.limit {
+            page.next_start = Some(version);
+            break;
+        }
+        let txn = store.get_transaction_by_channel_seq_number(version, false)?;
+        let new_balances = match &txn.signed_transaction {
+            AppliedChannelTxn::Travel(t) => {
+                base = history.chain_balances(t).await?;
+                base.clone()
+            }
+            AppliedChannelTxn::Offchain(_) => history.witness_balances(&base, version)?,
+        };
+        let record = to_record(
+            txn.signed_transaction,
+            store.get_txn_meta(version)?,
+            txn.proof.transaction_info().gas_used(),
+            balance_deltas(&balances, &new_balances),
+        );
+        balances = new_balances;
+        if query.matches(&record) {
+            page.txns.push(record);
+        }
+    }
+    Ok(page)
+}
+
+struct History<'a> {
+    channel_address: AccountAddress,
+    participants: BTreeSet<AccountAddress>,
+    store: &'a ChannelStore<ChannelDB>,
+    chain_client: Arc<dyn ChainClient>,
+}
+
+impl<'a> History<'a> {
+    /// Balances of the last travel state before `version`, and balances after `version`.
+    async fn balances_at(&self, version: u64) -> Result<(Balances, Balances)> {
+        let travel_version = self.store.last_travel_version(version)?;
+        let travel_txn = match self
+            .store
+            .get_transaction_by_channel_seq_number(travel_version, false)?
+            .signed_transaction
+        {
+            AppliedChannelTxn::Travel(t) => t,
+            AppliedChannelTxn::Offchain(_) => bail!("txn {} should be travel txn", travel_version),
+        };
+        let base = self.chain_balances(&travel_txn).await?;
+        let balances = self.witness_balances(&base, version)?;
+        Ok((base, balances))
+    }
+
+    async fn chain_balances(&self, txn: &SignedTransaction) -> Result<Balances> {
+        let (txn_with_proof, _) = self
+            .chain_client
+            .get_transaction_by_seq_num_async(txn.sender(), txn.sequence_number())
+            .await?;
+        let version = txn_with_proof
+            .ok_or(format_err!(
+                "travel txn {}-{} not found on chain",
+                txn.sender(),
+                txn.sequence_number()
+            ))?
+            .version;
+        let channel_state = self
+            .chain_client
+            .get_account_state_option(self.channel_address, Some(version))?
+            .map(|s| ChannelState::new(self.channel_address, s));
+
+        let mut balances = Balances::new();
+        for participant in &self.participants {
+            let data = channel_state
+                .as_ref()
+                .and_then(|s| s.get(&self.access_path(participant).path));
+            balances.insert(*participant, balance_of(data.map(|d| d.as_slice()))?);
+        }
+        Ok(balances)
+    }
+
+    fn witness_balances(&self, base: &Balances, version: u64) -> Result<Balances> {
+        let write_set = self.store.get_write_set_by_version(version)?;
+        let mut balances = base.clone();
+        for participant in &self.participants {
+            let balance = match write_set.get(&self.access_path(participant)) {
+                Some(WriteOp::Value(value)) => balance_of(Some(value.as_slice()))?,
+                Some(WriteOp::Deletion) => 0,
+                None => continue,
+            };
+            balances.insert(*participant, balance);
+        }
+        Ok(balances)
+    }
+
+    fn access_path(&self, participant: &AccountAddress) -> AccessPath {
+        AccessPath::new_for_data_path(
+            self.channel_address,
+            DataPath::channel_resource_path(
+                *participant,
+                ChannelParticipantAccountResource::struct_tag(),
+            ),
+        )
+    }
+}
+
+fn balance_of(data: Option<&[u8]>) -> Result<u64> {
+    Ok(data
+        .map(make_resource::<ChannelParticipantAccountResource>)
+        .transpose()?
+        .map(|r| r.balance())
+        .unwrap_or(0))
+}
+
+fn balance_deltas(old: &Balances, new: &Balances) -> BTreeMap<AccountAddress, i64> {
+    old.keys()
+        .chain(new.keys())
+        .filter_map(|addr| {
+            let delta = *new.get(addr).unwrap_or(&0) as i64 - *old.get(addr).unwrap_or(&0) as i64;
+            if delta == 0 {
+                None
+            } else {
+                Some((*addr, delta))
+            }
+        })
+        .collect()
+}
+
+fn to_record(
+    txn: AppliedChannelTxn,
+    meta: Option<ChannelTxnMeta>,
+    gas_used: u64,
+    balance_deltas: BTreeMap<AccountAddress, i64>,
+) -> ChannelTxnRecord {
+    let channel_sequence_number = txn.channel_sequence_number();
+    let proposer = txn.proposer();
+    let (applied_time, travel_op) = match meta {
+        Some(m) => (m.applied_time, m.travel_op),
+        None => (0, None),
+    };
+    let (travel, operator, args, expiration_time) = match txn {
+        AppliedChannelTxn::Offchain(t) => (
+            false,
+            Some(t.raw_tx.operator().clone()),
+            t.raw_tx.args().to_vec(),
+            t.raw_tx.expiration_time(),
+        ),
+        AppliedChannelTxn::Travel(t) => match travel_op {
+            Some((operator, args)) => (true, Some(operator), args, t.expiration_time()),
+            None => (true, None, vec![], t.expiration_time()),
+        },
+    };
+    ChannelTxnRecord {
+        channel_sequence_number,
+        proposer,
+        travel,
+        operator,
+        args,
+        expiration_time: expiration_time.as_secs(),
+        applied_time,
+        gas_used,
+        balance_deltas,
+    }
+}
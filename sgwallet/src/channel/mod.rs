@@ -1,40 +1,44 @@
 // Copyright (c) The Starcoin Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{chain_watcher::ChainWatcherHandle, scripts::PackageRegistry, tx_applier::TxApplier};
+use crate::{
+    chain_watcher::ChainWatcherHandle, scripts::PackageRegistry, signer::Signer,
+    tx_applier::TxApplier, utils::actor_timer::Timer,
+};
 use anyhow::{bail, Result};
 use coerce_rt::actor::{context::ActorContext, message::Message, ActorRef};
 
 use crate::{channel::channel_stm::ChannelStm, wallet::Wallet};
 pub use channel_handle::ChannelHandle;
 use futures::future::AbortHandle;
-use libra_crypto::{
-    ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
-    test_utils::KeyPair,
-    HashValue,
-};
+use libra_crypto::HashValue;
 use libra_types::{
     access_path::AccessPath,
     account_address::AccountAddress,
-    transaction::{TransactionArgument, TransactionOutput},
+    transaction::{SignedTransaction, TransactionArgument, TransactionOutput},
     write_set::{WriteOp, WriteSet},
 };
 use sgchain::star_chain_client::ChainClient;
 use sgstorage::{channel_db::ChannelDB, channel_store::ChannelStore};
 use sgtypes::{
     channel::ChannelState,
+    channel_reestablish::{ChannelReestablish, ReestablishAction},
     channel_transaction::{ChannelOp, ChannelTransactionProposal},
     channel_transaction_sigs::ChannelTransactionSigs,
     pending_txn::PendingTransaction,
 };
-use std::{collections::BTreeSet, sync::Arc};
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
 
 mod channel;
 mod channel_event_stream;
 mod channel_handle;
 mod channel_stm;
+mod history;
+mod restore;
 
 pub(crate) use channel::*;
+pub(crate) use history::list_channel_txns;
+pub(crate) use restore::RestoredChannel;
 
 pub struct Channel {
     store: ChannelStore<ChannelDB>,
@@ -48,6 +52,8 @@ pub struct Channel {
     chain_txn_watcher: ChainWatcherHandle,
     stm: ChannelStm,
     sub_tasks: Vec<AbortHandle>,
+    // cancel the pending txn once it's expired
+    expiration_timer: Option<Timer>,
 }
 
 impl Drop for Channel {
@@ -65,9 +71,10 @@ impl Channel {
         db: ChannelDB,
         chain_txn_watcher: ChainWatcherHandle,
         supervisor_ref: ActorRef<Wallet>,
-        keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+        signer: Arc<dyn Signer>,
         script_registry: Arc<PackageRegistry>,
         chain_client: Arc<dyn ChainClient>,
+        proposal_expiration: Duration,
     ) -> Self {
         let store = ChannelStore::new(
             initial_participant_addresses.unwrap_or_default(),
@@ -80,9 +87,10 @@ impl Channel {
             channel_address.clone(),
             account_address.clone(),
             ps,
-            keypair.clone(),
+            signer,
             script_registry.clone(),
             chain_client.clone(),
+            proposal_expiration,
         );
         let inner = Self {
             store: store.clone(),
@@ -92,6 +100,7 @@ impl Channel {
             chain_txn_watcher,
             stm,
             sub_tasks: Vec::new(),
+            expiration_timer: None,
         };
         inner
     }
@@ -168,6 +177,13 @@ pub(crate) struct CancelPendingTxn {
 impl Message for CancelPendingTxn {
     type Result = Result<()>;
 }
+/// Timer tick to cancel the pending txn which is past its deadline.
+#[derive(Clone, Debug)]
+pub(crate) struct ExpirePendingTxn;
+impl Message for ExpirePendingTxn {
+    type Result = ();
+}
+
 #[derive(Debug)]
 pub(crate) struct ApplyPendingTxn;
 /// return a (sender, seq_number) txn to watch if travel.
@@ -181,6 +197,23 @@ impl Message for GetPendingTxn {
     type Result = Option<PendingTransaction>;
 }
 
+/// Build the channel state to report to participants when they reconnect.
+#[derive(Debug)]
+pub(crate) struct BuildReestablish;
+impl Message for BuildReestablish {
+    type Result = Result<ChannelReestablish>;
+}
+
+/// Catch up with channel state reported by a participant.
+#[derive(Debug)]
+pub(crate) struct Reestablish {
+    pub remote: ChannelReestablish,
+}
+/// return the action taken, and whether the pending txn is agreed and should be applied.
+impl Message for Reestablish {
+    type Result = Result<(ReestablishAction, bool)>;
+}
+
 #[derive(Debug)]
 pub(crate) struct AccessingResource {
     pub path: AccessPath,
@@ -189,8 +222,31 @@ impl Message for AccessingResource {
     type Result = Result<Option<Vec<u8>>>;
 }
 
+#[derive(Debug)]
+pub(crate) struct BuildChallengeTxn;
+impl Message for BuildChallengeTxn {
+    type Result = Result<(u64, SignedTransaction)>;
+}
+
 pub enum ChannelEvent {
-    Stopped { channel_address: AccountAddress },
+    Stopped {
+        channel_address: AccountAddress,
+    },
+    /// an offchain state is replaced by a newer one, `revoked_witness` is hash of its witness.
+    StateRevoked {
+        channel_address: AccountAddress,
+        revoked_witness: HashValue,
+    },
+    /// a txn is applied into the channel, offchain or onchain.
+    StateApplied {
+        channel_address: AccountAddress,
+    },
+    /// pending proposal `channel_txn_id` is cancelled as it's past its deadline.
+    ProposalExpired {
+        channel_address: AccountAddress,
+        channel_txn_id: HashValue,
+        participants: BTreeSet<AccountAddress>,
+    },
 }
 
 pub(crate) fn access_local<'a>(
@@ -0,0 +1,183 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    chain_watcher::get_block_height, channel::channel_stm::ChannelStm, scripts::PackageRegistry,
+    signer::Signer, utils::contract::channel_close_name, wallet::TXN_EXPIRATION,
+};
+use anyhow::{ensure, format_err, Result};
+use libra_crypto::hash::CryptoHash;
+use libra_logger::prelude::*;
+use libra_types::{
+    account_address::AccountAddress,
+    account_config::{account_module_name, core_code_address},
+    transaction::{SignedTransaction, TransactionArgument},
+};
+use sgchain::star_chain_client::ChainClient;
+use sgtypes::{
+    channel::ChannelState,
+    channel_backup::ChannelBackup,
+    channel_transaction::{ChannelOp, ChannelTransactionRequest, ChannelTransactionResponse},
+    pending_txn::{PendingTransaction, ProposalLifecycle},
+};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Channel seeded from its static backup, after it's lost from local store.
+/// Without the txn history it can't go on offchain, so it's closed: cooperatively if the other
+/// participant agrees the backup is its latest state, or by force otherwise.
+pub(crate) struct RestoredChannel {
+    account: AccountAddress,
+    backup: ChannelBackup,
+    stm: ChannelStm,
+    chain_client: Arc<dyn ChainClient>,
+    /// the cooperative close proposed to the other participant.
+    pending_txn: Option<PendingTransaction>,
+}
+
+impl RestoredChannel {
+    pub(crate) fn new(
+        account: AccountAddress,
+        signer: Arc<dyn Signer>,
+        script_registry: Arc<PackageRegistry>,
+        chain_client: Arc<dyn ChainClient>,
+        backup: ChannelBackup,
+    ) -> Result<Self> {
+        let channel_address = backup.channel_address;
+        let channel_state = chain_client.get_account_state(channel_address, None)?;
+        let mut stm = ChannelStm::new(
+            channel_address,
+            account,
+            backup.participants.clone(),
+            signer,
+            script_registry,
+            chain_client.clone(),
+            TXN_EXPIRATION,
+        );
+        stm.advance_state(
+            Some(ChannelState::new(channel_address, channel_state)),
+            backup.witness.clone(),
+            backup.participant_keys.clone(),
+        );
+        Ok(Self {
+            account,
+            backup,
+            stm,
+            chain_client,
+            pending_txn: None,
+        })
+    }
+
+    pub fn channel_address(&self) -> AccountAddress {
+        self.backup.channel_address
+    }
+
+    /// The other participant, only two-party channels can be closed cooperatively.
+    pub fn participant(&self) -> Result<AccountAddress> {
+        self.backup
+            .participants
+            .iter()
+            .find(|p| *p != &self.account)
+            .cloned()
+            .ok_or(format_err!("channel should contain other participants"))
+    }
+
+    pub fn is_closed(&self) -> Result<bool> {
+        let channel_resource = self.stm.channel_resource().ok_or(format_err!(
+            "channel {} not exists on chain",
+            self.channel_address()
+        ))?;
+        Ok(channel_resource.closed())
+    }
+
+    /// Whether the channel is locked by a solo txn, and waiting for participants to resolve it.
+    pub fn is_locked(&self) -> bool {
+        self.stm.channel_lock_by_resource().is_some()
+    }
+
+    /// Propose to close the channel at the backup state, signed by me.
+    pub fn propose_close(&mut self) -> Result<ChannelTransactionRequest> {
+        ensure!(
+            self.backup.participants.len() == 2,
+            "only two-party channel can be closed cooperatively"
+        );
+        ensure!(
+            !self.is_locked(),
+            "channel {} is locked, it can only be closed by force",
+            self.channel_address()
+        );
+        let participant = self.participant()?;
+        let proposal = self
+            .stm
+            .generate_proposal(close_op(), vec![TransactionArgument::Address(participant)])?;
+        let output = self.stm.execute_proposal(&proposal)?;
+        let sigs = self.stm.generate_txn_sigs(&proposal.channel_txn, &output)?;
+        let is_travel_txn = output.is_travel_txn();
+        let mut pending_txn = PendingTransaction::new(proposal.clone(), output, BTreeMap::new());
+        pending_txn.set_lifecycle(ProposalLifecycle::Created);
+        self.stm.handle_proposal_signature(
+            &mut pending_txn,
+            CryptoHash::hash(&proposal.channel_txn),
+            sigs.clone(),
+        )?;
+        self.pending_txn = Some(pending_txn);
+        Ok(ChannelTransactionRequest::new(
+            proposal,
+            sigs,
+            is_travel_txn,
+        ))
+    }
+
+    /// Collect signatures of the close from the other participant,
+    /// and return the close txn to submit once it's agreed.
+    pub fn agreed_close_txn(
+        &mut self,
+        response: &ChannelTransactionResponse,
+    ) -> Result<Option<SignedTransaction>> {
+        let pending_txn = self.pending_txn.as_mut().ok_or(format_err!(
+            "no close of channel {} is proposed",
+            self.backup.channel_address
+        ))?;
+        let (_proposal, sigs) = response.clone().into();
+        self.stm
+            .handle_proposal_signature(pending_txn, response.request_id(), sigs)?;
+        if !pending_txn.consensus_reached() {
+            return Ok(None);
+        }
+        Ok(Some(self.stm.build_signed_txn(pending_txn)?))
+    }
+
+    /// Build a solo close txn of the backup state.
+    /// Return None if the channel is already closed, or is locked and waiting for resolve.
+    /// The first call locks the channel, participants resolve or challenge it by their own
+    /// channel, call again after the lock timeout to close the channel.
+    pub async fn force_close_txn(&self) -> Result<Option<SignedTransaction>> {
+        let channel_address = self.channel_address();
+        if self.is_closed()? {
+            info!("channel {} is already closed", channel_address);
+            return Ok(None);
+        }
+        if let Some(lock_by) = self.stm.channel_lock_by_resource() {
+            let block_height = get_block_height(self.chain_client.as_ref(), None).await?;
+            if block_height <= lock_by.time_lock {
+                info!(
+                    "channel {} is locked until {}, now {}",
+                    channel_address, lock_by.time_lock, block_height
+                );
+                return Ok(None);
+            }
+        }
+        let txn = self.stm.build_solo_txn(
+            close_op(),
+            vec![TransactionArgument::Address(self.participant()?)],
+        )?;
+        Ok(Some(txn))
+    }
+}
+
+fn close_op() -> ChannelOp {
+    ChannelOp::Action {
+        module_address: core_code_address(),
+        module_name: account_module_name().as_str().to_string(),
+        function_name: channel_close_name().as_str().to_string(),
+    }
+}
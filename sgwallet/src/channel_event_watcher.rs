@@ -87,6 +87,23 @@ async fn get_channel_change(
     }
 }
 
+/// Participants of the channel at `channel_address` on chain, None if the channel doesn't exist.
+pub async fn get_channel_participants(
+    chain_client: Arc<dyn ChainClient>,
+    channel_address: AccountAddress,
+) -> Result<Option<Vec<AccountAddress>>> {
+    let state = ChainClientEventQuerier(chain_client)
+        .get_account_state(channel_address, None)
+        .await?;
+    let path = DataPath::onchain_resource_path(channel_struct_tag()).to_vec();
+    match state.and_then(|mut s| s.remove(&path)) {
+        None => Ok(None),
+        Some(value) => Ok(Some(
+            ChannelResource::make_from(value)?.participants().to_vec(),
+        )),
+    }
+}
+
 fn parse_channel_event(event: &ContractEvent) -> Result<ChannelEvent> {
     match event.type_tag() {
         TypeTag::Struct(s) => {
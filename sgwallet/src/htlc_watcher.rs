@@ -0,0 +1,332 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Watch outgoing htlc payments of the wallet against chain height.
+//!
+//! An expired htlc is recalled by proposing `cancel_payment_after_timeout` to the participant.
+//! If the participant doesn't sign the recall in time, the recall is forced onchain.
+//! Every action taken is appended to the htlc audit log.
+
+use crate::{
+    chain_watcher::get_block_height,
+    utils::{
+        actor_timer::Timer, is_htlc_recall, is_htlc_receive, is_htlc_transfer, parse_htlc_payment,
+        parse_htlc_preimage,
+    },
+    wallet::WalletHandle,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use coerce_rt::actor::{
+    context::{ActorContext, ActorHandlerContext},
+    message::{Handler, Message},
+    Actor, ActorRef, GetActorRef,
+};
+use futures::channel::mpsc;
+use libra_crypto::HashValue;
+use libra_logger::prelude::*;
+use libra_types::account_address::AccountAddress;
+use sgstorage::{channel_db::ChannelDB, htlc_store::HtlcStore};
+use sgtypes::{
+    channel_transaction::{ChannelTransaction, ChannelTransactionRequest},
+    htlc::{HtlcAction, HtlcAuditRecord, HtlcRecord, HtlcState},
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// how often the watcher checks tracked htlcs.
+pub const HTLC_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// blocks to wait for the participant to sign a recall before forcing it onchain.
+pub const HTLC_FORCE_RECALL_AFTER: u64 = 10;
+/// give up a htlc after its recall failed this many times.
+pub const MAX_HTLC_RECALL_ATTEMPTS: u32 = 10;
+
+/// Recall proposal which should be delivered to the participant to get its signature.
+#[derive(Clone, Debug)]
+pub struct HtlcRecallRequest {
+    pub participant: AccountAddress,
+    pub request: ChannelTransactionRequest,
+}
+
+/// Bookkeeping of outgoing htlcs, shared by wallet handles and the watcher.
+pub(crate) struct HtlcBook {
+    account: AccountAddress,
+    store: HtlcStore<ChannelDB>,
+    /// serialize state transitions and audit appends.
+    lock: futures::lock::Mutex<()>,
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<HtlcRecallRequest>>>,
+}
+
+impl HtlcBook {
+    pub fn new(account: AccountAddress, store: HtlcStore<ChannelDB>) -> Self {
+        Self {
+            account,
+            store,
+            lock: futures::lock::Mutex::new(()),
+            subscribers: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn list_htlcs(&self) -> Result<Vec<HtlcRecord>> {
+        self.store.list_htlcs()
+    }
+
+    pub fn list_audits(
+        &self,
+        start_index: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, HtlcAuditRecord)>> {
+        self.store.list_audits(start_index, limit)
+    }
+
+    fn outstanding_htlcs(&self) -> Result<Vec<HtlcRecord>> {
+        let mut htlcs = self.store.list_htlcs()?;
+        htlcs.retain(|htlc| !htlc.state().is_final());
+        Ok(htlcs)
+    }
+
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<HtlcRecallRequest> {
+        let (tx, rx) = mpsc::unbounded();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    fn publish(&self, recall: HtlcRecallRequest) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.unbounded_send(recall.clone()).is_ok());
+        debug!(
+            "recall request {} to {} is published",
+            recall.request.request_id(),
+            recall.participant
+        );
+    }
+
+    /// Update the book by a channel txn applied in channel with `participant`.
+    pub async fn on_txn_applied(
+        &self,
+        participant: AccountAddress,
+        channel_txn: &ChannelTransaction,
+    ) -> Result<()> {
+        let op = channel_txn.operator();
+        if is_htlc_transfer(op) && channel_txn.proposer() == self.account {
+            let payment = parse_htlc_payment(channel_txn.args())?;
+            let _guard = self.lock.lock().await;
+            if self.store.get_htlc(payment.hash_lock())?.is_none() {
+                let record = HtlcRecord::new(participant, payment);
+                self.store.save_htlc(&record)?;
+                self.audit(&record, HtlcAction::Track, None, String::new())?;
+            }
+        } else if is_htlc_receive(op) && channel_txn.proposer() == participant {
+            let preimage = parse_htlc_preimage(channel_txn.args())?;
+            let hash_lock = HashValue::from_sha3_256(preimage.to_vec().as_slice());
+            self.update(&hash_lock, None, |record| {
+                record.set_state(HtlcState::Settled);
+                Some((HtlcAction::Settle, String::new()))
+            })
+            .await?;
+        } else if is_htlc_recall(op) && channel_txn.proposer() == self.account {
+            // a channel holds at most one outgoing htlc at a time, so the recall is for
+            // whatever is still unsettled with the participant.
+            let htlcs = self.outstanding_htlcs()?;
+            for htlc in htlcs.iter().filter(|h| h.participant() == participant) {
+                self.update(htlc.hash_lock(), None, |record| {
+                    record.set_state(HtlcState::Recalled);
+                    Some((HtlcAction::Recall, String::new()))
+                })
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply `f` to the latest record of `hash_lock` if it's not final yet, and save it.
+    /// The change is audited if `f` returns the action taken.
+    async fn update<F>(&self, hash_lock: &HashValue, block_height: Option<u64>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut HtlcRecord) -> Option<(HtlcAction, String)>,
+    {
+        let _guard = self.lock.lock().await;
+        let mut record = match self.store.get_htlc(hash_lock)? {
+            Some(record) if !record.state().is_final() => record,
+            _ => return Ok(()),
+        };
+        let action = f(&mut record);
+        self.store.save_htlc(&record)?;
+        if let Some((action, detail)) = action {
+            self.audit(&record, action, block_height, detail)?;
+        }
+        Ok(())
+    }
+
+    fn audit(
+        &self,
+        record: &HtlcRecord,
+        action: HtlcAction,
+        block_height: Option<u64>,
+        detail: String,
+    ) -> Result<()> {
+        info!(
+            "htlc {} with {}: {:?} {}",
+            record.hash_lock(),
+            record.participant(),
+            action,
+            &detail
+        );
+        let audit = HtlcAuditRecord::new(record, action, block_height, now_millis(), detail);
+        self.store.append_audit(&audit)?;
+        Ok(())
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_millis() as u64
+}
+
+pub(crate) struct HtlcWatcher {
+    wallet: WalletHandle,
+    book: Arc<HtlcBook>,
+    check_interval: Duration,
+    force_recall_after: u64,
+    timer: Option<Timer>,
+}
+
+impl HtlcWatcher {
+    pub fn new(wallet: WalletHandle, book: Arc<HtlcBook>) -> Self {
+        Self {
+            wallet,
+            book,
+            check_interval: HTLC_CHECK_INTERVAL,
+            force_recall_after: HTLC_FORCE_RECALL_AFTER,
+            timer: None,
+        }
+    }
+
+    pub async fn start(self, mut context: ActorContext) -> Result<ActorRef<HtlcWatcher>> {
+        let actor_ref = context.new_tracked_actor(self).await?;
+        Ok(actor_ref)
+    }
+
+    async fn check(&self) -> Result<()> {
+        let block_height = get_block_height(self.wallet.client(), None).await?;
+        for htlc in self.book.outstanding_htlcs()? {
+            let hash_lock = *htlc.hash_lock();
+            match htlc.expire_at() {
+                // the htlc is applied at some height not later than now,
+                // so it's safe to count the timeout from now.
+                None => {
+                    let expire_at = block_height.saturating_add(htlc.payment().timeout());
+                    self.book
+                        .update(&hash_lock, Some(block_height), |record| {
+                            record.set_expire_at(expire_at);
+                            None
+                        })
+                        .await?;
+                }
+                Some(_) if htlc.is_expired(block_height) => {
+                    self.recall(&htlc, block_height).await?;
+                }
+                Some(_) if htlc.is_recall_stuck(block_height, self.force_recall_after) => {
+                    self.force_recall(&htlc, block_height).await?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn recall(&self, htlc: &HtlcRecord, block_height: u64) -> Result<()> {
+        let participant = htlc.participant();
+        match self.wallet.recall_timeout_payment(participant).await {
+            Ok(request) => {
+                let request_id = request.request_id();
+                self.book
+                    .update(htlc.hash_lock(), Some(block_height), |record| {
+                        record.set_state(HtlcState::Recalling {
+                            proposed_at: block_height,
+                        });
+                        Some((HtlcAction::ProposeRecall, format!("request {}", request_id)))
+                    })
+                    .await?;
+                self.book.publish(HtlcRecallRequest {
+                    participant,
+                    request,
+                });
+            }
+            Err(e) => {
+                self.recall_failed(htlc, block_height, e.to_string())
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    async fn force_recall(&self, htlc: &HtlcRecord, block_height: u64) -> Result<()> {
+        self.book
+            .update(htlc.hash_lock(), Some(block_height), |_record| {
+                Some((HtlcAction::ForceRecall, String::new()))
+            })
+            .await?;
+        // the recall is marked as applied by wallet once the travel txn is applied.
+        if let Err(e) = self.wallet.force_travel_txn(htlc.participant()).await {
+            self.recall_failed(htlc, block_height, e.to_string())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Put the htlc back to pending, so that the recall is proposed again at next check.
+    async fn recall_failed(
+        &self,
+        htlc: &HtlcRecord,
+        block_height: u64,
+        reason: String,
+    ) -> Result<()> {
+        self.book
+            .update(htlc.hash_lock(), Some(block_height), |record| {
+                if record.inc_recall_attempts() >= MAX_HTLC_RECALL_ATTEMPTS {
+                    record.set_state(HtlcState::Failed);
+                } else {
+                    record.set_state(HtlcState::Pending);
+                }
+                Some((HtlcAction::Fail, reason))
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl Actor for HtlcWatcher {
+    async fn started(&mut self, ctx: &mut ActorHandlerContext) {
+        let myself = self.get_ref(ctx);
+        self.timer = Some(Timer::start(myself, self.check_interval, CheckHtlc));
+    }
+
+    async fn stopped(&mut self, _ctx: &mut ActorHandlerContext) {
+        if let Some(timer) = self.timer.take() {
+            timer.stop();
+        }
+        info!("htlc watcher of {} stopped", self.wallet.account());
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CheckHtlc;
+impl Message for CheckHtlc {
+    type Result = ();
+}
+
+#[async_trait]
+impl Handler<CheckHtlc> for HtlcWatcher {
+    async fn handle(&mut self, _message: CheckHtlc, _ctx: &mut ActorHandlerContext) {
+        if let Err(e) = self.check().await {
+            warn!("fail to check htlc payments, {}", e);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hand justice data of revoked channel states to watchtowers.
+//!
+//! Each time an offchain state is replaced, the wallet builds a challenge txn of the new state,
+//! encrypts it by the revoked witness, and publishes it to subscribers, who push it to towers.
+
+use crate::channel::ChannelHandle;
+use futures::channel::mpsc;
+use libra_crypto::HashValue;
+use libra_logger::prelude::*;
+use sgtypes::justice::{JusticeBlob, JusticeData};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+pub(crate) struct JusticeBook {
+    subscribers: Mutex<Vec<mpsc::UnboundedSender<JusticeBlob>>>,
+}
+
+impl JusticeBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> mpsc::UnboundedReceiver<JusticeBlob> {
+        let (tx, rx) = mpsc::unbounded();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| !subscriber.is_closed());
+        !subscribers.is_empty()
+    }
+
+    fn publish(&self, blob: JusticeBlob) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.unbounded_send(blob.clone()).is_ok());
+    }
+
+    /// Build justice data against `revoked_witness` from the channel, and publish it.
+    pub async fn revoke(&self, channel: Arc<ChannelHandle>, revoked_witness: HashValue) {
+        let (channel_sequence_number, challenge_txn) = match channel.build_challenge_txn().await {
+            Ok(r) => r,
+            Err(e) => {
+                error!(
+                    "fail to build challenge txn of channel {}, {}",
+                    channel.channel_address(),
+                    e
+                );
+                return;
+            }
+        };
+        let data = JusticeData::new(
+            channel.channel_address().clone(),
+            channel_sequence_number,
+            challenge_txn,
+        );
+        match data.encrypt(&revoked_witness) {
+            Ok(blob) => {
+                self.publish(blob);
+                debug!(
+                    "justice of channel {} at {} is published",
+                    channel.channel_address(),
+                    channel_sequence_number
+                );
+            }
+            Err(e) => error!("fail to encrypt justice data, {}", e),
+        }
+    }
+}
@@ -4,6 +4,7 @@
 pub mod channel;
 mod channel_state_view;
 pub mod scripts;
+pub mod signer;
 pub mod tx_applier;
 pub mod wallet;
 pub use crate::channel_state_view::ChannelStateView;
@@ -11,8 +12,10 @@ pub mod chain_state_access;
 pub mod chain_watcher;
 mod channel_event_watcher;
 mod data_stream;
+pub mod htlc_watcher;
+mod justice;
 pub mod utils;
-pub use channel_event_watcher::{get_channel_events, ChannelChangeEvent};
+pub use channel_event_watcher::{get_channel_events, get_channel_participants, ChannelChangeEvent};
 #[macro_use]
 extern crate include_dir;
 
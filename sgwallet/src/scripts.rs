@@ -15,10 +15,12 @@ use ir_to_bytecode::{
 use lazy_static::lazy_static;
 use libra_logger::prelude::*;
 use libra_types::{
-    account_address::AccountAddress, account_config::coin_struct_tag, language_storage::StructTag,
-    transaction::Script,
+    account_address::AccountAddress, account_config::coin_struct_tag,
+    channel::ChannelParticipantAccountResource, language_storage::StructTag,
+    libra_resource::LibraResource, transaction::Script,
 };
 use sgcompiler::{Compiler, ScriptFile};
+pub use sgtypes::script_package::ChannelAsset;
 use sgtypes::{
     channel_transaction::ChannelOp,
     script_package::{ChannelScriptPackage, ScriptCode},
@@ -29,9 +31,25 @@ static SCRIPTS_DIR: Dir = include_dir!("scripts");
 
 pub static DEFAULT_PACKAGE: &str = "libra";
 
+/// max participants of a channel, channels of more than two participants are opened by
+/// `open_{n}.mvir`, which takes addresses of other participants, then amount of the proposer,
+/// then amounts of others.
+pub const MAX_CHANNEL_PARTICIPANTS: usize = 4;
+
+/// scripts every asset package should have, deposit(amount), transfer(payee, amount) and withdraw(amount).
+pub static ASSET_SCRIPTS: [&str; 3] = ["deposit", "transfer", "withdraw"];
+
+/// optional script of an asset package, which opens the channel and deposits the asset too,
+/// open(participant, sender_amount, receiver_amount, sender_asset_amount, receiver_asset_amount).
+pub static ASSET_OPEN_SCRIPT: &str = "open";
+
 lazy_static! {
-    static ref ASSET_SCRIPT_FOLDERS: Vec<(&'static str, StructTag)> =
-        vec![("libra", coin_struct_tag())];
+    /// assets registered by default, (package, asset, channel resource holding balances of the asset).
+    static ref ASSET_SCRIPT_FOLDERS: Vec<(&'static str, StructTag, StructTag)> = vec![(
+        "libra",
+        coin_struct_tag(),
+        ChannelParticipantAccountResource::struct_tag()
+    )];
 }
 
 /// Returns the source code for create-account transaction script.
@@ -61,7 +79,10 @@ pub fn encode_enable_channel_script() -> Script {
 #[derive(Debug, Clone)]
 pub struct PackageRegistry {
     open_script: ScriptCode,
+    /// number of participants -> script opening the channel.
+    multi_party_open_scripts: HashMap<usize, ScriptCode>,
     packages: AtomicRefCell<HashMap<String, ChannelScriptPackage>>,
+    assets: AtomicRefCell<HashMap<StructTag, ChannelAsset>>,
     close_script: ScriptCode,
 }
 
@@ -73,6 +94,15 @@ impl PackageRegistry {
         let open_script = compiler.compile_script(open_script_source)?;
         let close_script_source = get_file_contents("close.mvir")?;
         let close_script = compiler.compile_script(close_script_source)?;
+        let mut multi_party_open_scripts = HashMap::new();
+        for participants in 3..=MAX_CHANNEL_PARTICIPANTS {
+            let source = get_file_contents(&format!("open_{}.mvir", participants))?;
+            let script = compiler.compile_script(source)?;
+            multi_party_open_scripts.insert(
+                participants,
+                ScriptCode::new(ChannelOp::Open.to_string(), source.to_string(), script),
+            );
+        }
         debug!("{:?}", SCRIPTS_DIR.dirs());
         for dir in SCRIPTS_DIR.dirs() {
             let package_name = dir.path().to_str().unwrap();
@@ -91,19 +121,29 @@ impl PackageRegistry {
             let package = compiler.compile_package_with_files(package_name, script_files)?;
             packages.insert(package.package_name().to_string(), package);
         }
-        Ok(Self {
+        let registry = Self {
             open_script: ScriptCode::new(
                 ChannelOp::Open.to_string(),
                 open_script_source.to_string(),
                 open_script,
             ),
+            multi_party_open_scripts,
             packages: AtomicRefCell::new(packages),
+            assets: AtomicRefCell::new(HashMap::new()),
             close_script: ScriptCode::new(
                 ChannelOp::Close.to_string(),
                 close_script_source.to_string(),
                 close_script,
             ),
-        })
+        };
+        for (package_name, asset, balance_tag) in ASSET_SCRIPT_FOLDERS.iter() {
+            registry.register_asset(ChannelAsset::new(
+                asset.clone(),
+                package_name.to_string(),
+                balance_tag.clone(),
+            ))?;
+        }
+        Ok(registry)
     }
 
     pub fn get_package(&self, package_name: &str) -> Option<ChannelScriptPackage> {
@@ -135,10 +175,42 @@ impl PackageRegistry {
         Ok(())
     }
 
+    /// Register `asset`, whose package should be installed with all `ASSET_SCRIPTS`.
+    pub fn register_asset(&self, asset: ChannelAsset) -> Result<()> {
+        if self.assets.borrow().contains_key(&asset.asset) {
+            bail!("asset {:?} is registered", asset.asset);
+        }
+        for script_name in ASSET_SCRIPTS.iter() {
+            if self.get_script(&asset.package_name, script_name).is_none() {
+                bail!(
+                    "package {} of asset {:?} has no {} script",
+                    asset.package_name,
+                    asset.asset,
+                    script_name
+                );
+            }
+        }
+        self.assets.borrow_mut().insert(asset.asset.clone(), asset);
+        Ok(())
+    }
+
+    pub fn get_asset(&self, asset: &StructTag) -> Option<ChannelAsset> {
+        self.assets.borrow().get(asset).cloned()
+    }
+
+    pub fn assets(&self) -> Vec<ChannelAsset> {
+        self.assets.borrow().values().cloned().collect()
+    }
+
     pub fn open_script(&self) -> ScriptCode {
         self.open_script.clone()
     }
 
+    /// Script to open channel of `participants`, which should be more than two.
+    pub fn multi_party_open_script(&self, participants: usize) -> Option<ScriptCode> {
+        self.multi_party_open_scripts.get(&participants).cloned()
+    }
+
     pub fn close_script(&self) -> ScriptCode {
         self.close_script.clone()
     }
@@ -154,6 +226,7 @@ fn get_file_contents(path: &str) -> Result<&str> {
 #[cfg(test)]
 mod tests {
     use libra_logger::try_init_for_testing;
+    use libra_types::identifier::Identifier;
 
     use super::*;
 
@@ -169,5 +242,27 @@ mod tests {
         let package = registry.get_package("libra").unwrap();
         println!("{}", package);
         registry.get_script("libra", "transfer").unwrap();
+        assert!(registry.multi_party_open_script(2).is_none());
+        for participants in 3..=MAX_CHANNEL_PARTICIPANTS {
+            registry.multi_party_open_script(participants).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_register_asset() {
+        try_init_for_testing();
+        let registry = PackageRegistry::build().unwrap();
+        let libra = registry.get_asset(&coin_struct_tag()).unwrap();
+        assert_eq!(DEFAULT_PACKAGE, libra.package_name.as_str());
+        assert!(registry.register_asset(libra.clone()).is_err());
+
+        let mut point = libra;
+        point.asset.name = Identifier::new("Point").unwrap();
+        point.package_name = "point".to_string();
+        assert!(registry.register_asset(point.clone()).is_err());
+        point.package_name = DEFAULT_PACKAGE.to_string();
+        registry.register_asset(point.clone()).unwrap();
+        assert_eq!(Some(point), registry.get_asset(&point.asset));
+        assert_eq!(2, registry.assets().len());
     }
 }
@@ -0,0 +1,540 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Everything done by the account key.
+//!
+//! The wallet and the node use the account key only through a `Signer`: to sign channel and
+//! chain txns, checkpoints, invoices, gossip, open negotiations and the network identity, to
+//! derive the channel backup key and secrets shared with onion senders. The key itself never
+//! leaves the signer, the network transport has its own key bound to the account by
+//! `sign_network_identity`.
+//! `LocalSigner` keeps the key in process, `RemoteSigner` asks a signer process listening on a
+//! unix socket, so that the key can be kept in a separately hardened process.
+//! The signer process runs a `SignerServer`, which rejects every request its policy doesn't
+//! allow, and by default allows nothing but fetching the public key.
+
+use crate::scripts::encode_enable_channel_script;
+use anyhow::{bail, ensure, format_err, Result};
+use libra_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey, Ed25519Signature},
+    hash::CryptoHash,
+    test_utils::KeyPair,
+    SigningKey, VerifyingKey,
+};
+use libra_logger::prelude::*;
+use libra_types::{
+    account_address::AccountAddress,
+    channel::WitnessData,
+    transaction::{
+        ChannelTransactionPayloadBody, RawTransaction, SignedTransaction, TransactionPayload,
+    },
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sgtypes::{
+    channel_backup::backup_key,
+    channel_checkpoint::ChannelCheckpoint,
+    channel_transaction::ChannelTransaction,
+    encryption::KEY_SIZE,
+    gossip::Gossip,
+    invoice::Invoice,
+    message::RawNegotiateMessage,
+    network_identity::NetworkIdentity,
+    onion::{shared_secret, SharedSecret},
+};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// max size of a request or response sent over the signer socket.
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+/// a request to the signer process fails if it's not answered in time.
+const SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub trait Signer: Send + Sync {
+    fn public_key(&self) -> Ed25519PublicKey;
+
+    /// sign the channel txn proposed to other participants.
+    /// a batch txn uses it to sign the channel payload too.
+    fn sign_channel_txn(&self, channel_txn: &ChannelTransaction) -> Result<Ed25519Signature>;
+
+    /// sign the payload body of a channel txn.
+    fn sign_payload_body(&self, body: &ChannelTransactionPayloadBody) -> Result<Ed25519Signature>;
+
+    /// sign the write set of a channel txn with the channel sequence number it applies to.
+    fn sign_witness_data(&self, witness_data: &WitnessData) -> Result<Ed25519Signature>;
+
+    fn sign_txn(&self, raw_txn: RawTransaction) -> Result<SignedTransaction>;
+
+    /// sign a checkpoint of channel state co-signed with other participants.
+    fn sign_checkpoint(&self, checkpoint: &ChannelCheckpoint) -> Result<Ed25519Signature>;
+
+    /// sign an invoice paid to the account.
+    fn sign_invoice(&self, invoice: &Invoice) -> Result<Ed25519Signature>;
+
+    /// sign a gossip about the account or its channels.
+    fn sign_gossip(&self, gossip: &Gossip) -> Result<Ed25519Signature>;
+
+    /// sign terms of opening a channel negotiated with a peer.
+    fn sign_negotiate_message(&self, message: &RawNegotiateMessage) -> Result<Ed25519Signature>;
+
+    /// key to encrypt the static channel backup.
+    fn backup_key(&self) -> Result<[u8; KEY_SIZE]>;
+
+    /// secret shared with the sender of an onion packet whose ephemeral key is `ephemeral_key`.
+    fn onion_shared_secret(&self, ephemeral_key: &[u8; 32]) -> Result<SharedSecret>;
+
+    /// bind the network key of the node to the account.
+    fn sign_network_identity(&self, identity: &NetworkIdentity) -> Result<Ed25519Signature>;
+}
+
+/// Signer holding the keypair in process.
+pub struct LocalSigner {
+    keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+}
+
+impl LocalSigner {
+    pub fn new(keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>) -> Self {
+        Self { keypair }
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public_key(&self) -> Ed25519PublicKey {
+        self.keypair.public_key.clone()
+    }
+
+    fn sign_channel_txn(&self, channel_txn: &ChannelTransaction) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(channel_txn)))
+    }
+
+    fn sign_payload_body(&self, body: &ChannelTransactionPayloadBody) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(body)))
+    }
+
+    fn sign_witness_data(&self, witness_data: &WitnessData) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(witness_data)))
+    }
+
+    fn sign_txn(&self, raw_txn: RawTransaction) -> Result<SignedTransaction> {
+        Ok(raw_txn
+            .sign(&self.keypair.private_key, self.keypair.public_key.clone())?
+            .into_inner())
+    }
+
+    fn sign_checkpoint(&self, checkpoint: &ChannelCheckpoint) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(checkpoint)))
+    }
+
+    fn sign_invoice(&self, invoice: &Invoice) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(invoice)))
+    }
+
+    fn sign_gossip(&self, gossip: &Gossip) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(gossip)))
+    }
+
+    fn sign_negotiate_message(&self, message: &RawNegotiateMessage) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(message)))
+    }
+
+    fn backup_key(&self) -> Result<[u8; KEY_SIZE]> {
+        Ok(backup_key(&self.keypair.private_key))
+    }
+
+    fn onion_shared_secret(&self, ephemeral_key: &[u8; 32]) -> Result<SharedSecret> {
+        shared_secret(&self.keypair.private_key, ephemeral_key)
+    }
+
+    fn sign_network_identity(&self, identity: &NetworkIdentity) -> Result<Ed25519Signature> {
+        Ok(self
+            .keypair
+            .private_key
+            .sign_message(&CryptoHash::hash(identity)))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SignRequest {
+    PublicKey,
+    ChannelTxn(ChannelTransaction),
+    PayloadBody(ChannelTransactionPayloadBody),
+    WitnessData(WitnessData),
+    Txn(RawTransaction),
+    Checkpoint(ChannelCheckpoint),
+    Invoice(Invoice),
+    Gossip(Gossip),
+    NegotiateMessage(RawNegotiateMessage),
+    BackupKey,
+    OnionSharedSecret([u8; 32]),
+    NetworkIdentity(NetworkIdentity),
+}
+
+#[cfg(unix)]
+#[derive(Debug, Serialize, Deserialize)]
+enum SignResponse {
+    PublicKey(Ed25519PublicKey),
+    Signature(Ed25519Signature),
+    Txn(SignedTransaction),
+    Key([u8; KEY_SIZE]),
+    SharedSecret(SharedSecret),
+    Rejected(String),
+}
+
+/// Signer delegating to a `SignerServer` listening on a unix socket.
+/// Signatures returned are verified against the public key fetched on connect.
+/// It's used inside a tokio runtime, requests block in place of the async worker.
+#[cfg(unix)]
+pub struct RemoteSigner {
+    path: PathBuf,
+    public_key: Ed25519PublicKey,
+}
+
+#[cfg(unix)]
+impl RemoteSigner {
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let public_key = match request(&path, &SignRequest::PublicKey)? {
+            SignResponse::PublicKey(public_key) => public_key,
+            resp => bail!("unexpected response to public key request: {:?}", resp),
+        };
+        Ok(Self { path, public_key })
+    }
+
+    fn sign<H: CryptoHash>(&self, req: SignRequest, message: &H) -> Result<Ed25519Signature> {
+        match request(&self.path, &req)? {
+            SignResponse::Signature(signature) => {
+                self.public_key
+                    .verify_signature(&CryptoHash::hash(message), &signature)?;
+                Ok(signature)
+            }
+            resp => bail!("unexpected response to sign request: {:?}", resp),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> Ed25519PublicKey {
+        self.public_key.clone()
+    }
+
+    fn sign_channel_txn(&self, channel_txn: &ChannelTransaction) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::ChannelTxn(channel_txn.clone()), channel_txn)
+    }
+
+    fn sign_payload_body(&self, body: &ChannelTransactionPayloadBody) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::PayloadBody(body.clone()), body)
+    }
+
+    fn sign_witness_data(&self, witness_data: &WitnessData) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::WitnessData(witness_data.clone()), witness_data)
+    }
+
+    fn sign_txn(&self, raw_txn: RawTransaction) -> Result<SignedTransaction> {
+        match request(&self.path, &SignRequest::Txn(raw_txn.clone()))? {
+            SignResponse::Txn(signed_txn) => {
+                ensure!(
+                    signed_txn.clone().into_raw_transaction() == raw_txn,
+                    "signer returned a different txn"
+                );
+                ensure!(
+                    signed_txn.public_key() == self.public_key,
+                    "txn is signed by unexpected key"
+                );
+                Ok(signed_txn.check_signature()?.into_inner())
+            }
+            resp => bail!("unexpected response to sign request: {:?}", resp),
+        }
+    }
+
+    fn sign_checkpoint(&self, checkpoint: &ChannelCheckpoint) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::Checkpoint(checkpoint.clone()), checkpoint)
+    }
+
+    fn sign_invoice(&self, invoice: &Invoice) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::Invoice(invoice.clone()), invoice)
+    }
+
+    fn sign_gossip(&self, gossip: &Gossip) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::Gossip(gossip.clone()), gossip)
+    }
+
+    fn sign_negotiate_message(&self, message: &RawNegotiateMessage) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::NegotiateMessage(message.clone()), message)
+    }
+
+    fn backup_key(&self) -> Result<[u8; KEY_SIZE]> {
+        match request(&self.path, &SignRequest::BackupKey)? {
+            SignResponse::Key(key) => Ok(key),
+            resp => bail!("unexpected response to backup key request: {:?}", resp),
+        }
+    }
+
+    fn onion_shared_secret(&self, ephemeral_key: &[u8; 32]) -> Result<SharedSecret> {
+        match request(&self.path, &SignRequest::OnionSharedSecret(*ephemeral_key))? {
+            SignResponse::SharedSecret(secret) => Ok(secret),
+            resp => bail!("unexpected response to shared secret request: {:?}", resp),
+        }
+    }
+
+    fn sign_network_identity(&self, identity: &NetworkIdentity) -> Result<Ed25519Signature> {
+        self.sign(SignRequest::NetworkIdentity(identity.clone()), identity)
+    }
+}
+
+/// Policy checked by `SignerServer` before signing a request, reject it by returning an error.
+pub type SignPolicy = Box<dyn Fn(&SignRequest) -> Result<()> + Send + Sync>;
+
+/// Policy of the signer serving the node of `account`. Chain txns of the account are only
+/// allowed to apply channel txns or enable the channel feature, so a client of the signer can't
+/// send funds of the account to an arbitrary receiver on chain.
+pub fn node_sign_policy(account: AccountAddress) -> SignPolicy {
+    let enable_channel_code = encode_enable_channel_script().code().to_vec();
+    Box::new(move |req| match req {
+        SignRequest::Txn(raw_txn) => {
+            ensure!(
+                raw_txn.sender() == account,
+                "txn of another account {} is not allowed",
+                raw_txn.sender()
+            );
+            match raw_txn.payload() {
+                TransactionPayload::Channel(_) => Ok(()),
+                TransactionPayload::Script(script) if script.code() == &enable_channel_code[..] => {
+                    Ok(())
+                }
+                _ => bail!("only channel txns are allowed on chain"),
+            }
+        }
+        _ => Ok(()),
+    })
+}
+
+/// Serve sign requests of `RemoteSigner`s, one request per connection.
+/// Requests but the public key are rejected unless a policy is set by `with_policy`.
+#[cfg(unix)]
+pub struct SignerServer {
+    signer: Arc<dyn Signer>,
+    policy: SignPolicy,
+}
+
+#[cfg(unix)]
+impl SignerServer {
+    pub fn new(signer: Arc<dyn Signer>) -> Self {
+        Self {
+            signer,
+            policy: Box::new(|req| bail!("no policy allows {:?}", req)),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: SignPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Serve connections of `listener` in background until accepting fails,
+    /// every connection is served on its own thread, so a slow client never blocks others.
+    pub fn serve(self, listener: UnixListener) -> JoinHandle<Result<()>> {
+        let server = Arc::new(self);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream?;
+                let server = server.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = server.serve_connection(&mut stream) {
+                        warn!("fail to serve sign request, err: {:?}", e);
+                    }
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn serve_connection(&self, stream: &mut UnixStream) -> Result<()> {
+        stream.set_read_timeout(Some(SIGNER_TIMEOUT))?;
+        stream.set_write_timeout(Some(SIGNER_TIMEOUT))?;
+        let req: SignRequest = read_frame(stream)?;
+        let resp = self.handle(&req).unwrap_or_else(|e| {
+            info!("reject sign request {:?}, err: {:?}", req, e);
+            SignResponse::Rejected(e.to_string())
+        });
+        write_frame(stream, &resp)
+    }
+
+    fn handle(&self, req: &SignRequest) -> Result<SignResponse> {
+        match req {
+            // the public key is no secret, clients need it to check signatures.
+            SignRequest::PublicKey => {}
+            _ => (self.policy)(req)?,
+        }
+        let resp = match req {
+            SignRequest::PublicKey => SignResponse::PublicKey(self.signer.public_key()),
+            SignRequest::ChannelTxn(channel_txn) => {
+                SignResponse::Signature(self.signer.sign_channel_txn(channel_txn)?)
+            }
+            SignRequest::PayloadBody(body) => {
+                SignResponse::Signature(self.signer.sign_payload_body(body)?)
+            }
+            SignRequest::WitnessData(witness_data) => {
+                SignResponse::Signature(self.signer.sign_witness_data(witness_data)?)
+            }
+            SignRequest::Txn(raw_txn) => SignResponse::Txn(self.signer.sign_txn(raw_txn.clone())?),
+            SignRequest::Checkpoint(checkpoint) => {
+                SignResponse::Signature(self.signer.sign_checkpoint(checkpoint)?)
+            }
+            SignRequest::Invoice(invoice) => {
+                SignResponse::Signature(self.signer.sign_invoice(invoice)?)
+            }
+            SignRequest::Gossip(gossip) => {
+                SignResponse::Signature(self.signer.sign_gossip(gossip)?)
+            }
+            SignRequest::NegotiateMessage(message) => {
+                SignResponse::Signature(self.signer.sign_negotiate_message(message)?)
+            }
+            SignRequest::BackupKey => SignResponse::Key(self.signer.backup_key()?),
+            SignRequest::OnionSharedSecret(ephemeral_key) => {
+                SignResponse::SharedSecret(self.signer.onion_shared_secret(ephemeral_key)?)
+            }
+            SignRequest::NetworkIdentity(identity) => {
+                SignResponse::Signature(self.signer.sign_network_identity(identity)?)
+            }
+        };
+        Ok(resp)
+    }
+}
+
+/// Socket I/O blocks, so it's moved off the async worker thread of the caller,
+/// e.g. a channel actor signing its proposal.
+#[cfg(unix)]
+fn request(path: &Path, req: &SignRequest) -> Result<SignResponse> {
+    tokio::task::block_in_place(|| {
+        let mut stream = UnixStream::connect(path)
+            .map_err(|e| format_err!("fail to connect signer at {:?}: {}", path, e))?;
+        stream.set_read_timeout(Some(SIGNER_TIMEOUT))?;
+        stream.set_write_timeout(Some(SIGNER_TIMEOUT))?;
+        write_frame(&mut stream, req)?;
+        match read_frame(&mut stream)? {
+            SignResponse::Rejected(reason) => bail!("signer rejected the request: {}", reason),
+            resp => Ok(resp),
+        }
+    })
+}
+
+#[cfg(unix)]
+fn write_frame<T: Serialize>(stream: &mut UnixStream, msg: &T) -> Result<()> {
+    let bytes = lcs::to_bytes(msg)?;
+    stream.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    stream.write_all(&bytes)?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_frame<T: DeserializeOwned>(stream: &mut UnixStream) -> Result<T> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    ensure!(len <= MAX_FRAME_SIZE, "frame too large: {}", len);
+    let mut bytes = vec![0u8; len];
+    stream.read_exact(&mut bytes)?;
+    Ok(lcs::from_bytes(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libra_crypto::Uniform;
+    use libra_tools::tempdir::TempPath;
+    use libra_types::{transaction::Script, write_set::WriteSet};
+    use rand::prelude::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remote_signer() -> Result<()> {
+        let mut rng: StdRng = SeedableRng::from_seed([0; 32]);
+        let keypair = Arc::new(KeyPair::generate_for_testing(&mut rng));
+        let account = AccountAddress::from_public_key(&keypair.public_key);
+        let local = LocalSigner::new(keypair.clone());
+
+        let path = TempPath::new();
+        let listener = UnixListener::bind(path.path())?;
+        let server = SignerServer::new(Arc::new(LocalSigner::new(keypair.clone())))
+            .with_policy(node_sign_policy(account));
+        let _ = server.serve(listener);
+        // a server without policy answers nothing but the public key.
+        let deny_path = TempPath::new();
+        let listener = UnixListener::bind(deny_path.path())?;
+        let _ = SignerServer::new(Arc::new(LocalSigner::new(keypair.clone()))).serve(listener);
+
+        let mut rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(async {
+            let remote = RemoteSigner::connect(path.path())?;
+            assert_eq!(keypair.public_key, remote.public_key());
+
+            let witness_data = WitnessData::new(1, WriteSet::default());
+            assert_eq!(
+                local.sign_witness_data(&witness_data)?,
+                remote.sign_witness_data(&witness_data)?
+            );
+            assert_eq!(local.backup_key()?, remote.backup_key()?);
+            assert_eq!(
+                local.onion_shared_secret(&[9u8; 32])?,
+                remote.onion_shared_secret(&[9u8; 32])?
+            );
+            let network_key = Ed25519PrivateKey::generate_for_testing(&mut rng);
+            let identity = NetworkIdentity::new(Ed25519PublicKey::from(&network_key));
+            assert_eq!(
+                local.sign_network_identity(&identity)?,
+                remote.sign_network_identity(&identity)?
+            );
+
+            let new_txn = |script: Script| {
+                RawTransaction::new_script(
+                    account,
+                    0,
+                    script,
+                    1000,
+                    1,
+                    Duration::from_secs(u64::max_value()),
+                )
+            };
+            let other_script = Script::new(vec![], vec![]);
+            assert!(local.sign_txn(new_txn(other_script.clone())).is_ok());
+            assert!(remote.sign_txn(new_txn(other_script)).is_err());
+            assert!(remote
+                .sign_txn(new_txn(encode_enable_channel_script()))
+                .is_ok());
+
+            let denied = RemoteSigner::connect(deny_path.path())?;
+            assert_eq!(keypair.public_key, denied.public_key());
+            assert!(denied.sign_witness_data(&witness_data).is_err());
+            Ok(())
+        })
+    }
+}
@@ -77,6 +77,7 @@ fn generate_txn_to_apply(
         events: vec![],
         major_status: StatusCode::ABORTED,
         gas_used: 0,
+        travel_op: None,
     };
 
     txn_to_apply
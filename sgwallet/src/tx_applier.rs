@@ -72,7 +72,7 @@ impl TxApplier {
             events,
             major_status,
             gas_used,
-            ..
+            travel_op,
         } = tx_to_commit;
         let channel_seq_number = signed_channel_txn.channel_sequence_number();
         ensure!(
@@ -125,6 +125,7 @@ impl TxApplier {
             events,
             major_status,
             gas_used,
+            travel_op,
         };
 
         self.store
@@ -38,6 +38,21 @@ pub fn is_htlc_receive(op: &ChannelOp) -> bool {
         _ => false,
     }
 }
+/// check if the `op` recalls a timeout htlc transfer
+pub fn is_htlc_recall(op: &ChannelOp) -> bool {
+    match op {
+        ChannelOp::Action {
+            module_address,
+            module_name,
+            function_name,
+        } => {
+            module_address == &AccountAddress::default()
+                && module_name.as_str() == "ChannelScript"
+                && function_name.as_str() == "cancel_payment_after_timeout"
+        }
+        _ => false,
+    }
+}
 /// get hash lock value from `args` which should be the args of `ChannelScript.send_payment`
 pub fn parse_htlc_payment(args: &[TransactionArgument]) -> Result<HtlcPayment> {
     ensure!(args.len() == 4, "send_payment should have 4 args");
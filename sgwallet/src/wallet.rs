@@ -3,10 +3,14 @@
 use crate::{
     chain_watcher::{ChainWatcher, ChainWatcherHandle},
     channel::{
-        ApplyPendingTxn, CancelPendingTxn, Channel, ChannelEvent, ChannelHandle,
-        CollectProposalWithSigs, Execute, GrantProposal,
+        list_channel_txns, ApplyPendingTxn, BuildReestablish, CancelPendingTxn, Channel,
+        ChannelEvent, ChannelHandle, CollectProposalWithSigs, Execute, GrantProposal, Reestablish,
+        RestoredChannel,
     },
+    htlc_watcher::{HtlcBook, HtlcRecallRequest, HtlcWatcher},
+    justice::JusticeBook,
     scripts::*,
+    signer::{LocalSigner, Signer},
 };
 use anyhow::{bail, ensure, format_err, Error, Result};
 use async_trait::async_trait;
@@ -27,7 +31,7 @@ use libra_crypto::{
 use libra_logger::prelude::*;
 use libra_state_view::StateView;
 use libra_types::{
-    access_path::DataPath,
+    access_path::{AccessPath, DataPath},
     account_address::AccountAddress,
     account_config::{coin_struct_tag, AccountResource},
     byte_array::ByteArray,
@@ -38,35 +42,49 @@ use libra_types::{
     language_storage::StructTag,
     libra_resource::{make_resource, LibraResource},
     transaction::{
-        helpers::{create_signed_payload_txn, TransactionSigner},
-        Module, RawTransaction, SignedTransaction, Transaction, TransactionArgument,
-        TransactionOutput, TransactionPayload, TransactionStatus, TransactionWithProof,
+        helpers::TransactionSigner, Module, RawTransaction, Script, SignedTransaction, Transaction,
+        TransactionArgument, TransactionOutput, TransactionPayload, TransactionStatus,
+        TransactionWithProof,
     },
     vm_error::*,
+    write_set::WriteOp,
 };
 use sgchain::star_chain_client::{ChainClient, StarChainClient};
 use sgconfig::config::WalletConfig;
-use sgstorage::{channel_db::ChannelDB, channel_store::ChannelStore, storage::SgStorage};
+use sgstorage::{
+    channel_db::ChannelDB, channel_store::ChannelStore, channel_terms_store::ChannelTermsStore,
+    fee_policy_store::FeePolicyStore, htlc_store::HtlcStore,
+    script_package_store::ScriptPackageStore, storage::SgStorage,
+};
 use sgtypes::{
     account_resource_ext,
     applied_channel_txn::AppliedChannelTxn,
+    channel_backup::{ChannelBackup, StaticChannelBackup},
+    channel_checkpoint::SignedChannelCheckpoint,
+    channel_reestablish::{ChannelReestablish, ReestablishAction},
     channel_transaction::{
-        ChannelOp, ChannelTransactionProposal, ChannelTransactionRequest,
-        ChannelTransactionResponse,
+        BatchedChannelOp, ChannelOp, ChannelTransaction, ChannelTransactionProposal,
+        ChannelTransactionRequest, ChannelTransactionResponse,
     },
+    channel_txn_history::{ChannelTxnPage, ChannelTxnQuery},
+    fee_policy::FeePolicy,
+    htlc::{HtlcAuditRecord, HtlcRecord},
+    justice::JusticeBlob,
+    open_channel_policy::ChannelTerms,
     pending_txn::PendingTransaction,
     script_package::{ChannelScriptPackage, ScriptCode},
     sg_error::SgError,
     signed_channel_transaction::SignedChannelTransaction,
 };
 use std::{
-    collections::{BTreeSet, HashMap, HashSet},
-    path::Path,
-    sync::Arc,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
-use crate::channel::WatchAndApplyTravelTxn;
+use crate::channel::ApplyTravelTxn;
+use transaction_builder::encode_transfer_script;
 use vm_runtime::{MoveVM, VMExecutor};
 
 lazy_static! {
@@ -75,16 +93,34 @@ lazy_static! {
 }
 
 // const RETRY_INTERVAL: u64 = 1000;
-const TXN_EXPIRATION: Duration = Duration::from_secs(24 * 60 * 60);
+pub(crate) const TXN_EXPIRATION: Duration = Duration::from_secs(24 * 60 * 60);
 pub(crate) const MAX_GAS_AMOUNT_OFFCHAIN: u64 = std::u64::MAX;
 pub(crate) const MAX_GAS_AMOUNT_ONCHAIN: u64 = 1_000_000;
 pub(crate) const GAS_UNIT_PRICE: u64 = 1;
+/// number of channel txns between two checkpoints.
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 1000;
+
+/// Pending proposal cancelled by its deadline, participants should be told about it.
+#[derive(Clone, Debug)]
+pub struct ExpiredProposal {
+    pub channel_address: AccountAddress,
+    pub channel_txn_id: HashValue,
+    pub participants: BTreeSet<AccountAddress>,
+}
+
+type ExpiredProposalSubscribers =
+    Arc<Mutex<Vec<futures::channel::mpsc::UnboundedSender<ExpiredProposal>>>>;
 
 #[derive(Clone)]
 pub struct WalletHandle {
     actor_ref: ActorRef<Wallet>,
     shared: Shared,
     sgdb: Arc<SgStorage>,
+    htlc_book: Arc<HtlcBook>,
+    justice_book: Arc<JusticeBook>,
+    expired_proposal_subscribers: ExpiredProposalSubscribers,
+    /// channels restored from backup, waiting to be closed.
+    restoring: Arc<Mutex<HashMap<AccountAddress, RestoredChannel>>>,
     actor_context: ActorContext,
 }
 
@@ -114,11 +150,121 @@ impl WalletHandle {
         Ok(txn.signed_transaction)
     }
 
+    /// List applied txns of the channel with `participant` page by page,
+    /// with balance changes of each txn.
+    pub async fn list_channel_txns(
+        &self,
+        participant_address: AccountAddress,
+        query: ChannelTxnQuery,
+    ) -> Result<ChannelTxnPage> {
+        let (channel_address, ps) = generate_channel_address(self.account(), participant_address);
+        let channel_db = ChannelDB::new(channel_address, self.sgdb.clone());
+        let store = ChannelStore::new(ps, channel_db)?;
+        list_channel_txns(channel_address, &store, self.get_chain_client(), &query).await
+    }
+
+    /// Channels with `checkpoint_interval` txns applied since their latest checkpoint.
+    pub async fn channels_due_for_checkpoint(&self) -> Result<Vec<AccountAddress>> {
+        let interval = self.shared.checkpoint_interval;
+        if interval == 0 {
+            return Ok(vec![]);
+        }
+        let mut channels = vec![];
+        for participant in self.get_all_channels().await? {
+            let (_, store) = self.channel_store(participant)?;
+            let latest_version = match store.get_startup_info()? {
+                Some(startup_info) => startup_info.latest_version,
+                None => continue,
+            };
+            if latest_version >= store.first_unpruned_version()? + interval {
+                channels.push(participant);
+            }
+        }
+        Ok(channels)
+    }
+
+    /// Checkpoint of the channel with `participant` at its latest txn, signed by us.
+    pub fn sign_checkpoint(&self, participant: AccountAddress) -> Result<SignedChannelCheckpoint> {
+        let (channel_address, store) = self.channel_store(participant)?;
+        let latest_version = store
+            .get_startup_info()?
+            .ok_or_else(|| format_err!("no txn is applied in channel {}", channel_address))?
+            .latest_version;
+        let mut checkpoint =
+            SignedChannelCheckpoint::new(store.build_checkpoint(channel_address, latest_version)?);
+        let signature = self.shared.signer.sign_checkpoint(&checkpoint.checkpoint)?;
+        checkpoint.add_signature(self.account(), signature);
+        Ok(checkpoint)
+    }
+
+    /// Check `checkpoint` signed by `participant` against local state, sign and save it.
+    pub fn cosign_checkpoint(
+        &self,
+        participant: AccountAddress,
+        mut checkpoint: SignedChannelCheckpoint,
+    ) -> Result<SignedChannelCheckpoint> {
+        let (channel_address, store) = self.channel_store(participant)?;
+        ensure!(
+            checkpoint.checkpoint.channel_address == channel_address,
+            "checkpoint is not of channel {}",
+            channel_address
+        );
+        let public_key = store
+            .get_participant_keys()
+            .remove(&participant)
+            .ok_or_else(|| format_err!("public key of {} is unknown", participant))?;
+        checkpoint.verify_signer(participant, &public_key)?;
+        let signature = self.shared.signer.sign_checkpoint(&checkpoint.checkpoint)?;
+        checkpoint.add_signature(self.account(), signature);
+        self.save_checkpoint(participant, checkpoint.clone())?;
+        Ok(checkpoint)
+    }
+
+    /// Save `checkpoint` signed by all participants of the channel with `participant`,
+    /// txns and write sets before it are pruned.
+    pub fn save_checkpoint(
+        &self,
+        participant: AccountAddress,
+        checkpoint: SignedChannelCheckpoint,
+    ) -> Result<()> {
+        let (channel_address, store) = self.channel_store(participant)?;
+        ensure!(
+            checkpoint.checkpoint.channel_address == channel_address,
+            "checkpoint is not of channel {}",
+            channel_address
+        );
+        checkpoint.verify(&store.get_participant_keys())?;
+        store.save_checkpoint(checkpoint)
+    }
+
+    pub fn latest_checkpoint(
+        &self,
+        participant: AccountAddress,
+    ) -> Result<Option<SignedChannelCheckpoint>> {
+        let (_, store) = self.channel_store(participant)?;
+        store.get_latest_checkpoint()
+    }
+
+    fn channel_store(
+        &self,
+        participant: AccountAddress,
+    ) -> Result<(AccountAddress, ChannelStore<ChannelDB>)> {
+        let (channel_address, ps) = generate_channel_address(self.account(), participant);
+        let channel_db = ChannelDB::new(channel_address, self.sgdb.clone());
+        Ok((channel_address, ChannelStore::new(ps, channel_db)?))
+    }
+
     pub fn account(&self) -> AccountAddress {
         self.shared.account
     }
-    pub fn keypair(&self) -> Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> {
-        self.shared.keypair.clone()
+    /// everything done by the account key goes through it, node uses it for network identity,
+    /// gossip, invoices, negotiations and onion packets too.
+    pub fn signer(&self) -> Arc<dyn Signer> {
+        self.shared.signer.clone()
+    }
+    /// storage shared with the wallet, node can keep its own data in it.
+    pub fn storage(&self) -> Arc<SgStorage> {
+        self.sgdb.clone()
     }
     pub fn client(&self) -> &dyn ChainClient {
         self.shared.client.as_ref()
@@ -128,6 +274,87 @@ impl WalletHandle {
         self.shared.client.clone()
     }
 
+    /// Receive recall proposals of timeout htlc, which should be sent to the participant.
+    pub fn subscribe_htlc_recall(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<HtlcRecallRequest> {
+        self.htlc_book.subscribe()
+    }
+
+    /// Receive justice data of every revoked channel state, which should be pushed to watchtowers.
+    pub fn subscribe_justice(&self) -> futures::channel::mpsc::UnboundedReceiver<JusticeBlob> {
+        self.justice_book.subscribe()
+    }
+
+    /// Receive pending proposals cancelled by their deadline,
+    /// the other participants should be told with a `PROPOSAL_EXPIRED` error.
+    pub fn subscribe_expired_proposals(
+        &self,
+    ) -> futures::channel::mpsc::UnboundedReceiver<ExpiredProposal> {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        self.expired_proposal_subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Return all outgoing htlcs tracked by the wallet.
+    pub fn list_htlcs(&self) -> Result<Vec<HtlcRecord>> {
+        self.htlc_book.list_htlcs()
+    }
+
+    /// Return audit log of htlc actions starting from `start_index`.
+    pub fn list_htlc_audits(
+        &self,
+        start_index: u64,
+        limit: usize,
+    ) -> Result<Vec<(u64, HtlcAuditRecord)>> {
+        self.htlc_book.list_audits(start_index, limit)
+    }
+
+    /// Fee policy to forward htlc to `participant`.
+    pub fn fee_policy(&self, participant: AccountAddress) -> Result<FeePolicy> {
+        self.fee_policy_store().get_fee_policy(participant)
+    }
+
+    /// Set fee policy of channel with `participant`, or the default one of all channels.
+    pub fn set_fee_policy(
+        &self,
+        participant: Option<AccountAddress>,
+        policy: FeePolicy,
+    ) -> Result<()> {
+        self.fee_policy_store()
+            .save_fee_policy(participant, &policy)
+    }
+
+    /// Terms agreed with `participant` when opening the channel, if any.
+    pub fn channel_terms(&self, participant: AccountAddress) -> Result<Option<ChannelTerms>> {
+        let (channel_address, _) = generate_channel_address(participant, self.shared.account);
+        self.channel_terms_store(channel_address)
+            .get_terms(channel_address)
+    }
+
+    /// Persist terms agreed with `participant`, proposals of the channel must keep them.
+    pub fn set_channel_terms(
+        &self,
+        participant: AccountAddress,
+        terms: ChannelTerms,
+    ) -> Result<()> {
+        let (channel_address, _) = generate_channel_address(participant, self.shared.account);
+        self.channel_terms_store(channel_address)
+            .save_terms(channel_address, &terms)
+    }
+
+    fn channel_terms_store(&self, channel_address: AccountAddress) -> ChannelTermsStore<ChannelDB> {
+        ChannelTermsStore::new(ChannelDB::new(channel_address, self.sgdb.clone()))
+    }
+
+    fn fee_policy_store(&self) -> FeePolicyStore<ChannelDB> {
+        FeePolicyStore::new(ChannelDB::new(self.account(), self.sgdb.clone()))
+    }
+
+    fn script_package_store(&self) -> ScriptPackageStore<ChannelDB> {
+        ScriptPackageStore::new(ChannelDB::new(self.account(), self.sgdb.clone()))
+    }
+
     /// TODO: use async version of cient
     pub fn account_resource(&self) -> Result<AccountResource> {
         // account_resource must exist.
@@ -163,11 +390,16 @@ impl WalletHandle {
     pub async fn channel_sequence_number(&self, participant: AccountAddress) -> Result<u64> {
         let (generated_channel_address, _participants) =
             generate_channel_address(participant, self.shared.account);
-        let channel = self.get_channel(generated_channel_address).await?;
+        self.channel_sequence_number_of(generated_channel_address)
+            .await
+    }
+
+    pub async fn channel_sequence_number_of(&self, channel_address: AccountAddress) -> Result<u64> {
+        let channel = self.get_channel(channel_address).await?;
 
         let struct_tag = channel_mirror_struct_tag();
         // channel mirror resource is a shared resource
-        let data_path = DataPath::channel_resource_path(generated_channel_address, struct_tag);
+        let data_path = DataPath::channel_resource_path(channel_address, struct_tag);
         let mirror = channel
             .get_channel_resource::<ChannelMirrorResource>(data_path)
             .await?;
@@ -176,27 +408,196 @@ impl WalletHandle {
     }
 
     pub async fn participant_channel_balance(&self, participant: AccountAddress) -> Result<u64> {
+        self.participant_asset_channel_balance(participant, &DEFAULT_ASSET)
+            .await
+    }
+
+    pub async fn channel_balance(&self, participant: AccountAddress) -> Result<u64> {
+        self.asset_channel_balance(participant, &DEFAULT_ASSET)
+            .await
+    }
+
+    /// Balance of `participant` in the channel with it, of the given asset.
+    pub async fn participant_asset_channel_balance(
+        &self,
+        participant: AccountAddress,
+        asset: &StructTag,
+    ) -> Result<u64> {
+        let asset = self.get_asset(asset)?;
         Ok(self
-            .channel_participant_account_resource(participant, participant)
+            .channel_participant_account_resource(participant, participant, asset.balance_tag)
             .await?
             .map(|account| account.balance())
             .unwrap_or(0))
     }
 
-    pub async fn channel_balance(&self, participant: AccountAddress) -> Result<u64> {
+    /// My balance in the channel with `participant`, of the given asset.
+    pub async fn asset_channel_balance(
+        &self,
+        participant: AccountAddress,
+        asset: &StructTag,
+    ) -> Result<u64> {
+        let asset = self.get_asset(asset)?;
         Ok(self
-            .channel_participant_account_resource(participant, self.shared.account)
+            .channel_participant_account_resource(
+                participant,
+                self.shared.account,
+                asset.balance_tag,
+            )
             .await?
             .map(|account| account.balance())
             .unwrap_or(0))
     }
 
+    /// Balance of `participant` in the channel, which may have more than two participants.
+    pub async fn balance_in_channel(
+        &self,
+        channel_address: AccountAddress,
+        participant: AccountAddress,
+    ) -> Result<u64> {
+        let channel = self.get_channel(channel_address).await?;
+        let data_path = DataPath::channel_resource_path(
+            participant,
+            self.get_asset(&DEFAULT_ASSET)?.balance_tag,
+        );
+        Ok(channel
+            .get_channel_resource::<ChannelParticipantAccountResource>(data_path)
+            .await?
+            .map(|account| account.balance())
+            .unwrap_or(0))
+    }
+
+    /// My balances of all registered assets in the channel with `participant`.
+    pub async fn channel_balances(
+        &self,
+        participant: AccountAddress,
+    ) -> Result<Vec<(StructTag, u64)>> {
+        let mut balances = vec![];
+        for asset in self.assets() {
+            let balance = self
+                .asset_channel_balance(participant, &asset.asset)
+                .await?;
+            balances.push((asset.asset, balance));
+        }
+        Ok(balances)
+    }
+
+    /// Assets which can be held in channels.
+    pub fn assets(&self) -> Vec<ChannelAsset> {
+        self.shared.script_registry.assets()
+    }
+
+    /// Register a new asset, whose package should be installed before.
+    /// It's kept in the wallet store, and registered again when the wallet restarts.
+    pub fn register_asset(&self, asset: ChannelAsset) -> Result<()> {
+        self.shared.script_registry.register_asset(asset.clone())?;
+        self.script_package_store().save_asset(&asset)
+    }
+
+    fn get_asset(&self, asset: &StructTag) -> Result<ChannelAsset> {
+        self.shared
+            .script_registry
+            .get_asset(asset)
+            .ok_or_else(|| format_err!("asset {:?} is not registered", asset))
+    }
+
+    /// Deposit `amount` of `asset` into the channel with `receiver`.
+    pub async fn deposit_asset(
+        &self,
+        receiver: AccountAddress,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.deposit_asset receiver:{}, asset:{:?}, amount:{}",
+            receiver, asset, amount
+        );
+        self.execute_asset_script(
+            receiver,
+            asset,
+            "deposit",
+            vec![TransactionArgument::U64(amount)],
+        )
+        .await
+    }
+
+    /// Transfer `amount` of `asset` to `receiver` in the channel with it.
+    pub async fn transfer_asset(
+        &self,
+        receiver: AccountAddress,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.transfer_asset receiver:{}, asset:{:?}, amount:{}",
+            receiver, asset, amount
+        );
+        self.execute_asset_script(
+            receiver,
+            asset,
+            "transfer",
+            vec![
+                TransactionArgument::Address(receiver),
+                TransactionArgument::U64(amount),
+            ],
+        )
+        .await
+    }
+
+    /// Withdraw `amount` of `asset` out of the channel with `receiver`.
+    pub async fn withdraw_asset(
+        &self,
+        receiver: AccountAddress,
+        asset: &StructTag,
+        amount: u64,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.withdraw_asset receiver:{}, asset:{:?}, amount:{}",
+            receiver, asset, amount
+        );
+        self.execute_asset_script(
+            receiver,
+            asset,
+            "withdraw",
+            vec![TransactionArgument::U64(amount)],
+        )
+        .await
+    }
+
+    async fn execute_asset_script(
+        &self,
+        receiver: AccountAddress,
+        asset: &StructTag,
+        script_name: &str,
+        args: Vec<TransactionArgument>,
+    ) -> Result<ChannelTransactionRequest> {
+        let asset = self.get_asset(asset)?;
+        self.execute_async(
+            receiver,
+            ChannelOp::Execute {
+                package_name: asset.package_name,
+                script_name: script_name.to_string(),
+            },
+            args,
+        )
+        .await
+    }
+
     pub async fn channel_handle(&self, participant: AccountAddress) -> Result<Arc<ChannelHandle>> {
         let (channel_address, _) = generate_channel_address(participant, self.shared.account);
         let handle = self.get_channel(channel_address).await?;
         Ok(handle)
     }
 
+    /// Participants of the channel, including myself.
+    pub async fn channel_participants(
+        &self,
+        channel_address: AccountAddress,
+    ) -> Result<BTreeSet<AccountAddress>> {
+        let channel = self.get_channel(channel_address).await?;
+        Ok(channel.participant_addresses().clone())
+    }
+
     /// Open channel and deposit default asset.
     pub async fn open(
         &self,
@@ -208,17 +609,95 @@ impl WalletHandle {
             "wallet.open receiver:{}, sender_amount:{}, receiver_amount:{}",
             participant, sender_amount, receiver_amount
         );
+        let mut deposits = BTreeMap::new();
+        deposits.insert(self.shared.account, sender_amount);
+        deposits.insert(participant, receiver_amount);
+        self.open_multi_party(deposits).await
+    }
 
-        self.execute_async(
+    /// Open channel, deposit default asset and `asset` too, whose package should have an open script.
+    pub async fn open_with_asset(
+        &self,
+        participant: AccountAddress,
+        asset: &StructTag,
+        sender_amount: u64,
+        receiver_amount: u64,
+        sender_asset_amount: u64,
+        receiver_asset_amount: u64,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.open_with_asset receiver:{}, asset:{:?}, amounts:{}/{}, asset amounts:{}/{}",
             participant,
-            ChannelOp::Open,
-            vec![
-                TransactionArgument::Address(participant),
-                TransactionArgument::U64(sender_amount),
-                TransactionArgument::U64(receiver_amount),
-            ],
-        )
-        .await
+            asset,
+            sender_amount,
+            receiver_amount,
+            sender_asset_amount,
+            receiver_asset_amount
+        );
+        let asset = self.get_asset(asset)?;
+        ensure!(
+            self.shared
+                .script_registry
+                .get_script(&asset.package_name, ASSET_OPEN_SCRIPT)
+                .is_some(),
+            "package {} of asset {:?} can not open channel",
+            asset.package_name,
+            asset.asset
+        );
+        let (channel_address, participants) =
+            generate_channel_address(participant, self.shared.account);
+        // the package name tells the channel to open by the asset's open script.
+        let args = vec![
+            TransactionArgument::Address(participant),
+            TransactionArgument::U64(sender_amount),
+            TransactionArgument::U64(receiver_amount),
+            TransactionArgument::ByteArray(ByteArray::new(asset.package_name.into_bytes())),
+            TransactionArgument::U64(sender_asset_amount),
+            TransactionArgument::U64(receiver_asset_amount),
+        ];
+        self.execute_in_channel(channel_address, Some(participants), ChannelOp::Open, args)
+            .await
+    }
+
+    /// Open channel with all participants in `deposits`, each deposits the default asset of given amount.
+    /// `deposits` should contain myself, who is the proposer of the open txn.
+    pub async fn open_multi_party(
+        &self,
+        deposits: BTreeMap<AccountAddress, u64>,
+    ) -> Result<ChannelTransactionRequest> {
+        info!("wallet.open_multi_party deposits:{:?}", deposits);
+        let sender_amount = deposits
+            .get(&self.shared.account)
+            .cloned()
+            .ok_or(format_err!(
+                "proposer should be a participant of the channel"
+            ))?;
+        ensure!(
+            deposits.len() >= 2 && deposits.len() <= MAX_CHANNEL_PARTICIPANTS,
+            "channel should have 2 to {} participants",
+            MAX_CHANNEL_PARTICIPANTS
+        );
+        let participants = deposits.keys().cloned().collect::<BTreeSet<_>>();
+        let channel_address = generate_multi_party_channel_address(&participants);
+
+        // addresses of other participants, then amount of proposer, then amount of others.
+        let others = deposits
+            .iter()
+            .filter(|(addr, _)| *addr != &self.shared.account)
+            .collect::<Vec<_>>();
+        let mut args = others
+            .iter()
+            .map(|(addr, _)| TransactionArgument::Address(**addr))
+            .collect::<Vec<_>>();
+        args.push(TransactionArgument::U64(sender_amount));
+        args.extend(
+            others
+                .iter()
+                .map(|(_, amount)| TransactionArgument::U64(**amount)),
+        );
+
+        self.execute_in_channel(channel_address, Some(participants), ChannelOp::Open, args)
+            .await
     }
 
     pub async fn deposit(
@@ -350,10 +829,64 @@ impl WalletHandle {
         .await
     }
 
+    /// Resize the channel with `participant` in one travel txn:
+    /// deposit `deposit_amount` into the channel, rebalance `transfer_amount` to `participant`
+    /// or `receive_amount` from it, then withdraw `withdraw_amount` out of it to me, and
+    /// `participant_withdraw_amount` to `participant`.
+    /// Deposit only moves my onchain coins, `participant` deposits by proposing its own splice.
+    /// Offchain txns go on while the splice is waiting to be committed onchain.
+    pub async fn splice(
+        &self,
+        participant: AccountAddress,
+        deposit_amount: u64,
+        withdraw_amount: u64,
+        participant_withdraw_amount: u64,
+        transfer_amount: u64,
+        receive_amount: u64,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.splice participant:{}, deposit:{}, withdraw:{}, participant withdraw:{}, transfer:{}, receive:{}",
+            participant, deposit_amount, withdraw_amount, participant_withdraw_amount, transfer_amount, receive_amount
+        );
+        ensure!(
+            deposit_amount > 0 || withdraw_amount > 0 || participant_withdraw_amount > 0,
+            "splice should deposit or withdraw something"
+        );
+        ensure!(
+            transfer_amount == 0 || receive_amount == 0,
+            "splice should rebalance in one direction"
+        );
+        self.execute_async(
+            participant,
+            ChannelOp::Execute {
+                package_name: DEFAULT_PACKAGE.to_string(),
+                script_name: "splice".to_string(),
+            },
+            vec![
+                TransactionArgument::Address(participant),
+                TransactionArgument::U64(deposit_amount),
+                TransactionArgument::U64(withdraw_amount),
+                TransactionArgument::U64(participant_withdraw_amount),
+                TransactionArgument::U64(transfer_amount),
+                TransactionArgument::U64(receive_amount),
+            ],
+        )
+        .await
+    }
+
     pub async fn close(&mut self, receiver: AccountAddress) -> Result<ChannelTransactionRequest> {
         self.execute_async(receiver, ChannelOp::Close, vec![]).await
     }
 
+    /// Close the channel, which may have more than two participants.
+    pub async fn close_channel(
+        &self,
+        channel_address: AccountAddress,
+    ) -> Result<ChannelTransactionRequest> {
+        self.execute_in_channel(channel_address, None, ChannelOp::Close, vec![])
+            .await
+    }
+
     pub async fn execute_script(
         &self,
         receiver: AccountAddress,
@@ -376,6 +909,59 @@ impl WalletHandle {
         .await
     }
 
+    /// Execute script in the channel, which may have more than two participants.
+    pub async fn execute_script_in_channel(
+        &self,
+        channel_address: AccountAddress,
+        package_name: &str,
+        script_name: &str,
+        args: Vec<TransactionArgument>,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.execute_script_in_channel channel:{}, package_name:{}, script_name:{}, args:{:?}",
+            channel_address, package_name, script_name, args
+        );
+        self.execute_in_channel(
+            channel_address,
+            None,
+            ChannelOp::Execute {
+                package_name: package_name.to_string(),
+                script_name: script_name.to_string(),
+            },
+            args,
+        )
+        .await
+    }
+
+    /// Execute `ops` in the channel with `participant` atomically,
+    /// under one channel sequence number and one signature set.
+    pub async fn execute_batch(
+        &self,
+        participant: AccountAddress,
+        ops: Vec<BatchedChannelOp>,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.execute_batch participant:{}, ops:{:?}",
+            participant, ops
+        );
+        self.execute_async(participant, ChannelOp::Batch { ops }, vec![])
+            .await
+    }
+
+    /// Execute batched ops in the channel, which may have more than two participants.
+    pub async fn execute_batch_in_channel(
+        &self,
+        channel_address: AccountAddress,
+        ops: Vec<BatchedChannelOp>,
+    ) -> Result<ChannelTransactionRequest> {
+        info!(
+            "wallet.execute_batch_in_channel channel:{}, ops:{:?}",
+            channel_address, ops
+        );
+        self.execute_in_channel(channel_address, None, ChannelOp::Batch { ops }, vec![])
+            .await
+    }
+
     async fn execute_async(
         &self,
         participant: AccountAddress,
@@ -384,7 +970,21 @@ impl WalletHandle {
     ) -> Result<ChannelTransactionRequest> {
         let (channel_address, participants) =
             generate_channel_address(participant, self.shared.account);
+        self.execute_in_channel(channel_address, Some(participants), channel_op, args)
+            .await
+    }
+
+    /// `participants` is only needed when open the channel.
+    async fn execute_in_channel(
+        &self,
+        channel_address: AccountAddress,
+        participants: Option<BTreeSet<AccountAddress>>,
+        channel_op: ChannelOp,
+        args: Vec<TransactionArgument>,
+    ) -> Result<ChannelTransactionRequest> {
         let channel = if channel_op.is_open() {
+            let participants =
+                participants.ok_or(format_err!("participants are required to open channel"))?;
             self.spawn_new_channel(channel_address, participants)
                 .await?
         } else {
@@ -416,18 +1016,28 @@ impl WalletHandle {
             "peer id and txn proposer mismatch"
         );
 
-        let (generated_channel_address, participants) =
-            generate_channel_address(channel_txn.proposer(), self.shared.account);
-        ensure!(
-            generated_channel_address == channel_txn.channel_address(),
-            "invalid channel address in txn"
-        );
-
         let channel = if channel_txn.operator().is_open() {
+            let participants = open_participants(channel_txn);
+            ensure!(
+                participants.contains(&self.shared.account),
+                "not a participant of the channel to open"
+            );
+            let generated_channel_address = generate_multi_party_channel_address(&participants);
+            ensure!(
+                generated_channel_address == channel_txn.channel_address(),
+                "invalid channel address in txn"
+            );
             self.spawn_new_channel(generated_channel_address, participants)
                 .await?
         } else {
-            self.get_channel(generated_channel_address).await?
+            let channel = self.get_channel(channel_txn.channel_address()).await?;
+            ensure!(
+                channel
+                    .participant_addresses()
+                    .contains(&channel_txn.proposer()),
+                "proposer is not a participant of the channel"
+            );
+            channel
         };
 
         let (proposal, sigs, _) = txn_request.clone().into();
@@ -447,9 +1057,20 @@ impl WalletHandle {
         &self,
         participant: AccountAddress,
         request_id: HashValue,
+    ) -> Result<ChannelTransactionResponse> {
+        let (generated_channel_address, _participants) =
+            generate_channel_address(participant, self.shared.account);
+        self.approve_channel_txn(generated_channel_address, request_id)
+            .await
+    }
+
+    pub async fn approve_channel_txn(
+        &self,
+        channel_address: AccountAddress,
+        request_id: HashValue,
     ) -> Result<ChannelTransactionResponse> {
         let resp = self
-            .grant_txn_request(participant, request_id, true)
+            .grant_txn_request(channel_address, request_id, true)
             .await?;
         debug_assert!(
             resp.is_some(),
@@ -462,9 +1083,20 @@ impl WalletHandle {
         &self,
         participant: AccountAddress,
         request_id: HashValue,
+    ) -> Result<()> {
+        let (generated_channel_address, _participants) =
+            generate_channel_address(participant, self.shared.account);
+        self.reject_channel_txn(generated_channel_address, request_id)
+            .await
+    }
+
+    pub async fn reject_channel_txn(
+        &self,
+        channel_address: AccountAddress,
+        request_id: HashValue,
     ) -> Result<()> {
         let resp = self
-            .grant_txn_request(participant, request_id, false)
+            .grant_txn_request(channel_address, request_id, false)
             .await?;
         debug_assert!(
             resp.is_none(),
@@ -474,13 +1106,11 @@ impl WalletHandle {
     }
     async fn grant_txn_request(
         &self,
-        participant: AccountAddress,
+        channel_address: AccountAddress,
         request_id: HashValue,
         grant: bool,
     ) -> Result<Option<ChannelTransactionResponse>> {
-        let (generated_channel_address, _participants) =
-            generate_channel_address(participant, self.shared.account);
-        let channel = self.get_channel(generated_channel_address).await?;
+        let channel = self.get_channel(channel_address).await?;
         let pending_txn = channel.get_pending_txn().await?;
         let proposal: ChannelTransactionProposal = match pending_txn {
             Some(r) => {
@@ -518,8 +1148,25 @@ impl WalletHandle {
     ) -> Result<()> {
         let (generated_channel_address, _participants) =
             generate_channel_address(participant, self.shared.account);
+        self.cancel_pending_request_in_channel(generated_channel_address, participant, request_id)
+            .await
+    }
 
-        let channel = self.get_channel(generated_channel_address).await?;
+    /// Cancel pending txn `request_id` of channel `channel_address`, which is told by
+    /// `participant`, e.g. it's expired. It works for multi-party channels too.
+    pub async fn cancel_pending_request_in_channel(
+        &self,
+        channel_address: AccountAddress,
+        participant: AccountAddress,
+        request_id: HashValue,
+    ) -> Result<()> {
+        let channel = self.get_channel(channel_address).await?;
+        ensure!(
+            channel.participant_addresses().contains(&participant),
+            "{} is not a participant of channel {}",
+            participant,
+            channel_address
+        );
 
         let _resp = channel
             .channel_ref()
@@ -531,22 +1178,47 @@ impl WalletHandle {
         Ok(())
     }
 
+    /// Collect signatures of a participant, which may not be the proposer in multi-party channel.
+    /// Return true if the txn is signed by all participants, and can be applied.
     pub async fn verify_txn_response(
         &self,
         participant: AccountAddress,
         txn_response: &ChannelTransactionResponse,
-    ) -> Result<()> {
-        let (generated_channel_address, _participants) =
-            generate_channel_address(participant, self.shared.account);
-
-        let channel = self.get_channel(generated_channel_address).await?;
+    ) -> Result<bool> {
+        ensure!(
+            participant == txn_response.channel_txn_sigs().address,
+            "peer id and signer mismatch"
+        );
+        let channel = self
+            .get_channel(txn_response.channel_txn().channel_address())
+            .await?;
 
         let (proposal, sigs) = txn_response.clone().into();
         let _ = channel
             .channel_ref()
             .send(CollectProposalWithSigs { proposal, sigs })
             .await??;
-        Ok(())
+        self.is_pending_txn_agreed(
+            txn_response.channel_txn().channel_address(),
+            txn_response.request_id(),
+        )
+        .await
+    }
+
+    /// Check whether pending txn `request_id` of the channel is signed by all participants.
+    pub async fn is_pending_txn_agreed(
+        &self,
+        channel_address: AccountAddress,
+        request_id: HashValue,
+    ) -> Result<bool> {
+        let channel = self.get_channel(channel_address).await?;
+        let agreed = channel
+            .get_pending_txn()
+            .await?
+            .filter(|p| CryptoHash::hash(&p.proposal().channel_txn) == request_id)
+            .map(|p| p.consensus_reached())
+            .unwrap_or(false);
+        Ok(agreed)
     }
 
     pub async fn force_travel_txn(&self, participant: AccountAddress) -> Result<u64> {
@@ -554,6 +1226,10 @@ impl WalletHandle {
             generate_channel_address(participant, self.shared.account);
 
         let channel = self.get_channel(generated_channel_address).await?;
+        let pending_txn = channel
+            .get_pending_txn()
+            .await?
+            .ok_or(format_err!("should have txn to apply"))?;
 
         let (txn_sender, seq_number) = channel
             .channel_ref()
@@ -561,15 +1237,12 @@ impl WalletHandle {
             .await??
             .ok_or(format_err!("already travelling"))?;
 
-        channel
-            .channel_ref()
-            .send(WatchAndApplyTravelTxn {
-                sender: txn_sender,
-                seq_number,
-            })
-            .await
-            .map_err(|_| format_err!("channel actor gone"))
-            .and_then(|r| r)
+        let gas_used = self
+            .watch_and_apply_travel_txn(&channel, txn_sender, seq_number)
+            .await?;
+        self.on_txn_applied(participant, &pending_txn.proposal().channel_txn)
+            .await;
+        Ok(gas_used)
     }
 
     pub async fn apply_txn(
@@ -577,30 +1250,106 @@ impl WalletHandle {
         participant: AccountAddress,
         txn_response: &ChannelTransactionResponse,
     ) -> Result<u64> {
-        let (generated_channel_address, _participants) =
-            generate_channel_address(participant, self.shared.account);
-
-        let channel = self.get_channel(generated_channel_address).await?;
+        let channel = self
+            .get_channel(txn_response.channel_txn().channel_address())
+            .await?;
+        let pending_txn = channel
+            .get_pending_txn()
+            .await?
+            .ok_or(format_err!("should have txn to apply"))?;
+        ensure!(
+            pending_txn.consensus_reached(),
+            "txn is not signed by all participants yet"
+        );
 
-        let (_proposal, _) = txn_response.clone().into();
+        let gas_used = self.apply_agreed_txn(&channel).await?;
+        self.on_txn_applied(participant, txn_response.channel_txn())
+            .await;
+        Ok(gas_used)
+    }
 
+    /// Apply the pending txn signed by all participants, offchain or onchain.
+    async fn apply_agreed_txn(&self, channel: &ChannelHandle) -> Result<u64> {
         let option_watch = channel.channel_ref().send(ApplyPendingTxn).await??;
-        if option_watch.is_none() {
-            return Ok(0);
-        }
-        let (txn_sender, seq_number) = option_watch.unwrap();
+        let gas_used = match option_watch {
+            None => 0,
+            Some((txn_sender, seq_number)) => {
+                self.watch_and_apply_travel_txn(channel, txn_sender, seq_number)
+                    .await?
+            }
+        };
+        Ok(gas_used)
+    }
 
+    /// Wait the travel txn to be committed onchain, and apply it into the channel.
+    /// It's watched out of the channel actor, which goes on with offchain txns meanwhile.
+    async fn watch_and_apply_travel_txn(
+        &self,
+        channel: &ChannelHandle,
+        txn_sender: AccountAddress,
+        seq_number: u64,
+    ) -> Result<u64> {
+        let txn_with_proof =
+            watch_transaction(self.get_chain_client(), txn_sender, seq_number).await?;
         channel
             .channel_ref()
-            .send(WatchAndApplyTravelTxn {
-                sender: txn_sender,
-                seq_number,
-            })
+            .send(ApplyTravelTxn { txn_with_proof })
             .await
             .map_err(|_| format_err!("channel actor gone"))
             .and_then(|r| r)
     }
 
+    /// State of the channel to report to its participants when they reconnect.
+    pub async fn channel_reestablish(
+        &self,
+        channel_address: AccountAddress,
+    ) -> Result<ChannelReestablish> {
+        let channel = self.get_channel(channel_address).await?;
+        channel.channel_ref().send(BuildReestablish).await?
+    }
+
+    /// Catch up with channel state `remote` reported by `participant`.
+    /// Signatures it has of the pending txn are collected, and the txn is applied once agreed,
+    /// or the pending txn is cancelled if `participant` never saw it. Return the action taken.
+    pub async fn reestablish_channel(
+        &self,
+        participant: AccountAddress,
+        remote: ChannelReestablish,
+    ) -> Result<ReestablishAction> {
+        let channel = self.get_channel(remote.channel_address).await?;
+        ensure!(
+            channel.participant_addresses().contains(&participant),
+            "{} is not a participant of channel {}",
+            participant,
+            remote.channel_address
+        );
+        let pending_txn = channel.get_pending_txn().await?;
+        let (action, agreed) = channel.channel_ref().send(Reestablish { remote }).await??;
+        if agreed {
+            let pending_txn =
+                pending_txn.ok_or_else(|| format_err!("agreed txn should be pending"))?;
+            self.apply_agreed_txn(&channel).await?;
+            self.on_txn_applied(participant, &pending_txn.proposal().channel_txn)
+                .await;
+        }
+        Ok(action)
+    }
+
+    /// Keep track of htlc payments, failure here should not fail the applied txn.
+    async fn on_txn_applied(&self, participant: AccountAddress, channel_txn: &ChannelTransaction) {
+        if let Err(e) = self
+            .htlc_book
+            .on_txn_applied(participant, channel_txn)
+            .await
+        {
+            error!(
+                "fail to track htlc of channel txn {}, {}",
+                channel_txn.channel_sequence_number(),
+                e
+            );
+        }
+    }
+
     /// Called by receiver to get proposal waiting user approval.
     pub async fn get_waiting_proposal(
         &self,
@@ -624,6 +1373,41 @@ impl WalletHandle {
         Ok(proposal)
     }
 
+    /// Amount of every registered asset the pending txn of the channel takes from our channel
+    /// balance, assets it doesn't take are left out.
+    pub async fn pending_txn_outflows(
+        &self,
+        channel_address: AccountAddress,
+    ) -> Result<Vec<(ChannelAsset, u64)>> {
+        let channel = self.get_channel(channel_address).await?;
+        let pending_txn = channel.get_pending_txn().await?.ok_or(format_err!(
+            "channel {} has no pending txn",
+            channel_address
+        ))?;
+        let mut outflows = vec![];
+        for asset in self.assets() {
+            let data_path =
+                DataPath::channel_resource_path(self.shared.account, asset.balance_tag.clone());
+            let access_path = AccessPath::new_for_data_path(channel_address, data_path.clone());
+            let pending = match pending_txn.output().write_set().get(&access_path) {
+                Some(WriteOp::Value(value)) => {
+                    make_resource::<ChannelParticipantAccountResource>(value)?.balance()
+                }
+                Some(WriteOp::Deletion) => 0,
+                None => continue,
+            };
+            let current = channel
+                .get_channel_resource::<ChannelParticipantAccountResource>(data_path)
+                .await?
+                .map(|r| r.balance())
+                .unwrap_or(0);
+            if current > pending {
+                outflows.push((asset, current - pending));
+            }
+        }
+        Ok(outflows)
+    }
+
     /// Get pending txn request.
     pub async fn get_pending_txn_request(
         &self,
@@ -661,9 +1445,12 @@ impl WalletHandle {
         channel.get_pending_txn().await
     }
 
+    /// Install `package`, it's kept in the wallet store, and installed again when the wallet restarts.
     pub async fn install_package(&self, package: ChannelScriptPackage) -> Result<()> {
-        self.shared.script_registry.install_package(package)?;
-        Ok(())
+        self.shared
+            .script_registry
+            .install_package(package.clone())?;
+        self.script_package_store().save_package(&package)
     }
     pub async fn deploy_module(&self, module_byte_code: Vec<u8>) -> Result<TransactionWithProof> {
         self.actor_ref
@@ -671,6 +1458,11 @@ impl WalletHandle {
             .send(DeployModule { module_byte_code })
             .await?
     }
+
+    /// Run `script` on chain, e.g. to mint an asset before depositing it into channels.
+    pub async fn run_script(&self, script: Script) -> Result<TransactionWithProof> {
+        self.actor_ref.clone().send(RunScript { script }).await?
+    }
     pub async fn get_script(
         &self,
         package_name: String,
@@ -683,10 +1475,54 @@ impl WalletHandle {
         Ok(resp)
     }
 
+    /// Installed script package of `package_name`.
+    pub fn get_package(&self, package_name: &str) -> Option<ChannelScriptPackage> {
+        self.shared.script_registry.get_package(package_name)
+    }
+
+    /// Move all balance but gas of the txn to `receiver` on chain, e.g. the account replacing
+    /// this one after its key is rotated. Channels can't be moved, they should be closed first.
+    /// Return the amount moved.
+    pub async fn sweep(&self, receiver: AccountAddress) -> Result<u64> {
+        let amount = self.sweep_amount().await?;
+        self.run_script(encode_transfer_script(&receiver, amount))
+            .await?;
+        Ok(amount)
+    }
+
+    /// Amount `sweep` would move, fail if the account has channels or can't pay the gas.
+    pub async fn sweep_amount(&self) -> Result<u64> {
+        let channels = self.get_all_channels().await?;
+        let multi_party_channels = self.get_multi_party_channels().await?;
+        ensure!(
+            channels.is_empty() && multi_party_channels.is_empty(),
+            "close channels with {:?} and {:?} first, channels can't be moved",
+            channels,
+            multi_party_channels.keys().collect::<Vec<_>>()
+        );
+        let max_gas = MAX_GAS_AMOUNT_ONCHAIN * GAS_UNIT_PRICE;
+        let balance = self.balance()?;
+        ensure!(
+            balance > max_gas,
+            "balance {} is not enough to pay gas {}",
+            balance,
+            max_gas
+        );
+        Ok(balance - max_gas)
+    }
+
+    /// Participants of all two-party channels.
     pub async fn get_all_channels(&self) -> Result<HashSet<AccountAddress>> {
         self.actor_ref.clone().send(GetAllChannels).await?
     }
 
+    /// Channels with more than two participants, channel address -> participants.
+    pub async fn get_multi_party_channels(
+        &self,
+    ) -> Result<HashMap<AccountAddress, BTreeSet<AccountAddress>>> {
+        self.actor_ref.clone().send(GetMultiPartyChannels).await?
+    }
+
     pub async fn stop_channel(&self, participant: AccountAddress) -> Result<()> {
         self.actor_ref
             .clone()
@@ -701,6 +1537,132 @@ impl WalletHandle {
         Ok(())
     }
 
+    /// Load channels in the static backup at `path`, which are lost from local store.
+    /// They can't go on offchain without their txn history, and should be closed at the backup
+    /// state, by `restore_close_request` if the other participant agrees it,
+    /// or by `force_close_restored_channel` if it's unreachable or refuses.
+    /// Return the restored channels which are not closed yet.
+    pub async fn restore_channels<P: AsRef<Path>>(&self, path: P) -> Result<Vec<AccountAddress>> {
+        let bytes = std::fs::read(path)?;
+        let backup = StaticChannelBackup::decrypt(&bytes, &self.shared.signer.backup_key()?)?;
+        ensure!(
+            backup.account == self.shared.account,
+            "channel backup is for account {}",
+            backup.account
+        );
+        let mut restored = vec![];
+        for channel in backup.channels.into_iter() {
+            let channel_address = channel.channel_address;
+            if self.get_channel(channel_address).await.is_ok() {
+                debug!("channel {} exists, no need to restore", channel_address);
+                continue;
+            }
+            let channel = RestoredChannel::new(
+                self.shared.account,
+                self.shared.signer.clone(),
+                self.shared.script_registry.clone(),
+                self.shared.client.clone(),
+                channel,
+            )?;
+            if channel.is_closed()? {
+                info!("channel {} is already closed", channel_address);
+                continue;
+            }
+            self.restoring
+                .lock()
+                .unwrap()
+                .insert(channel_address, channel);
+            restored.push(channel_address);
+        }
+        Ok(restored)
+    }
+
+    pub fn is_restoring_channel(&self, channel_address: AccountAddress) -> bool {
+        self.restoring
+            .lock()
+            .unwrap()
+            .contains_key(&channel_address)
+    }
+
+    /// Propose to close the restored channel at the backup state,
+    /// return the other participant and the request to send to it.
+    pub fn restore_close_request(
+        &self,
+        channel_address: AccountAddress,
+    ) -> Result<(AccountAddress, ChannelTransactionRequest)> {
+        let mut restoring = self.restoring.lock().unwrap();
+        let channel = restoring.get_mut(&channel_address).ok_or(format_err!(
+            "channel {} is not restored from backup",
+            channel_address
+        ))?;
+        let request = channel.propose_close()?;
+        Ok((channel.participant()?, request))
+    }
+
+    /// Submit the close of restored channel once `participant` signed it, return gas used.
+    pub async fn apply_restore_close(
+        &self,
+        participant: AccountAddress,
+        txn_response: &ChannelTransactionResponse,
+    ) -> Result<u64> {
+        ensure!(
+            participant == txn_response.channel_txn_sigs().address,
+            "peer id and signer mismatch"
+        );
+        let channel_address = txn_response.channel_txn().channel_address();
+        let txn = {
+            let mut restoring = self.restoring.lock().unwrap();
+            let channel = restoring.get_mut(&channel_address).ok_or(format_err!(
+                "channel {} is not restored from backup",
+                channel_address
+            ))?;
+            channel.agreed_close_txn(txn_response)?.ok_or(format_err!(
+                "close of channel {} is not signed by all participants yet",
+                channel_address
+            ))?
+        };
+        let (sender, seq_number) = (txn.sender(), txn.sequence_number());
+        submit_transaction(self.shared.client.as_ref(), txn).await?;
+        let txn_with_proof =
+            watch_transaction(self.shared.client.clone(), sender, seq_number).await?;
+        {
+            self.restoring.lock().unwrap().remove(&channel_address);
+        }
+        info!(
+            "channel {} restored from backup is closed with {}",
+            channel_address, participant
+        );
+        Ok(txn_with_proof.proof.transaction_info().gas_used())
+    }
+
+    /// Force close the restored channel when its participant is unreachable or refuses the close.
+    /// Return true if the close txn is submitted. Participants resolve the close by their
+    /// running channels, or challenge it if the backup is stale. Restore it again after the lock
+    /// timeout to close the channel whose participants are offline.
+    pub async fn force_close_restored_channel(
+        &self,
+        channel_address: AccountAddress,
+    ) -> Result<bool> {
+        let channel = {
+            self.restoring
+                .lock()
+                .unwrap()
+                .remove(&channel_address)
+                .ok_or(format_err!(
+                    "channel {} is not restored from backup",
+                    channel_address
+                ))?
+        };
+        match channel.force_close_txn().await? {
+            Some(txn) => {
+                submit_transaction(self.shared.client.as_ref(), txn).await?;
+                info!("force close channel {} from backup", channel_address);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     pub async fn stop(&self) -> Result<()> {
         if let Err(_) = self.actor_ref.clone().stop().await {
             warn!("actor {} already stopped", &self.actor_ref);
@@ -722,19 +1684,18 @@ impl WalletHandle {
             })
             .await?
     }
+    /// Balance resource of `address` in the channel with `participant`, `balance_tag` decides the asset.
     async fn channel_participant_account_resource(
         &self,
         participant: AccountAddress,
         address: AccountAddress,
+        balance_tag: StructTag,
     ) -> Result<Option<ChannelParticipantAccountResource>> {
         let (generated_channel_address, _participants) =
             generate_channel_address(participant, self.shared.account);
         let channel = self.get_channel(generated_channel_address).await?;
 
-        let data_path = DataPath::channel_resource_path(
-            address,
-            ChannelParticipantAccountResource::struct_tag(),
-        );
+        let data_path = DataPath::channel_resource_path(address, balance_tag);
         let resp = channel
             .get_channel_resource::<ChannelParticipantAccountResource>(data_path)
             .await?;
@@ -757,6 +1718,9 @@ pub struct Wallet {
     sgdb: Arc<SgStorage>,
     chain_txn_handle: Option<ChainWatcherHandle>,
     actor_context: Option<ActorContext>,
+    justice_book: Arc<JusticeBook>,
+    expired_proposal_subscribers: ExpiredProposalSubscribers,
+    channel_backup_path: Option<PathBuf>,
 }
 
 impl Wallet {
@@ -777,14 +1741,45 @@ impl Wallet {
         client: Arc<dyn ChainClient>,
         store_dir: P,
     ) -> Result<Self> {
+        Self::new_with_signer(
+            account,
+            Arc::new(LocalSigner::new(keypair)),
+            client,
+            store_dir,
+        )
+    }
+
+    /// Wallet whose account key is used only through `signer`,
+    /// e.g. a `RemoteSigner` whose key lives in another process.
+    pub fn new_with_signer<P: AsRef<Path>>(
+        account: AccountAddress,
+        signer: Arc<dyn Signer>,
+        client: Arc<dyn ChainClient>,
+        store_dir: P,
+    ) -> Result<Self> {
+        ensure!(
+            AccountAddress::from_public_key(&signer.public_key()) == account,
+            "public key of signer mismatch with account {}",
+            account
+        );
         let sgdb = Arc::new(SgStorage::new(account, store_dir));
         let script_registry = Arc::new(PackageRegistry::build()?);
+        // packages and assets installed by user before.
+        let script_package_store = ScriptPackageStore::new(ChannelDB::new(account, sgdb.clone()));
+        for package in script_package_store.list_packages()? {
+            script_registry.install_package(package)?;
+        }
+        for asset in script_package_store.list_assets()? {
+            script_registry.register_asset(asset)?;
+        }
 
         let shared = Shared {
             account,
-            keypair,
+            signer,
             client,
             script_registry,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            proposal_expiration: TXN_EXPIRATION,
         };
         let wallet = Wallet {
             inner: shared.clone(),
@@ -793,23 +1788,59 @@ impl Wallet {
             sgdb: sgdb.clone(),
             chain_txn_handle: None,
             actor_context: None,
+            justice_book: Arc::new(JusticeBook::new()),
+            expired_proposal_subscribers: Arc::new(Mutex::new(vec![])),
+            channel_backup_path: None,
         };
         Ok(wallet)
     }
 
+    /// Keep an encrypted static backup of all channels at `path`, updated on every applied txn.
+    /// It should be kept out of the wallet store dir.
+    pub fn with_channel_backup<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.channel_backup_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Checkpoint channels every `interval` txns, 0 to disable checkpoints.
+    pub fn with_checkpoint_interval(mut self, interval: u64) -> Self {
+        self.inner.checkpoint_interval = interval;
+        self
+    }
+
+    /// Expire channel txns proposed by the wallet `expiration` after they are proposed.
+    pub fn with_proposal_expiration(mut self, expiration: Duration) -> Self {
+        self.inner.proposal_expiration = expiration;
+        self
+    }
+
     pub async fn start(mut self) -> Result<WalletHandle> {
         // TODO: should keep actor context
         let mut actor_context = ActorContext::new();
         let shared = self.inner.clone();
         let sgdb = self.sgdb.clone();
+        let justice_book = self.justice_book.clone();
+        let expired_proposal_subscribers = self.expired_proposal_subscribers.clone();
+        let htlc_book = Arc::new(HtlcBook::new(
+            shared.account,
+            HtlcStore::new(ChannelDB::new(shared.account, sgdb.clone())),
+        ));
         self.actor_context = Some(actor_context.clone());
         let actor_ref = actor_context.new_tracked_actor(self).await?;
-        Ok(WalletHandle {
+        let handle = WalletHandle {
             actor_ref,
             shared,
             sgdb,
-            actor_context,
-        })
+            htlc_book: htlc_book.clone(),
+            justice_book,
+            expired_proposal_subscribers,
+            restoring: Arc::new(Mutex::new(HashMap::new())),
+            actor_context: actor_context.clone(),
+        };
+        HtlcWatcher::new(handle.clone(), htlc_book)
+            .start(actor_context)
+            .await?;
+        Ok(handle)
     }
 }
 
@@ -844,6 +1875,13 @@ impl Actor for Wallet {
             let user_channels: UserChannelsResource =
                 make_resource(blob.as_slice()).expect("parse user channels should work");
             for channel_address in user_channels.channels() {
+                if self.is_channel_lost(channel_address) {
+                    warn!(
+                        "channel {} is lost from local store, restore it from backup",
+                        channel_address
+                    );
+                    continue;
+                }
                 if let Err(e) = self.spawn_channel(channel_address.clone(), None, ctx).await {
                     error!("fail to start channel {}, {}", channel_address, e);
                     ctx.set_status(ActorStatus::Stopping);
@@ -959,6 +1997,7 @@ impl Handler<GetAllChannels> for Wallet {
         let all_channels = self
             .channels
             .values()
+            .filter(|c| c.participant_addresses().len() == 2)
             .map(|c| {
                 let mut participants = c
                     .participant_addresses()
@@ -974,6 +2013,27 @@ impl Handler<GetAllChannels> for Wallet {
     }
 }
 
+struct GetMultiPartyChannels;
+impl Message for GetMultiPartyChannels {
+    type Result = Result<HashMap<AccountAddress, BTreeSet<AccountAddress>>>;
+}
+#[async_trait]
+impl Handler<GetMultiPartyChannels> for Wallet {
+    async fn handle(
+        &mut self,
+        _message: GetMultiPartyChannels,
+        _ctx: &mut ActorHandlerContext,
+    ) -> <GetMultiPartyChannels as Message>::Result {
+        let channels = self
+            .channels
+            .iter()
+            .filter(|(_, c)| c.participant_addresses().len() > 2)
+            .map(|(addr, c)| (*addr, c.participant_addresses().clone()))
+            .collect::<HashMap<_, _>>();
+        Ok(channels)
+    }
+}
+
 struct DeployModule {
     pub module_byte_code: Vec<u8>,
 }
@@ -992,6 +2052,25 @@ impl Handler<DeployModule> for Wallet {
     }
 }
 
+struct RunScript {
+    pub script: Script,
+}
+impl Message for RunScript {
+    type Result = Result<TransactionWithProof>;
+}
+#[async_trait]
+impl Handler<RunScript> for Wallet {
+    async fn handle(
+        &mut self,
+        message: RunScript,
+        _ctx: &mut ActorHandlerContext,
+    ) -> <RunScript as Message>::Result {
+        let RunScript { script } = message;
+        self.submit_payload(TransactionPayload::Script(script))
+            .await
+    }
+}
+
 struct StopChannel {
     pub participant: AccountAddress,
 }
@@ -1037,11 +2116,85 @@ impl Handler<ChannelNotifyEvent> for Wallet {
             ChannelEvent::Stopped { channel_address } => {
                 self.channels.remove(&channel_address);
             }
+            ChannelEvent::StateApplied { channel_address } => {
+                if let Err(e) = self.save_channel_backup() {
+                    error!(
+                        "fail to save channel backup after {} applied txn, {}",
+                        channel_address, e
+                    );
+                }
+            }
+            ChannelEvent::StateRevoked {
+                channel_address,
+                revoked_witness,
+            } => {
+                if !self.justice_book.has_subscribers() {
+                    return;
+                }
+                if let Some(channel) = self.channels.get(&channel_address) {
+                    let channel = channel.clone();
+                    let justice_book = self.justice_book.clone();
+                    // the channel may be busy, don't wait it.
+                    tokio::task::spawn(async move {
+                        justice_book.revoke(channel, revoked_witness).await
+                    });
+                }
+            }
+            ChannelEvent::ProposalExpired {
+                channel_address,
+                channel_txn_id,
+                participants,
+            } => {
+                let expired = ExpiredProposal {
+                    channel_address,
+                    channel_txn_id,
+                    participants,
+                };
+                self.expired_proposal_subscribers
+                    .lock()
+                    .unwrap()
+                    .retain(|subscriber| subscriber.unbounded_send(expired.clone()).is_ok());
+            }
         }
     }
 }
 
 impl Wallet {
+    fn save_channel_backup(&self) -> Result<()> {
+        let path = match &self.channel_backup_path {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        let key = self.inner.signer.backup_key()?;
+        // keep channels which are not running now, they may be stopped by errors.
+        let mut channels = match std::fs::read(path) {
+            Ok(bytes) => StaticChannelBackup::decrypt(&bytes, &key)?
+                .channels
+                .into_iter()
+                .filter(|c| !self.channels.contains_key(&c.channel_address))
+                .collect(),
+            Err(_) => vec![],
+        };
+        for (channel_address, channel) in self.channels.iter() {
+            let store = ChannelStore::new(
+                channel.participant_addresses().clone(),
+                ChannelDB::new(channel_address.clone(), self.sgdb.clone()),
+            )?;
+            channels.push(ChannelBackup::new(
+                channel_address.clone(),
+                store.participant_addresses(),
+                store.get_participant_keys(),
+                store.get_latest_witness().unwrap_or_default(),
+            ));
+        }
+        let bytes = StaticChannelBackup::new(self.inner.account, channels).encrypt(&key)?;
+        // write to a temp file first, so the old backup is kept if it fails.
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, bytes)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
     fn get_channel(&self, participant: &AccountAddress) -> Result<Arc<ChannelHandle>> {
         let channel = match self.channels.get(&participant) {
             Some(channel) => Ok(channel.clone()),
@@ -1064,12 +2217,7 @@ impl Wallet {
             1,
             Duration::from_secs(u64::max_value()),
         );
-        let signed_txn = raw_txn
-            .sign(
-                &self.inner.keypair.private_key,
-                self.inner.keypair.public_key.clone(),
-            )?
-            .into_inner();
+        let signed_txn = self.inner.signer.sign_txn(raw_txn)?;
         let _ = submit_transaction(self.inner.client.as_ref(), signed_txn).await?;
         let proof =
             watch_transaction(self.inner.client.clone(), self.inner.account, seq_number).await?;
@@ -1079,17 +2227,21 @@ impl Wallet {
 
     /// Deploy a module to Chain
     async fn deploy_module(&self, module_byte_code: Vec<u8>) -> Result<TransactionWithProof> {
-        let payload = TransactionPayload::Module(Module::new(module_byte_code));
+        self.submit_payload(TransactionPayload::Module(Module::new(module_byte_code)))
+            .await
+    }
+
+    async fn submit_payload(&self, payload: TransactionPayload) -> Result<TransactionWithProof> {
         //TODO pre execute deploy module txn on local , and get real gas used to set max_gas_amount.
-        let txn = create_signed_payload_txn(
-            self.inner.keypair.as_ref(),
-            payload,
+        let raw_txn = RawTransaction::new(
             self.inner.account,
             self.sequence_number()?,
+            payload,
             MAX_GAS_AMOUNT_ONCHAIN,
             GAS_UNIT_PRICE,
-            TXN_EXPIRATION.as_secs() as i64,
-        )?;
+            txn_expiration(),
+        );
+        let txn = self.inner.signer.sign_txn(raw_txn)?;
         //TODO need execute at local vm for check?
         let address = txn.sender();
         let seq_number = txn.sequence_number();
@@ -1135,9 +2287,10 @@ impl Wallet {
             self.get_channel_db(channel_address),
             self.chain_txn_handle.as_ref().unwrap().clone(),
             my_actor_ref,
-            self.inner.keypair.clone(),
+            self.inner.signer.clone(),
             self.inner.script_registry.clone(),
             self.inner.client.clone(),
+            self.inner.proposal_expiration,
         );
 
         let channel_handle = channel
@@ -1151,6 +2304,14 @@ impl Wallet {
         Ok(())
     }
 
+    /// channel is onchain, but nothing of it is in local store.
+    fn is_channel_lost(&self, channel_address: &AccountAddress) -> bool {
+        match ChannelStore::new(BTreeSet::new(), self.get_channel_db(*channel_address)) {
+            Ok(store) => store.participant_addresses().is_empty(),
+            Err(_) => true,
+        }
+    }
+
     #[inline]
     fn get_channel_db(&self, participant_address: AccountAddress) -> ChannelDB {
         ChannelDB::new(participant_address, self.sgdb.clone())
@@ -1159,15 +2320,19 @@ impl Wallet {
 
 impl TransactionSigner for WalletHandle {
     fn sign_txn(&self, raw_txn: RawTransaction) -> Result<SignedTransaction> {
-        self.shared.keypair.sign_txn(raw_txn)
+        self.shared.signer.sign_txn(raw_txn)
     }
 }
 
 pub struct Shared {
     account: AccountAddress,
-    keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+    signer: Arc<dyn Signer>,
     client: Arc<dyn ChainClient>,
     script_registry: Arc<PackageRegistry>,
+    /// number of channel txns between two checkpoints, 0 to disable checkpoints.
+    checkpoint_interval: u64,
+    /// how long a proposal waits for signatures of participants.
+    proposal_expiration: Duration,
 }
 
 // NOTICE: need to manually implement clone, due to https://github.com/rust-lang/rust/issues/26925
@@ -1175,20 +2340,32 @@ impl Clone for Shared {
     fn clone(&self) -> Self {
         Self {
             account: self.account.clone(),
-            keypair: Arc::clone(&self.keypair),
+            signer: Arc::clone(&self.signer),
             client: Arc::clone(&self.client),
             script_registry: Arc::clone(&self.script_registry),
+            checkpoint_interval: self.checkpoint_interval,
+            proposal_expiration: self.proposal_expiration,
         }
     }
 }
 
 pub(crate) fn txn_expiration() -> Duration {
+    txn_expiration_after(TXN_EXPIRATION)
+}
+
+/// Deadline of a txn which should be done in `duration` from now.
+pub(crate) fn txn_expiration_after(duration: Duration) -> Duration {
     std::time::Duration::new(
-        (Utc::now().timestamp() + TXN_EXPIRATION.as_secs() as i64) as u64,
+        (Utc::now().timestamp() + duration.as_secs() as i64) as u64,
         0,
     )
 }
 
+/// Whether `channel_txn` is past its deadline, it can't be signed any more.
+pub(crate) fn is_txn_expired(channel_txn: &ChannelTransaction) -> bool {
+    channel_txn.expiration_time().as_secs() <= Utc::now().timestamp() as u64
+}
+
 pub(crate) fn execute_transaction(
     state_view: &dyn StateView,
     transaction: SignedTransaction,
@@ -1252,3 +2429,25 @@ fn generate_channel_address(
     addresses.insert(p2);
     (AccountAddress::from(&addresses), addresses)
 }
+
+/// channel address of `participants`, which can be more than two.
+pub fn generate_multi_party_channel_address(
+    participants: &BTreeSet<AccountAddress>,
+) -> AccountAddress {
+    AccountAddress::from(participants)
+}
+
+/// participants of the channel to open by `channel_txn`,
+/// the open args start with addresses of all participants other than proposer.
+fn open_participants(channel_txn: &ChannelTransaction) -> BTreeSet<AccountAddress> {
+    let mut participants = channel_txn
+        .args()
+        .iter()
+        .filter_map(|arg| match arg {
+            TransactionArgument::Address(addr) => Some(*addr),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    participants.insert(channel_txn.proposer());
+    participants
+}
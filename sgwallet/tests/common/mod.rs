@@ -13,21 +13,40 @@ use libra_tools::tempdir::TempPath;
 use libra_types::{account_address::AccountAddress, transaction::TransactionArgument};
 use rand::prelude::*;
 use sgchain::star_chain_client::ChainClient;
+use sgtypes::channel_transaction::{
+    BatchedChannelOp, ChannelTransactionRequest, ChannelTransactionResponse,
+};
 use sgwallet::wallet::{Wallet, WalletHandle};
-use std::{future::Future, sync::Arc};
+use std::{future::Future, path::Path, sync::Arc};
 use tokio::runtime::Runtime;
 
-pub async fn setup_wallet(client: Arc<dyn ChainClient>, init_balance: u64) -> Result<WalletHandle> {
+pub fn gen_keypair() -> Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> {
     let mut seed_rng = rand::rngs::OsRng::new().expect("can't access OsRng");
     let seed_buf: [u8; 32] = seed_rng.gen();
     let mut rng0: StdRng = SeedableRng::from_seed(seed_buf);
-    let account_keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> =
-        Arc::new(KeyPair::generate_for_testing(&mut rng0));
+    Arc::new(KeyPair::generate_for_testing(&mut rng0))
+}
+
+pub async fn setup_wallet(client: Arc<dyn ChainClient>, init_balance: u64) -> Result<WalletHandle> {
+    setup_wallet_with(client, init_balance, |wallet| wallet).await
+}
+
+/// Setup a wallet configured by `configure` before it's started.
+pub async fn setup_wallet_with(
+    client: Arc<dyn ChainClient>,
+    init_balance: u64,
+    configure: impl FnOnce(Wallet) -> Wallet,
+) -> Result<WalletHandle> {
+    let account_keypair = gen_keypair();
     let account = AccountAddress::from_public_key(&account_keypair.public_key);
     client.faucet(account, init_balance).await?;
     // enable channel for wallet
-    let wallet =
-        Wallet::new_with_client(account, account_keypair, client.clone(), TempPath::new())?;
+    let wallet = configure(Wallet::new_with_client(
+        account,
+        account_keypair,
+        client.clone(),
+        TempPath::new(),
+    )?);
     let handle = wallet.start().await?;
     let gas_used = handle.enable_channel().await?;
     handle.get_chain_client().faucet(account, gas_used).await?;
@@ -41,6 +60,19 @@ pub async fn setup_wallet(client: Arc<dyn ChainClient>, init_balance: u64) -> Re
     Ok(handle)
 }
 
+/// Start wallet of an existing account in a new store dir, keeping channel backup at `backup`.
+pub async fn start_wallet_with_backup(
+    client: Arc<dyn ChainClient>,
+    keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>>,
+    backup: &Path,
+) -> Result<WalletHandle> {
+    let account = AccountAddress::from_public_key(&keypair.public_key);
+    Wallet::new_with_client(account, keypair, client, TempPath::new())?
+        .with_channel_backup(backup)
+        .start()
+        .await
+}
+
 pub async fn with_init_wallet_async<T, F>(
     chain_client: Arc<dyn ChainClient>,
     f: impl Fn(Arc<WalletHandle>, Arc<WalletHandle>) -> F,
@@ -284,6 +316,99 @@ pub async fn withdraw(
     Ok(sender_gas_used)
 }
 
+pub async fn splice(
+    sender_wallet: Arc<WalletHandle>,
+    receiver_wallet: Arc<WalletHandle>,
+    amounts: SpliceAmounts,
+) -> Result<u64> {
+    let sender = sender_wallet.account();
+    let receiver = receiver_wallet.account();
+    let splice_txn = agree_splice(sender_wallet.clone(), receiver_wallet.clone(), amounts).await?;
+    let sender_gas_used = sender_wallet.apply_txn(receiver, &splice_txn).await?;
+    receiver_wallet.apply_txn(sender, &splice_txn).await?;
+    Ok(sender_gas_used)
+}
+
+/// Amounts moved by a splice proposed by sender.
+#[derive(Clone, Copy, Default)]
+pub struct SpliceAmounts {
+    pub deposit: u64,
+    pub withdraw: u64,
+    pub receiver_withdraw: u64,
+    pub transfer: u64,
+    pub receive: u64,
+}
+
+/// Get the splice signed by both participants, without applying it.
+pub async fn agree_splice(
+    sender_wallet: Arc<WalletHandle>,
+    receiver_wallet: Arc<WalletHandle>,
+    amounts: SpliceAmounts,
+) -> Result<ChannelTransactionResponse> {
+    let sender = sender_wallet.account();
+    let receiver = receiver_wallet.account();
+
+    let splice_txn = sender_wallet
+        .splice(
+            receiver,
+            amounts.deposit,
+            amounts.withdraw,
+            amounts.receiver_withdraw,
+            amounts.transfer,
+            amounts.receive,
+        )
+        .await?;
+
+    debug_assert!(splice_txn.is_travel_txn(), "splice_txn must travel txn");
+
+    let receiver_splice_txn = match receiver_wallet.verify_txn(sender, &splice_txn).await? {
+        Some(t) => t,
+        None => {
+            receiver_wallet
+                .approve_txn(sender, splice_txn.request_id())
+                .await?
+        }
+    };
+
+    sender_wallet
+        .verify_txn_response(receiver, &receiver_splice_txn)
+        .await?;
+    Ok(receiver_splice_txn)
+}
+
+pub async fn execute_batch(
+    sender_wallet: Arc<WalletHandle>,
+    receiver_wallet: Arc<WalletHandle>,
+    ops: Vec<BatchedChannelOp>,
+) -> Result<u64> {
+    let sender = sender_wallet.account();
+    let receiver = receiver_wallet.account();
+
+    let batch_txn = sender_wallet.execute_batch(receiver, ops).await?;
+
+    debug_assert!(!batch_txn.is_travel_txn(), "batch_txn must not travel txn");
+
+    let receiver_batch_txn = match receiver_wallet.verify_txn(sender, &batch_txn).await? {
+        Some(t) => t,
+        None => {
+            receiver_wallet
+                .approve_txn(sender, batch_txn.request_id())
+                .await?
+        }
+    };
+
+    receiver_wallet
+        .apply_txn(sender, &receiver_batch_txn)
+        .await?;
+    sender_wallet
+        .verify_txn_response(receiver, &receiver_batch_txn)
+        .await?;
+    let sender_gas_used = sender_wallet
+        .apply_txn(receiver, &receiver_batch_txn)
+        .await?;
+    Ok(sender_gas_used)
+}
+
 pub async fn execute_script(
     sender_wallet: Arc<WalletHandle>,
     receiver_wallet: Arc<WalletHandle>,
@@ -317,3 +442,40 @@ pub async fn execute_script(
     receiver_future.await?;
     Ok(gas_used)
 }
+
+/// Let `others` sign `request` proposed by `proposer`, pass signatures around,
+/// then apply it by all participants of the channel.
+pub async fn sign_and_apply_by_all(
+    proposer: Arc<WalletHandle>,
+    others: Vec<Arc<WalletHandle>>,
+    request: ChannelTransactionRequest,
+) -> Result<u64> {
+    let channel_address = request.channel_txn().channel_address();
+    let mut responses = vec![];
+    for wallet in others.iter() {
+        let response = match wallet.verify_txn(proposer.account(), &request).await? {
+            Some(t) => t,
+            None => {
+                wallet
+                    .approve_channel_txn(channel_address, request.request_id())
+                    .await?
+            }
+        };
+        responses.push((wallet.account(), response));
+    }
+    let mut participants = vec![proposer.clone()];
+    participants.extend(others.iter().cloned());
+    for wallet in participants.iter() {
+        for (signer, response) in responses.iter() {
+            if *signer != wallet.account() {
+                wallet.verify_txn_response(*signer, response).await?;
+            }
+        }
+    }
+    let (first, response) = &responses[0];
+    let gas_used = proposer.apply_txn(*first, response).await?;
+    for (wallet, (_, response)) in others.iter().zip(responses.iter()) {
+        wallet.apply_txn(proposer.account(), response).await?;
+    }
+    Ok(gas_used)
+}
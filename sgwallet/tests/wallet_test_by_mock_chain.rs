@@ -2,12 +2,38 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use anyhow::Result;
+use common::SpliceAmounts;
+use futures::StreamExt;
+use libra_crypto::HashValue;
 use libra_logger::prelude::*;
-use libra_types::transaction::TransactionArgument;
+use libra_tools::tempdir::TempPath;
+use libra_types::{
+    account_address::AccountAddress, identifier::Identifier, language_storage::StructTag,
+    transaction::TransactionArgument,
+};
 use mock_chain_test_helper::run_with_mock_client;
-use sgtypes::script_package::ChannelScriptPackage;
+use sgtypes::{
+    channel_backup::StaticChannelBackup,
+    channel_reestablish::ReestablishAction,
+    channel_transaction::{BatchedChannelOp, ChannelOp},
+    channel_txn_history::ChannelTxnQuery,
+    htlc::{HtlcAction, HtlcState},
+    open_channel_policy::ChannelTerms,
+    script_package::ChannelScriptPackage,
+    sg_error::{SgError, SgErrorCode},
+};
+use sgwallet::{
+    scripts::{ChannelAsset, DEFAULT_PACKAGE},
+    wallet::{watch_transaction, DEFAULT_ASSET},
+};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use wallet_test_helper::{
-    deploy_custom_module_and_script, test_deploy_custom_module, test_wallet_async,
+    deploy_custom_module_and_script, run_test_case_script, test_deploy_custom_module,
+    test_wallet_async,
 };
 
 mod common;
@@ -50,6 +76,110 @@ fn test_gobang() {
     }
 }
 
+#[test]
+fn test_channel_backup_and_restore() {
+    if let Err(e) = run_test_channel_backup_and_restore() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_channel_restore_by_reestablish() {
+    if let Err(e) = run_test_channel_restore_by_reestablish() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_splice() {
+    if let Err(e) = run_test_splice() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_splice_with_payments() {
+    if let Err(e) = run_test_splice_with_payments() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_list_channel_txns() {
+    if let Err(e) = run_test_list_channel_txns() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_execute_batch() {
+    if let Err(e) = run_test_execute_batch() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_channel_checkpoint() {
+    if let Err(e) = run_test_channel_checkpoint() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_channel_asset() {
+    if let Err(e) = run_test_channel_asset() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_channel_reestablish() {
+    if let Err(e) = run_test_channel_reestablish() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_multi_party_channel() {
+    if let Err(e) = run_test_multi_party_channel() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_proposal_expiration() {
+    if let Err(e) = run_test_proposal_expiration() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_channel_terms() {
+    if let Err(e) = run_test_channel_terms() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
+#[test]
+fn test_htlc_tracking() {
+    if let Err(e) = run_test_htlc_tracking() {
+        println!("err: {:?}", e);
+        assert!(false)
+    }
+}
+
 fn run_test_wallet_install_package() -> Result<()> {
     run_with_mock_client(|chain_client| {
         common::with_wallet(chain_client, |rt, alice, bob| {
@@ -81,6 +211,131 @@ fn run_test_wallet_install_package() -> Result<()> {
     Ok(())
 }
 
+fn run_test_channel_asset() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async move {
+                // point is deployed by alice, each participant keeps its points in channel by
+                // Point.Balance, separated from its libra balance.
+                deploy_custom_module_and_script(alice.clone(), bob.clone(), "test_point_asset")
+                    .await?;
+                let point_tag = |name: &str| -> Result<StructTag> {
+                    Ok(StructTag {
+                        address: alice.account(),
+                        module: Identifier::new("Point")?,
+                        name: Identifier::new(name)?,
+                        type_params: vec![],
+                    })
+                };
+                let point = ChannelAsset::new(
+                    point_tag("T")?,
+                    "scripts".to_string(),
+                    point_tag("Balance")?,
+                );
+                for wallet in vec![alice.clone(), bob.clone()] {
+                    wallet.register_asset(point.clone())?;
+                    assert!(wallet.register_asset(point.clone()).is_err());
+                    run_test_case_script(
+                        alice.clone(),
+                        wallet.clone(),
+                        "test_point_asset",
+                        "mint.mvir",
+                        vec![TransactionArgument::U64(1000)],
+                    )
+                    .await?;
+                }
+                assert_eq!(2, alice.assets().len());
+                // libra package has no open script.
+                assert!(alice
+                    .open_with_asset(bob.account(), &DEFAULT_ASSET, 10000, 10000, 10, 10)
+                    .await
+                    .is_err());
+
+                // open deposits both assets.
+                let request = alice
+                    .open_with_asset(bob.account(), &point.asset, 10000, 10000, 500, 300)
+                    .await?;
+                common::sign_and_apply_by_all(alice.clone(), vec![bob.clone()], request).await?;
+                assert_eq!(10000, alice.channel_balance(bob.account()).await?);
+                assert_eq!(10000, bob.channel_balance(alice.account()).await?);
+                assert_eq!(
+                    500,
+                    alice
+                        .asset_channel_balance(bob.account(), &point.asset)
+                        .await?
+                );
+                assert_eq!(
+                    300,
+                    alice
+                        .participant_asset_channel_balance(bob.account(), &point.asset)
+                        .await?
+                );
+
+                // points move without touching libra balances.
+                let request = alice
+                    .transfer_asset(bob.account(), &point.asset, 100)
+                    .await?;
+                common::sign_and_apply_by_all(alice.clone(), vec![bob.clone()], request).await?;
+                assert_eq!(
+                    400,
+                    alice
+                        .asset_channel_balance(bob.account(), &point.asset)
+                        .await?
+                );
+                assert_eq!(
+                    400,
+                    bob.asset_channel_balance(alice.account(), &point.asset)
+                        .await?
+                );
+                assert_eq!(10000, alice.channel_balance(bob.account()).await?);
+                assert_eq!(10000, bob.channel_balance(alice.account()).await?);
+
+                // and libra moves without touching points.
+                common::transfer(alice.clone(), bob.clone(), 1000).await?;
+                assert_eq!(9000, alice.channel_balance(bob.account()).await?);
+                assert_eq!(11000, bob.channel_balance(alice.account()).await?);
+                let mut balances = bob.channel_balances(alice.account()).await?;
+                balances.sort_by_key(|(_, balance)| *balance);
+                assert_eq!(
+                    vec![(point.asset.clone(), 400), (DEFAULT_ASSET.clone(), 11000)],
+                    balances
+                );
+
+                // points deposited and withdrawn by bob only change his point balance.
+                let request = bob
+                    .deposit_asset(alice.account(), &point.asset, 200)
+                    .await?;
+                common::sign_and_apply_by_all(bob.clone(), vec![alice.clone()], request).await?;
+                let request = bob
+                    .withdraw_asset(alice.account(), &point.asset, 50)
+                    .await?;
+                common::sign_and_apply_by_all(bob.clone(), vec![alice.clone()], request).await?;
+                assert_eq!(
+                    550,
+                    bob.asset_channel_balance(alice.account(), &point.asset)
+                        .await?
+                );
+                assert_eq!(
+                    400,
+                    alice
+                        .asset_channel_balance(bob.account(), &point.asset)
+                        .await?
+                );
+                assert_eq!(11000, bob.channel_balance(alice.account()).await?);
+
+                let unknown_tag = point_tag("Unknown")?;
+                assert!(alice
+                    .asset_channel_balance(bob.account(), &unknown_tag)
+                    .await
+                    .is_err());
+                Ok(())
+            })
+        })
+    })?;
+
+    Ok(())
+}
+
 fn run_test_deploy_custom_module_by_mock_client() -> Result<()> {
     run_with_mock_client(|chain_client| {
         common::with_wallet(chain_client, |rt, alice, bob| {
@@ -227,3 +482,790 @@ fn run_test_gobang() -> Result<()> {
         })
     })
 }
+
+fn run_test_htlc_tracking() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async move {
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+
+                let preimage = HashValue::random().to_vec();
+                let hash_lock = HashValue::from_sha3_256(preimage.as_slice());
+                common::send_payment(alice.clone(), bob.clone(), 500, hash_lock.to_vec(), 10)
+                    .await?;
+                let htlcs = alice.list_htlcs()?;
+                assert_eq!(1, htlcs.len());
+                assert_eq!(&hash_lock, htlcs[0].hash_lock());
+                assert_eq!(bob.account(), htlcs[0].participant());
+                assert_eq!(HtlcState::Pending, htlcs[0].state());
+                // only the payer tracks the htlc.
+                assert!(bob.list_htlcs()?.is_empty());
+
+                common::receive_payment(bob.clone(), alice.clone(), preimage).await?;
+                let htlcs = alice.list_htlcs()?;
+                assert_eq!(HtlcState::Settled, htlcs[0].state());
+
+                let actions = alice
+                    .list_htlc_audits(0, 10)?
+                    .into_iter()
+                    .map(|(_, audit)| audit.action)
+                    .collect::<Vec<_>>();
+                assert_eq!(vec![HtlcAction::Track, HtlcAction::Settle], actions);
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_channel_backup_and_restore() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client.clone(), |rt, _sender, bob| {
+            rt.block_on(async {
+                let backup_path = TempPath::new();
+                backup_path.create_as_file()?;
+                // alice's wallet with channel backup enabled
+                let keypair = common::gen_keypair();
+                let account = AccountAddress::from_public_key(&keypair.public_key);
+                chain_client.faucet(account, 10_000_000).await?;
+                let alice = Arc::new(
+                    common::start_wallet_with_backup(
+                        chain_client.clone(),
+                        keypair.clone(),
+                        backup_path.path(),
+                    )
+                    .await?,
+                );
+                alice.enable_channel().await?;
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+                common::transfer(alice.clone(), bob.clone(), 300).await?;
+                // delay to let wallet handle channel events
+                tokio::time::delay_for(Duration::from_millis(500)).await;
+
+                let bytes = std::fs::read(backup_path.path())?;
+                let backup = StaticChannelBackup::decrypt(&bytes, &alice.signer().backup_key()?)?;
+                assert_eq!(alice.account(), backup.account);
+                assert_eq!(1, backup.channels.len());
+                let channel = &backup.channels[0];
+                assert!(channel.participants.contains(&bob.account()));
+                assert_eq!(2, channel.participant_keys.len());
+                assert_eq!(
+                    alice.channel_sequence_number(bob.account()).await?,
+                    channel.witness.channel_sequence_number()
+                );
+
+                // alice loses her store, and restores channels from the backup.
+                alice.stop().await?;
+                let alice = common::start_wallet_with_backup(
+                    chain_client.clone(),
+                    keypair,
+                    backup_path.path(),
+                )
+                .await?;
+                assert!(alice.get_all_channels().await?.is_empty());
+                assert_eq!(
+                    vec![channel.channel_address],
+                    alice.restore_channels(backup_path.path()).await?
+                );
+                // bob refuses to close it, so alice closes it by force.
+                let (participant, request) =
+                    alice.restore_close_request(channel.channel_address)?;
+                assert_eq!(bob.account(), participant);
+                assert!(bob.verify_txn(alice.account(), &request).await?.is_none());
+                bob.reject_txn(alice.account(), request.request_id())
+                    .await?;
+                let seq_number = alice.sequence_number()?;
+                assert!(
+                    alice
+                        .force_close_restored_channel(channel.channel_address)
+                        .await?
+                );
+                assert!(!alice.is_restoring_channel(channel.channel_address));
+                watch_transaction(chain_client.clone(), alice.account(), seq_number).await?;
+                // the channel is locked or closed now, nothing to do until timeout.
+                if !alice.restore_channels(backup_path.path()).await?.is_empty() {
+                    assert!(alice
+                        .restore_close_request(channel.channel_address)
+                        .is_err());
+                    assert!(
+                        !alice
+                            .force_close_restored_channel(channel.channel_address)
+                            .await?
+                    );
+                }
+                alice.stop().await?;
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_channel_restore_by_reestablish() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client.clone(), |rt, _sender, bob| {
+            rt.block_on(async {
+                let backup_path = TempPath::new();
+                backup_path.create_as_file()?;
+                let keypair = common::gen_keypair();
+                let account = AccountAddress::from_public_key(&keypair.public_key);
+                chain_client.faucet(account, 10_000_000).await?;
+                let alice = Arc::new(
+                    common::start_wallet_with_backup(
+                        chain_client.clone(),
+                        keypair.clone(),
+                        backup_path.path(),
+                    )
+                    .await?,
+                );
+                alice.enable_channel().await?;
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+                common::transfer(alice.clone(), bob.clone(), 300).await?;
+                tokio::time::delay_for(Duration::from_millis(500)).await;
+                let channel_address = alice
+                    .channel_handle(bob.account())
+                    .await?
+                    .channel_address()
+                    .clone();
+
+                // alice loses her store, bob agrees her backup, and they close the channel together.
+                alice.stop().await?;
+                let alice = common::start_wallet_with_backup(
+                    chain_client.clone(),
+                    keypair,
+                    backup_path.path(),
+                )
+                .await?;
+                assert_eq!(
+                    vec![channel_address],
+                    alice.restore_channels(backup_path.path()).await?
+                );
+                let (participant, request) = alice.restore_close_request(channel_address)?;
+                assert_eq!(bob.account(), participant);
+                let response = match bob.verify_txn(alice.account(), &request).await? {
+                    Some(response) => response,
+                    None => {
+                        bob.approve_txn(alice.account(), request.request_id())
+                            .await?
+                    }
+                };
+                let bob_balance = bob.balance()?;
+                let alice_balance = alice.balance()?;
+                let gas_used = alice.apply_restore_close(bob.account(), &response).await?;
+                bob.apply_txn(alice.account(), &response).await?;
+                assert!(!alice.is_restoring_channel(channel_address));
+                assert_eq!(bob_balance + 10300, bob.balance()?);
+                assert_eq!(alice_balance + 9700 - gas_used, alice.balance()?);
+                // no lock is left on the channel, nothing to restore.
+                assert!(alice.restore_channels(backup_path.path()).await?.is_empty());
+                alice.stop().await?;
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_splice() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async {
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+                let seq_number = alice.channel_sequence_number(bob.account()).await?;
+                let balance = alice.balance()?;
+
+                // top up and rebalance part of it to bob in one travel txn.
+                let gas_used = common::splice(
+                    alice.clone(),
+                    bob.clone(),
+                    SpliceAmounts {
+                        deposit: 5000,
+                        transfer: 2000,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                assert_eq!(13000, alice.channel_balance(bob.account()).await?);
+                assert_eq!(12000, bob.channel_balance(alice.account()).await?);
+                assert_eq!(balance - 5000 - gas_used, alice.balance()?);
+                assert_eq!(
+                    seq_number + 1,
+                    alice.channel_sequence_number(bob.account()).await?
+                );
+
+                // payments flow on the resized channel.
+                common::transfer(alice.clone(), bob.clone(), 1000).await?;
+                assert_eq!(12000, alice.channel_balance(bob.account()).await?);
+
+                // rebalance and take the rest out.
+                let balance = alice.balance()?;
+                let gas_used = common::splice(
+                    alice.clone(),
+                    bob.clone(),
+                    SpliceAmounts {
+                        withdraw: 4000,
+                        transfer: 3000,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                assert_eq!(5000, alice.channel_balance(bob.account()).await?);
+                assert_eq!(16000, bob.channel_balance(alice.account()).await?);
+                assert_eq!(balance + 4000 - gas_used, alice.balance()?);
+
+                // bob resizes too: takes part of alice's balance, and withdraws for both.
+                let alice_balance = alice.balance()?;
+                let bob_balance = bob.balance()?;
+                let gas_used = common::splice(
+                    bob.clone(),
+                    alice.clone(),
+                    SpliceAmounts {
+                        withdraw: 6000,
+                        receiver_withdraw: 1000,
+                        receive: 2000,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                assert_eq!(2000, alice.channel_balance(bob.account()).await?);
+                assert_eq!(12000, bob.channel_balance(alice.account()).await?);
+                assert_eq!(alice_balance + 1000, alice.balance()?);
+                assert_eq!(bob_balance + 6000 - gas_used, bob.balance()?);
+
+                assert!(alice.splice(bob.account(), 0, 0, 0, 100, 0).await.is_err());
+                assert!(alice
+                    .splice(bob.account(), 100, 0, 0, 100, 100)
+                    .await
+                    .is_err());
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_splice_with_payments() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async {
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+                let seq_number = alice.channel_sequence_number(bob.account()).await?;
+                let alice_balance = alice.balance()?;
+                let bob_balance = bob.balance()?;
+
+                let splice_txn = common::agree_splice(
+                    bob.clone(),
+                    alice.clone(),
+                    SpliceAmounts {
+                        withdraw: 3000,
+                        receiver_withdraw: 2000,
+                        receive: 1000,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                // both wait the splice to be committed in background.
+                let bob_apply = tokio::task::spawn({
+                    let (bob, alice, splice_txn) = (bob.clone(), alice.clone(), splice_txn.clone());
+                    async move { bob.apply_txn(alice.account(), &splice_txn).await }
+                });
+                let alice_apply = tokio::task::spawn({
+                    let (alice, bob) = (alice.clone(), bob.clone());
+                    async move { alice.apply_txn(bob.account(), &splice_txn).await }
+                });
+                while alice.channel_sequence_number(bob.account()).await? <= seq_number
+                    || bob.channel_sequence_number(alice.account()).await? <= seq_number
+                {
+                    tokio::time::delay_for(Duration::from_millis(10)).await;
+                }
+
+                // mock chain watches txn with a delay, payments are made before it's applied.
+                common::transfer(alice.clone(), bob.clone(), 500).await?;
+                common::transfer(bob.clone(), alice.clone(), 200).await?;
+                assert_eq!(6700, alice.channel_balance(bob.account()).await?);
+                assert_eq!(8300, bob.channel_balance(alice.account()).await?);
+
+                let gas_used = bob_apply.await??;
+                alice_apply.await??;
+                assert_eq!(6700, alice.channel_balance(bob.account()).await?);
+                assert_eq!(8300, bob.channel_balance(alice.account()).await?);
+                assert_eq!(
+                    seq_number + 3,
+                    alice.channel_sequence_number(bob.account()).await?
+                );
+                assert_eq!(
+                    seq_number + 3,
+                    bob.channel_sequence_number(alice.account()).await?
+                );
+                assert_eq!(alice_balance + 2000, alice.balance()?);
+                assert_eq!(bob_balance + 3000 - gas_used, bob.balance()?);
+
+                // the channel goes on after the splice.
+                common::transfer(bob.clone(), alice.clone(), 300).await?;
+                assert_eq!(7000, alice.channel_balance(bob.account()).await?);
+                common::splice(
+                    alice.clone(),
+                    bob.clone(),
+                    SpliceAmounts {
+                        deposit: 1000,
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                assert_eq!(8000, alice.channel_balance(bob.account()).await?);
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_list_channel_txns() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async {
+                let start_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                common::open_channel(alice.clone(), bob.clone(), 10000, 8000).await?;
+                common::transfer(alice.clone(), bob.clone(), 300).await?;
+                common::transfer(alice.clone(), bob.clone(), 200).await?;
+
+                let page = alice
+                    .list_channel_txns(bob.account(), ChannelTxnQuery::new(0, 0))
+                    .await?;
+                assert_eq!(3, page.txns.len());
+                assert_eq!(None, page.next_start);
+                let open = &page.txns[0];
+                assert!(open.travel);
+                assert_eq!(Some(ChannelOp::Open), open.operator);
+                assert!(page.txns.iter().all(|t| t.applied_time >= start_time));
+                assert_eq!(Some(&10000), open.balance_deltas.get(&alice.account()));
+                assert_eq!(Some(&8000), open.balance_deltas.get(&bob.account()));
+                let transfer = &page.txns[1];
+                assert!(!transfer.travel);
+                assert_eq!(Some(&-300), transfer.balance_deltas.get(&alice.account()));
+                assert_eq!(Some(&300), transfer.balance_deltas.get(&bob.account()));
+
+                // paging from the middle keeps the deltas.
+                let page = alice
+                    .list_channel_txns(bob.account(), ChannelTxnQuery::new(1, 1))
+                    .await?;
+                assert_eq!(vec![transfer.clone()], page.txns);
+                assert_eq!(Some(2), page.next_start);
+                let page = alice
+                    .list_channel_txns(bob.account(), ChannelTxnQuery::new(2, 1))
+                    .await?;
+                assert_eq!(
+                    Some(&-200),
+                    page.txns[0].balance_deltas.get(&alice.account())
+                );
+                assert_eq!(None, page.next_start);
+
+                let transfer_op = ChannelOp::Action {
+                    module_address: AccountAddress::default(),
+                    module_name: "ChannelScript".to_string(),
+                    function_name: "transfer".to_string(),
+                };
+                let page = bob
+                    .list_channel_txns(
+                        alice.account(),
+                        ChannelTxnQuery::new(0, 0).operator(transfer_op),
+                    )
+                    .await?;
+                assert_eq!(2, page.txns.len());
+                let page = bob
+                    .list_channel_txns(
+                        alice.account(),
+                        ChannelTxnQuery::new(0, 0).proposer(bob.account()),
+                    )
+                    .await?;
+                assert!(page.txns.is_empty());
+
+                let page = alice
+                    .list_channel_txns(
+                        bob.account(),
+                        ChannelTxnQuery::new(0, 0).time_window(Some(start_time), None),
+                    )
+                    .await?;
+                assert_eq!(3, page.txns.len());
+                let page = alice
+                    .list_channel_txns(
+                        bob.account(),
+                        ChannelTxnQuery::new(0, 0).time_window(None, Some(start_time)),
+                    )
+                    .await?;
+                assert!(page.txns.is_empty());
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_execute_batch() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async {
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+                let seq_number = alice.channel_sequence_number(bob.account()).await?;
+
+                let transfer = |amount: u64| {
+                    BatchedChannelOp::new(
+                        ChannelOp::Action {
+                            module_address: AccountAddress::default(),
+                            module_name: "ChannelScript".to_string(),
+                            function_name: "transfer".to_string(),
+                        },
+                        vec![
+                            TransactionArgument::Address(bob.account()),
+                            TransactionArgument::U64(amount),
+                        ],
+                    )
+                };
+                // the second transfer sees the balance left by the first one.
+                common::execute_batch(
+                    alice.clone(),
+                    bob.clone(),
+                    vec![transfer(300), transfer(200)],
+                )
+                .await?;
+                assert_eq!(9500, alice.channel_balance(bob.account()).await?);
+                assert_eq!(10500, bob.channel_balance(alice.account()).await?);
+                assert_eq!(
+                    seq_number + 1,
+                    alice.channel_sequence_number(bob.account()).await?
+                );
+                assert_eq!(
+                    seq_number + 1,
+                    bob.channel_sequence_number(alice.account()).await?
+                );
+
+                // one failing op aborts the whole batch.
+                assert!(alice
+                    .execute_batch(bob.account(), vec![transfer(300), transfer(100000)])
+                    .await
+                    .is_err());
+                assert!(alice.execute_batch(bob.account(), vec![]).await.is_err());
+                assert!(alice
+                    .execute_batch(
+                        bob.account(),
+                        vec![BatchedChannelOp::new(ChannelOp::Close, vec![])]
+                    )
+                    .await
+                    .is_err());
+                assert_eq!(9500, alice.channel_balance(bob.account()).await?);
+
+                common::transfer(alice.clone(), bob.clone(), 500).await?;
+                assert_eq!(9000, alice.channel_balance(bob.account()).await?);
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_channel_checkpoint() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async {
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+                common::transfer(alice.clone(), bob.clone(), 300).await?;
+                common::transfer(alice.clone(), bob.clone(), 200).await?;
+                let version = alice.channel_sequence_number(bob.account()).await? - 1;
+
+                let checkpoint = alice.sign_checkpoint(bob.account())?;
+                assert_eq!(version, checkpoint.version());
+                // checkpoint signed only by alice can not be saved.
+                assert!(alice
+                    .save_checkpoint(bob.account(), checkpoint.clone())
+                    .is_err());
+                let checkpoint = bob.cosign_checkpoint(alice.account(), checkpoint)?;
+                alice.save_checkpoint(bob.account(), checkpoint.clone())?;
+                assert_eq!(Some(checkpoint), alice.latest_checkpoint(bob.account())?);
+                assert_eq!(
+                    alice.latest_checkpoint(bob.account())?,
+                    bob.latest_checkpoint(alice.account())?
+                );
+
+                // txns before the checkpoint are pruned, channel keeps working.
+                let page = alice
+                    .list_channel_txns(bob.account(), ChannelTxnQuery::new(0, 0))
+                    .await?;
+                assert!(page.txns.is_empty());
+                common::transfer(alice.clone(), bob.clone(), 100).await?;
+                assert_eq!(9400, alice.channel_balance(bob.account()).await?);
+                let page = bob
+                    .list_channel_txns(alice.account(), ChannelTxnQuery::new(0, 0))
+                    .await?;
+                assert_eq!(1, page.txns.len());
+                assert_eq!(version + 1, page.txns[0].channel_sequence_number);
+                assert_eq!(Some(&100), page.txns[0].balance_deltas.get(&bob.account()));
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_channel_reestablish() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async {
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+                let channel_address = alice
+                    .channel_handle(bob.account())
+                    .await?
+                    .channel_address()
+                    .clone();
+
+                // bob applies the transfer, but his response never reaches alice.
+                let request = alice.transfer(bob.account(), 300).await?;
+                let response = match bob.verify_txn(alice.account(), &request).await? {
+                    Some(t) => t,
+                    None => {
+                        bob.approve_txn(alice.account(), request.request_id())
+                            .await?
+                    }
+                };
+                bob.apply_txn(alice.account(), &response).await?;
+                let alice_state = alice.channel_reestablish(channel_address).await?;
+                let bob_state = bob.channel_reestablish(channel_address).await?;
+                assert_eq!(
+                    ReestablishAction::None,
+                    bob.reestablish_channel(alice.account(), alice_state)
+                        .await?
+                );
+                match alice.reestablish_channel(bob.account(), bob_state).await? {
+                    ReestablishAction::CatchUp(_) => {}
+                    action => panic!("expect catch up, got {:?}", action),
+                }
+                assert_eq!(
+                    bob.channel_sequence_number(alice.account()).await?,
+                    alice.channel_sequence_number(bob.account()).await?
+                );
+                assert_eq!(9700, alice.channel_balance(bob.account()).await?);
+
+                // the proposal of alice never reaches bob.
+                let request = alice.transfer(bob.account(), 100).await?;
+                let alice_state = alice.channel_reestablish(channel_address).await?;
+                let bob_state = bob.channel_reestablish(channel_address).await?;
+                assert_eq!(
+                    ReestablishAction::None,
+                    bob.reestablish_channel(alice.account(), alice_state)
+                        .await?
+                );
+                assert_eq!(
+                    ReestablishAction::Rollback(request.request_id()),
+                    alice.reestablish_channel(bob.account(), bob_state).await?
+                );
+                assert!(alice
+                    .get_pending_txn_request(bob.account())
+                    .await?
+                    .is_none());
+                common::transfer(alice.clone(), bob.clone(), 100).await?;
+                assert_eq!(9600, alice.channel_balance(bob.account()).await?);
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_proposal_expiration() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        let mut rt = tokio::runtime::Runtime::new()?;
+        rt.block_on(async {
+            let alice = Arc::new(
+                common::setup_wallet_with(chain_client.clone(), 10_000_000, |wallet| {
+                    wallet.with_proposal_expiration(Duration::from_secs(2))
+                })
+                .await?,
+            );
+            let bob = Arc::new(common::setup_wallet(chain_client.clone(), 10_000_000).await?);
+            common::open_channel(bob.clone(), alice.clone(), 10000, 10000).await?;
+            let channel_address = alice
+                .channel_handle(bob.account())
+                .await?
+                .channel_address()
+                .clone();
+            let mut expired_proposals = alice.subscribe_expired_proposals();
+
+            // bob never approves the transfer, so it's expired by alice.
+            let request = alice.transfer(bob.account(), 100).await?;
+            assert!(bob.verify_txn(alice.account(), &request).await?.is_none());
+            let expired = tokio::time::timeout(Duration::from_secs(10), expired_proposals.next())
+                .await?
+                .expect("should receive expired proposal");
+            assert_eq!(channel_address, expired.channel_address);
+            assert_eq!(request.request_id(), expired.channel_txn_id);
+            assert!(expired.participants.contains(&alice.account()));
+            assert!(expired.participants.contains(&bob.account()));
+            assert!(alice
+                .get_pending_txn_request(bob.account())
+                .await?
+                .is_none());
+
+            // bob is told the proposal is expired, and gives it up too.
+            bob.cancel_pending_request_in_channel(
+                channel_address,
+                alice.account(),
+                request.request_id(),
+            )
+            .await?;
+            assert!(bob
+                .get_pending_txn_request(alice.account())
+                .await?
+                .is_none());
+
+            // late signature of bob can't revive an expired proposal.
+            let request = alice.transfer(bob.account(), 200).await?;
+            tokio::time::delay_for(Duration::from_secs(3)).await;
+            let err = bob
+                .verify_txn(alice.account(), &request)
+                .await
+                .expect_err("expired proposal should be rejected");
+            assert_eq!(
+                Some(SgErrorCode::PROPOSAL_EXPIRED),
+                err.downcast_ref::<SgError>().map(|e| e.error_code)
+            );
+            let expired = tokio::time::timeout(Duration::from_secs(10), expired_proposals.next())
+                .await?
+                .expect("should receive expired proposal");
+            assert_eq!(request.request_id(), expired.channel_txn_id);
+
+            common::transfer(alice.clone(), bob.clone(), 300).await?;
+            assert_eq!(9700, alice.channel_balance(bob.account()).await?);
+            assert_eq!(10300, bob.channel_balance(alice.account()).await?);
+
+            alice.stop().await?;
+            bob.stop().await?;
+            Ok(())
+        })
+    })
+}
+
+fn run_test_multi_party_channel() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client.clone(), |rt, alice, bob| {
+            rt.block_on(async {
+                let carol = Arc::new(common::setup_wallet(chain_client.clone(), 10_000_000).await?);
+                let mut deposits = BTreeMap::new();
+                deposits.insert(alice.account(), 10000);
+                deposits.insert(bob.account(), 8000);
+                deposits.insert(carol.account(), 6000);
+                let balance = alice.balance()?;
+
+                let request = alice.open_multi_party(deposits).await?;
+                let channel_address = request.channel_txn().channel_address();
+                let gas_used = common::sign_and_apply_by_all(
+                    alice.clone(),
+                    vec![bob.clone(), carol.clone()],
+                    request,
+                )
+                .await?;
+                assert_eq!(balance - 10000 - gas_used, alice.balance()?);
+                for wallet in vec![alice.clone(), bob.clone(), carol.clone()] {
+                    assert_eq!(3, wallet.channel_participants(channel_address).await?.len());
+                    assert_eq!(
+                        10000,
+                        wallet
+                            .balance_in_channel(channel_address, alice.account())
+                            .await?
+                    );
+                    assert_eq!(
+                        8000,
+                        wallet
+                            .balance_in_channel(channel_address, bob.account())
+                            .await?
+                    );
+                    assert_eq!(
+                        6000,
+                        wallet
+                            .balance_in_channel(channel_address, carol.account())
+                            .await?
+                    );
+                }
+
+                let request = bob
+                    .execute_script_in_channel(
+                        channel_address,
+                        DEFAULT_PACKAGE,
+                        "transfer",
+                        vec![
+                            TransactionArgument::Address(carol.account()),
+                            TransactionArgument::U64(3000),
+                        ],
+                    )
+                    .await?;
+                common::sign_and_apply_by_all(
+                    bob.clone(),
+                    vec![alice.clone(), carol.clone()],
+                    request,
+                )
+                .await?;
+                for wallet in vec![alice.clone(), bob.clone(), carol.clone()] {
+                    assert_eq!(
+                        5000,
+                        wallet
+                            .balance_in_channel(channel_address, bob.account())
+                            .await?
+                    );
+                    assert_eq!(
+                        9000,
+                        wallet
+                            .balance_in_channel(channel_address, carol.account())
+                            .await?
+                    );
+                }
+
+                let carol_balance = carol.balance()?;
+                let request = alice.close_channel(channel_address).await?;
+                common::sign_and_apply_by_all(
+                    alice.clone(),
+                    vec![bob.clone(), carol.clone()],
+                    request,
+                )
+                .await?;
+                assert_eq!(carol_balance + 9000, carol.balance()?);
+                carol.stop().await?;
+                Ok(())
+            })
+        })
+    })
+}
+
+fn run_test_channel_terms() -> Result<()> {
+    run_with_mock_client(|chain_client| {
+        common::with_wallet(chain_client, |rt, alice, bob| {
+            rt.block_on(async move {
+                let transfer_code = alice
+                    .get_script(DEFAULT_PACKAGE.to_string(), "transfer".to_string())
+                    .await?
+                    .unwrap();
+                let package = ChannelScriptPackage::new("test".to_string(), vec![transfer_code]);
+                alice.install_package(package.clone()).await?;
+                bob.install_package(package).await?;
+                common::open_channel(alice.clone(), bob.clone(), 10000, 10000).await?;
+
+                // alice agreed to run the test package, bob didn't.
+                alice.set_channel_terms(
+                    bob.account(),
+                    ChannelTerms::new(5000, vec!["test".to_string()]),
+                )?;
+                bob.set_channel_terms(alice.account(), ChannelTerms::new(5000, vec![]))?;
+                assert_eq!(
+                    Some(ChannelTerms::new(5000, vec![])),
+                    bob.channel_terms(alice.account())?
+                );
+
+                assert!(alice.transfer(bob.account(), 6000).await.is_err());
+                common::transfer(alice.clone(), bob.clone(), 4000).await?;
+                assert!(alice.transfer(bob.account(), 2000).await.is_err());
+
+                let request = alice
+                    .execute_script(
+                        bob.account(),
+                        "test",
+                        "transfer",
+                        vec![
+                            TransactionArgument::Address(bob.account()),
+                            TransactionArgument::U64(100),
+                        ],
+                    )
+                    .await?;
+                assert!(bob.verify_txn(alice.account(), &request).await.is_err());
+                Ok(())
+            })
+        })
+    })
+}
@@ -6,7 +6,10 @@ use super::common;
 use anyhow::Result;
 use libra_logger::prelude::*;
 use libra_types::{
-    access_path::AccessPath, account_address::AccountAddress, channel::ChannelEvent,
+    access_path::AccessPath,
+    account_address::AccountAddress,
+    channel::ChannelEvent,
+    transaction::{Script, TransactionArgument},
 };
 use sgchain::client_state_view::ClientStateView;
 use sgcompiler::{Compiler, StateViewModuleLoader};
@@ -248,6 +251,31 @@ async fn compile_and_deploy_module(wallet: Arc<WalletHandle>, test_case: &str) -
     Ok(())
 }
 
+/// Run script `script_file` of `test_case` on chain by `runner`,
+/// the script imports modules of the test case deployed by `deployer`.
+pub async fn run_test_case_script(
+    deployer: Arc<WalletHandle>,
+    runner: Arc<WalletHandle>,
+    test_case: &str,
+    script_file: &str,
+    args: Vec<TransactionArgument>,
+) -> Result<()> {
+    let script_byte_code = {
+        let path = get_test_case_path(test_case);
+        let script_source = std::fs::read_to_string(path.join(script_file))?;
+
+        let client_state_view = ClientStateView::new(None, deployer.client());
+        let module_loader = StateViewModuleLoader::new(&client_state_view);
+        let compiler = Compiler::new_with_module_loader(deployer.account(), &module_loader);
+        compiler.compile_script(script_source.as_str())?
+    };
+
+    runner
+        .run_script(Script::new(script_byte_code, args))
+        .await?;
+    Ok(())
+}
+
 fn compile_package(wallet: Arc<WalletHandle>, test_case: &str) -> Result<ChannelScriptPackage> {
     let path = get_test_case_path(test_case);
 
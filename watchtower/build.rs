@@ -0,0 +1,12 @@
+fn main() {
+    let protos = ["src/proto/watchtower.proto"];
+
+    let includes = ["src/proto/"];
+
+    grpcio_compiler::prost_codegen::compile_protos(
+        &protos,
+        &includes,
+        &std::env::var("OUT_DIR").unwrap(),
+    )
+    .unwrap();
+}
@@ -0,0 +1,38 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::proto::watchtower::WatchtowerClient as GrpcWatchtowerClient;
+use anyhow::{bail, Result};
+use futures::{channel::mpsc, StreamExt};
+use grpcio::{ChannelBuilder, Environment};
+use libra_logger::prelude::*;
+use sgtypes::justice::JusticeBlob;
+use std::sync::Arc;
+
+pub struct WatchtowerClient {
+    client: GrpcWatchtowerClient,
+}
+
+impl WatchtowerClient {
+    pub fn new(env: Arc<Environment>, host: &str, port: u16) -> Self {
+        let channel = ChannelBuilder::new(env).connect(&format!("{}:{}", host, port));
+        let client = GrpcWatchtowerClient::new(channel);
+        WatchtowerClient { client }
+    }
+
+    pub fn push_justice(&self, blob: JusticeBlob) -> Result<()> {
+        match self.client.push_justice(&blob.into()) {
+            Ok(_) => Ok(()),
+            Err(err) => bail!("GRPC error: {}", err),
+        }
+    }
+
+    /// Push justice data published by wallet to the tower, until the wallet stops.
+    pub async fn forward_justice(&self, mut justices: mpsc::UnboundedReceiver<JusticeBlob>) {
+        while let Some(blob) = justices.next().await {
+            if let Err(e) = self.push_justice(blob) {
+                error!("fail to push justice to watchtower, {}", e);
+            }
+        }
+    }
+}
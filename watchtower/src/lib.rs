@@ -0,0 +1,37 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Watchtower defends offline users against stale channel states.
+//!
+//! Users push encrypted justice data of each revoked state to the tower.
+//! The tower watches solo channel txns on chain, and when one carries a revoked state,
+//! it decrypts the justice data by the witness of the txn and submits the challenge.
+
+pub mod client;
+pub mod proto;
+pub mod service;
+mod tower;
+
+pub use crate::tower::Watchtower;
+
+use anyhow::{ensure, Result};
+use sgtypes::justice::{JusticeBlob, HINT_SIZE};
+use std::convert::TryFrom;
+
+impl TryFrom<crate::proto::watchtower::JusticeBlob> for JusticeBlob {
+    type Error = anyhow::Error;
+
+    fn try_from(proto: crate::proto::watchtower::JusticeBlob) -> Result<Self> {
+        ensure!(proto.hint.len() == HINT_SIZE, "invalid justice hint");
+        Ok(JusticeBlob::new(proto.hint, proto.data))
+    }
+}
+
+impl From<JusticeBlob> for crate::proto::watchtower::JusticeBlob {
+    fn from(blob: JusticeBlob) -> Self {
+        Self {
+            hint: blob.hint,
+            data: blob.data,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use coerce_rt::actor::context::ActorContext;
+use libra_logger::prelude::*;
+use sgchain::star_chain_client::StarChainClient;
+use std::sync::Arc;
+use structopt::StructOpt;
+use watchtower::{service::WatchtowerService, Watchtower};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Watchtower",
+    about = "Challenge stale channel states on behalf of offline users"
+)]
+struct Args {
+    /// Host to listen on.
+    #[structopt(short = "a", long = "host", default_value = "127.0.0.1")]
+    pub host: String,
+    /// Port to listen on.
+    #[structopt(short = "p", long = "port", default_value = "9050")]
+    pub port: u16,
+    /// Chain Host address/name to connect to.
+    #[structopt(short = "s", long = "chain_host", default_value = "127.0.0.1")]
+    pub chain_host: String,
+    /// Chain port to connect to.
+    #[structopt(short = "r", long = "chain_port", default_value = "8000")]
+    pub chain_port: u16,
+    /// Directory to keep justice data.
+    #[structopt(short = "d", long = "data_dir", default_value = "/tmp/watchtower")]
+    pub data_dir: String,
+}
+
+fn main() {
+    let _logger = libra_logger::set_default_global_logger(false /* async */, None);
+    let args = Args::from_args();
+
+    let chain_client = Arc::new(StarChainClient::new(
+        &args.chain_host,
+        args.chain_port as u32,
+    ));
+    let tower = Watchtower::new(chain_client, &args.data_dir);
+
+    let mut rt = tokio::runtime::Runtime::new().expect("create tokio runtime should ok");
+    let _watcher = rt
+        .block_on(tower.start(ActorContext::new()))
+        .expect("start watchtower should ok");
+    let _handle = WatchtowerService::new(tower).run(args.host.clone(), args.port);
+    info!("watchtower started at {}:{}", args.host, args.port);
+
+    rt.block_on(futures::future::pending::<()>());
+}
@@ -0,0 +1,3 @@
+pub mod watchtower {
+    include!(concat!(env!("OUT_DIR"), "/watchtower.rs"));
+}
@@ -0,0 +1,47 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    proto::watchtower::{
+        create_watchtower, JusticeBlob as JusticeBlobProto, Watchtower as WatchtowerGrpc,
+    },
+    Watchtower,
+};
+use anyhow::Result;
+use grpc_helpers::{provide_grpc_response, spawn_service_thread_with_drop_closure, ServerHandle};
+use sgtypes::justice::JusticeBlob;
+use std::convert::TryFrom;
+
+#[derive(Clone)]
+pub struct WatchtowerService {
+    tower: Watchtower,
+}
+
+impl WatchtowerService {
+    pub fn new(tower: Watchtower) -> Self {
+        Self { tower }
+    }
+
+    pub fn run(self, host: String, port: u16) -> ServerHandle {
+        spawn_service_thread_with_drop_closure(
+            create_watchtower(self),
+            host,
+            port,
+            "watchtower",
+            Some(100_000_000),
+            move || {},
+        )
+    }
+}
+
+impl WatchtowerGrpc for WatchtowerService {
+    fn push_justice(
+        &mut self,
+        ctx: ::grpcio::RpcContext,
+        req: JusticeBlobProto,
+        sink: ::grpcio::UnarySink<()>,
+    ) {
+        let resp: Result<()> = JusticeBlob::try_from(req).and_then(|b| self.tower.add_justice(b));
+        provide_grpc_response(resp, ctx, sink);
+    }
+}
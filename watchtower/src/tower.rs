@@ -0,0 +1,113 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::{ensure, Result};
+use coerce_rt::actor::context::ActorContext;
+use futures::StreamExt;
+use libra_logger::prelude::*;
+use libra_types::{
+    account_address::AccountAddress,
+    account_config::association_address,
+    transaction::{ChannelTransactionPayload, Transaction, TransactionPayload},
+};
+use sgchain::star_chain_client::ChainClient;
+use sgstorage::{channel_db::ChannelDB, justice_store::JusticeStore, storage::SgStorage};
+use sgtypes::justice::{justice_hint, witness_hash, JusticeBlob};
+use sgwallet::{
+    chain_watcher::{ChainWatcher, ChainWatcherHandle, Interest, TransactionWithInfo},
+    wallet::submit_transaction,
+};
+use std::{path::Path, sync::Arc};
+
+/// The tower only learns a channel when its revoked state goes onchain,
+/// and a challenge txn becomes invalid once its signer sends another txn from the account,
+/// so users should keep pushing justice data of every new state.
+#[derive(Clone)]
+pub struct Watchtower {
+    chain_client: Arc<dyn ChainClient>,
+    store: JusticeStore<ChannelDB>,
+}
+
+impl Watchtower {
+    pub fn new<P: AsRef<Path>>(chain_client: Arc<dyn ChainClient>, store_dir: P) -> Self {
+        // the tower is not a chain account, keep its data under the default address.
+        let owner = AccountAddress::default();
+        let sgdb = Arc::new(SgStorage::new(owner, store_dir));
+        Self {
+            chain_client,
+            store: JusticeStore::new(ChannelDB::new(owner, sgdb)),
+        }
+    }
+
+    pub fn add_justice(&self, blob: JusticeBlob) -> Result<()> {
+        self.store.save_justice(&blob)
+    }
+
+    /// Watch solo channel txns from the latest version, return the watcher handle.
+    pub async fn start(&self, context: ActorContext) -> Result<ChainWatcherHandle> {
+        let start_version = self
+            .chain_client
+            .get_latest_ledger(&association_address())
+            .version();
+        let chain_watcher = ChainWatcher::new(self.chain_client.clone(), start_version, 16);
+        let handle = chain_watcher.start(context).await?;
+        let mut solo_txns = handle.add_interest(solo_channel_txn_interest()).await?;
+        let tower = self.clone();
+        tokio::task::spawn(async move {
+            while let Some(txn) = solo_txns.next().await {
+                if let Err(e) = tower.handle_solo_txn(txn).await {
+                    error!("fail to handle solo channel txn, {}", e);
+                }
+            }
+            info!("watchtower stopped");
+        });
+        Ok(handle)
+    }
+
+    async fn handle_solo_txn(&self, txn: TransactionWithInfo) -> Result<()> {
+        let payload = match channel_payload(&txn) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        let witness = payload.witness();
+        let revoked_witness = witness_hash(witness);
+        let blob = match self.store.get_justice(&justice_hint(&revoked_witness))? {
+            Some(blob) => blob,
+            None => return Ok(()),
+        };
+        let justice = blob.decrypt(&revoked_witness)?;
+        ensure!(
+            justice.channel_address == payload.channel_address(),
+            "justice data is not for channel {}",
+            payload.channel_address()
+        );
+        ensure!(
+            justice.channel_sequence_number > witness.channel_sequence_number(),
+            "justice data doesn't prove a newer state"
+        );
+        info!(
+            "channel {} is breached at version {}, challenge it with state {}",
+            payload.channel_address(),
+            txn.version,
+            justice.channel_sequence_number
+        );
+        submit_transaction(self.chain_client.as_ref(), justice.challenge_txn).await
+    }
+}
+
+fn channel_payload(txn: &TransactionWithInfo) -> Option<&ChannelTransactionPayload> {
+    match &txn.txn {
+        Transaction::UserTransaction(s) => match s.payload() {
+            TransactionPayload::Channel(cp) => Some(cp),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn solo_channel_txn_interest() -> Interest {
+    Box::new(|txn| match channel_payload(txn) {
+        Some(cp) => !cp.is_authorized(),
+        None => false,
+    })
+}
@@ -0,0 +1,158 @@
+// Copyright (c) The Starcoin Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use anyhow::Result;
+use coerce_rt::actor::context::ActorContext;
+use futures::StreamExt;
+use libra_crypto::{
+    ed25519::{Ed25519PrivateKey, Ed25519PublicKey},
+    test_utils::KeyPair,
+    HashValue, Uniform,
+};
+use libra_tools::tempdir::TempPath;
+use libra_types::{
+    access_path::DataPath,
+    account_address::AccountAddress,
+    channel::ChannelResource,
+    libra_resource::{make_resource, LibraResource},
+    transaction::{Transaction, TransactionPayload},
+};
+use rand::prelude::*;
+use sgchain::star_chain_client::{ChainClient, MockChainClient};
+use sgwallet::wallet::{Wallet, WalletHandle};
+use std::sync::Arc;
+use watchtower::Watchtower;
+
+#[test]
+fn test_watchtower_challenge_for_offline_user() -> Result<()> {
+    libra_logger::try_init_for_testing();
+    let (mock_chain_service, _handle) = MockChainClient::new();
+    let chain_client: Arc<dyn ChainClient> = Arc::new(mock_chain_service);
+    let mut rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(challenge_for_offline_user(chain_client))
+}
+
+async fn challenge_for_offline_user(chain_client: Arc<dyn ChainClient>) -> Result<()> {
+    let tower = Watchtower::new(chain_client.clone(), TempPath::new());
+    let _watcher = tower.start(ActorContext::new()).await?;
+
+    let sender = Arc::new(setup_wallet(chain_client.clone(), 10_000_000).await?);
+    let receiver = Arc::new(setup_wallet(chain_client.clone(), 10_000_000).await?);
+    let mut justices = receiver.subscribe_justice();
+
+    open_channel(sender.clone(), receiver.clone()).await?;
+    transfer(sender.clone(), receiver.clone(), 300).await?;
+    assert_eq!(2, receiver.channel_sequence_number(sender.account()).await?);
+
+    // receiver signs and applies the payment, but sender never gets the signature.
+    let request = sender
+        .send_payment(receiver.account(), 500, HashValue::random().to_vec(), 10)
+        .await?;
+    let resp = receiver
+        .verify_txn(sender.account(), &request)
+        .await?
+        .expect("receiver should auto sign");
+    receiver.apply_txn(sender.account(), &resp).await?;
+    assert_eq!(3, receiver.channel_sequence_number(sender.account()).await?);
+
+    // justice of state 1 and state 2 is handed to the tower, then receiver goes offline.
+    for _ in 0..2 {
+        let blob = justices.next().await.expect("justice should be published");
+        tower.add_justice(blob)?;
+    }
+    let channel_address = receiver
+        .channel_handle(sender.account())
+        .await?
+        .channel_address()
+        .clone();
+    receiver.stop().await?;
+
+    // sender submits the stale state 2.
+    sender.force_travel_txn(receiver.account()).await?;
+
+    let chain_watcher = sgwallet::chain_watcher::ChainWatcher::new(chain_client.clone(), 0, 10);
+    let chain_watcher_handle = chain_watcher.start(ActorContext::new()).await?;
+    let challenge_txn = chain_watcher_handle
+        .add_interest_oneshot(Box::new(move |txn| match &txn.txn {
+            Transaction::UserTransaction(s) => match s.payload() {
+                TransactionPayload::Channel(cp) => {
+                    cp.channel_address() == channel_address && cp.channel_sequence_number() == 3
+                }
+                _ => false,
+            },
+            _ => false,
+        }))
+        .await?
+        .await?;
+    assert_eq!(receiver.account(), challenge_txn_sender(&challenge_txn.txn));
+
+    let channel_state =
+        chain_client.get_account_state(channel_address, Some(challenge_txn.version))?;
+    let channel_resource = channel_state
+        .get(&DataPath::onchain_resource_path(ChannelResource::struct_tag()).to_vec())
+        .map(|b| make_resource::<ChannelResource>(&b))
+        .transpose()?
+        .expect("channel resource should exists");
+    assert!(channel_resource.closed(), "channel should be closed");
+
+    sender.stop().await?;
+    Ok(())
+}
+
+fn challenge_txn_sender(txn: &Transaction) -> AccountAddress {
+    match txn {
+        Transaction::UserTransaction(s) => s.sender(),
+        _ => panic!("should be user txn"),
+    }
+}
+
+async fn setup_wallet(client: Arc<dyn ChainClient>, init_balance: u64) -> Result<WalletHandle> {
+    let mut seed_rng = rand::rngs::OsRng::new().expect("can't access OsRng");
+    let mut rng: StdRng = SeedableRng::from_seed(seed_rng.gen());
+    let keypair: Arc<KeyPair<Ed25519PrivateKey, Ed25519PublicKey>> =
+        Arc::new(KeyPair::generate_for_testing(&mut rng));
+    let account = AccountAddress::from_public_key(&keypair.public_key);
+    client.faucet(account, init_balance).await?;
+    let handle = Wallet::new_with_client(account, keypair, client.clone(), TempPath::new())?
+        .start()
+        .await?;
+    let gas_used = handle.enable_channel().await?;
+    client.faucet(account, gas_used).await?;
+    Ok(handle)
+}
+
+async fn open_channel(sender: Arc<WalletHandle>, receiver: Arc<WalletHandle>) -> Result<()> {
+    let req = sender.open(receiver.account(), 10000, 10000).await?;
+    let resp = match receiver.verify_txn(sender.account(), &req).await? {
+        Some(resp) => resp,
+        None => {
+            receiver
+                .approve_txn(sender.account(), req.request_id())
+                .await?
+        }
+    };
+    sender
+        .verify_txn_response(receiver.account(), &resp)
+        .await?;
+    sender.apply_txn(receiver.account(), &resp).await?;
+    receiver.apply_txn(sender.account(), &resp).await?;
+    Ok(())
+}
+
+async fn transfer(
+    sender: Arc<WalletHandle>,
+    receiver: Arc<WalletHandle>,
+    amount: u64,
+) -> Result<()> {
+    let req = sender.transfer(receiver.account(), amount).await?;
+    let resp = receiver
+        .verify_txn(sender.account(), &req)
+        .await?
+        .expect("receiver should auto sign");
+    sender
+        .verify_txn_response(receiver.account(), &resp)
+        .await?;
+    sender.apply_txn(receiver.account(), &resp).await?;
+    receiver.apply_txn(sender.account(), &resp).await?;
+    Ok(())
+}